@@ -1,38 +1,54 @@
 use {
     anyhow::Context,
     backoff::{future::retry, ExponentialBackoff},
+    chrono::{DateTime, SecondsFormat, Utc},
     clap::{Parser, Subcommand, ValueEnum},
-    futures::{future::TryFutureExt, sink::SinkExt, stream::StreamExt},
+    futures::{
+        channel::mpsc,
+        future::TryFutureExt,
+        sink::{Sink, SinkExt},
+        stream::{Stream, StreamExt},
+    },
     indicatif::{MultiProgress, ProgressBar, ProgressStyle},
-    log::{error, info},
+    log::{error, info, warn},
     serde_json::{json, Value},
-    solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature},
-    solana_transaction_status::UiTransactionEncoding,
     std::{
-        collections::HashMap,
+        collections::{HashMap, HashSet},
         env,
         fs::File,
-        path::PathBuf,
+        io,
+        path::{Path, PathBuf},
         sync::Arc,
         time::{Duration, Instant, SystemTime, UNIX_EPOCH},
     },
-    tokio::{fs, sync::Mutex},
-    tonic::transport::{channel::ClientTlsConfig, Certificate},
+    tokio::{
+        fs,
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+        sync::Mutex,
+        time::{sleep, timeout_at},
+    },
+    tonic::{
+        transport::{channel::ClientTlsConfig, Certificate},
+        Status,
+    },
     yellowstone_grpc_client::{GeyserGrpcClient, GeyserGrpcClientError, Interceptor},
     yellowstone_grpc_proto::{
-        convert_from,
+        json,
         plugin::filter::message::FilteredUpdate,
         prelude::{
             subscribe_request_filter_accounts_filter::Filter as AccountsFilterOneof,
             subscribe_request_filter_accounts_filter_lamports::Cmp as AccountsFilterLamports,
             subscribe_request_filter_accounts_filter_memcmp::Data as AccountsFilterMemcmpOneof,
-            subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
-            SubscribeRequestAccountsDataSlice, SubscribeRequestFilterAccounts,
-            SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterAccountsFilterLamports,
+            subscribe_update::UpdateOneof, AccountsDataEncoding, CommitmentLevel, DecompressCodec,
+            SlotStatus, SubscribeRequest, SubscribeRequestAccountsDataSlice,
+            SubscribeRequestFilterAccounts, SubscribeRequestFilterAccountsFilter,
+            SubscribeRequestFilterAccountsFilterChecksum,
+            SubscribeRequestFilterAccountsFilterLamports,
             SubscribeRequestFilterAccountsFilterMemcmp, SubscribeRequestFilterBlocks,
             SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterEntry,
-            SubscribeRequestFilterSlots, SubscribeRequestFilterTransactions, SubscribeRequestPing,
-            SubscribeUpdateAccountInfo, SubscribeUpdateEntry, SubscribeUpdateTransactionInfo,
+            SubscribeRequestFilterProgramEvents, SubscribeRequestFilterSlots,
+            SubscribeRequestFilterTransactions, SubscribeRequestFilterTransactionsError,
+            SubscribeRequestPing, SubscribeUpdate, UpdatesEncoding,
         },
         prost::Message,
     },
@@ -45,6 +61,7 @@ type TransactionsStatusFilterMap = HashMap<String, SubscribeRequestFilterTransac
 type EntryFilterMap = HashMap<String, SubscribeRequestFilterEntry>;
 type BlocksFilterMap = HashMap<String, SubscribeRequestFilterBlocks>;
 type BlocksMetaFilterMap = HashMap<String, SubscribeRequestFilterBlocksMeta>;
+type ProgramEventsFilterMap = HashMap<String, SubscribeRequestFilterProgramEvents>;
 
 #[derive(Debug, Clone, Parser)]
 #[clap(author, version, about)]
@@ -188,11 +205,70 @@ impl From<ArgsCommitment> for CommitmentLevel {
     }
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ArgsAccountsDataEncoding {
+    Base64,
+    Base58,
+}
+
+impl From<ArgsAccountsDataEncoding> for AccountsDataEncoding {
+    fn from(encoding: ArgsAccountsDataEncoding) -> Self {
+        match encoding {
+            ArgsAccountsDataEncoding::Base64 => AccountsDataEncoding::Base64,
+            ArgsAccountsDataEncoding::Base58 => AccountsDataEncoding::Base58,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ArgsDecompressCodec {
+    Zstd,
+    Gzip,
+}
+
+impl From<ArgsDecompressCodec> for DecompressCodec {
+    fn from(codec: ArgsDecompressCodec) -> Self {
+        match codec {
+            ArgsDecompressCodec::Zstd => DecompressCodec::Zstd,
+            ArgsDecompressCodec::Gzip => DecompressCodec::Gzip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ArgsSlotStatus {
+    Processed,
+    Confirmed,
+    Finalized,
+    FirstShredReceived,
+    Completed,
+    CreatedBank,
+    Dead,
+}
+
+impl From<ArgsSlotStatus> for SlotStatus {
+    fn from(status: ArgsSlotStatus) -> Self {
+        match status {
+            ArgsSlotStatus::Processed => SlotStatus::SlotProcessed,
+            ArgsSlotStatus::Confirmed => SlotStatus::SlotConfirmed,
+            ArgsSlotStatus::Finalized => SlotStatus::SlotFinalized,
+            ArgsSlotStatus::FirstShredReceived => SlotStatus::SlotFirstShredReceived,
+            ArgsSlotStatus::Completed => SlotStatus::SlotCompleted,
+            ArgsSlotStatus::CreatedBank => SlotStatus::SlotCreatedBank,
+            ArgsSlotStatus::Dead => SlotStatus::SlotDead,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Subcommand)]
 enum Action {
     HealthCheck,
     HealthWatch,
     Subscribe(Box<ActionSubscribe>),
+    /// Export the current state of matching accounts to a file and exit
+    Snapshot(ActionSnapshot),
+    /// Print updates captured with `subscribe --out-file`, the server is not used
+    Replay(ActionReplay),
     Ping {
         #[clap(long, short, default_value_t = 0)]
         count: i32,
@@ -205,6 +281,25 @@ enum Action {
         blockhash: String,
     },
     GetVersion,
+    GetFilterLimits,
+    GetBlockMetaRange {
+        #[clap(long)]
+        from_slot: u64,
+        #[clap(long)]
+        to_slot: u64,
+    },
+    GetTransactionAccounts {
+        #[clap(long)]
+        signature: String,
+    },
+    GetAccountData {
+        #[clap(long)]
+        pubkey: String,
+        #[clap(long)]
+        slot: u64,
+        #[clap(long)]
+        write_version: u64,
+    },
 }
 
 #[derive(Debug, Clone, clap::Args)]
@@ -244,10 +339,86 @@ struct ActionSubscribe {
     #[clap(long)]
     accounts_lamports: Vec<String>,
 
+    /// Filter by rent epoch, format: `eq:42` / `gt:42` / `lt:42`
+    #[clap(long)]
+    accounts_rent_epoch: Option<String>,
+
+    /// Filter by CRC32 checksum, format: `crc_offset,body_offset,body_length`
+    #[clap(long)]
+    accounts_checksum: Option<String>,
+
+    /// Filter by whole account data equal to the value in base58
+    #[clap(long)]
+    accounts_data_equals: Option<String>,
+
+    /// Receive only the last write of the account in the slot
+    #[clap(long)]
+    accounts_dedup_by_slot: Option<bool>,
+
+    /// Hold accounts updates until the slot reaches commitment
+    #[clap(long)]
+    accounts_deliver_at_commitment: Option<ArgsCommitment>,
+
+    /// Receive only named fields of account data from server schema
+    #[clap(long)]
+    accounts_data_fields: Vec<String>,
+
+    /// Receive only accounts modified by successful transactions
+    #[clap(long)]
+    accounts_only_from_successful_tx: Option<bool>,
+
+    /// Receive every account only once per connection
+    #[clap(long, default_value_t = false)]
+    accounts_new_accounts_only: bool,
+
+    /// Receive accounts only when the owner is changed
+    #[clap(long, default_value_t = false)]
+    accounts_on_owner_change: bool,
+
+    /// Receive accounts only when data is changed
+    #[clap(long, default_value_t = false)]
+    accounts_on_data_change_only: bool,
+
+    /// With `--accounts-on-data-change-only` receive lamports or owner changes too
+    #[clap(long, default_value_t = false)]
+    accounts_on_data_change_include_lamports_owner: bool,
+
+    /// Receive changed bytes against the previous update instead of the whole data
+    #[clap(long, default_value_t = false)]
+    accounts_diff: bool,
+
+    /// Filter accounts owned by native programs (`true`) or user-deployed programs (`false`)
+    #[clap(long)]
+    accounts_native_program_owned: Option<bool>,
+
+    /// Filter executable accounts (`true`) or data accounts (`false`)
+    #[clap(long)]
+    accounts_executable: Option<bool>,
+
+    /// Skip accounts with data bigger than the value
+    #[clap(long)]
+    accounts_data_size_max: Option<u64>,
+
+    /// Decompress account data on the server
+    #[clap(long)]
+    accounts_decompress_data: Option<ArgsDecompressCodec>,
+
+    /// Receive current state of matching accounts before live updates
+    #[clap(long)]
+    accounts_snapshot: bool,
+
     /// Receive only part of updated data account, format: `offset,size`
     #[clap(long)]
     accounts_data_slice: Vec<String>,
 
+    /// Receive account data encoded by the server
+    #[clap(long)]
+    accounts_data_encoding: Option<ArgsAccountsDataEncoding>,
+
+    /// Receive data slices as separate segments with offsets instead of concatenated data
+    #[clap(long)]
+    accounts_data_segments: bool,
+
     /// Subscribe on slots updates
     #[clap(long)]
     slots: bool,
@@ -260,6 +431,26 @@ struct ActionSubscribe {
     #[clap(long)]
     slots_interslot_updates: bool,
 
+    /// Receive notifications about skipped slots
+    #[clap(long)]
+    slots_gaps: bool,
+
+    /// Skip slots far behind the tip while node is catching up
+    #[clap(long)]
+    slots_tip_only: bool,
+
+    /// Receive only slot updates with these statuses
+    #[clap(long)]
+    slots_statuses: Vec<ArgsSlotStatus>,
+
+    /// Receive slot updates in compact batches collected over the interval
+    #[clap(long)]
+    slots_delta_batch_interval_ms: Option<u32>,
+
+    /// Replay the given number of the latest finalized slots before live updates
+    #[clap(long)]
+    slots_replay_finalized: Option<u32>,
+
     /// Subscribe on transactions updates
     #[clap(long)]
     transactions: bool,
@@ -288,6 +479,34 @@ struct ActionSubscribe {
     #[clap(long)]
     transactions_account_required: Vec<String>,
 
+    /// Filter invoked program in transactions
+    #[clap(long)]
+    transactions_program_include: Vec<String>,
+
+    /// Filter transactions signed by any of the accounts
+    #[clap(long)]
+    transactions_signer_include: Vec<String>,
+
+    /// Filter program invoked through CPI in transactions
+    #[clap(long)]
+    transactions_cpi_program_include: Vec<String>,
+
+    /// Filter transactions with at least this number of account keys
+    #[clap(long)]
+    transactions_account_count_min: Option<u64>,
+
+    /// Filter transactions with at most this number of account keys
+    #[clap(long)]
+    transactions_account_count_max: Option<u64>,
+
+    /// Filter failed transactions by error, format: `error[:instruction_index[:instruction_error]]`
+    #[clap(long)]
+    transactions_error_match: Option<String>,
+
+    /// Skip transactions with already received signature
+    #[clap(long)]
+    transactions_dedup_signatures: Option<bool>,
+
     /// Subscribe on transactions_status updates
     #[clap(long)]
     transactions_status: bool,
@@ -316,9 +535,50 @@ struct ActionSubscribe {
     #[clap(long)]
     transactions_status_account_required: Vec<String>,
 
+    /// Filter invoked program in transactions for transactions_status
+    #[clap(long)]
+    transactions_status_program_include: Vec<String>,
+
+    /// Filter transactions signed by any of the accounts
+    #[clap(long)]
+    transactions_status_signer_include: Vec<String>,
+
+    /// Filter program invoked through CPI in transactions for transactions_status
+    #[clap(long)]
+    transactions_status_cpi_program_include: Vec<String>,
+
+    /// Filter transactions with at least this number of account keys for transactions_status
+    #[clap(long)]
+    transactions_status_account_count_min: Option<u64>,
+
+    /// Filter transactions with at most this number of account keys for transactions_status
+    #[clap(long)]
+    transactions_status_account_count_max: Option<u64>,
+
+    /// Filter failed transactions by error for transactions_status,
+    /// format: `error[:instruction_index[:instruction_error]]`
+    #[clap(long)]
+    transactions_status_error_match: Option<String>,
+
+    /// Skip transactions with already received signature for transactions_status
+    #[clap(long)]
+    transactions_status_dedup_signatures: Option<bool>,
+
     #[clap(long)]
     entries: bool,
 
+    /// Receive only the last entry of the slot
+    #[clap(long)]
+    entries_last_entry_only: Option<bool>,
+
+    /// Receive entries only from this slot
+    #[clap(long)]
+    entries_slot_from: Option<u64>,
+
+    /// Receive entries only up to this slot (inclusive)
+    #[clap(long)]
+    entries_slot_to: Option<u64>,
+
     /// Subscribe on block updates
     #[clap(long)]
     blocks: bool,
@@ -327,6 +587,22 @@ struct ActionSubscribe {
     #[clap(long)]
     blocks_account_include: Vec<String>,
 
+    /// Match included accounts against account owners too
+    #[clap(long)]
+    blocks_account_include_owner: Option<bool>,
+
+    /// Include accounts owned by the program to block message
+    #[clap(long)]
+    blocks_account_owner_include: Vec<String>,
+
+    /// Receive only blocks with a transaction invoking any of the programs
+    #[clap(long)]
+    blocks_require_program_include: Vec<String>,
+
+    /// Include only transactions matching `--blocks-require-program-include` to block message
+    #[clap(long)]
+    blocks_include_only_matching_transactions: Option<bool>,
+
     /// Include transactions to block message
     #[clap(long)]
     blocks_include_transactions: Option<bool>,
@@ -343,10 +619,50 @@ struct ActionSubscribe {
     #[clap(long)]
     blocks_meta: bool,
 
+    /// Receive only slot and rewards of block meta
+    #[clap(long)]
+    blocks_meta_rewards_only: Option<bool>,
+
+    /// Subscribe on events parsed from logs of the program
+    #[clap(long)]
+    program_events: Vec<String>,
+
     /// Re-send message from slot
     #[clap(long)]
     from_slot: Option<u64>,
 
+    /// Resume the stream after the update with the hex encoded `resume_token`
+    #[clap(long)]
+    resume_token: Option<String>,
+
+    /// Override server ping interval, in milliseconds
+    #[clap(long)]
+    ping_interval_ms: Option<u64>,
+
+    /// Receive updates wrapped into `Any` envelope
+    #[clap(long)]
+    envelope: bool,
+
+    /// Receive updates rendered as JSON by the server, must be enabled on the server
+    #[clap(long)]
+    json_encoding: bool,
+
+    /// Receive per filter aggregates of accounts and transactions every interval instead of updates, in milliseconds
+    #[clap(long)]
+    filter_stats_interval_ms: Option<u64>,
+
+    /// Limit rate of updates, capped by the server
+    #[clap(long)]
+    max_updates_per_second: Option<u32>,
+
+    /// Number of updates queued for the stream on the server, capped by the server
+    #[clap(long)]
+    channel_capacity: Option<u64>,
+
+    /// Drop vote transactions from transactions, program events and blocks updates
+    #[clap(long, default_value_t = false)]
+    exclude_votes: bool,
+
     /// Send ping in subscribe request
     #[clap(long)]
     ping: Option<i32>,
@@ -362,6 +678,180 @@ struct ActionSubscribe {
     /// Verify manually implemented encoding against prost
     #[clap(long, default_value_t = false)]
     verify_encoding: bool,
+
+    /// Track `seq` of updates, log gaps and regressions and exit with error on stream end
+    /// if any update was lost, requires server with sequence numbers support
+    #[clap(long, default_value_t = false)]
+    verify_sequence: bool,
+
+    /// Exit with error if filter with the name did not match any update before timeout
+    #[clap(long)]
+    expect_filter: Vec<String>,
+
+    /// Timeout for `--expect-filter` in seconds
+    #[clap(long, default_value_t = 30)]
+    expect_filter_timeout: u64,
+
+    /// Append updates as JSON lines to the file instead of logging them
+    #[clap(long)]
+    out_file: Option<PathBuf>,
+
+    /// Rotate `--out-file` once it reaches the size in bytes
+    #[clap(long)]
+    out_file_max_bytes: Option<u64>,
+
+    /// Append length-delimited raw `SubscribeUpdate` frames to the file without decoding them
+    #[clap(long, conflicts_with = "out_file")]
+    raw_out: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct ActionSnapshot {
+    /// Filter by Account Pubkey
+    #[clap(long)]
+    account: Vec<String>,
+
+    /// Filter by Owner Pubkey
+    #[clap(long)]
+    owner: Vec<String>,
+
+    /// Path of the output file
+    #[clap(long, short)]
+    output: PathBuf,
+
+    /// Output file format
+    #[clap(long, value_enum, default_value_t)]
+    format: ArgsSnapshotFormat,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+struct ActionReplay {
+    /// Path of JSON lines capture
+    #[clap(long, short)]
+    path: PathBuf,
+
+    /// Capture is written with `--raw-out` instead of `--out-file`
+    #[clap(long)]
+    raw: bool,
+
+    /// Honor original timing of updates scaled by the factor, as fast as possible if not set
+    #[clap(long)]
+    speed: Option<f64>,
+}
+
+impl ActionSnapshot {
+    fn get_subscribe_request(&self, commitment: Option<CommitmentLevel>) -> SubscribeRequest {
+        let mut accounts: AccountFilterMap = HashMap::new();
+        accounts.insert(
+            "snapshot".to_owned(),
+            SubscribeRequestFilterAccounts {
+                nonempty_txn_signature: None,
+                account: self.account.clone(),
+                owner: self.owner.clone(),
+                filters: vec![],
+                dedup_by_slot: None,
+                deliver_at_commitment: None,
+                data_fields: vec![],
+                only_from_successful_tx: None,
+                new_accounts_only: None,
+                data_size_max: None,
+                decompress_data: None,
+                snapshot: Some(true),
+                on_owner_change: None,
+                native_program_owned: None,
+                on_data_change_only: None,
+                on_data_change_include_lamports_owner: None,
+                diff: None,
+                rent_epoch_eq: None,
+                rent_epoch_gt: None,
+                rent_epoch_lt: None,
+                executable: None,
+            },
+        );
+
+        SubscribeRequest {
+            slots: HashMap::new(),
+            accounts,
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            entry: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            commitment: commitment.map(|x| x as i32),
+            accounts_data_slice: vec![],
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum ArgsSnapshotFormat {
+    /// JSON object with the snapshot slot and array of accounts
+    #[default]
+    Json,
+    /// Length-delimited protobuf `SubscribeUpdateAccount` messages
+    Binary,
+}
+
+#[derive(Debug)]
+struct ExpectFilters {
+    names: HashSet<String>,
+    deadline: Instant,
+}
+
+impl ExpectFilters {
+    fn new(args: &ActionSubscribe) -> Option<Self> {
+        (!args.expect_filter.is_empty()).then(|| Self {
+            names: args.expect_filter.iter().cloned().collect(),
+            deadline: Instant::now() + Duration::from_secs(args.expect_filter_timeout),
+        })
+    }
+}
+
+/// Tracks `seq` of the stream updates and counts lost updates
+#[derive(Debug, Default)]
+struct SequenceVerifier {
+    last: Option<u64>,
+    lost: u64,
+    regressions: u64,
+}
+
+impl SequenceVerifier {
+    fn new(args: &ActionSubscribe) -> Option<Self> {
+        args.verify_sequence.then(Self::default)
+    }
+
+    fn update(&mut self, seq: u64) {
+        match self.last {
+            Some(last) if seq <= last => {
+                error!("sequence regression: {seq} after {last}");
+                self.regressions += 1;
+            }
+            Some(last) if seq > last + 1 => {
+                let lost = seq - last - 1;
+                error!("sequence gap: {lost} updates lost between {last} and {seq}");
+                self.lost += lost;
+            }
+            _ => {}
+        }
+        self.last = Some(self.last.map_or(seq, |last| last.max(seq)));
+    }
+
+    const fn is_valid(&self) -> bool {
+        self.lost == 0 && self.regressions == 0
+    }
 }
 
 impl Action {
@@ -437,6 +927,49 @@ impl Action {
                             _ => anyhow::bail!("invalid lamports"),
                         }
                     }
+                    let (mut rent_epoch_eq, mut rent_epoch_gt, mut rent_epoch_lt) =
+                        (None, None, None);
+                    if let Some(filter) = &args.accounts_rent_epoch {
+                        let Some((cmp, value)) = filter.split_once(':') else {
+                            anyhow::bail!("invalid rent_epoch");
+                        };
+                        let Ok(value) = value.parse() else {
+                            anyhow::bail!("invalid rent_epoch value: {value}");
+                        };
+                        match cmp {
+                            "eq" => rent_epoch_eq = Some(value),
+                            "gt" => rent_epoch_gt = Some(value),
+                            "lt" => rent_epoch_lt = Some(value),
+                            _ => anyhow::bail!("invalid rent_epoch filter: {cmp}"),
+                        }
+                    }
+                    if let Some(checksum) = &args.accounts_checksum {
+                        let values = checksum
+                            .split(',')
+                            .map(|value| value.trim().parse::<u64>())
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|_| anyhow::anyhow!("invalid checksum value"))?;
+                        let [crc_offset, body_offset, body_length] = values[..] else {
+                            anyhow::bail!("invalid checksum");
+                        };
+                        filters.push(SubscribeRequestFilterAccountsFilter {
+                            filter: Some(AccountsFilterOneof::Checksum(
+                                SubscribeRequestFilterAccountsFilterChecksum {
+                                    crc_offset,
+                                    body_offset,
+                                    body_length,
+                                },
+                            )),
+                        });
+                    }
+                    if let Some(data) = &args.accounts_data_equals {
+                        let data = bs58::decode(data.trim())
+                            .into_vec()
+                            .context("invalid data_equals")?;
+                        filters.push(SubscribeRequestFilterAccountsFilter {
+                            filter: Some(AccountsFilterOneof::DataEquals(data)),
+                        });
+                    }
 
                     accounts.insert(
                         "client".to_owned(),
@@ -445,6 +978,29 @@ impl Action {
                             account: accounts_account,
                             owner: args.accounts_owner.clone(),
                             filters,
+                            dedup_by_slot: args.accounts_dedup_by_slot,
+                            deliver_at_commitment: args
+                                .accounts_deliver_at_commitment
+                                .map(|commitment| CommitmentLevel::from(commitment) as i32),
+                            data_fields: args.accounts_data_fields.clone(),
+                            only_from_successful_tx: args.accounts_only_from_successful_tx,
+                            new_accounts_only: Some(args.accounts_new_accounts_only),
+                            data_size_max: args.accounts_data_size_max,
+                            decompress_data: args
+                                .accounts_decompress_data
+                                .map(|codec| DecompressCodec::from(codec) as i32),
+                            snapshot: Some(args.accounts_snapshot),
+                            on_owner_change: Some(args.accounts_on_owner_change),
+                            native_program_owned: args.accounts_native_program_owned,
+                            on_data_change_only: Some(args.accounts_on_data_change_only),
+                            on_data_change_include_lamports_owner: Some(
+                                args.accounts_on_data_change_include_lamports_owner,
+                            ),
+                            diff: Some(args.accounts_diff),
+                            rent_epoch_eq,
+                            rent_epoch_gt,
+                            rent_epoch_lt,
+                            executable: args.accounts_executable,
                         },
                     );
                 }
@@ -456,6 +1012,15 @@ impl Action {
                         SubscribeRequestFilterSlots {
                             filter_by_commitment: Some(args.slots_filter_by_commitment),
                             interslot_updates: Some(args.slots_interslot_updates),
+                            slot_gaps: Some(args.slots_gaps),
+                            tip_only: Some(args.slots_tip_only),
+                            statuses: args
+                                .slots_statuses
+                                .iter()
+                                .map(|status| SlotStatus::from(*status) as i32)
+                                .collect(),
+                            delta_batch_interval_ms: args.slots_delta_batch_interval_ms,
+                            replay_finalized: args.slots_replay_finalized,
                         },
                     );
                 }
@@ -471,6 +1036,17 @@ impl Action {
                             account_include: args.transactions_account_include.clone(),
                             account_exclude: args.transactions_account_exclude.clone(),
                             account_required: args.transactions_account_required.clone(),
+                            program_include: args.transactions_program_include.clone(),
+                            dedup_signatures: args.transactions_dedup_signatures,
+                            signer_include: args.transactions_signer_include.clone(),
+                            cpi_program_include: args.transactions_cpi_program_include.clone(),
+                            account_count_min: args.transactions_account_count_min,
+                            account_count_max: args.transactions_account_count_max,
+                            error_match: args
+                                .transactions_error_match
+                                .as_deref()
+                                .map(parse_error_match)
+                                .transpose()?,
                         },
                     );
                 }
@@ -486,13 +1062,33 @@ impl Action {
                             account_include: args.transactions_status_account_include.clone(),
                             account_exclude: args.transactions_status_account_exclude.clone(),
                             account_required: args.transactions_status_account_required.clone(),
+                            program_include: args.transactions_status_program_include.clone(),
+                            dedup_signatures: args.transactions_status_dedup_signatures,
+                            signer_include: args.transactions_status_signer_include.clone(),
+                            cpi_program_include: args
+                                .transactions_status_cpi_program_include
+                                .clone(),
+                            account_count_min: args.transactions_status_account_count_min,
+                            account_count_max: args.transactions_status_account_count_max,
+                            error_match: args
+                                .transactions_status_error_match
+                                .as_deref()
+                                .map(parse_error_match)
+                                .transpose()?,
                         },
                     );
                 }
 
                 let mut entries: EntryFilterMap = HashMap::new();
                 if args.entries {
-                    entries.insert("client".to_owned(), SubscribeRequestFilterEntry {});
+                    entries.insert(
+                        "client".to_owned(),
+                        SubscribeRequestFilterEntry {
+                            last_entry_only: args.entries_last_entry_only,
+                            slot_from: args.entries_slot_from,
+                            slot_to: args.entries_slot_to,
+                        },
+                    );
                 }
 
                 let mut blocks: BlocksFilterMap = HashMap::new();
@@ -504,13 +1100,39 @@ impl Action {
                             include_transactions: args.blocks_include_transactions,
                             include_accounts: args.blocks_include_accounts,
                             include_entries: args.blocks_include_entries,
+                            account_include_owner: args.blocks_account_include_owner,
+                            account_owner_include: args.blocks_account_owner_include.clone(),
+                            require_matching_transaction: (!args
+                                .blocks_require_program_include
+                                .is_empty())
+                            .then(|| SubscribeRequestFilterTransactions {
+                                program_include: args.blocks_require_program_include.clone(),
+                                ..Default::default()
+                            }),
+                            include_only_matching_transactions: args
+                                .blocks_include_only_matching_transactions,
                         },
                     );
                 }
 
                 let mut blocks_meta: BlocksMetaFilterMap = HashMap::new();
                 if args.blocks_meta {
-                    blocks_meta.insert("client".to_owned(), SubscribeRequestFilterBlocksMeta {});
+                    blocks_meta.insert(
+                        "client".to_owned(),
+                        SubscribeRequestFilterBlocksMeta {
+                            rewards_only: args.blocks_meta_rewards_only,
+                        },
+                    );
+                }
+
+                let mut program_events: ProgramEventsFilterMap = HashMap::new();
+                for program in args.program_events.iter() {
+                    program_events.insert(
+                        program.clone(),
+                        SubscribeRequestFilterProgramEvents {
+                            program: program.clone(),
+                        },
+                    );
                 }
 
                 let mut accounts_data_slice = Vec::new();
@@ -542,6 +1164,24 @@ impl Action {
                         accounts_data_slice,
                         ping,
                         from_slot: args.from_slot,
+                        ping_interval_ms: args.ping_interval_ms,
+                        envelope: Some(args.envelope),
+                        accounts_data_encoding: args
+                            .accounts_data_encoding
+                            .map(|encoding| AccountsDataEncoding::from(encoding) as i32),
+                        stats_interval_ms: args.filter_stats_interval_ms,
+                        max_updates_per_second: args.max_updates_per_second,
+                        program_events,
+                        channel_capacity: args.channel_capacity,
+                        exclude_votes: Some(args.exclude_votes),
+                        accounts_data_segments: Some(args.accounts_data_segments),
+                        resume_token: args
+                            .resume_token
+                            .as_deref()
+                            .map(hex::decode)
+                            .transpose()
+                            .context("invalid resume_token")?,
+                        encoding: args.json_encoding.then_some(UpdatesEncoding::Json as i32),
                     },
                     args.resub.unwrap_or(0),
                     args.stats,
@@ -562,6 +1202,9 @@ async fn main() -> anyhow::Result<()> {
     env_logger::init();
 
     let args = Args::parse();
+    if let Action::Replay(replay_args) = &args.action {
+        return geyser_replay(replay_args, args.max_decoding_message_size).await;
+    }
     let zero_attempts = Arc::new(Mutex::new(true));
 
     // The default exponential backoff strategy intervals:
@@ -591,7 +1234,7 @@ async fn main() -> anyhow::Result<()> {
                     .map_err(anyhow::Error::new)
                     .map(|response| info!("response: {response:?}")),
                 Action::HealthWatch => geyser_health_watch(client).await,
-                Action::Subscribe(_) => {
+                Action::Subscribe(subscribe_args) => {
                     let (request, resub, stats, verify_encoding) = args
                         .action
                         .get_subscribe_request(commitment)
@@ -600,9 +1243,43 @@ async fn main() -> anyhow::Result<()> {
                         .ok_or(backoff::Error::Permanent(anyhow::anyhow!(
                             "expect subscribe action"
                         )))?;
+                    let expect_filters = ExpectFilters::new(subscribe_args);
+                    let sequence = SequenceVerifier::new(subscribe_args);
+                    let out_file = match &subscribe_args.out_file {
+                        Some(path) => Some(
+                            JsonLinesWriter::open(path.clone(), subscribe_args.out_file_max_bytes)
+                                .await
+                                .map_err(backoff::Error::Permanent)?,
+                        ),
+                        None => None,
+                    };
+                    let raw_out = match &subscribe_args.raw_out {
+                        Some(path) => Some(
+                            RawFramesWriter::open(path)
+                                .await
+                                .map_err(backoff::Error::Permanent)?,
+                        ),
+                        None => None,
+                    };
 
-                    geyser_subscribe(client, request, resub, stats, verify_encoding).await
+                    geyser_subscribe(
+                        client,
+                        request,
+                        resub,
+                        stats,
+                        verify_encoding,
+                        expect_filters,
+                        sequence,
+                        out_file,
+                        raw_out,
+                    )
+                    .await
                 }
+                Action::Snapshot(snapshot_args) => {
+                    let request = snapshot_args.get_subscribe_request(commitment);
+                    geyser_snapshot(client, request, snapshot_args).await
+                }
+                Action::Replay(_) => unreachable!("replay does not connect to the server"),
                 Action::Ping { count } => client
                     .ping(*count)
                     .await
@@ -633,6 +1310,39 @@ async fn main() -> anyhow::Result<()> {
                     .await
                     .map_err(anyhow::Error::new)
                     .map(|response| info!("response: {response:?}")),
+                Action::GetFilterLimits => client
+                    .get_filter_limits()
+                    .await
+                    .map_err(anyhow::Error::new)
+                    .map(|response| info!("response: {response:?}")),
+                Action::GetBlockMetaRange { from_slot, to_slot } => client
+                    .get_block_meta_range(*from_slot, *to_slot)
+                    .await
+                    .map_err(anyhow::Error::new)
+                    .map(|response| info!("response: {response:?}")),
+                Action::GetTransactionAccounts { signature } => client
+                    .get_transaction_accounts(signature.clone())
+                    .await
+                    .map_err(anyhow::Error::new)
+                    .map(|response| info!("response: {response:?}")),
+                Action::GetAccountData {
+                    pubkey,
+                    slot,
+                    write_version,
+                } => match bs58::decode(pubkey).into_vec() {
+                    Ok(pubkey) => client
+                        .get_account_data(pubkey, *slot, *write_version)
+                        .await
+                        .map_err(anyhow::Error::new)
+                        .map(|response| {
+                            info!(
+                                "response: {} bytes, data: {}",
+                                response.data.len(),
+                                bs58::encode(&response.data).into_string()
+                            )
+                        }),
+                    Err(error) => Err(anyhow::Error::new(error)),
+                },
             }
             .map_err(backoff::Error::transient)?;
 
@@ -654,12 +1364,290 @@ async fn geyser_health_watch(mut client: GeyserGrpcClient<impl Interceptor>) ->
     Ok(())
 }
 
+async fn geyser_snapshot(
+    mut client: GeyserGrpcClient<impl Interceptor>,
+    request: SubscribeRequest,
+    args: &ActionSnapshot,
+) -> anyhow::Result<()> {
+    let (_subscribe_tx, mut stream) = client.subscribe_with_request(Some(request)).await?;
+    info!("stream opened");
+
+    let mut accounts = vec![];
+    let slot = loop {
+        let Some(message) = stream.next().await else {
+            anyhow::bail!("stream closed before snapshot completed");
+        };
+        match message?.update_oneof {
+            // snapshot accounts are sent with `is_startup`, the first live update ends it
+            // if the server does not send the completion message
+            Some(UpdateOneof::Account(msg)) if msg.is_startup => accounts.push(msg),
+            Some(UpdateOneof::Account(msg)) => {
+                info!("snapshot completed by first live update");
+                break accounts
+                    .iter()
+                    .map(|msg| msg.slot)
+                    .max()
+                    .unwrap_or(msg.slot);
+            }
+            Some(UpdateOneof::SnapshotComplete(msg)) => {
+                if msg.accounts != accounts.len() as u64 {
+                    anyhow::bail!(
+                        "snapshot accounts mismatch, expected {} received {}",
+                        msg.accounts,
+                        accounts.len()
+                    );
+                }
+                break msg.slot;
+            }
+            Some(UpdateOneof::ServerShutdown(_)) => {
+                anyhow::bail!("server is shutting down before snapshot completed");
+            }
+            _ => {}
+        }
+    };
+    info!(
+        "snapshot received: {} accounts, slot {slot}",
+        format_thousands(accounts.len() as u64)
+    );
+
+    let data = match args.format {
+        ArgsSnapshotFormat::Json => {
+            let mut values = Vec::with_capacity(accounts.len());
+            for msg in accounts {
+                let account = msg
+                    .account
+                    .ok_or(anyhow::anyhow!("no account in the message"))?;
+                let mut value =
+                    json::create_pretty_account(account).map_err(|error| anyhow::anyhow!(error))?;
+                value["slot"] = json!(msg.slot);
+                values.push(value);
+            }
+            serde_json::to_vec(&json!({ "slot": slot, "accounts": values }))?
+        }
+        ArgsSnapshotFormat::Binary => {
+            let mut data = vec![];
+            for msg in accounts {
+                msg.encode_length_delimited(&mut data)?;
+            }
+            data
+        }
+    };
+    fs::write(&args.output, data)
+        .await
+        .context("failed to save snapshot")?;
+    info!("snapshot saved to {:?}", args.output);
+
+    Ok(())
+}
+
+async fn geyser_replay(
+    args: &ActionReplay,
+    max_decoding_message_size: usize,
+) -> anyhow::Result<()> {
+    if args.speed.is_some_and(|speed| speed <= 0.0) {
+        anyhow::bail!("speed should be greater than 0");
+    }
+    let replayed = if args.raw {
+        replay_raw_file(
+            &args.path,
+            args.speed,
+            max_decoding_message_size,
+            |update| info!("{update:?}"),
+        )
+        .await?
+    } else {
+        replay_file(&args.path, args.speed, |update| {
+            info!(
+                "{} ({}) at {}: {}",
+                update.kind,
+                update.filters.join(","),
+                update
+                    .created_at
+                    .to_rfc3339_opts(SecondsFormat::Micros, true),
+                update.update
+            );
+        })
+        .await?
+    };
+    info!("replayed {replayed} updates");
+    Ok(())
+}
+
+/// Update written by `subscribe --out-file`
+#[derive(Debug, PartialEq)]
+struct ReplayUpdate {
+    kind: String,
+    filters: Vec<String>,
+    created_at: DateTime<Utc>,
+    update: Value,
+}
+
+impl ReplayUpdate {
+    /// Unknown fields are ignored
+    fn parse(line: &str) -> anyhow::Result<Self> {
+        let mut value: Value = serde_json::from_str(line).context("invalid json")?;
+        let kind = value["type"]
+            .as_str()
+            .ok_or(anyhow::anyhow!("no `type` in the line"))?
+            .to_owned();
+        let filters = match value.get_mut("filters") {
+            Some(filters) => serde_json::from_value(filters.take()).context("invalid `filters`")?,
+            None => vec![],
+        };
+        let created_at = value["createdAt"]
+            .as_str()
+            .ok_or(anyhow::anyhow!("no `createdAt` in the line"))?;
+        let created_at = DateTime::parse_from_rfc3339(created_at)
+            .context("invalid `createdAt`")?
+            .with_timezone(&Utc);
+        let update = value["update"].take();
+        anyhow::ensure!(!update.is_null(), "no `update` in the line");
+        Ok(Self {
+            kind,
+            filters,
+            created_at,
+            update,
+        })
+    }
+}
+
+/// Returns the number of replayed updates, malformed lines are skipped with a warning
+async fn replay_file(
+    path: &Path,
+    speed: Option<f64>,
+    mut on_update: impl FnMut(ReplayUpdate),
+) -> anyhow::Result<usize> {
+    let file = fs::File::open(path)
+        .await
+        .with_context(|| format!("failed to open {path:?}"))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let mut replayed = 0;
+    let mut line_number = 0;
+    let mut pacer = ReplayPacer::new(speed);
+    while let Some(line) = lines.next_line().await? {
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let update = match ReplayUpdate::parse(&line) {
+            Ok(update) => update,
+            Err(error) => {
+                warn!("skip malformed line {line_number}: {error:#}");
+                continue;
+            }
+        };
+
+        pacer.wait(update.created_at).await;
+        on_update(update);
+        replayed += 1;
+    }
+    Ok(replayed)
+}
+
+/// Returns the number of replayed updates, a malformed frame stops the replay with an error
+async fn replay_raw_file(
+    path: &Path,
+    speed: Option<f64>,
+    frame_length_max: usize,
+    mut on_update: impl FnMut(SubscribeUpdate),
+) -> anyhow::Result<usize> {
+    let mut reader = RawFramesReader::open(path, frame_length_max).await?;
+
+    let mut replayed = 0;
+    let mut pacer = ReplayPacer::new(speed);
+    while let Some(update) = reader
+        .read()
+        .await
+        .with_context(|| format!("failed to read frame {}", replayed + 1))?
+    {
+        if let Some(created_at) = update.created_at {
+            let created_at: SystemTime = created_at
+                .try_into()
+                .context("failed to parse created_at")?;
+            pacer.wait(created_at.into()).await;
+        }
+        on_update(update);
+        replayed += 1;
+    }
+    Ok(replayed)
+}
+
+/// Keeps original timing of replayed updates scaled by `speed`
+struct ReplayPacer {
+    speed: Option<f64>,
+    started: Option<(Instant, DateTime<Utc>)>,
+}
+
+impl ReplayPacer {
+    const fn new(speed: Option<f64>) -> Self {
+        Self {
+            speed,
+            started: None,
+        }
+    }
+
+    async fn wait(&mut self, created_at: DateTime<Utc>) {
+        if let Some(speed) = self.speed {
+            let (started_at, first_created_at) =
+                *self.started.get_or_insert((Instant::now(), created_at));
+            // updates are not reordered, older ones are replayed without delay
+            let offset = (created_at - first_created_at).to_std().unwrap_or_default();
+            let deadline = started_at + offset.div_f64(speed);
+            sleep(deadline.saturating_duration_since(Instant::now())).await;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn geyser_subscribe(
     mut client: GeyserGrpcClient<impl Interceptor>,
     request: SubscribeRequest,
     resub: usize,
     stats: bool,
     verify_encoding: bool,
+    expect_filters: Option<ExpectFilters>,
+    sequence: Option<SequenceVerifier>,
+    out_file: Option<JsonLinesWriter>,
+    raw_out: Option<RawFramesWriter>,
+) -> anyhow::Result<()> {
+    // checksum is computed by the server over the whole raw data
+    let verify_data_checksum = request.accounts_data_slice.is_empty()
+        && request
+            .accounts
+            .values()
+            .all(|filter| filter.data_fields.is_empty() && filter.decompress_data.is_none());
+
+    let (subscribe_tx, stream) = client.subscribe_with_request(Some(request)).await?;
+    info!("stream opened");
+
+    geyser_subscribe_stream(
+        subscribe_tx,
+        stream,
+        resub,
+        stats,
+        verify_encoding,
+        verify_data_checksum,
+        expect_filters,
+        sequence,
+        out_file,
+        raw_out,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn geyser_subscribe_stream(
+    mut subscribe_tx: impl Sink<SubscribeRequest, Error = mpsc::SendError> + Unpin,
+    mut stream: impl Stream<Item = Result<SubscribeUpdate, Status>> + Unpin,
+    resub: usize,
+    stats: bool,
+    verify_encoding: bool,
+    verify_data_checksum: bool,
+    mut expect_filters: Option<ExpectFilters>,
+    mut sequence: Option<SequenceVerifier>,
+    mut out_file: Option<JsonLinesWriter>,
+    mut raw_out: Option<RawFramesWriter>,
 ) -> anyhow::Result<()> {
     let pb_multi = MultiProgress::new();
     let mut pb_accounts_c = 0;
@@ -678,23 +1666,78 @@ async fn geyser_subscribe(
     let pb_blocks = crate_progress_bar(&pb_multi, ProgressBarTpl::Msg("blocks"))?;
     let mut pb_pp_c = 0;
     let pb_pp = crate_progress_bar(&pb_multi, ProgressBarTpl::Msg("ping/pong"))?;
+    let mut pb_envelopes_c = 0;
+    let pb_envelopes = crate_progress_bar(&pb_multi, ProgressBarTpl::Msg("envelopes"))?;
+    let mut pb_filter_stats_c = 0;
+    let pb_filter_stats = crate_progress_bar(&pb_multi, ProgressBarTpl::Msg("filter stats"))?;
     let mut pb_total_c = 0;
     let pb_total = crate_progress_bar(&pb_multi, ProgressBarTpl::Total)?;
     let mut pb_verify_c = verify_encoding.then_some((0, 0));
     let pb_verify = crate_progress_bar(&pb_multi, ProgressBarTpl::Verify)?;
 
-    let (mut subscribe_tx, mut stream) = client.subscribe_with_request(Some(request)).await?;
-
-    info!("stream opened");
     let mut counter = 0;
-    while let Some(message) = stream.next().await {
+    loop {
+        let message = match &expect_filters {
+            Some(expect) => match timeout_at(expect.deadline.into(), stream.next()).await {
+                Ok(message) => message,
+                Err(_elapsed) => {
+                    error!(
+                        "expected filters did not match any update: {:?}",
+                        expect.names
+                    );
+                    if let Some(out_file) = &mut out_file {
+                        out_file.flush().await?;
+                    }
+                    if let Some(raw_out) = &mut raw_out {
+                        raw_out.flush().await?;
+                    }
+                    std::process::exit(1);
+                }
+            },
+            None => stream.next().await,
+        };
+        let Some(message) = message else {
+            break;
+        };
+
         match message {
             Ok(msg) => {
+                if let (Some(sequence), Some(seq)) = (&mut sequence, msg.seq) {
+                    sequence.update(seq);
+                }
+                if let Some(resume_token) = &msg.resume_token {
+                    info!("resume token: {}", hex::encode(resume_token));
+                }
+
+                if let Some(expect) = &mut expect_filters {
+                    expect.names.retain(|name| !msg.filters.contains(name));
+                    if expect.names.is_empty() {
+                        info!("all expected filters matched");
+                        expect_filters = None;
+                    }
+                }
+
+                if let Some(raw_out) = &mut raw_out {
+                    raw_out.write(&msg).await?;
+                    if matches!(msg.update_oneof, Some(UpdateOneof::Ping(_))) {
+                        subscribe_tx
+                            .send(SubscribeRequest {
+                                ping: Some(SubscribeRequestPing { id: 1 }),
+                                ..Default::default()
+                            })
+                            .await?;
+                        raw_out.maybe_flush().await?;
+                    }
+                    continue;
+                }
+
                 if stats {
                     let encoded_len = msg.encoded_len() as u64;
                     let (pb_c, pb) = match msg.update_oneof {
                         Some(UpdateOneof::Account(_)) => (&mut pb_accounts_c, &pb_accounts),
                         Some(UpdateOneof::Slot(_)) => (&mut pb_slots_c, &pb_slots),
+                        Some(UpdateOneof::SlotGap(_)) => (&mut pb_slots_c, &pb_slots),
+                        Some(UpdateOneof::SlotDeltaBatch(_)) => (&mut pb_slots_c, &pb_slots),
                         Some(UpdateOneof::Transaction(_)) => (&mut pb_txs_c, &pb_txs),
                         Some(UpdateOneof::TransactionStatus(_)) => (&mut pb_txs_st_c, &pb_txs_st),
                         Some(UpdateOneof::Entry(_)) => (&mut pb_entries_c, &pb_entries),
@@ -702,6 +1745,17 @@ async fn geyser_subscribe(
                         Some(UpdateOneof::Block(_)) => (&mut pb_blocks_c, &pb_blocks),
                         Some(UpdateOneof::Ping(_)) => (&mut pb_pp_c, &pb_pp),
                         Some(UpdateOneof::Pong(_)) => (&mut pb_pp_c, &pb_pp),
+                        Some(UpdateOneof::Envelope(_)) => (&mut pb_envelopes_c, &pb_envelopes),
+                        Some(UpdateOneof::Json(_)) => (&mut pb_envelopes_c, &pb_envelopes),
+                        Some(UpdateOneof::FilterStats(_)) => {
+                            (&mut pb_filter_stats_c, &pb_filter_stats)
+                        }
+                        Some(UpdateOneof::SnapshotComplete(_)) => {
+                            (&mut pb_accounts_c, &pb_accounts)
+                        }
+                        Some(UpdateOneof::ServerShutdown(_)) => (&mut pb_pp_c, &pb_pp),
+                        Some(UpdateOneof::ProgramEvent(_)) => (&mut pb_txs_c, &pb_txs),
+                        Some(UpdateOneof::SubscribeAccepted(_)) => (&mut pb_pp_c, &pb_pp),
                         None => {
                             pb_multi.println("update not found in the message")?;
                             break;
@@ -767,105 +1821,6 @@ async fn geyser_subscribe(
                     .try_into()
                     .context("failed to parse created_at")?;
                 match msg.update_oneof {
-                    Some(UpdateOneof::Account(msg)) => {
-                        let account = msg
-                            .account
-                            .ok_or(anyhow::anyhow!("no account in the message"))?;
-                        let mut value = create_pretty_account(account)?;
-                        value["isStartup"] = json!(msg.is_startup);
-                        value["slot"] = json!(msg.slot);
-                        print_update("account", created_at, &filters, value);
-                    }
-                    Some(UpdateOneof::Slot(msg)) => {
-                        let status = CommitmentLevel::try_from(msg.status)
-                            .context("failed to decode commitment")?;
-                        print_update(
-                            "slot",
-                            created_at,
-                            &filters,
-                            json!({
-                                "slot": msg.slot,
-                                "parent": msg.parent,
-                                "status": status.as_str_name(),
-                                "deadError": msg.dead_error,
-                            }),
-                        );
-                    }
-                    Some(UpdateOneof::Transaction(msg)) => {
-                        let tx = msg
-                            .transaction
-                            .ok_or(anyhow::anyhow!("no transaction in the message"))?;
-                        let mut value = create_pretty_transaction(tx)?;
-                        value["slot"] = json!(msg.slot);
-                        print_update("transaction", created_at, &filters, value);
-                    }
-                    Some(UpdateOneof::TransactionStatus(msg)) => {
-                        print_update(
-                            "transactionStatus",
-                            created_at,
-                            &filters,
-                            json!({
-                                "slot": msg.slot,
-                                "signature": Signature::try_from(msg.signature.as_slice()).context("invalid signature")?.to_string(),
-                                "isVote": msg.is_vote,
-                                "index": msg.index,
-                                "err": convert_from::create_tx_error(msg.err.as_ref())
-                                    .map_err(|error| anyhow::anyhow!(error))
-                                    .context("invalid error")?,
-                            }),
-                        );
-                    }
-                    Some(UpdateOneof::Entry(msg)) => {
-                        print_update("entry", created_at, &filters, create_pretty_entry(msg)?);
-                    }
-                    Some(UpdateOneof::BlockMeta(msg)) => {
-                        print_update(
-                            "blockmeta",
-                            created_at,
-                            &filters,
-                            json!({
-                                "slot": msg.slot,
-                                "blockhash": msg.blockhash,
-                                "rewards": if let Some(rewards) = msg.rewards {
-                                    Some(convert_from::create_rewards_obj(rewards).map_err(|error| anyhow::anyhow!(error))?)
-                                } else {
-                                    None
-                                },
-                                "blockTime": msg.block_time.map(|obj| obj.timestamp),
-                                "blockHeight": msg.block_height.map(|obj| obj.block_height),
-                                "parentSlot": msg.parent_slot,
-                                "parentBlockhash": msg.parent_blockhash,
-                                "executedTransactionCount": msg.executed_transaction_count,
-                                "entriesCount": msg.entries_count,
-                            }),
-                        );
-                    }
-                    Some(UpdateOneof::Block(msg)) => {
-                        print_update(
-                            "block",
-                            created_at,
-                            &filters,
-                            json!({
-                                "slot": msg.slot,
-                                "blockhash": msg.blockhash,
-                                "rewards": if let Some(rewards) = msg.rewards {
-                                    Some(convert_from::create_rewards_obj(rewards).map_err(|error| anyhow::anyhow!(error))?)
-                                } else {
-                                    None
-                                },
-                                "blockTime": msg.block_time.map(|obj| obj.timestamp),
-                                "blockHeight": msg.block_height.map(|obj| obj.block_height),
-                                "parentSlot": msg.parent_slot,
-                                "parentBlockhash": msg.parent_blockhash,
-                                "executedTransactionCount": msg.executed_transaction_count,
-                                "transactions": msg.transactions.into_iter().map(create_pretty_transaction).collect::<Result<Value, _>>()?,
-                                "updatedAccountCount": msg.updated_account_count,
-                                "accounts": msg.accounts.into_iter().map(create_pretty_account).collect::<Result<Value, _>>()?,
-                                "entriesCount": msg.entries_count,
-                                "entries": msg.entries.into_iter().map(create_pretty_entry).collect::<Result<Value, _>>()?,
-                            }),
-                        );
-                    }
                     Some(UpdateOneof::Ping(_)) => {
                         // This is necessary to keep load balancers that expect client pings alive. If your load balancer doesn't
                         // require periodic client pings then this is unnecessary
@@ -875,8 +1830,42 @@ async fn geyser_subscribe(
                                 ..Default::default()
                             })
                             .await?;
+                        // pings are sent by the server every 15s, flush buffered updates on idle stream
+                        if let Some(out_file) = &mut out_file {
+                            out_file.maybe_flush().await?;
+                        }
+                    }
+                    Some(UpdateOneof::Json(msg)) => {
+                        let value = serde_json::from_str(&msg.value)
+                            .context("failed to parse JSON update")?;
+                        print_update(&mut out_file, &msg.kind, created_at, &filters, value).await?;
+                    }
+                    Some(update) => {
+                        if let UpdateOneof::Account(msg) = &update {
+                            if let Some(account) = &msg.account {
+                                if let Some(checksum) = account.data_checksum {
+                                    if verify_data_checksum
+                                        && account.data_encoded.is_none()
+                                        && msg.data_diff.is_none()
+                                        && msg.data_ref.is_none()
+                                        && crc32fast::hash(&account.data) != checksum
+                                    {
+                                        error!(
+                                            "data checksum mismatch for account {} in slot {}",
+                                            bs58::encode(&account.pubkey).into_string(),
+                                            msg.slot
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                        if let Some((kind, value)) = json::create_pretty_update(update)
+                            .map_err(|error| anyhow::anyhow!(error))
+                            .context("failed to render update")?
+                        {
+                            print_update(&mut out_file, kind, created_at, &filters, value).await?;
+                        }
                     }
-                    Some(UpdateOneof::Pong(_)) => {}
                     None => {
                         error!("update not found in the message");
                         break;
@@ -908,12 +1897,42 @@ async fn geyser_subscribe(
                     accounts_data_slice: Vec::default(),
                     ping: None,
                     from_slot: None,
+                    ping_interval_ms: None,
+                    envelope: None,
+                    accounts_data_encoding: None,
+                    stats_interval_ms: None,
+                    max_updates_per_second: None,
+                    program_events: HashMap::new(),
+                    channel_capacity: None,
+                    exclude_votes: None,
+                    accounts_data_segments: None,
+                    resume_token: None,
+                    encoding: None,
                 })
                 .await
                 .map_err(GeyserGrpcClientError::SubscribeSendError)?;
         }
     }
+    if let Some(out_file) = &mut out_file {
+        out_file.flush().await?;
+    }
+    if let Some(raw_out) = &mut raw_out {
+        raw_out.flush().await?;
+    }
     info!("stream closed");
+    if let Some(sequence) = sequence {
+        match sequence.last {
+            None => warn!("no sequence numbers received, server support is required"),
+            Some(_) if !sequence.is_valid() => {
+                error!(
+                    "sequence verification failed: {} updates lost, {} regressions",
+                    sequence.lost, sequence.regressions
+                );
+                std::process::exit(1);
+            }
+            Some(last) => info!("sequence verified up to {last}"),
+        }
+    }
     Ok(())
 }
 
@@ -956,51 +1975,466 @@ fn format_thousands(value: u64) -> String {
         .join(",")
 }
 
-fn create_pretty_account(account: SubscribeUpdateAccountInfo) -> anyhow::Result<Value> {
-    Ok(json!({
-        "pubkey": Pubkey::try_from(account.pubkey).map_err(|_| anyhow::anyhow!("invalid account pubkey"))?.to_string(),
-        "lamports": account.lamports,
-        "owner": Pubkey::try_from(account.owner).map_err(|_| anyhow::anyhow!("invalid account owner"))?.to_string(),
-        "executable": account.executable,
-        "rentEpoch": account.rent_epoch,
-        "data": hex::encode(account.data),
-        "writeVersion": account.write_version,
-        "txnSignature": account.txn_signature.map(|sig| bs58::encode(sig).into_string()),
-    }))
-}
-
-fn create_pretty_transaction(tx: SubscribeUpdateTransactionInfo) -> anyhow::Result<Value> {
-    Ok(json!({
-        "signature": Signature::try_from(tx.signature.as_slice()).context("invalid signature")?.to_string(),
-        "isVote": tx.is_vote,
-        "tx": convert_from::create_tx_with_meta(tx)
-            .map_err(|error| anyhow::anyhow!(error))
-            .context("invalid tx with meta")?
-            .encode(UiTransactionEncoding::Base64, Some(u8::MAX), true)
-            .context("failed to encode transaction")?,
-    }))
-}
-
-fn create_pretty_entry(msg: SubscribeUpdateEntry) -> anyhow::Result<Value> {
-    Ok(json!({
-        "slot": msg.slot,
-        "index": msg.index,
-        "numHashes": msg.num_hashes,
-        "hash": Hash::new_from_array(<[u8; 32]>::try_from(msg.hash.as_slice()).context("invalid entry hash")?).to_string(),
-        "executedTransactionCount": msg.executed_transaction_count,
-        "startingTransactionIndex": msg.starting_transaction_index,
-    }))
-}
-
-fn print_update(kind: &str, created_at: SystemTime, filters: &[String], value: Value) {
+/// Parse `error[:instruction_index[:instruction_error]]`, e.g. `InstructionError::Custom`
+fn parse_error_match(value: &str) -> anyhow::Result<SubscribeRequestFilterTransactionsError> {
+    let mut parts = value.split(':');
+    let error = parts.next().unwrap_or_default().to_owned();
+    let instruction_index = parts
+        .next()
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse())
+        .transpose()
+        .context("invalid instruction index in error match")?;
+    let instruction_error = parts
+        .next()
+        .filter(|value| !value.is_empty())
+        .map(ToOwned::to_owned);
+    Ok(SubscribeRequestFilterTransactionsError {
+        error,
+        instruction_index,
+        instruction_error,
+    })
+}
+
+async fn print_update(
+    out_file: &mut Option<JsonLinesWriter>,
+    kind: &str,
+    created_at: SystemTime,
+    filters: &[String],
+    value: Value,
+) -> anyhow::Result<()> {
+    if let Some(out_file) = out_file {
+        let created_at =
+            DateTime::<Utc>::from(created_at).to_rfc3339_opts(SecondsFormat::Micros, true);
+        return out_file
+            .write(&json!({
+                "type": kind,
+                "filters": filters,
+                "createdAt": created_at,
+                "update": value,
+            }))
+            .await;
+    }
+
     let unix_since = created_at
         .duration_since(UNIX_EPOCH)
         .expect("valid system time");
+    // clocks of the server and the client can be out of sync
+    let latency = SystemTime::now()
+        .duration_since(created_at)
+        .unwrap_or_default();
     info!(
-        "{kind} ({}) at {}.{:0>6}: {}",
+        "{kind} ({}) at {}.{:0>6} (latency {:.3}ms): {}",
         filters.join(","),
         unix_since.as_secs(),
         unix_since.subsec_micros(),
+        latency.as_secs_f64() * 1_000.0,
         serde_json::to_string(&value).expect("json serialization failed")
     );
+    Ok(())
+}
+
+/// Appends updates to the file as JSON lines, the file is rotated once `max_bytes` is reached
+struct JsonLinesWriter {
+    path: PathBuf,
+    max_bytes: Option<u64>,
+    file: BufWriter<fs::File>,
+    written: u64,
+    flushed_at: Instant,
+}
+
+impl JsonLinesWriter {
+    const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+    async fn open(path: PathBuf, max_bytes: Option<u64>) -> anyhow::Result<Self> {
+        let file = Self::open_file(&path).await?;
+        let written = file.metadata().await?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            file: BufWriter::new(file),
+            written,
+            flushed_at: Instant::now(),
+        })
+    }
+
+    async fn open_file(path: &Path) -> anyhow::Result<fs::File> {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("failed to open out file {path:?}"))
+    }
+
+    async fn write(&mut self, value: &Value) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(value)?;
+        line.push(b'\n');
+        if let Some(max_bytes) = self.max_bytes {
+            if self.written > 0 && self.written + line.len() as u64 > max_bytes {
+                self.rotate().await?;
+            }
+        }
+        self.file
+            .write_all(&line)
+            .await
+            .context("failed to write out file")?;
+        self.written += line.len() as u64;
+        self.maybe_flush().await
+    }
+
+    async fn maybe_flush(&mut self) -> anyhow::Result<()> {
+        if self.flushed_at.elapsed() >= Self::FLUSH_INTERVAL {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        self.file
+            .flush()
+            .await
+            .context("failed to flush out file")?;
+        self.flushed_at = Instant::now();
+        Ok(())
+    }
+
+    /// Current file is renamed with unix time suffix, writes continue to a new file
+    async fn rotate(&mut self) -> anyhow::Result<()> {
+        self.flush().await?;
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(format!(
+            ".{}",
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos()
+        ));
+        fs::rename(&self.path, &rotated)
+            .await
+            .context("failed to rotate out file")?;
+        self.file = BufWriter::new(Self::open_file(&self.path).await?);
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// Appends updates to the file as length-delimited protobuf frames, as is
+struct RawFramesWriter {
+    file: BufWriter<fs::File>,
+    flushed_at: Instant,
+}
+
+impl RawFramesWriter {
+    const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+    async fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("failed to open raw out file {path:?}"))?;
+        Ok(Self {
+            file: BufWriter::new(file),
+            flushed_at: Instant::now(),
+        })
+    }
+
+    async fn write(&mut self, update: &SubscribeUpdate) -> anyhow::Result<()> {
+        let frame = update.encode_length_delimited_to_vec();
+        self.file
+            .write_all(&frame)
+            .await
+            .context("failed to write raw out file")?;
+        self.maybe_flush().await
+    }
+
+    async fn maybe_flush(&mut self) -> anyhow::Result<()> {
+        if self.flushed_at.elapsed() >= Self::FLUSH_INTERVAL {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        self.file
+            .flush()
+            .await
+            .context("failed to flush raw out file")?;
+        self.flushed_at = Instant::now();
+        Ok(())
+    }
+}
+
+/// Reads frames written by `RawFramesWriter`
+struct RawFramesReader {
+    file: BufReader<fs::File>,
+    // frames are not allocated above the limit
+    length_max: usize,
+}
+
+impl RawFramesReader {
+    /// Max length of varint in bytes
+    const LENGTH_BYTES_MAX: usize = 10;
+
+    async fn open(path: &Path, length_max: usize) -> anyhow::Result<Self> {
+        let file = fs::File::open(path)
+            .await
+            .with_context(|| format!("failed to open {path:?}"))?;
+        Ok(Self {
+            file: BufReader::new(file),
+            length_max,
+        })
+    }
+
+    /// Returns `None` at the end of the file, truncated frame or frame longer than
+    /// `length_max` is an error
+    async fn read(&mut self) -> anyhow::Result<Option<SubscribeUpdate>> {
+        let mut length = 0u64;
+        for index in 0..Self::LENGTH_BYTES_MAX {
+            let byte = match self.file.read_u8().await {
+                Ok(byte) => byte,
+                Err(error) if index == 0 && error.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(None)
+                }
+                Err(error) => return Err(error).context("failed to read frame length"),
+            };
+            length |= u64::from(byte & 0x7f) << (7 * index);
+            if byte & 0x80 == 0 {
+                let length = usize::try_from(length)?;
+                anyhow::ensure!(
+                    length <= self.length_max,
+                    "frame length {length} exceeds max {}",
+                    self.length_max
+                );
+                let mut frame = vec![0; length];
+                self.file
+                    .read_exact(&mut frame)
+                    .await
+                    .context("failed to read frame")?;
+                return SubscribeUpdate::decode(frame.as_slice())
+                    .map(Some)
+                    .context("failed to decode frame");
+            }
+        }
+        anyhow::bail!("invalid frame length")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::pubkey::Pubkey,
+        yellowstone_grpc_proto::{
+            prelude::{SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdateSlot},
+            prost_types::Timestamp,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_subscribe_out_file() -> anyhow::Result<()> {
+        let dir = env::temp_dir().join(format!("grpc-client-out-file-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir).await;
+        fs::create_dir_all(&dir).await?;
+        let path = dir.join("updates.jsonl");
+
+        let create_update = |update_oneof| {
+            Ok::<_, Status>(SubscribeUpdate {
+                filters: vec!["client".to_owned()],
+                created_at: Some(Timestamp::from(SystemTime::now())),
+                update_oneof: Some(update_oneof),
+                seq: None,
+                resume_token: None,
+                slot_seq: None,
+            })
+        };
+        let create_slot = |slot| {
+            create_update(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                ..Default::default()
+            }))
+        };
+        let stream = futures::stream::iter(vec![
+            create_slot(1),
+            create_update(UpdateOneof::Ping(Default::default())),
+            create_slot(2),
+        ]);
+        let (subscribe_tx, mut subscribe_rx) = mpsc::unbounded();
+
+        // every update after the first one rotates the file
+        let out_file = JsonLinesWriter::open(path.clone(), Some(1)).await?;
+        geyser_subscribe_stream(
+            subscribe_tx,
+            stream,
+            0,
+            false,
+            false,
+            false,
+            None,
+            None,
+            Some(out_file),
+            None,
+        )
+        .await?;
+
+        // ping is answered while updates are written
+        let request = subscribe_rx.next().await.expect("ping request");
+        assert_eq!(request.ping, Some(SubscribeRequestPing { id: 1 }));
+
+        let mut slots = vec![];
+        let mut files = fs::read_dir(&dir).await?;
+        while let Some(entry) = files.next_entry().await? {
+            let data = fs::read_to_string(entry.path()).await?;
+            assert!(data.ends_with('\n'));
+            for line in data.lines() {
+                let value: Value = serde_json::from_str(line)?;
+                assert_eq!(value["type"], "slot");
+                assert_eq!(value["filters"], json!(["client"]));
+                slots.push(value["update"]["slot"].as_u64().expect("slot"));
+            }
+        }
+        slots.sort_unstable();
+        assert_eq!(slots, [1, 2]);
+        assert!(fs::metadata(&path).await?.len() > 0);
+
+        fs::remove_dir_all(&dir).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_raw_out() -> anyhow::Result<()> {
+        let path = env::temp_dir().join(format!("grpc-client-raw-out-{}", std::process::id()));
+        let _ = fs::remove_file(&path).await;
+
+        let created_at = SystemTime::now();
+        let updates = [
+            UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot: 1,
+                ..Default::default()
+            }),
+            UpdateOneof::Ping(Default::default()),
+            UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: Pubkey::new_unique().to_bytes().to_vec(),
+                    data: vec![0, 1, 2, 255],
+                    write_version: 3,
+                    ..Default::default()
+                }),
+                slot: 2,
+                ..Default::default()
+            }),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(seq, update_oneof)| SubscribeUpdate {
+            filters: vec!["client".to_owned()],
+            created_at: Some(Timestamp::from(
+                created_at + Duration::from_millis(seq as u64),
+            )),
+            update_oneof: Some(update_oneof),
+            seq: Some(seq as u64),
+            resume_token: None,
+            slot_seq: None,
+        })
+        .collect::<Vec<_>>();
+        let stream = futures::stream::iter(updates.clone().into_iter().map(Ok::<_, Status>));
+        let (subscribe_tx, mut subscribe_rx) = mpsc::unbounded();
+
+        let raw_out = RawFramesWriter::open(&path).await?;
+        geyser_subscribe_stream(
+            subscribe_tx,
+            stream,
+            0,
+            false,
+            false,
+            false,
+            None,
+            None,
+            None,
+            Some(raw_out),
+        )
+        .await?;
+
+        // ping is answered while frames are written
+        let request = subscribe_rx.next().await.expect("ping request");
+        assert_eq!(request.ping, Some(SubscribeRequestPing { id: 1 }));
+
+        // frames are read back into equal updates
+        let mut replayed = vec![];
+        let count =
+            replay_raw_file(&path, None, usize::MAX, |update| replayed.push(update)).await?;
+        assert_eq!(count, updates.len());
+        assert_eq!(replayed, updates);
+
+        // truncated frame is an error
+        let data = fs::read(&path).await?;
+        fs::write(&path, &data[..data.len() - 1]).await?;
+        let mut reader = RawFramesReader::open(&path, usize::MAX).await?;
+        for _ in 0..updates.len() - 1 {
+            assert!(reader.read().await?.is_some());
+        }
+        assert!(reader.read().await.is_err());
+
+        // frame longer than the limit is an error
+        let mut reader = RawFramesReader::open(&path, 1).await?;
+        assert!(reader.read().await.is_err());
+
+        fs::remove_file(&path).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequence_verifier() {
+        let mut sequence = SequenceVerifier::default();
+        for seq in [0, 1, 2] {
+            sequence.update(seq);
+        }
+        assert!(sequence.is_valid());
+
+        // gap counts every missed update
+        sequence.update(5);
+        assert_eq!(sequence.lost, 2);
+
+        // regression does not move the last seen number back
+        sequence.update(4);
+        sequence.update(6);
+        assert_eq!((sequence.lost, sequence.regressions), (2, 1));
+        assert_eq!(sequence.last, Some(6));
+        assert!(!sequence.is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_replay_file() -> anyhow::Result<()> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/replay.jsonl");
+
+        for (speed, min, max) in [
+            (None, Duration::ZERO, Duration::from_millis(100)),
+            (
+                Some(2.0),
+                Duration::from_millis(200),
+                Duration::from_millis(400),
+            ),
+        ] {
+            let mut updates = vec![];
+            let ts = Instant::now();
+            let replayed = replay_file(&path, speed, |update| updates.push(update)).await?;
+            let elapsed = ts.elapsed();
+            assert!(min <= elapsed && elapsed < max, "elapsed {elapsed:?}");
+
+            // invalid json and invalid `createdAt` are skipped, unknown fields are ignored
+            assert_eq!(replayed, 3);
+            assert_eq!(
+                updates
+                    .iter()
+                    .map(|update| (update.kind.as_str(), update.update["slot"].as_u64()))
+                    .collect::<Vec<_>>(),
+                [("slot", Some(1)), ("slot", Some(2)), ("blockmeta", Some(2))]
+            );
+            assert_eq!(updates[1].filters, ["client"]);
+            assert_eq!(
+                updates[2].created_at - updates[0].created_at,
+                chrono::Duration::milliseconds(400)
+            );
+        }
+        Ok(())
+    }
 }
@@ -48,7 +48,12 @@ async fn main() -> anyhow::Result<()> {
                     slots: maplit::hashmap! {
                         "".to_owned() => SubscribeRequestFilterSlots {
                             filter_by_commitment: Some(true),
-                            interslot_updates: Some(false)
+                            interslot_updates: Some(false),
+                            slot_gaps: None,
+                            tip_only: None,
+                            statuses: vec![],
+                            delta_batch_interval_ms: None,
+                            replay_finalized: None,
                         }
                     },
                     commitment: Some(CommitmentLevel::Processed as i32),
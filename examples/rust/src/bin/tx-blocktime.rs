@@ -104,14 +104,32 @@ async fn main() -> anyhow::Result<()> {
                 account_include: args.account_include,
                 account_exclude: args.account_exclude,
                 account_required: args.account_required,
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
             } },
             entry: HashMap::new(),
             blocks: HashMap::new(),
-            blocks_meta: hashmap! { "".to_owned() => SubscribeRequestFilterBlocksMeta {} },
+            blocks_meta: hashmap! { "".to_owned() => SubscribeRequestFilterBlocksMeta::default() },
             commitment: Some(commitment as i32),
             accounts_data_slice: vec![],
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         })
         .await?;
 
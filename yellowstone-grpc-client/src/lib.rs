@@ -16,9 +16,12 @@ use {
     },
     tonic_health::pb::{health_client::HealthClient, HealthCheckRequest, HealthCheckResponse},
     yellowstone_grpc_proto::prelude::{
-        geyser_client::GeyserClient, CommitmentLevel, GetBlockHeightRequest,
-        GetBlockHeightResponse, GetLatestBlockhashRequest, GetLatestBlockhashResponse,
-        GetSlotRequest, GetSlotResponse, GetVersionRequest, GetVersionResponse,
+        geyser_client::GeyserClient, CommitmentLevel, ExplainFilterRequest, ExplainFilterResponse,
+        GetAccountDataRequest, GetAccountDataResponse, GetBlockHeightRequest,
+        GetBlockHeightResponse, GetBlockMetaRangeRequest, GetBlockMetaRangeResponse,
+        GetFilterLimitsRequest, GetFilterLimitsResponse, GetLatestBlockhashRequest,
+        GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetTransactionAccountsRequest,
+        GetTransactionAccountsResponse, GetVersionRequest, GetVersionResponse,
         IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest, PongResponse,
         SubscribeRequest, SubscribeUpdate,
     },
@@ -195,6 +198,59 @@ impl<F: Interceptor> GeyserGrpcClient<F> {
         let response = self.geyser.get_version(request).await?;
         Ok(response.into_inner())
     }
+
+    pub async fn get_block_meta_range(
+        &mut self,
+        from_slot: u64,
+        to_slot: u64,
+    ) -> GeyserGrpcClientResult<GetBlockMetaRangeResponse> {
+        let request = tonic::Request::new(GetBlockMetaRangeRequest { from_slot, to_slot });
+        let response = self.geyser.get_block_meta_range(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_filter_limits(&mut self) -> GeyserGrpcClientResult<GetFilterLimitsResponse> {
+        let request = tonic::Request::new(GetFilterLimitsRequest {});
+        let response = self.geyser.get_filter_limits(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_transaction_accounts(
+        &mut self,
+        signature: String,
+    ) -> GeyserGrpcClientResult<GetTransactionAccountsResponse> {
+        let request = tonic::Request::new(GetTransactionAccountsRequest { signature });
+        let response = self.geyser.get_transaction_accounts(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_account_data(
+        &mut self,
+        pubkey: Vec<u8>,
+        slot: u64,
+        write_version: u64,
+    ) -> GeyserGrpcClientResult<GetAccountDataResponse> {
+        let request = tonic::Request::new(GetAccountDataRequest {
+            pubkey,
+            slot,
+            write_version,
+        });
+        let response = self.geyser.get_account_data(request).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn explain_filter(
+        &mut self,
+        request: SubscribeRequest,
+        update: SubscribeUpdate,
+    ) -> GeyserGrpcClientResult<ExplainFilterResponse> {
+        let request = tonic::Request::new(ExplainFilterRequest {
+            request: Some(request),
+            update: Some(update),
+        });
+        let response = self.geyser.explain_filter(request).await?;
+        Ok(response.into_inner())
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -3,7 +3,13 @@ use {
         GeyserPluginError, Result as PluginResult,
     },
     serde::{de, Deserialize, Deserializer},
-    std::{collections::HashSet, fs::read_to_string, net::SocketAddr, path::Path, time::Duration},
+    std::{
+        collections::{HashMap, HashSet},
+        fs::read_to_string,
+        net::SocketAddr,
+        path::Path,
+        time::Duration,
+    },
     tokio::sync::Semaphore,
     tonic::codec::CompressionEncoding,
     yellowstone_grpc_proto::plugin::filter::limits::FilterLimits,
@@ -157,12 +163,34 @@ pub struct ConfigGrpc {
         deserialize_with = "deserialize_usize_str"
     )]
     pub snapshot_client_channel_capacity: usize,
-    /// Capacity of the channel per connection
+    /// Max number of concurrent subscribe streams, not limited by default
+    #[serde(default)]
+    pub max_subscriptions: Option<usize>,
+    /// Max number of concurrent subscribe streams per `x-token` (or client certificate identity),
+    /// streams without token are limited only by `max_subscriptions`, not limited by default
+    #[serde(default)]
+    pub max_subscriptions_per_token: Option<usize>,
+    /// Capacity of the channel per connection, max accepted value of `channel_capacity`
+    /// in the request
     #[serde(
         default = "ConfigGrpc::channel_capacity_default",
         deserialize_with = "deserialize_usize_str"
     )]
     pub channel_capacity: usize,
+    /// Action on full connection channel: `disconnect` closes the stream with an error,
+    /// `drop_oldest` skips the oldest queued updates
+    #[serde(default)]
+    pub channel_full_policy: ConfigGrpcClientChannelFullPolicy,
+    /// Capacity of the queue between geyser callbacks and messages processing, unbounded by default
+    #[serde(
+        default = "ConfigGrpc::geyser_channel_capacity_default",
+        deserialize_with = "deserialize_usize_str"
+    )]
+    pub geyser_channel_capacity: usize,
+    /// Action on full geyser queue: `block` protects memory but slows down the validator,
+    /// `drop` protects the validator but loses messages
+    #[serde(default)]
+    pub geyser_channel_full_policy: ConfigGrpcChannelFullPolicy,
     /// Concurrency limit for unary requests
     #[serde(
         default = "ConfigGrpc::unary_concurrency_limit_default",
@@ -172,9 +200,60 @@ pub struct ConfigGrpc {
     /// Enable/disable unary methods
     #[serde(default)]
     pub unary_disabled: bool,
+    /// Max number of slots in `GetBlockMetaRange` request
+    #[serde(
+        default = "ConfigGrpc::default_block_meta_range_max_slots",
+        deserialize_with = "deserialize_usize_str"
+    )]
+    pub block_meta_range_max_slots: usize,
+    /// Max number of transaction signatures with account updates for `GetTransactionAccounts`,
+    /// oldest signatures are evicted first, `0` disables the cache (default)
+    #[serde(
+        default = "ConfigGrpc::default_transaction_accounts_cache_size",
+        deserialize_with = "deserialize_usize_str"
+    )]
+    pub transaction_accounts_cache_size: usize,
+    /// Max total size of account data kept for `GetTransactionAccounts`, oldest signatures are
+    /// evicted first
+    #[serde(
+        default = "ConfigGrpc::default_transaction_accounts_cache_bytes_max",
+        deserialize_with = "deserialize_usize_str"
+    )]
+    pub transaction_accounts_cache_bytes_max: usize,
+    /// Send accounts with big data without data, data is fetched with `GetAccountData`.
+    /// Requires unary methods, disabled by default
+    #[serde(default)]
+    pub account_data_out_of_band: Option<ConfigGrpcAccountDataOutOfBand>,
+    /// Keep the latest state of every account (startup accounts and writes of finalized slots,
+    /// writes of newer slots are kept by slot) in memory to serve `snapshot` in accounts filter,
+    /// memory is not limited and costs the size of all accounts data plus ~200 bytes per
+    /// account, disabled by default
+    #[serde(default)]
+    pub accounts_snapshot: bool,
+    /// Number of the latest finalized slots kept for `replay_finalized` in slots filter,
+    /// `0` disables the replay
+    #[serde(
+        default = "ConfigGrpc::default_slots_replay_finalized_max",
+        deserialize_with = "deserialize_usize_str"
+    )]
+    pub slots_replay_finalized_max: usize,
+    /// Allow `UPDATES_ENCODING_JSON` in subscribe requests, updates are rendered as JSON by the
+    /// server which costs several times more CPU than protobuf encoding, disabled by default
+    #[serde(default)]
+    pub json_encoding: bool,
+    /// Compute CRC32 of account data for `data_checksum` of account updates
+    #[serde(default)]
+    pub accounts_data_checksum: bool,
     /// Limits for possible filters
     #[serde(default, alias = "filters")]
     pub filter_limits: FilterLimits,
+    /// Named filter limits profiles, e.g. per API tier
+    #[serde(default)]
+    pub filter_limits_profiles: HashMap<String, FilterLimits>,
+    /// Profile from `filter_limits_profiles` by `x-token` (or client certificate identity),
+    /// `filter_limits` is used for unknown tokens
+    #[serde(default)]
+    pub filter_limits_tokens: HashMap<String, String>,
     /// x_token to enforce on connections
     pub x_token: Option<String>,
     /// Filter name size limit
@@ -189,9 +268,63 @@ pub struct ConfigGrpc {
         with = "humantime_serde"
     )]
     pub filter_names_cleanup_interval: Duration,
+    /// Number of decoded filters cached for identical subscribe requests, disabled with 0
+    #[serde(default)]
+    pub filter_cache_size: usize,
+    /// Lifetime of cached filter, names of cached filters are not removed on cleanup
+    #[serde(
+        default = "ConfigGrpc::default_filter_cache_ttl",
+        with = "humantime_serde"
+    )]
+    pub filter_cache_ttl: Duration,
     /// Number of slots stored for re-broadcast (replay)
     #[serde(default = "ConfigGrpc::default_replay_stored_slots")]
     pub replay_stored_slots: u64,
+    /// Max age of slots stored for re-broadcast (replay), slots with messages older than
+    /// the duration are removed even if `replay_stored_slots` is not reached
+    #[serde(default, with = "humantime_serde")]
+    pub replay_retention_duration: Option<Duration>,
+    /// Interval of resume tokens in updates, disabled by default. Tokens are resolved with
+    /// stored slots, requires `replay_stored_slots`
+    #[serde(default, with = "humantime_serde")]
+    pub resume_token_interval: Option<Duration>,
+    /// Deliver updates only once the slot reached the request commitment, updates of slots
+    /// which never reach it are dropped. Adds latency of the slot commitment, disabled by default
+    #[serde(default)]
+    pub strict_commitment: bool,
+    /// Max number of partial blocks buffered for block reconstruction,
+    /// the oldest incomplete block is dropped once the limit is reached
+    #[serde(
+        default = "ConfigGrpc::default_max_concurrent_block_buffers",
+        deserialize_with = "deserialize_usize_str"
+    )]
+    pub max_concurrent_block_buffers: usize,
+    /// Interval of server pings, can be overridden with `ping_interval_ms` in the request
+    #[serde(
+        default = "ConfigGrpc::default_ping_interval",
+        with = "humantime_serde"
+    )]
+    pub ping_interval: Duration,
+    /// Close stream with `DeadlineExceeded` if the client consumed no updates within timeout,
+    /// disabled by default
+    #[serde(default, with = "humantime_serde")]
+    pub stale_stream_timeout: Option<Duration>,
+    /// Report `NOT_SERVING` in the gRPC health service if no new slot was received within
+    /// the window, health status is always `SERVING` by default
+    #[serde(default, with = "humantime_serde")]
+    pub health_slot_staleness: Option<Duration>,
+    /// Max time to flush queued updates to connected clients on shutdown
+    #[serde(
+        default = "ConfigGrpc::default_shutdown_timeout",
+        with = "humantime_serde"
+    )]
+    pub shutdown_timeout: Duration,
+    /// Weighted fair scheduling of updates processing across subscribers, disabled by default
+    #[serde(default)]
+    pub fair_scheduling: Option<ConfigGrpcFairScheduling>,
+    /// Limit of updates per second per connection, not limited by default
+    #[serde(default)]
+    pub rate_limit: ConfigGrpcRateLimit,
     #[serde(default)]
     pub server_http2_adaptive_window: Option<bool>,
     #[serde(default, with = "humantime_serde")]
@@ -217,14 +350,34 @@ impl ConfigGrpc {
         50_000_000
     }
 
-    const fn channel_capacity_default() -> usize {
+    pub(crate) const fn channel_capacity_default() -> usize {
         250_000
     }
 
+    const fn geyser_channel_capacity_default() -> usize {
+        Semaphore::MAX_PERMITS
+    }
+
     const fn unary_concurrency_limit_default() -> usize {
         Semaphore::MAX_PERMITS
     }
 
+    const fn default_block_meta_range_max_slots() -> usize {
+        1_000
+    }
+
+    const fn default_transaction_accounts_cache_size() -> usize {
+        0
+    }
+
+    const fn default_transaction_accounts_cache_bytes_max() -> usize {
+        1024 * 1024 * 1024
+    }
+
+    const fn default_slots_replay_finalized_max() -> usize {
+        256
+    }
+
     const fn default_filter_name_size_limit() -> usize {
         128
     }
@@ -237,9 +390,25 @@ impl ConfigGrpc {
         Duration::from_secs(1)
     }
 
+    const fn default_filter_cache_ttl() -> Duration {
+        Duration::from_secs(60)
+    }
+
     const fn default_replay_stored_slots() -> u64 {
         0
     }
+
+    const fn default_max_concurrent_block_buffers() -> usize {
+        usize::MAX
+    }
+
+    const fn default_ping_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    pub(crate) const fn default_shutdown_timeout() -> Duration {
+        Duration::from_secs(10)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -247,6 +416,133 @@ impl ConfigGrpc {
 pub struct ConfigGrpcServerTls {
     pub cert_path: String,
     pub key_path: String,
+    /// Interval of checking cert and key files for changes, modified files are loaded without
+    /// restart and used for new connections, disabled by default
+    #[serde(default, with = "humantime_serde")]
+    pub reload_interval: Option<Duration>,
+    /// CA certificates to verify client certificates (mTLS), connections without valid client
+    /// certificate are rejected on handshake. Loaded on start only
+    #[serde(default)]
+    pub client_ca_path: Option<String>,
+    /// Field of the client certificate used as client identity for per-client limits instead
+    /// of `x-token`
+    #[serde(default)]
+    pub client_identity: Option<ConfigGrpcServerTlsClientIdentity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigGrpcServerTlsClientIdentity {
+    /// Subject common name
+    CommonName,
+    /// First DNS name, email or URI of subject alternative names
+    SubjectAltName,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigGrpcChannelFullPolicy {
+    #[default]
+    Block,
+    Drop,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigGrpcClientChannelFullPolicy {
+    #[default]
+    Disconnect,
+    DropOldest,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigGrpcAccountDataOutOfBand {
+    /// Accounts with data in the update (after slices and encoding) bigger than the value in
+    /// bytes are sent without data
+    #[serde(deserialize_with = "deserialize_usize_str")]
+    pub threshold: usize,
+    /// Max total size of data kept for `GetAccountData`, oldest accounts are evicted first
+    #[serde(
+        default = "ConfigGrpcAccountDataOutOfBand::default_cache_bytes_max",
+        deserialize_with = "deserialize_usize_str"
+    )]
+    pub cache_bytes_max: usize,
+}
+
+impl ConfigGrpcAccountDataOutOfBand {
+    const fn default_cache_bytes_max() -> usize {
+        1024 * 1024 * 1024
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigGrpcFairScheduling {
+    /// Number of subscribers processing updates at the same time
+    #[serde(
+        default = "ConfigGrpcFairScheduling::default_concurrency",
+        deserialize_with = "deserialize_usize_str"
+    )]
+    pub concurrency: usize,
+    /// Weight of subscribers without token in `token_weights`
+    #[serde(default = "ConfigGrpcFairScheduling::default_weight")]
+    pub default_weight: u32,
+    /// Weights by `x-token` (or client certificate identity, see `tls_config.client_identity`),
+    /// subscriber with weight 2 gets twice more capacity than with 1
+    #[serde(default)]
+    pub token_weights: HashMap<String, u32>,
+}
+
+impl ConfigGrpcFairScheduling {
+    const fn default_concurrency() -> usize {
+        8
+    }
+
+    const fn default_weight() -> u32 {
+        1
+    }
+
+    pub fn get_weight(&self, x_token: Option<&str>) -> u32 {
+        x_token
+            .and_then(|x_token| self.token_weights.get(x_token))
+            .copied()
+            .unwrap_or(self.default_weight)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigGrpcRateLimit {
+    /// Limit for connections without `max_updates_per_second` in the request
+    #[serde(default)]
+    pub default_updates_per_second: Option<u32>,
+    /// Max accepted value of `max_updates_per_second`, applied to all connections
+    #[serde(default)]
+    pub max_updates_per_second: Option<u32>,
+    /// Action on exceeded limit: `backpressure` delays updates, `drop` skips them
+    #[serde(default)]
+    pub policy: ConfigGrpcRateLimitPolicy,
+}
+
+impl ConfigGrpcRateLimit {
+    pub fn get_updates_per_second(&self, requested: Option<u32>) -> Option<u32> {
+        match (
+            requested.or(self.default_updates_per_second),
+            self.max_updates_per_second,
+        ) {
+            (Some(value), Some(max)) => Some(value.min(max)),
+            (value, max) => value.or(max),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigGrpcRateLimitPolicy {
+    #[default]
+    Backpressure,
+    Drop,
 }
 
 #[derive(Debug, Clone, Deserialize)]
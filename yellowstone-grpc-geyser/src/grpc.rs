@@ -1,7 +1,13 @@
 use {
     crate::{
-        config::{ConfigGrpc, ConfigTokio},
+        config::{
+            ConfigGrpc, ConfigGrpcAccountDataOutOfBand, ConfigGrpcChannelFullPolicy,
+            ConfigGrpcClientChannelFullPolicy, ConfigGrpcFairScheduling, ConfigGrpcRateLimit,
+            ConfigGrpcRateLimitPolicy, ConfigTokio,
+        },
         metrics::{self, DebugClientMessage},
+        scheduler::{FairScheduler, FairSchedulerClient, FairSchedulerPermit},
+        tls::{self, CertResolver, ClientIdentity},
         version::GrpcVersionInfo,
     },
     anyhow::Context,
@@ -10,55 +16,91 @@ use {
     solana_sdk::{
         clock::{Slot, MAX_RECENT_BLOCKHASHES},
         pubkey::Pubkey,
+        signature::Signature,
     },
     std::{
-        collections::{BTreeMap, HashMap},
+        collections::{
+            btree_map::Entry as BTreeMapEntry,
+            hash_map::{DefaultHasher, Entry as HashMapEntry},
+            BTreeMap, BTreeSet, HashMap, VecDeque,
+        },
+        fmt,
+        future::Future,
+        hash::{Hash, Hasher},
+        net::SocketAddr,
+        ops::Bound,
+        pin::Pin,
+        str::FromStr,
         sync::{
-            atomic::{AtomicUsize, Ordering},
-            Arc,
+            atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+            Arc, Mutex as StdMutex,
         },
+        task::{Context as TaskContext, Poll},
         time::SystemTime,
     },
     tokio::{
-        fs,
         runtime::Builder,
-        sync::{broadcast, mpsc, oneshot, Mutex, Notify, RwLock, Semaphore},
+        sync::{broadcast, mpsc, oneshot, watch, Mutex, Notify, RwLock, Semaphore},
         task::spawn_blocking,
-        time::{sleep, Duration, Instant},
+        time::{sleep, timeout, timeout_at, Duration, Instant},
     },
-    tokio_stream::wrappers::ReceiverStream,
+    tokio_stream::{Stream, StreamExt},
     tonic::{
+        server::NamedService,
         service::interceptor::interceptor,
-        transport::{
-            server::{Server, TcpIncoming},
-            Identity, ServerTlsConfig,
-        },
+        transport::server::{Server, TcpIncoming},
         Request, Response, Result as TonicResult, Status, Streaming,
     },
-    tonic_health::server::health_reporter,
+    tonic_health::{
+        server::{health_reporter, HealthReporter},
+        ServingStatus,
+    },
     yellowstone_grpc_proto::{
         plugin::{
             filter::{
-                limits::FilterLimits,
-                message::{FilteredUpdate, FilteredUpdateOneof},
+                cache::FilterCache,
+                limits::{FilterCachesMemory, FilterLimits},
+                message::{
+                    FilteredUpdate, FilteredUpdateAccount, FilteredUpdateOneof, FilteredUpdates,
+                },
                 name::FilterNames,
-                Filter,
+                Filter, FilterAccountsDataSlice,
             },
             message::{
-                CommitmentLevel, Message, MessageBlock, MessageBlockMeta, MessageEntry,
-                MessageSlot, MessageTransactionInfo, SlotStatus,
+                CommitmentLevel, Message, MessageAccount, MessageAccountInfo, MessageBlock,
+                MessageBlockMeta, MessageEntry, MessageSlot, MessageTransactionInfo, SlotStatus,
             },
             proto::geyser_server::{Geyser, GeyserServer},
         },
         prelude::{
-            CommitmentLevel as CommitmentLevelProto, GetBlockHeightRequest, GetBlockHeightResponse,
+            explain_filter_response, CommitmentLevel as CommitmentLevelProto, ExplainFilterRequest,
+            ExplainFilterResponse, GetAccountDataRequest, GetAccountDataResponse,
+            GetBlockHeightRequest, GetBlockHeightResponse, GetBlockMetaRangeRequest,
+            GetBlockMetaRangeResponse, GetFilterLimitsRequest, GetFilterLimitsResponse,
             GetLatestBlockhashRequest, GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse,
-            GetVersionRequest, GetVersionResponse, IsBlockhashValidRequest,
-            IsBlockhashValidResponse, PingRequest, PongResponse, SubscribeRequest,
+            GetTransactionAccountsRequest, GetTransactionAccountsResponse, GetVersionRequest,
+            GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
+            PongResponse, SubscribeRequest, SubscribeUpdateAccount, SubscribeUpdateBlockMeta,
         },
     },
 };
 
+const PING_INTERVAL_MIN: Duration = Duration::from_secs(1);
+
+/// Log within the span of the current connection task, see `ConnectionSpan`
+macro_rules! client_log {
+    ($level:ident, $($arg:tt)+) => {
+        $level!("{}: {}", ConnectionSpan::current(), format_args!($($arg)+))
+    };
+}
+
+/// Max interval between checks of slots liveness for the health status
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Capacity of the stream channel with `drop_oldest` policy, the rest of updates are queued by
+/// the client loop where the oldest can be dropped
+const CHANNEL_DROP_OLDEST_WINDOW: usize = 1_024;
+
 #[derive(Debug)]
 struct BlockhashStatus {
     slot: u64,
@@ -80,27 +122,59 @@ impl BlockhashStatus {
 
 #[derive(Debug, Default)]
 struct BlockMetaStorageInner {
-    blocks: HashMap<u64, Arc<MessageBlockMeta>>,
+    blocks: BTreeMap<u64, Arc<MessageBlockMeta>>,
     blockhashes: HashMap<String, BlockhashStatus>,
     processed: Option<u64>,
     confirmed: Option<u64>,
     finalized: Option<u64>,
 }
 
+impl BlockMetaStorageInner {
+    fn get_block_meta_range(
+        &self,
+        from_slot: Slot,
+        to_slot: Slot,
+        max_slots: usize,
+    ) -> Result<Vec<SubscribeUpdateBlockMeta>, Status> {
+        if from_slot > to_slot {
+            return Err(Status::invalid_argument(
+                "from_slot should be less or equal to to_slot",
+            ));
+        }
+        if to_slot - from_slot >= max_slots as u64 {
+            let msg = format!("max {max_slots} slots allowed per request");
+            return Err(Status::invalid_argument(msg));
+        }
+
+        Ok(self
+            .blocks
+            .range(from_slot..=to_slot)
+            .map(|(_slot, block)| block.block_meta.clone())
+            .collect())
+    }
+}
+
 #[derive(Debug)]
 struct BlockMetaStorage {
     read_sem: Semaphore,
     inner: Arc<RwLock<BlockMetaStorageInner>>,
+    range_max_slots: usize,
 }
 
 impl BlockMetaStorage {
-    fn new(unary_concurrency_limit: usize) -> (Self, mpsc::UnboundedSender<Message>) {
+    fn new(
+        unary_concurrency_limit: usize,
+        replay_stored_slots: u64,
+        range_max_slots: usize,
+    ) -> (Self, mpsc::UnboundedSender<Message>) {
         let inner = Arc::new(RwLock::new(BlockMetaStorageInner::default()));
         let (tx, mut rx) = mpsc::unbounded_channel();
 
         let storage = Arc::clone(&inner);
         tokio::spawn(async move {
             const KEEP_SLOTS: u64 = 3;
+            // keep blocks meta for range requests as long as messages for replay
+            let keep_slots = KEEP_SLOTS.max(replay_stored_slots);
 
             while let Some(message) = rx.recv().await {
                 let mut storage = storage.write().await;
@@ -144,7 +218,7 @@ impl BlockMetaStorage {
                         }
 
                         if msg.status == SlotStatus::Finalized {
-                            if let Some(keep_slot) = msg.slot.checked_sub(KEEP_SLOTS) {
+                            if let Some(keep_slot) = msg.slot.checked_sub(keep_slots) {
                                 storage.blocks.retain(|slot, _block| *slot >= keep_slot);
                             }
 
@@ -171,6 +245,7 @@ impl BlockMetaStorage {
             Self {
                 read_sem: Semaphore::new(unary_concurrency_limit),
                 inner,
+                range_max_slots,
             },
             tx,
         )
@@ -245,6 +320,515 @@ impl BlockMetaStorage {
 
         Ok(Response::new(IsBlockhashValidResponse { valid, slot }))
     }
+
+    async fn get_block_meta_range(
+        &self,
+        from_slot: Slot,
+        to_slot: Slot,
+    ) -> Result<Response<GetBlockMetaRangeResponse>, Status> {
+        let _permit = self.read_sem.acquire().await;
+        let storage = self.inner.read().await;
+
+        let blocks_meta = storage.get_block_meta_range(from_slot, to_slot, self.range_max_slots)?;
+        Ok(Response::new(GetBlockMetaRangeResponse { blocks_meta }))
+    }
+}
+
+#[derive(Debug, Default)]
+struct TransactionAccountsStorageInner {
+    signatures: VecDeque<Signature>,
+    accounts: HashMap<Signature, Vec<MessageAccount>>,
+    bytes: usize,
+}
+
+impl TransactionAccountsStorageInner {
+    fn push(&mut self, message: MessageAccount, max_signatures: usize, bytes_max: usize) {
+        let Some(signature) = message.account.txn_signature else {
+            return;
+        };
+
+        self.bytes += message.account.data.len();
+        match self.accounts.entry(signature) {
+            HashMapEntry::Occupied(mut entry) => entry.get_mut().push(message),
+            HashMapEntry::Vacant(entry) => {
+                entry.insert(vec![message]);
+                self.signatures.push_back(signature);
+            }
+        }
+        while self.signatures.len() > max_signatures || self.bytes > bytes_max {
+            let Some(signature) = self.signatures.pop_front() else {
+                break;
+            };
+            if let Some(messages) = self.accounts.remove(&signature) {
+                for message in messages {
+                    self.bytes -= message.account.data.len();
+                }
+            }
+        }
+    }
+
+    fn get(&self, signature: &Signature) -> Option<Vec<SubscribeUpdateAccount>> {
+        self.accounts.get(signature).map(|messages| {
+            let mut messages = messages.iter().collect::<Vec<_>>();
+            messages.sort_by_key(|message| message.account.write_version);
+            messages
+                .into_iter()
+                .map(|message| SubscribeUpdateAccount {
+                    account: Some(FilteredUpdate::as_subscribe_update_account(
+                        &message.account,
+                        &FilterAccountsDataSlice::default(),
+                    )),
+                    slot: message.slot,
+                    is_startup: message.is_startup,
+                    commitment: None,
+                    data_decompress_failed: false,
+                    data_diff: None,
+                    data_ref: None,
+                })
+                .collect()
+        })
+    }
+}
+
+#[derive(Debug)]
+struct TransactionAccountsStorage {
+    read_sem: Semaphore,
+    inner: Arc<RwLock<TransactionAccountsStorageInner>>,
+}
+
+impl TransactionAccountsStorage {
+    fn new(
+        unary_concurrency_limit: usize,
+        max_signatures: usize,
+        bytes_max: usize,
+    ) -> (Self, mpsc::UnboundedSender<MessageAccount>) {
+        let inner = Arc::new(RwLock::new(TransactionAccountsStorageInner::default()));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let storage = Arc::clone(&inner);
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                storage
+                    .write()
+                    .await
+                    .push(message, max_signatures, bytes_max);
+            }
+        });
+
+        (
+            Self {
+                read_sem: Semaphore::new(unary_concurrency_limit),
+                inner,
+            },
+            tx,
+        )
+    }
+
+    async fn get_transaction_accounts(
+        &self,
+        signature: &str,
+    ) -> Result<Response<GetTransactionAccountsResponse>, Status> {
+        let signature = Signature::from_str(signature)
+            .map_err(|_error| Status::invalid_argument("failed to parse signature"))?;
+
+        let _permit = self.read_sem.acquire().await;
+        let storage = self.inner.read().await;
+
+        match storage.get(&signature) {
+            Some(accounts) => Ok(Response::new(GetTransactionAccountsResponse { accounts })),
+            None => Err(Status::not_found("transaction is not in the cache")),
+        }
+    }
+}
+
+/// Data of accounts sent out-of-band, keyed by pubkey, slot and write version
+#[derive(Debug, Default)]
+struct AccountDataStorageInner {
+    keys: VecDeque<(Pubkey, Slot, u64)>,
+    accounts: HashMap<(Pubkey, Slot, u64), Arc<MessageAccountInfo>>,
+    bytes: usize,
+}
+
+impl AccountDataStorageInner {
+    fn push(&mut self, message: &MessageAccount, bytes_max: usize) {
+        let key = (
+            message.account.pubkey,
+            message.slot,
+            message.account.write_version,
+        );
+        if let HashMapEntry::Vacant(entry) = self.accounts.entry(key) {
+            entry.insert(Arc::clone(&message.account));
+            self.keys.push_back(key);
+            self.bytes += message.account.data.len();
+            while self.bytes > bytes_max {
+                let Some(key) = self.keys.pop_front() else {
+                    break;
+                };
+                if let Some(account) = self.accounts.remove(&key) {
+                    self.bytes -= account.data.len();
+                }
+            }
+        }
+    }
+
+    fn get(&self, pubkey: Pubkey, slot: Slot, write_version: u64) -> Option<Vec<u8>> {
+        self.accounts
+            .get(&(pubkey, slot, write_version))
+            .map(|account| account.data.clone())
+    }
+}
+
+#[derive(Debug)]
+struct AccountDataStorage {
+    read_sem: Semaphore,
+    inner: Arc<RwLock<AccountDataStorageInner>>,
+}
+
+impl AccountDataStorage {
+    fn new(
+        unary_concurrency_limit: usize,
+        config: ConfigGrpcAccountDataOutOfBand,
+    ) -> (Self, mpsc::UnboundedSender<MessageAccount>) {
+        let inner = Arc::new(RwLock::new(AccountDataStorageInner::default()));
+        let (tx, mut rx) = mpsc::unbounded_channel::<MessageAccount>();
+
+        let storage = Arc::clone(&inner);
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                storage.write().await.push(&message, config.cache_bytes_max);
+            }
+        });
+
+        (
+            Self {
+                read_sem: Semaphore::new(unary_concurrency_limit),
+                inner,
+            },
+            tx,
+        )
+    }
+
+    /// Data of accounts sent in `snapshot` is read from the accounts state if it is evicted
+    async fn get_account_data(
+        &self,
+        request: GetAccountDataRequest,
+        accounts_snapshot: Option<&AccountsSnapshotStorage>,
+    ) -> Result<Response<GetAccountDataResponse>, Status> {
+        let pubkey = Pubkey::try_from(request.pubkey.as_slice())
+            .map_err(|_error| Status::invalid_argument("failed to parse pubkey"))?;
+
+        let _permit = self.read_sem.acquire().await;
+        let mut data = self
+            .inner
+            .read()
+            .await
+            .get(pubkey, request.slot, request.write_version);
+        if let (None, Some(accounts_snapshot)) = (&data, accounts_snapshot) {
+            data = accounts_snapshot.inner.read().await.get(
+                pubkey,
+                request.slot,
+                request.write_version,
+            );
+        }
+
+        match data {
+            Some(data) => Ok(Response::new(GetAccountDataResponse { data })),
+            None => Err(Status::not_found("account data is not in the cache")),
+        }
+    }
+}
+
+/// Latest state of accounts for `snapshot` in accounts filter, writes are applied to `accounts`
+/// once their slot is finalized, writes of newer slots are kept by slot until then
+#[derive(Debug, Default)]
+struct AccountsSnapshotStorageInner {
+    accounts: BTreeMap<Pubkey, MessageAccount>,
+    slots: BTreeMap<Slot, AccountsSnapshotSlot>,
+    finalized: Option<Slot>,
+}
+
+/// Latest writes of a not finalized slot, closed accounts are kept to hide the finalized state
+#[derive(Debug, Default)]
+struct AccountsSnapshotSlot {
+    confirmed: bool,
+    accounts: HashMap<Pubkey, MessageAccount>,
+}
+
+impl AccountsSnapshotStorageInner {
+    /// Number of finalized accounts read under the lock by [`AccountsSnapshotReader`]
+    const CHUNK_SIZE: usize = 4_096;
+
+    /// Expects statuses of parent slots before the status of the slot, as in the geyser loop
+    fn push(&mut self, message: Message) {
+        match message {
+            Message::Account(message) => {
+                if message.is_startup || self.finalized.is_some_and(|slot| message.slot <= slot) {
+                    self.push_finalized(message);
+                } else {
+                    let slot = self.slots.entry(message.slot).or_default();
+                    match slot.accounts.entry(message.account.pubkey) {
+                        HashMapEntry::Occupied(mut entry) => {
+                            if entry.get().account.write_version < message.account.write_version {
+                                entry.insert(message);
+                            }
+                        }
+                        HashMapEntry::Vacant(entry) => {
+                            entry.insert(message);
+                        }
+                    }
+                }
+            }
+            Message::Slot(message) => match message.status {
+                SlotStatus::Confirmed => {
+                    if let Some(slot) = self.slots.get_mut(&message.slot) {
+                        slot.confirmed = true;
+                    }
+                }
+                SlotStatus::Finalized => {
+                    // parents are finalized first, slots left behind are on other forks
+                    let newer = self.slots.split_off(&(message.slot + 1));
+                    let mut older = std::mem::replace(&mut self.slots, newer);
+                    if let Some(slot) = older.remove(&message.slot) {
+                        for message in slot.accounts.into_values() {
+                            self.push_finalized(message);
+                        }
+                    }
+                    self.finalized = Some(message.slot);
+                }
+                SlotStatus::Dead => {
+                    self.slots.remove(&message.slot);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn push_finalized(&mut self, message: MessageAccount) {
+        match self.accounts.entry(message.account.pubkey) {
+            BTreeMapEntry::Occupied(mut entry) => {
+                let current = entry.get();
+                if (current.slot, current.account.write_version)
+                    < (message.slot, message.account.write_version)
+                {
+                    if message.account.lamports == 0 {
+                        entry.remove();
+                    } else {
+                        entry.insert(message);
+                    }
+                }
+            }
+            BTreeMapEntry::Vacant(entry) => {
+                if message.account.lamports != 0 {
+                    entry.insert(message);
+                }
+            }
+        }
+    }
+
+    fn get(&self, pubkey: Pubkey, slot: Slot, write_version: u64) -> Option<Vec<u8>> {
+        let message = match self.slots.get(&slot) {
+            Some(slot) => slot.accounts.get(&pubkey),
+            None => self.accounts.get(&pubkey),
+        };
+        message
+            .filter(|message| {
+                message.slot == slot && message.account.write_version == write_version
+            })
+            .map(|message| message.account.data.clone())
+    }
+
+    /// Latest writes of not finalized slots which reached the commitment
+    fn get_unfinalized(&self, commitment: CommitmentLevel) -> HashMap<Pubkey, MessageAccount> {
+        let mut accounts = HashMap::new();
+        let slots = self.slots.values().filter(|slot| match commitment {
+            CommitmentLevel::Processed => true,
+            CommitmentLevel::Confirmed => slot.confirmed,
+            CommitmentLevel::Finalized => false,
+        });
+        // slots are sorted, newer slot replaces the write
+        for slot in slots {
+            for (pubkey, message) in slot.accounts.iter() {
+                accounts.insert(*pubkey, message.clone());
+            }
+        }
+        accounts
+    }
+
+    /// Finalized accounts ordered by pubkey, starting after `after`
+    fn get_finalized(&self, after: Option<Pubkey>, limit: usize) -> Vec<MessageAccount> {
+        let start = after.map_or(Bound::Unbounded, Bound::Excluded);
+        self.accounts
+            .range((start, Bound::Unbounded))
+            .take(limit)
+            .map(|(_pubkey, message)| message.clone())
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct AccountsSnapshotStorage {
+    inner: Arc<RwLock<AccountsSnapshotStorageInner>>,
+}
+
+impl AccountsSnapshotStorage {
+    fn new() -> (Self, mpsc::UnboundedSender<Message>) {
+        let inner = Arc::new(RwLock::new(AccountsSnapshotStorageInner::default()));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let storage = Arc::clone(&inner);
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                storage.write().await.push(message);
+            }
+        });
+
+        (Self { inner }, tx)
+    }
+
+    /// Reader of the state at the commitment of the filter
+    async fn reader(&self, commitment: CommitmentLevel) -> AccountsSnapshotReader<'_> {
+        let unfinalized = self.inner.read().await.get_unfinalized(commitment);
+        AccountsSnapshotReader {
+            storage: self,
+            unfinalized,
+            after: None,
+            accounts: 0,
+            slot: 0,
+            completed: false,
+        }
+    }
+}
+
+/// Reads finalized accounts in chunks so writes are not blocked for the whole scan, writes of not
+/// finalized slots are taken once and sent after finalized accounts
+#[derive(Debug)]
+struct AccountsSnapshotReader<'a> {
+    storage: &'a AccountsSnapshotStorage,
+    unfinalized: HashMap<Pubkey, MessageAccount>,
+    after: Option<Pubkey>,
+    accounts: u64,
+    slot: Slot,
+    completed: bool,
+}
+
+impl AccountsSnapshotReader<'_> {
+    /// Matched accounts of the next chunk with `is_startup` set, completion message is the last
+    /// update of the last chunk
+    async fn next(&mut self, filter: &Filter) -> Option<FilteredUpdates> {
+        if self.completed {
+            return None;
+        }
+
+        let mut messages = self
+            .storage
+            .inner
+            .read()
+            .await
+            .get_finalized(self.after, AccountsSnapshotStorageInner::CHUNK_SIZE);
+        self.completed = messages.len() < AccountsSnapshotStorageInner::CHUNK_SIZE;
+        if let Some(message) = messages.last() {
+            self.after = Some(message.account.pubkey);
+        }
+        messages.retain(|message| !self.unfinalized.contains_key(&message.account.pubkey));
+        if self.completed {
+            messages.extend(
+                self.unfinalized
+                    .drain()
+                    .map(|(_pubkey, message)| message)
+                    .filter(|message| message.account.lamports != 0),
+            );
+        }
+
+        let mut updates = FilteredUpdates::new();
+        for message in messages {
+            let message = MessageAccount {
+                is_startup: true,
+                ..message
+            };
+            let matched = filter.get_snapshot_updates(&message);
+            if !matched.is_empty() {
+                self.accounts += 1;
+                self.slot = self.slot.max(message.slot);
+                updates.extend(matched);
+            }
+        }
+        if self.completed {
+            updates.push(filter.get_snapshot_complete_msg(self.accounts, self.slot));
+        }
+        Some(updates)
+    }
+}
+
+/// Latest finalized slots for `replay_finalized` in slots filter
+#[derive(Debug)]
+struct SlotsFinalizedStorageInner {
+    slots: VecDeque<MessageSlot>,
+    slots_max: usize,
+}
+
+impl SlotsFinalizedStorageInner {
+    const fn new(slots_max: usize) -> Self {
+        Self {
+            slots: VecDeque::new(),
+            slots_max,
+        }
+    }
+
+    fn push(&mut self, message: MessageSlot) {
+        // finalized slots never go back, repeated statuses are skipped
+        if message.status != SlotStatus::Finalized
+            || self
+                .slots
+                .back()
+                .is_some_and(|last| last.slot >= message.slot)
+        {
+            return;
+        }
+        self.slots.push_back(message);
+        while self.slots.len() > self.slots_max {
+            self.slots.pop_front();
+        }
+    }
+
+    fn get_updates(&self, filter: &Filter) -> Vec<FilteredUpdate> {
+        let count = filter.get_slots_replay_finalized().min(self.slots.len());
+        let messages = self
+            .slots
+            .iter()
+            .skip(self.slots.len() - count)
+            .cloned()
+            .collect::<Vec<_>>();
+        filter
+            .get_slots_replay_finalized_updates(&messages)
+            .into_iter()
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct SlotsFinalizedStorage {
+    inner: Arc<RwLock<SlotsFinalizedStorageInner>>,
+}
+
+impl SlotsFinalizedStorage {
+    fn new(slots_max: usize) -> (Self, mpsc::UnboundedSender<MessageSlot>) {
+        let inner = Arc::new(RwLock::new(SlotsFinalizedStorageInner::new(slots_max)));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let storage = Arc::clone(&inner);
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                storage.write().await.push(message);
+            }
+        });
+
+        (Self { inner }, tx)
+    }
+
+    async fn get_updates(&self, filter: &Filter) -> Vec<FilteredUpdate> {
+        self.inner.read().await.get_updates(filter)
+    }
 }
 
 #[derive(Debug, Default)]
@@ -268,17 +852,19 @@ struct SlotMessages {
     accounts_dedup: HashMap<Pubkey, (u64, usize)>, // (write_version, message_index)
     entries: Vec<Arc<MessageEntry>>,
     sealed: bool,
+    evicted: bool,
     entries_count: usize,
     confirmed_at: Option<usize>,
     finalized_at: Option<usize>,
     parent_slot: Option<Slot>,
     confirmed: bool,
     finalized: bool,
+    last_created_at: Timestamp,
 }
 
 impl SlotMessages {
     pub fn try_seal(&mut self, msgid_gen: &mut MessageId) -> Option<(u64, Message)> {
-        if !self.sealed {
+        if !self.sealed && !self.evicted {
             if let Some(block_meta) = &self.block_meta {
                 let executed_transaction_count = block_meta.executed_transaction_count as usize;
                 let entries_count = block_meta.entries_count as usize;
@@ -319,29 +905,884 @@ impl SlotMessages {
 
         None
     }
+
+    const fn is_buffered(&self) -> bool {
+        !self.sealed && !self.evicted
+    }
+
+    fn evict(&mut self) {
+        self.block_meta = None;
+        self.transactions = Vec::new();
+        self.entries = Vec::new();
+        self.evicted = true;
+    }
+
+    /// Drop reconstruction state of the oldest incomplete blocks, messages are kept for
+    /// commitment updates and replay. Returns number of evicted blocks.
+    fn evict_block_buffers(messages: &mut BTreeMap<u64, Self>, max: usize) -> usize {
+        let mut buffered = messages.values().filter(|m| m.is_buffered()).count();
+        let mut evicted = 0;
+        for slot_messages in messages.values_mut() {
+            if buffered <= max {
+                break;
+            }
+            if slot_messages.is_buffered() {
+                slot_messages.evict();
+                buffered -= 1;
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    /// First slot to keep on finalized slot, extra 10 slots are always kept for block
+    /// reconstruction, slots for replay are limited by count and by age of messages
+    fn retention_first_slot(
+        messages: &BTreeMap<u64, Self>,
+        finalized_slot: Slot,
+        replay_stored_slots: u64,
+        replay_retention_duration: Option<Duration>,
+        now: SystemTime,
+    ) -> Slot {
+        let keep_slot = finalized_slot.saturating_sub(10);
+        let first_slot = keep_slot.saturating_sub(replay_stored_slots);
+        let Some(expired_at) = replay_retention_duration
+            .and_then(|duration| now.checked_sub(duration))
+            .map(Timestamp::from)
+        else {
+            return first_slot;
+        };
+
+        messages
+            .range(first_slot..keep_slot)
+            .find(|(_slot, slot_messages)| {
+                let created_at = &slot_messages.last_created_at;
+                (created_at.seconds, created_at.nanos) >= (expired_at.seconds, expired_at.nanos)
+            })
+            .map(|(slot, _slot_messages)| *slot)
+            .unwrap_or(keep_slot)
+            .max(first_slot)
+    }
+
+    /// Collect stored messages starting from `replay_slot`, if the slot is older than
+    /// the first stored slot `Lagged` with the first available slot is returned.
+    fn replay(
+        messages: &BTreeMap<u64, Self>,
+        commitment: CommitmentLevel,
+        replay_slot: Slot,
+    ) -> ReplayedResponse {
+        if let Some((slot, _)) = messages.first_key_value() {
+            if replay_slot < *slot {
+                return ReplayedResponse::Lagged(*slot);
+            }
+        }
+
+        let mut replayed_messages = Vec::with_capacity(32_768);
+        for (_slot, messages) in messages.range(replay_slot..) {
+            replayed_messages.extend_from_slice(&messages.messages_slots);
+            if commitment == CommitmentLevel::Processed
+                || (commitment == CommitmentLevel::Finalized && messages.finalized)
+                || (commitment == CommitmentLevel::Confirmed && messages.confirmed)
+            {
+                replayed_messages.extend(messages.messages.iter().filter_map(|v| v.clone()));
+            }
+        }
+        ReplayedResponse::Messages(replayed_messages)
+    }
+
+    /// Collect stored messages after the position of the resume token. Processed messages are
+    /// broadcasted in the order of ids, older slots can have messages after the position, messages
+    /// with other commitments are broadcasted slot by slot.
+    fn replay_after(messages: &BTreeMap<u64, Self>, token: ResumeToken) -> ReplayedResponse {
+        let replay_slot = match (token.commitment, messages.first_key_value()) {
+            (CommitmentLevel::Processed, Some((slot, _))) if *slot <= token.slot => *slot,
+            _ => token.slot,
+        };
+        match Self::replay(messages, token.commitment, replay_slot) {
+            ReplayedResponse::Messages(mut replayed_messages) => {
+                replayed_messages.retain(|(msgid, message)| {
+                    *msgid > token.msgid
+                        || (token.commitment != CommitmentLevel::Processed
+                            && message.get_slot() > token.slot)
+                });
+                ReplayedResponse::Messages(replayed_messages)
+            }
+            ReplayedResponse::Lagged(slot) => ReplayedResponse::Lagged(slot),
+        }
+    }
 }
 
 type BroadcastedMessage = (CommitmentLevel, Arc<Vec<(u64, Message)>>);
 
+#[derive(Debug)]
 enum ReplayedResponse {
     Messages(Vec<(u64, Message)>),
     Lagged(Slot),
 }
 
-type ReplayStoredSlotsRequest = (CommitmentLevel, Slot, oneshot::Sender<ReplayedResponse>);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReplayFrom {
+    Slot(Slot),
+    Token(ResumeToken),
+}
 
-#[derive(Debug)]
-pub struct GrpcService {
-    config_snapshot_client_channel_capacity: usize,
-    config_channel_capacity: usize,
-    config_filter_limits: Arc<FilterLimits>,
-    blocks_meta: Option<BlockMetaStorage>,
-    subscribe_id: AtomicUsize,
-    snapshot_rx: Mutex<Option<crossbeam_channel::Receiver<Box<Message>>>>,
+type ReplayStoredSlotsRequest = (
+    CommitmentLevel,
+    ReplayFrom,
+    oneshot::Sender<ReplayedResponse>,
+);
+
+/// Position of the stream: the last message with the request commitment sent to the client.
+/// Message ids are unique only within the server instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ResumeToken {
+    instance: u64,
+    commitment: CommitmentLevel,
+    slot: Slot,
+    msgid: u64,
+}
+
+impl ResumeToken {
+    const VERSION: u8 = 1;
+    const SIZE: usize = 26;
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::SIZE);
+        data.push(Self::VERSION);
+        data.extend_from_slice(&self.instance.to_le_bytes());
+        data.push(CommitmentLevelProto::from(self.commitment) as u8);
+        data.extend_from_slice(&self.slot.to_le_bytes());
+        data.extend_from_slice(&self.msgid.to_le_bytes());
+        data
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::SIZE || data[0] != Self::VERSION {
+            return None;
+        }
+        let read_u64 = |offset: usize| {
+            u64::from_le_bytes(data[offset..offset + 8].try_into().expect("valid size"))
+        };
+        Some(Self {
+            instance: read_u64(1),
+            commitment: CommitmentLevelProto::try_from(i32::from(data[9]))
+                .ok()?
+                .into(),
+            slot: read_u64(10),
+            msgid: read_u64(18),
+        })
+    }
+}
+
+/// Adds resume token to updates of messages with the request commitment every `interval`
+#[derive(Debug)]
+struct ResumeTokens {
+    instance: u64,
+    interval: Duration,
+    next_at: Instant,
+}
+
+impl ResumeTokens {
+    fn new(instance: u64, interval: Duration) -> Self {
+        Self {
+            instance,
+            interval,
+            next_at: Instant::now() + interval,
+        }
+    }
+
+    /// Token is set on the last update of the message, other updates of the message are sent
+    /// before it. Postponed to the next message if the message has no updates.
+    fn maybe_set(
+        &mut self,
+        updates: &mut FilteredUpdates,
+        commitment: CommitmentLevel,
+        slot: Slot,
+        msgid: u64,
+        now: Instant,
+    ) {
+        if now < self.next_at {
+            return;
+        }
+        if let Some(update) = updates.last_mut() {
+            let token = ResumeToken {
+                instance: self.instance,
+                commitment,
+                slot,
+                msgid,
+            };
+            update.resume_token = Some(token.encode());
+            self.next_at = now + self.interval;
+        }
+    }
+}
+
+/// Holds updates of the request commitment until the slot status reaches the commitment,
+/// updates of slots which never reach it (dead or abandoned forks) are dropped
+#[derive(Debug)]
+struct StrictCommitmentGate {
+    commitment: CommitmentLevel,
+    reached: BTreeSet<Slot>,
+    buffer: BTreeMap<Slot, FilteredUpdates>,
+}
+
+impl StrictCommitmentGate {
+    const fn new(commitment: CommitmentLevel) -> Self {
+        Self {
+            commitment,
+            reached: BTreeSet::new(),
+            buffer: BTreeMap::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns updates which can be delivered, buffered updates of the slot are released
+    /// before updates of the slot status message which confirmed the commitment
+    fn push(&mut self, message: &Message, mut updates: FilteredUpdates) -> FilteredUpdates {
+        if self.commitment == CommitmentLevel::Processed {
+            return updates;
+        }
+
+        let slot = message.get_slot();
+        if let Message::Slot(message) = message {
+            let commitment = match message.status {
+                SlotStatus::Confirmed => Some(CommitmentLevel::Confirmed),
+                SlotStatus::Finalized => Some(CommitmentLevel::Finalized),
+                // buffered updates are dropped, but the dead status itself is delivered
+                SlotStatus::Dead => {
+                    self.buffer.remove(&slot);
+                    return updates;
+                }
+                _ => None,
+            };
+            if commitment.is_some_and(|commitment| commitment >= self.commitment) {
+                self.reached.insert(slot);
+                let mut released = self.buffer.remove(&slot).unwrap_or_default();
+                released.append(&mut updates);
+                updates = released;
+            }
+
+            // slots from abandoned forks never reach commitment
+            if commitment == Some(CommitmentLevel::Finalized) {
+                self.buffer = self.buffer.split_off(&(slot + 1));
+                self.reached = self.reached.split_off(&slot);
+            }
+        }
+
+        if !self.reached.contains(&slot) && !updates.is_empty() {
+            self.buffer.entry(slot).or_default().append(&mut updates);
+        }
+        updates
+    }
+}
+
+/// Sequence numbers of updates within the slot, only recent slots are tracked, numbers of an
+/// evicted slot start from 0 again
+#[derive(Debug, Default)]
+struct SlotSeqs {
+    slots: BTreeMap<Slot, u64>,
+}
+
+impl SlotSeqs {
+    /// Enough to cover slots between processed and finalized
+    const SLOTS_MAX: usize = 256;
+
+    fn set(&mut self, update: &mut FilteredUpdate) {
+        let Some(slot) = update.message.get_slot() else {
+            return;
+        };
+        let seq = self.slots.entry(slot).or_default();
+        update.slot_seq = Some(*seq);
+        *seq += 1;
+        while self.slots.len() > Self::SLOTS_MAX {
+            self.slots.pop_first();
+        }
+    }
+}
+
+tokio::task_local! {
+    static CONNECTION_SPAN: ConnectionSpan;
+}
+
+/// Context of the connection attached to log lines of its tasks
+#[derive(Debug, Clone)]
+struct ConnectionSpan {
+    id: usize,
+    peer: Option<SocketAddr>,
+    // `x-token` is not logged, only its hash to group connections of the same token
+    token_hash: Option<u64>,
+}
+
+impl fmt::Display for ConnectionSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "client #{}", self.id)?;
+        if let Some(peer) = self.peer {
+            write!(f, " peer={peer}")?;
+        }
+        if let Some(token_hash) = self.token_hash {
+            write!(f, " token={token_hash:016x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Hash of `x-token` used in logs and metrics instead of the secret value
+fn token_hash(token: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl ConnectionSpan {
+    fn new(id: usize, peer: Option<SocketAddr>, token: Option<&str>) -> Self {
+        Self {
+            id,
+            peer,
+            token_hash: token.map(token_hash),
+        }
+    }
+
+    /// Runs the task within the span, `client_log!` in the task is prefixed with the context
+    fn instrument<F: Future>(self, future: F) -> impl Future<Output = F::Output> {
+        CONNECTION_SPAN.scope(self, future)
+    }
+
+    /// Runs the closure within the span, for lines logged before tasks of the connection start
+    fn in_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        CONNECTION_SPAN.sync_scope(self.clone(), f)
+    }
+
+    fn current() -> String {
+        CONNECTION_SPAN
+            .try_with(ToString::to_string)
+            .unwrap_or_else(|_error| "client".to_owned())
+    }
+}
+
+/// Settings and shared state of the task reading requests of the connection
+#[derive(Debug)]
+struct IncomingContext {
+    filter_names: Arc<Mutex<FilterNames>>,
+    filter_cache: Arc<Mutex<FilterCache>>,
+    filter_limits: Arc<FilterLimits>,
+    ping_interval: Duration,
+    ping_interval_tx: watch::Sender<Duration>,
+    rate_limit: ConfigGrpcRateLimit,
+    rate_limit_tx: watch::Sender<Option<u32>>,
+    channel_capacity: usize,
+    channel_capacity_tx: watch::Sender<usize>,
+    accounts_snapshot_enabled: bool,
+    slots_replay_finalized_enabled: bool,
+    json_encoding_enabled: bool,
+    resume_tokens_enabled: bool,
+}
+
+/// Settings and shared state of the client loop of the connection
+#[derive(Debug)]
+struct ClientLoopContext {
+    endpoint: String,
+    snapshot_rx: Option<crossbeam_channel::Receiver<Box<Message>>>,
+    replay_stored_slots_tx: Option<mpsc::Sender<ReplayStoredSlotsRequest>>,
+    resume_tokens: Option<ResumeTokens>,
+    strict_commitment: bool,
+    accounts_snapshot: Option<Arc<AccountsSnapshotStorage>>,
+    slots_finalized: Option<Arc<SlotsFinalizedStorage>>,
+    account_data_threshold: Option<usize>,
+    debug_client_tx: Option<mpsc::UnboundedSender<DebugClientMessage>>,
+    fair_scheduler: Option<FairSchedulerClient>,
+    rate_limit_rx: watch::Receiver<Option<u32>>,
+    rate_limit_policy: ConfigGrpcRateLimitPolicy,
+    channel_capacity_rx: watch::Receiver<usize>,
+    channel_full_policy: ConfigGrpcClientChannelFullPolicy,
+    drained_rx: watch::Receiver<bool>,
+    shutdown_timeout: Duration,
+    caches_memory: FilterCachesMemory,
+}
+
+impl Default for ClientLoopContext {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            snapshot_rx: None,
+            replay_stored_slots_tx: None,
+            resume_tokens: None,
+            strict_commitment: false,
+            accounts_snapshot: None,
+            slots_finalized: None,
+            account_data_threshold: None,
+            debug_client_tx: None,
+            fair_scheduler: None,
+            rate_limit_rx: watch::channel(None).1,
+            rate_limit_policy: ConfigGrpcRateLimitPolicy::default(),
+            channel_capacity_rx: watch::channel(ConfigGrpc::channel_capacity_default()).1,
+            channel_full_policy: ConfigGrpcClientChannelFullPolicy::default(),
+            drained_rx: watch::channel(false).1,
+            shutdown_timeout: ConfigGrpc::default_shutdown_timeout(),
+            caches_memory: FilterCachesMemory::default(),
+        }
+    }
+}
+
+/// Time of the last update consumed from the client stream
+#[derive(Debug)]
+struct StreamActivity {
+    created_at: Instant,
+    last_read: AtomicU64,
+    stale: AtomicBool,
+}
+
+impl StreamActivity {
+    fn new() -> Self {
+        Self {
+            created_at: Instant::now(),
+            last_read: AtomicU64::new(0),
+            stale: AtomicBool::new(false),
+        }
+    }
+
+    fn touch(&self) {
+        let elapsed = self.created_at.elapsed().as_millis() as u64;
+        self.last_read.store(elapsed, Ordering::Relaxed);
+    }
+
+    fn idle(&self) -> Duration {
+        let last_read = Duration::from_millis(self.last_read.load(Ordering::Relaxed));
+        self.created_at.elapsed().saturating_sub(last_read)
+    }
+
+    fn set_stale(&self) {
+        self.stale.store(true, Ordering::Relaxed);
+    }
+
+    fn is_stale(&self) -> bool {
+        self.stale.load(Ordering::Relaxed)
+    }
+}
+
+/// Response stream of `Subscribe`, records consumed updates in `StreamActivity` and ends
+/// with `DeadlineExceeded` once the stream is marked as stale, even if the channel is full
+#[derive(Debug)]
+pub struct SubscribeStream {
+    stream_rx: mpsc::Receiver<TonicResult<FilteredUpdate>>,
+    activity: Arc<StreamActivity>,
+    finished: bool,
+}
+
+impl SubscribeStream {
+    fn new(
+        stream_rx: mpsc::Receiver<TonicResult<FilteredUpdate>>,
+        activity: Arc<StreamActivity>,
+    ) -> Self {
+        Self {
+            stream_rx,
+            activity,
+            finished: false,
+        }
+    }
+}
+
+impl Stream for SubscribeStream {
+    type Item = TonicResult<FilteredUpdate>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+        if self.activity.is_stale() {
+            self.finished = true;
+            self.stream_rx.close();
+            return Poll::Ready(Some(Err(Status::deadline_exceeded("stream is stale"))));
+        }
+
+        let poll = self.stream_rx.poll_recv(cx);
+        match &poll {
+            Poll::Ready(Some(_)) => self.activity.touch(),
+            Poll::Ready(None) => self.finished = true,
+            Poll::Pending => {}
+        }
+        poll
+    }
+}
+
+/// Counts of active subscribe streams, total and per client token
+#[derive(Debug)]
+struct Subscriptions {
+    max_total: Option<usize>,
+    max_per_token: Option<usize>,
+    total: AtomicUsize,
+    per_token: StdMutex<HashMap<String, usize>>,
+}
+
+impl Subscriptions {
+    fn new(max_total: Option<usize>, max_per_token: Option<usize>) -> Self {
+        Self {
+            max_total,
+            max_per_token,
+            total: AtomicUsize::new(0),
+            per_token: StdMutex::new(HashMap::new()),
+        }
+    }
+
+    /// Subscription is counted until the returned guard is dropped
+    fn try_acquire(self: &Arc<Self>, token: Option<&str>) -> Result<SubscriptionGuard, Status> {
+        let total = self
+            .total
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+                match self.max_total {
+                    Some(max) if total >= max => None,
+                    _ => Some(total + 1),
+                }
+            })
+            .map_err(|total| {
+                Status::resource_exhausted(format!("max number of subscriptions reached: {total}"))
+            })?;
+        metrics::set_subscriptions_active(total + 1);
+        let mut guard = SubscriptionGuard {
+            subscriptions: Arc::clone(self),
+            token: None,
+        };
+
+        if let Some(token) = token {
+            let mut per_token = self.per_token.lock().expect("unpoisoned mutex");
+            let count = per_token.entry(token.to_owned()).or_default();
+            if matches!(self.max_per_token, Some(max) if *count >= max) {
+                let count = *count;
+                if count == 0 {
+                    per_token.remove(token);
+                }
+                return Err(Status::resource_exhausted(format!(
+                    "max number of subscriptions per token reached: {count}"
+                )));
+            }
+            *count += 1;
+            metrics::set_subscriptions_active_per_token(token_hash(token), *count);
+            guard.token = Some(token.to_owned());
+        }
+
+        Ok(guard)
+    }
+}
+
+/// Decrements counts of active subscriptions on drop, including abnormal termination
+#[derive(Debug)]
+struct SubscriptionGuard {
+    subscriptions: Arc<Subscriptions>,
+    token: Option<String>,
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        let total = self.subscriptions.total.fetch_sub(1, Ordering::Relaxed);
+        metrics::set_subscriptions_active(total - 1);
+
+        if let Some(token) = self.token.take() {
+            let mut per_token = self
+                .subscriptions
+                .per_token
+                .lock()
+                .expect("unpoisoned mutex");
+            if let HashMapEntry::Occupied(mut entry) = per_token.entry(token) {
+                *entry.get_mut() -= 1;
+                metrics::set_subscriptions_active_per_token(token_hash(entry.key()), *entry.get());
+                if *entry.get() == 0 {
+                    entry.remove();
+                }
+            }
+        }
+    }
+}
+
+/// Time when the highest slot was observed by the geyser loop, nothing observed at start
+#[derive(Debug)]
+struct SlotLiveness {
+    created_at: Instant,
+    slot: AtomicU64,
+    slot_at: AtomicU64,
+}
+
+impl SlotLiveness {
+    const fn new(created_at: Instant) -> Self {
+        Self {
+            created_at,
+            slot: AtomicU64::new(0),
+            slot_at: AtomicU64::new(0),
+        }
+    }
+
+    /// Only a new highest slot advances liveness, statuses of known slots are ignored
+    fn update(&self, slot: Slot, now: Instant) {
+        if self.slot.fetch_max(slot, Ordering::Relaxed) < slot {
+            let elapsed = now.saturating_duration_since(self.created_at).as_millis() as u64;
+            self.slot_at.store(elapsed, Ordering::Relaxed);
+        }
+    }
+
+    fn is_serving(&self, staleness: Duration, now: Instant) -> bool {
+        let slot_at = self.created_at + Duration::from_millis(self.slot_at.load(Ordering::Relaxed));
+        now.saturating_duration_since(slot_at) <= staleness
+    }
+
+    /// Reports `NOT_SERVING` for the server and the geyser service while slots are stalled
+    async fn health_loop(
+        self: Arc<Self>,
+        mut health_reporter: HealthReporter,
+        staleness: Duration,
+    ) {
+        let mut is_serving = true;
+        loop {
+            sleep(staleness.min(HEALTH_CHECK_INTERVAL)).await;
+            let is_serving_new = self.is_serving(staleness, Instant::now());
+            if is_serving_new != is_serving {
+                is_serving = is_serving_new;
+                let status = if is_serving {
+                    info!("slots advance, health status is serving");
+                    ServingStatus::Serving
+                } else {
+                    error!("no new slots within {staleness:?}, health status is not serving");
+                    ServingStatus::NotServing
+                };
+                health_reporter.set_service_status("", status).await;
+                health_reporter
+                    .set_service_status(<GeyserServer<GrpcService> as NamedService>::NAME, status)
+                    .await;
+            }
+        }
+    }
+}
+
+/// Token bucket limiter of live updates sent to the client stream, burst is equal to the rate
+#[derive(Debug)]
+struct UpdatesRateLimiter {
+    rate: u32,
+    policy: ConfigGrpcRateLimitPolicy,
+    tokens: f64,
+    updated_at: Instant,
+    throttled: u64,
+}
+
+impl UpdatesRateLimiter {
+    fn new(rate: u32, policy: ConfigGrpcRateLimitPolicy) -> Self {
+        Self {
+            rate,
+            policy,
+            tokens: rate as f64,
+            updated_at: Instant::now(),
+            throttled: 0,
+        }
+    }
+
+    /// Slots, pings and service messages are never throttled
+    const fn is_limited(message: &FilteredUpdateOneof) -> bool {
+        !matches!(
+            message,
+            FilteredUpdateOneof::Slot(_)
+                | FilteredUpdateOneof::SlotGap(_)
+                | FilteredUpdateOneof::SlotDeltaBatch(_)
+                | FilteredUpdateOneof::Ping
+                | FilteredUpdateOneof::Pong(_)
+                | FilteredUpdateOneof::FilterStats(_)
+                | FilteredUpdateOneof::SnapshotComplete(_)
+                | FilteredUpdateOneof::ServerShutdown
+                | FilteredUpdateOneof::SubscribeAccepted(_)
+        )
+    }
+
+    /// Returns time to wait for the next token if the limit is reached
+    fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.rate as f64);
+        self.updated_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.rate as f64,
+            ))
+        }
+    }
+
+    /// Returns `false` if the update should be dropped, scheduler permit is released before
+    /// waiting for the next token to not hold the turn of other clients
+    async fn acquire(
+        &mut self,
+        queue_metrics: &metrics::ConnectionQueueMetrics,
+        message: &FilteredUpdateOneof,
+        permit: &mut Option<FairSchedulerPermit>,
+    ) -> bool {
+        if !Self::is_limited(message) {
+            return true;
+        }
+
+        let Err(mut wait) = self.try_acquire(Instant::now()) else {
+            return true;
+        };
+        self.throttled += 1;
+        queue_metrics.throttled_inc();
+        match self.policy {
+            ConfigGrpcRateLimitPolicy::Backpressure => {
+                drop(permit.take());
+                loop {
+                    sleep(wait).await;
+                    match self.try_acquire(Instant::now()) {
+                        Ok(()) => return true,
+                        Err(wait_new) => wait = wait_new,
+                    }
+                }
+            }
+            ConfigGrpcRateLimitPolicy::Drop => false,
+        }
+    }
+}
+
+/// Filter limits selected by client token, default limits are used for unknown tokens
+#[derive(Debug)]
+struct FilterLimitsProfiles {
+    default: Arc<FilterLimits>,
+    tokens: HashMap<String, Arc<FilterLimits>>,
+}
+
+impl FilterLimitsProfiles {
+    fn new(
+        default: FilterLimits,
+        profiles: HashMap<String, FilterLimits>,
+        tokens: HashMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        let profiles = profiles
+            .into_iter()
+            .map(|(name, mut limits)| {
+                // memory budget of caches is shared by all connections
+                limits.caches.memory_max = default.caches.memory_max;
+                limits.caches.memory = default.caches.memory.clone();
+                (name, Arc::new(limits))
+            })
+            .collect::<HashMap<_, _>>();
+        let tokens = tokens
+            .into_iter()
+            .map(|(token, profile)| {
+                let limits = profiles
+                    .get(&profile)
+                    .with_context(|| format!("unknown filter limits profile: {profile}"))?;
+                Ok((token, Arc::clone(limits)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            default: Arc::new(default),
+            tokens,
+        })
+    }
+
+    fn get(&self, token: Option<&str>) -> &Arc<FilterLimits> {
+        token
+            .and_then(|token| self.tokens.get(token))
+            .unwrap_or(&self.default)
+    }
+}
+
+#[derive(Debug)]
+pub struct GeyserMessageSender {
+    tx: mpsc::Sender<Message>,
+    full_policy: ConfigGrpcChannelFullPolicy,
+    accounts_snapshot_tx: Option<mpsc::UnboundedSender<Message>>,
+}
+
+impl GeyserMessageSender {
+    /// Startup accounts are kept only if `accounts_snapshot` is enabled
+    pub const fn is_startup_accounts_used(&self) -> bool {
+        self.accounts_snapshot_tx.is_some()
+    }
+
+    /// Startup accounts are not sent to live clients, only stored for `snapshot` in accounts
+    /// filter
+    pub fn send_startup_account(&self, message: &MessageAccount) {
+        if let Some(accounts_snapshot_tx) = &self.accounts_snapshot_tx {
+            let _ = accounts_snapshot_tx.send(Message::Account(message.clone()));
+        }
+    }
+
+    /// Returns `false` if message was not queued
+    pub fn send(&self, message: Message) -> bool {
+        let message = match self.tx.try_send(message) {
+            Ok(()) => return true,
+            Err(mpsc::error::TrySendError::Full(message)) => message,
+            Err(mpsc::error::TrySendError::Closed(_)) => return false,
+        };
+
+        match self.full_policy {
+            ConfigGrpcChannelFullPolicy::Block => self.tx.blocking_send(message).is_ok(),
+            ConfigGrpcChannelFullPolicy::Drop => {
+                metrics::geyser_messages_dropped_inc();
+                false
+            }
+        }
+    }
+}
+
+/// Graceful shutdown of the service, should be used after geyser messages sender is dropped
+#[derive(Debug)]
+pub struct GrpcShutdown {
+    server: Arc<Notify>,
+    clients_tx: mpsc::Sender<()>,
+    clients_rx: mpsc::Receiver<()>,
+    timeout: Duration,
+}
+
+impl GrpcShutdown {
+    /// Rejects new subscriptions and waits until queued updates are sent to connected clients,
+    /// bounded by `shutdown_timeout`
+    pub async fn drain(self) {
+        let Self {
+            server,
+            clients_tx,
+            mut clients_rx,
+            timeout: shutdown_timeout,
+        } = self;
+
+        server.notify_one();
+        drop(clients_tx);
+        // every client holds a sender until the stream is drained
+        match timeout(shutdown_timeout, clients_rx.recv()).await {
+            Ok(_) => info!("all clients drained"),
+            Err(_elapsed) => error!("failed to drain clients within {shutdown_timeout:?}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GrpcService {
+    config_snapshot_client_channel_capacity: usize,
+    config_channel_capacity: usize,
+    config_channel_full_policy: ConfigGrpcClientChannelFullPolicy,
+    config_filter_limits: FilterLimitsProfiles,
+    config_ping_interval: Duration,
+    config_stale_stream_timeout: Option<Duration>,
+    config_rate_limit: ConfigGrpcRateLimit,
+    config_shutdown_timeout: Duration,
+    config_resume_token_interval: Option<Duration>,
+    resume_token_instance: u64,
+    config_strict_commitment: bool,
+    config_json_encoding: bool,
+    fair_scheduler: Option<(Arc<FairScheduler>, ConfigGrpcFairScheduling)>,
+    blocks_meta: Option<BlockMetaStorage>,
+    transaction_accounts: Option<TransactionAccountsStorage>,
+    account_data: Option<AccountDataStorage>,
+    config_account_data_threshold: Option<usize>,
+    accounts_snapshot: Option<Arc<AccountsSnapshotStorage>>,
+    slots_finalized: Option<Arc<SlotsFinalizedStorage>>,
+    subscribe_id: AtomicUsize,
+    subscriptions: Arc<Subscriptions>,
+    snapshot_rx: Mutex<Option<crossbeam_channel::Receiver<Box<Message>>>>,
     broadcast_tx: broadcast::Sender<BroadcastedMessage>,
     replay_stored_slots_tx: Option<mpsc::Sender<ReplayStoredSlotsRequest>>,
     debug_clients_tx: Option<mpsc::UnboundedSender<DebugClientMessage>>,
     filter_names: Arc<Mutex<FilterNames>>,
+    filter_cache: Arc<Mutex<FilterCache>>,
+    clients_tx: mpsc::WeakSender<()>,
+    drained_rx: watch::Receiver<bool>,
 }
 
 impl GrpcService {
@@ -353,8 +1794,8 @@ impl GrpcService {
         is_reload: bool,
     ) -> anyhow::Result<(
         Option<crossbeam_channel::Sender<Box<Message>>>,
-        mpsc::UnboundedSender<Message>,
-        Arc<Notify>,
+        GeyserMessageSender,
+        GrpcShutdown,
     )> {
         // Bind service address
         let incoming = TcpIncoming::new(
@@ -377,11 +1818,65 @@ impl GrpcService {
         let (blocks_meta, blocks_meta_tx) = if config.unary_disabled {
             (None, None)
         } else {
-            let (blocks_meta, blocks_meta_tx) =
-                BlockMetaStorage::new(config.unary_concurrency_limit);
+            let (blocks_meta, blocks_meta_tx) = BlockMetaStorage::new(
+                config.unary_concurrency_limit,
+                config.replay_stored_slots,
+                config.block_meta_range_max_slots,
+            );
             (Some(blocks_meta), Some(blocks_meta_tx))
         };
 
+        // Account updates by transaction signature
+        let (transaction_accounts, transaction_accounts_tx) = if config.unary_disabled
+            || config.transaction_accounts_cache_size == 0
+        {
+            (None, None)
+        } else {
+            let (transaction_accounts, transaction_accounts_tx) = TransactionAccountsStorage::new(
+                config.unary_concurrency_limit,
+                config.transaction_accounts_cache_size,
+                config.transaction_accounts_cache_bytes_max,
+            );
+            (Some(transaction_accounts), Some(transaction_accounts_tx))
+        };
+
+        // Data of big accounts sent out-of-band
+        anyhow::ensure!(
+            config.account_data_out_of_band.is_none() || !config.unary_disabled,
+            "`account_data_out_of_band` requires unary methods"
+        );
+        let (account_data, account_data_tx) = match config.account_data_out_of_band {
+            Some(config_account_data) => {
+                let (account_data, account_data_tx) =
+                    AccountDataStorage::new(config.unary_concurrency_limit, config_account_data);
+                (
+                    Some(account_data),
+                    Some((account_data_tx, config_account_data.threshold)),
+                )
+            }
+            None => (None, None),
+        };
+
+        // Latest accounts state for snapshot on subscribe
+        let (accounts_snapshot, accounts_snapshot_tx) = if config.accounts_snapshot {
+            let (accounts_snapshot, accounts_snapshot_tx) = AccountsSnapshotStorage::new();
+            (
+                Some(Arc::new(accounts_snapshot)),
+                Some(accounts_snapshot_tx),
+            )
+        } else {
+            (None, None)
+        };
+
+        // Latest finalized slots for replay on subscribe
+        let (slots_finalized, slots_finalized_tx) = if config.slots_replay_finalized_max > 0 {
+            let (slots_finalized, slots_finalized_tx) =
+                SlotsFinalizedStorage::new(config.slots_replay_finalized_max);
+            (Some(Arc::new(slots_finalized)), Some(slots_finalized_tx))
+        } else {
+            (None, None)
+        };
+
         // Messages to clients combined by commitment
         let (broadcast_tx, _) = broadcast::channel(config.channel_capacity);
         // attempt to prevent spam of geyser loop with capacity eq 1
@@ -391,19 +1886,30 @@ impl GrpcService {
             let (tx, rx) = mpsc::channel(1);
             (Some(tx), Some(rx))
         };
+        anyhow::ensure!(
+            config.resume_token_interval.is_none() || replay_stored_slots_tx.is_some(),
+            "resume_token_interval requires replay_stored_slots"
+        );
+        // message ids are started from zero, tokens of previous instances are not resolved
+        let resume_token_instance = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
 
-        // gRPC server builder with optional TLS
-        let mut server_builder = Server::builder();
-        if let Some(tls_config) = &config.tls_config {
-            let (cert, key) = tokio::try_join!(
-                fs::read(&tls_config.cert_path),
-                fs::read(&tls_config.key_path)
-            )
-            .context("failed to load tls_config files")?;
-            server_builder = server_builder
-                .tls_config(ServerTlsConfig::new().identity(Identity::from_pem(cert, key)))
-                .context("failed to apply tls_config")?;
-        }
+        // Optional TLS with reloadable certificate
+        let tls = match config.tls_config {
+            Some(tls_config) => {
+                let certified_key = tls::load_certified_key(&tls_config).await?;
+                let resolver = Arc::new(CertResolver::new(certified_key));
+                let client_verifier = tls::load_client_verifier(&tls_config).await?;
+                let acceptor = tls::create_acceptor(Arc::clone(&resolver), client_verifier)
+                    .context("failed to apply tls_config")?;
+                Some((acceptor, resolver, tls_config))
+            }
+            None => None,
+        };
+
+        // gRPC server builder
+        let mut server_builder = Server::builder();
         if let Some(enabled) = config.server_http2_adaptive_window {
             server_builder = server_builder.http2_adaptive_window(Some(enabled));
         }
@@ -426,20 +1932,58 @@ impl GrpcService {
             config.filter_names_size_limit,
             config.filter_names_cleanup_interval,
         )));
+        let filter_cache = Arc::new(Mutex::new(FilterCache::new(
+            config.filter_cache_size,
+            config.filter_cache_ttl,
+        )));
+
+        // Connected clients and drain of queued messages on shutdown
+        let (clients_tx, clients_rx) = mpsc::channel(1);
+        let (drained_tx, drained_rx) = watch::channel(false);
 
         // Create Server
         let max_decoding_message_size = config.max_decoding_message_size;
         let mut service = GeyserServer::new(Self {
             config_snapshot_client_channel_capacity: config.snapshot_client_channel_capacity,
             config_channel_capacity: config.channel_capacity,
-            config_filter_limits: Arc::new(config.filter_limits),
+            config_channel_full_policy: config.channel_full_policy,
+            config_filter_limits: FilterLimitsProfiles::new(
+                config.filter_limits,
+                config.filter_limits_profiles,
+                config.filter_limits_tokens,
+            )?,
+            config_ping_interval: config.ping_interval,
+            config_stale_stream_timeout: config.stale_stream_timeout,
+            config_rate_limit: config.rate_limit,
+            config_shutdown_timeout: config.shutdown_timeout,
+            config_resume_token_interval: config.resume_token_interval,
+            resume_token_instance,
+            config_strict_commitment: config.strict_commitment,
+            config_json_encoding: config.json_encoding,
+            fair_scheduler: config
+                .fair_scheduling
+                .map(|config| (FairScheduler::new(config.concurrency), config)),
             blocks_meta,
+            transaction_accounts,
+            account_data,
+            config_account_data_threshold: config
+                .account_data_out_of_band
+                .map(|config| config.threshold),
+            accounts_snapshot,
+            slots_finalized,
             subscribe_id: AtomicUsize::new(0),
+            subscriptions: Arc::new(Subscriptions::new(
+                config.max_subscriptions,
+                config.max_subscriptions_per_token,
+            )),
             snapshot_rx: Mutex::new(snapshot_rx),
             broadcast_tx: broadcast_tx.clone(),
             replay_stored_slots_tx,
             debug_clients_tx,
             filter_names,
+            filter_cache,
+            clients_tx: clients_tx.downgrade(),
+            drained_rx,
         })
         .max_decoding_message_size(max_decoding_message_size);
         for encoding in config.compression.accept {
@@ -450,7 +1994,14 @@ impl GrpcService {
         }
 
         // Run geyser message loop
-        let (messages_tx, messages_rx) = mpsc::unbounded_channel();
+        let (messages_tx, messages_rx) = mpsc::channel(config.geyser_channel_capacity);
+        let messages_tx = GeyserMessageSender {
+            tx: messages_tx,
+            full_policy: config.geyser_channel_full_policy,
+            accounts_snapshot_tx: accounts_snapshot_tx.clone(),
+        };
+        let slot_liveness = Arc::new(SlotLiveness::new(Instant::now()));
+        let slot_liveness_geyser = Arc::clone(&slot_liveness);
         spawn_blocking(move || {
             let mut builder = Builder::new_multi_thread();
             if let Some(worker_threads) = config_tokio.worker_threads {
@@ -469,12 +2020,24 @@ impl GrpcService {
                 .block_on(Self::geyser_loop(
                     messages_rx,
                     blocks_meta_tx,
+                    transaction_accounts_tx,
+                    account_data_tx,
+                    accounts_snapshot_tx,
+                    slots_finalized_tx,
                     broadcast_tx,
                     replay_stored_slots_rx,
                     config.replay_stored_slots,
+                    config.replay_retention_duration,
+                    config.max_concurrent_block_buffers,
+                    slot_liveness_geyser,
+                    drained_tx,
                 ));
         });
 
+        let client_identity = tls
+            .as_ref()
+            .and_then(|(_acceptor, _resolver, tls_config)| tls_config.client_identity);
+
         // Run Server
         let shutdown = Arc::new(Notify::new());
         let shutdown_grpc = Arc::clone(&shutdown);
@@ -482,9 +2045,22 @@ impl GrpcService {
             // gRPC Health check service
             let (mut health_reporter, health_service) = health_reporter();
             health_reporter.set_serving::<GeyserServer<Self>>().await;
+            if let Some(staleness) = config.health_slot_staleness {
+                tokio::spawn(slot_liveness.health_loop(health_reporter, staleness));
+            }
 
-            server_builder
-                .layer(interceptor(move |request: Request<()>| {
+            let router = server_builder
+                .layer(interceptor(move |mut request: Request<()>| {
+                    if let Some(field) = client_identity {
+                        let identity = request.peer_certs().and_then(|certs| {
+                            certs
+                                .first()
+                                .and_then(|cert| ClientIdentity::from_cert(cert, field))
+                        });
+                        if let Some(identity) = identity {
+                            request.extensions_mut().insert(identity);
+                        }
+                    }
                     if let Some(x_token) = &config.x_token {
                         match request.metadata().get("x-token") {
                             Some(token) if x_token == token => Ok(request),
@@ -495,20 +2071,47 @@ impl GrpcService {
                     }
                 }))
                 .add_service(health_service)
-                .add_service(service)
-                .serve_with_incoming_shutdown(incoming, shutdown_grpc.notified())
-                .await
+                .add_service(service);
+            match tls {
+                Some((acceptor, resolver, tls_config)) => {
+                    let incoming = tls::incoming(incoming, acceptor, resolver, tls_config);
+                    router
+                        .serve_with_incoming_shutdown(incoming, shutdown_grpc.notified())
+                        .await
+                }
+                None => {
+                    router
+                        .serve_with_incoming_shutdown(incoming, shutdown_grpc.notified())
+                        .await
+                }
+            }
         });
 
+        let shutdown = GrpcShutdown {
+            server: shutdown,
+            clients_tx,
+            clients_rx,
+            timeout: config.shutdown_timeout,
+        };
+
         Ok((snapshot_tx, messages_tx, shutdown))
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn geyser_loop(
-        mut messages_rx: mpsc::UnboundedReceiver<Message>,
+        mut messages_rx: mpsc::Receiver<Message>,
         blocks_meta_tx: Option<mpsc::UnboundedSender<Message>>,
+        transaction_accounts_tx: Option<mpsc::UnboundedSender<MessageAccount>>,
+        account_data_tx: Option<(mpsc::UnboundedSender<MessageAccount>, usize)>,
+        accounts_snapshot_tx: Option<mpsc::UnboundedSender<Message>>,
+        slots_finalized_tx: Option<mpsc::UnboundedSender<MessageSlot>>,
         broadcast_tx: broadcast::Sender<BroadcastedMessage>,
         replay_stored_slots_rx: Option<mpsc::Receiver<ReplayStoredSlotsRequest>>,
         replay_stored_slots: u64,
+        replay_retention_duration: Option<Duration>,
+        max_concurrent_block_buffers: usize,
+        slot_liveness: Arc<SlotLiveness>,
+        drained_tx: watch::Sender<bool>,
     ) {
         const PROCESSED_MESSAGES_MAX: usize = 31;
         const PROCESSED_MESSAGES_SLEEP: Duration = Duration::from_millis(10);
@@ -531,6 +2134,7 @@ impl GrpcService {
                     // Update metrics
                     if let Message::Slot(slot_message) = &message {
                         metrics::update_slot_plugin_status(slot_message.status, slot_message.slot);
+                        slot_liveness.update(slot_message.slot, Instant::now());
                     }
 
                     // Update blocks info
@@ -540,6 +2144,43 @@ impl GrpcService {
                         }
                     }
 
+                    // Update account updates by transaction signature
+                    if let Some(transaction_accounts_tx) = &transaction_accounts_tx {
+                        if let Message::Account(msg) = &message {
+                            if msg.account.txn_signature.is_some() {
+                                let _ = transaction_accounts_tx.send(msg.clone());
+                            }
+                        }
+                    }
+
+                    // Update data of accounts sent out-of-band
+                    if let Some((account_data_tx, threshold)) = &account_data_tx {
+                        if let Message::Account(msg) = &message {
+                            if FilteredUpdateAccount::is_data_out_of_band(
+                                msg.account.data.len(),
+                                *threshold,
+                            ) {
+                                let _ = account_data_tx.send(msg.clone());
+                            }
+                        }
+                    }
+
+                    // Update latest accounts state, slot statuses are sent with missed statuses
+                    if let Some(accounts_snapshot_tx) = &accounts_snapshot_tx {
+                        if matches!(&message, Message::Account(_)) {
+                            let _ = accounts_snapshot_tx.send(message.clone());
+                        }
+                    }
+
+                    // Update latest finalized slots
+                    if let Some(slots_finalized_tx) = &slots_finalized_tx {
+                        if let Message::Slot(msg) = &message {
+                            if msg.status == SlotStatus::Finalized {
+                                let _ = slots_finalized_tx.send(msg.clone());
+                            }
+                        }
+                    }
+
                     // Remove outdated block reconstruction info
                     match &message {
                         // On startup we can receive multiple Confirmed/Finalized slots without BlockMeta message
@@ -549,51 +2190,66 @@ impl GrpcService {
                         }
                         Message::Slot(msg) if msg.status == SlotStatus::Finalized => {
                             // keep extra 10 slots + slots for replay
-                            if let Some(msg_slot) = msg.slot.checked_sub(10 + replay_stored_slots) {
-                                loop {
-                                    match messages.keys().next().cloned() {
-                                        Some(slot) if slot < msg_slot => {
-                                            if let Some(slot_messages) = messages.remove(&slot) {
-                                                match processed_first_slot {
-                                                    Some(processed_first) if slot <= processed_first => continue,
-                                                    None => continue,
-                                                    _ => {}
-                                                }
+                            let msg_slot = SlotMessages::retention_first_slot(
+                                &messages,
+                                msg.slot,
+                                replay_stored_slots,
+                                replay_retention_duration,
+                                SystemTime::now(),
+                            );
+                            loop {
+                                match messages.keys().next().cloned() {
+                                    Some(slot) if slot < msg_slot => {
+                                        if let Some(slot_messages) = messages.remove(&slot) {
+                                            match processed_first_slot {
+                                                Some(processed_first) if slot <= processed_first => continue,
+                                                None => continue,
+                                                _ => {}
+                                            }
 
-                                                if !slot_messages.sealed && slot_messages.finalized_at.is_some() {
-                                                    let mut reasons = vec![];
-                                                    if let Some(block_meta) = slot_messages.block_meta {
-                                                        let block_txn_count = block_meta.executed_transaction_count as usize;
-                                                        let msg_txn_count = slot_messages.transactions.len();
-                                                        if block_txn_count != msg_txn_count {
-                                                            reasons.push("InvalidTxnCount");
-                                                            error!("failed to reconstruct #{slot} -- tx count: {block_txn_count} vs {msg_txn_count}");
-                                                        }
-                                                        let block_entries_count = block_meta.entries_count as usize;
-                                                        let msg_entries_count = slot_messages.entries.len();
-                                                        if block_entries_count != msg_entries_count {
-                                                            reasons.push("InvalidEntriesCount");
-                                                            error!("failed to reconstruct #{slot} -- entries count: {block_entries_count} vs {msg_entries_count}");
-                                                        }
-                                                    } else {
-                                                        reasons.push("NoBlockMeta");
+                                            if slot_messages.is_buffered() && slot_messages.finalized_at.is_some() {
+                                                let mut reasons = vec![];
+                                                if let Some(block_meta) = slot_messages.block_meta {
+                                                    let block_txn_count = block_meta.executed_transaction_count as usize;
+                                                    let msg_txn_count = slot_messages.transactions.len();
+                                                    if block_txn_count != msg_txn_count {
+                                                        reasons.push("InvalidTxnCount");
+                                                        error!("failed to reconstruct #{slot} -- tx count: {block_txn_count} vs {msg_txn_count}");
                                                     }
-                                                    let reason = reasons.join(",");
-
-                                                    metrics::update_invalid_blocks(format!("failed reconstruct {reason}"));
+                                                    let block_entries_count = block_meta.entries_count as usize;
+                                                    let msg_entries_count = slot_messages.entries.len();
+                                                    if block_entries_count != msg_entries_count {
+                                                        reasons.push("InvalidEntriesCount");
+                                                        error!("failed to reconstruct #{slot} -- entries count: {block_entries_count} vs {msg_entries_count}");
+                                                    }
+                                                } else {
+                                                    reasons.push("NoBlockMeta");
                                                 }
+                                                let reason = reasons.join(",");
+
+                                                metrics::update_invalid_blocks(format!("failed reconstruct {reason}"));
                                             }
                                         }
-                                        _ => break,
                                     }
+                                    _ => break,
                                 }
                             }
                         }
                         _ => {}
                     }
 
+                    // Limit number of partial blocks
+                    if let BTreeMapEntry::Vacant(entry) = messages.entry(message.get_slot()) {
+                        entry.insert(SlotMessages::default());
+                        let evicted = SlotMessages::evict_block_buffers(&mut messages, max_concurrent_block_buffers);
+                        if evicted > 0 {
+                            metrics::block_buffers_evicted_inc(evicted);
+                        }
+                    }
+
                     // Update block reconstruction info
                     let slot_messages = messages.entry(message.get_slot()).or_default();
+                    slot_messages.last_created_at = message.get_created_at();
                     if let Message::Slot(msg) = &message {
                         match msg.status {
                             SlotStatus::Processed => {
@@ -628,6 +2284,7 @@ impl GrpcService {
                     }
                     let mut sealed_block_msg = None;
                     match &message {
+                        Message::BlockMeta(_) | Message::Transaction(_) | Message::Entry(_) if slot_messages.evicted => {}
                         Message::BlockMeta(msg) => {
                             if slot_messages.block_meta.is_some() {
                                 metrics::update_invalid_blocks("unexpected message: BlockMeta (duplicate)");
@@ -696,6 +2353,7 @@ impl GrpcService {
                                     parent: entry.parent_slot,
                                     status,
                                     dead_error: None,
+                                    raw_status: None,
                                     created_at: Timestamp::from(SystemTime::now())
                                 });
                                 messages_vec.push((msgid_gen.next(), message_slot));
@@ -706,6 +2364,9 @@ impl GrpcService {
 
                     for message in messages_vec.into_iter().rev() {
                         if let Message::Slot(slot) = &message.1 {
+                            if let Some(accounts_snapshot_tx) = &accounts_snapshot_tx {
+                                let _ = accounts_snapshot_tx.send(message.1.clone());
+                            }
                             let (mut confirmed_messages, mut finalized_messages) = match slot.status {
                                 SlotStatus::Processed | SlotStatus::FirstShredReceived | SlotStatus::Completed | SlotStatus::CreatedBank | SlotStatus::Dead => {
                                     (Vec::with_capacity(1), Vec::with_capacity(1))
@@ -804,61 +2465,88 @@ impl GrpcService {
                         let _ = broadcast_tx.send((CommitmentLevel::Processed, processed_messages.into()));
                         processed_messages = Vec::with_capacity(PROCESSED_MESSAGES_MAX);
                     }
+                    // sender is dropped on shutdown, all queued messages are broadcasted
+                    if messages_rx.is_closed() && messages_rx.is_empty() {
+                        break;
+                    }
                     processed_sleep.as_mut().reset(Instant::now() + PROCESSED_MESSAGES_SLEEP);
                 }
-                Some((commitment, replay_slot, tx)) = replay_stored_slots_rx.recv() => {
-                    if let Some((slot, _)) = messages.first_key_value() {
-                        if replay_slot < *slot {
-                            let _ = tx.send(ReplayedResponse::Lagged(*slot));
-                            continue;
-                        }
-                    }
-
-                    let mut replayed_messages = Vec::with_capacity(32_768);
-                    for (slot, messages) in messages.iter() {
-                        if *slot >= replay_slot {
-                            replayed_messages.extend_from_slice(&messages.messages_slots);
-                            if commitment == CommitmentLevel::Processed
-                                || (commitment == CommitmentLevel::Finalized && messages.finalized)
-                                || (commitment == CommitmentLevel::Confirmed && messages.confirmed)
-                            {
-                                replayed_messages.extend(messages.messages.iter().filter_map(|v| v.clone()));
-                            }
-                        }
-                    }
-                    let _ = tx.send(ReplayedResponse::Messages(replayed_messages));
+                Some((commitment, replay_from, tx)) = replay_stored_slots_rx.recv() => {
+                    let _ = tx.send(match replay_from {
+                        ReplayFrom::Slot(slot) => SlotMessages::replay(&messages, commitment, slot),
+                        ReplayFrom::Token(token) => SlotMessages::replay_after(&messages, token),
+                    });
                 }
                 else => break,
             }
         }
+        let _ = drained_tx.send(true);
+    }
+
+    /// Replaces data of big accounts by the reference for `GetAccountData`
+    fn set_account_data_out_of_band(message: &mut FilteredUpdate, threshold: Option<usize>) {
+        if let (Some(threshold), FilteredUpdateOneof::Account(account)) =
+            (threshold, &mut message.message)
+        {
+            account.set_data_out_of_band(threshold);
+        }
     }
 
-    #[allow(clippy::too_many_arguments)]
     async fn client_loop(
         id: usize,
-        endpoint: String,
         stream_tx: mpsc::Sender<TonicResult<FilteredUpdate>>,
-        mut client_rx: mpsc::UnboundedReceiver<Option<(Option<u64>, Filter)>>,
-        mut snapshot_rx: Option<crossbeam_channel::Receiver<Box<Message>>>,
+        mut client_rx: mpsc::UnboundedReceiver<Option<(Option<ReplayFrom>, Filter)>>,
         mut messages_rx: broadcast::Receiver<BroadcastedMessage>,
-        replay_stored_slots_tx: Option<mpsc::Sender<ReplayStoredSlotsRequest>>,
-        debug_client_tx: Option<mpsc::UnboundedSender<DebugClientMessage>>,
+        context: ClientLoopContext,
+        _client_guard: mpsc::Sender<()>,
         drop_client: impl FnOnce(),
     ) {
+        let ClientLoopContext {
+            endpoint,
+            mut snapshot_rx,
+            replay_stored_slots_tx,
+            mut resume_tokens,
+            strict_commitment,
+            accounts_snapshot,
+            slots_finalized,
+            account_data_threshold,
+            debug_client_tx,
+            fair_scheduler,
+            mut rate_limit_rx,
+            rate_limit_policy,
+            mut channel_capacity_rx,
+            channel_full_policy,
+            mut drained_rx,
+            shutdown_timeout,
+            caches_memory,
+        } = context;
+
         let mut filter = Filter::default();
+        let mut strict_commitment_gate = None;
+        let mut rate_limiter = (*rate_limit_rx.borrow_and_update())
+            .map(|rate| UpdatesRateLimiter::new(rate, rate_limit_policy));
+        let mut channel_capacity = *channel_capacity_rx.borrow_and_update();
+        // updates queued by the client loop with `drop_oldest` policy
+        let mut pending = VecDeque::new();
+        // sequence number of the next filtered update in the stream
+        let mut seq = 0;
+        let mut slot_seqs = SlotSeqs::default();
         metrics::update_subscriptions(&endpoint, None, Some(&filter));
+        let mut queue_metrics = metrics::ConnectionQueueMetrics::new(id);
 
         metrics::connections_total_inc();
         DebugClientMessage::maybe_send(&debug_client_tx, || DebugClientMessage::UpdateFilter {
             id,
             filter: Box::new(filter.clone()),
         });
-        info!("client #{id}: new");
+        client_log!(info, "new");
 
         let mut is_alive = true;
+        // sent before updates of the first accepted filter
+        let mut subscribe_accepted = false;
         if let Some(snapshot_rx) = snapshot_rx.take() {
+            subscribe_accepted = true;
             Self::client_loop_snapshot(
-                id,
                 &endpoint,
                 &stream_tx,
                 &mut client_rx,
@@ -870,6 +2558,10 @@ impl GrpcService {
         }
 
         if is_alive {
+            let stats_sleep = sleep(Duration::ZERO);
+            tokio::pin!(stats_sleep);
+            let mut drain_deadline = None;
+
             'outer: loop {
                 tokio::select! {
                     mut message = client_rx.recv() => {
@@ -888,25 +2580,62 @@ impl GrpcService {
                         }
 
                         match message {
-                            Some(Some((from_slot, filter_new))) => {
+                            Some(Some((replay_from, filter_new))) => {
                                 metrics::update_subscriptions(&endpoint, Some(&filter), Some(&filter_new));
                                 filter = filter_new;
+                                // updates buffered for the previous filter are dropped
+                                strict_commitment_gate = strict_commitment.then(|| StrictCommitmentGate::new(filter.get_commitment_level()));
                                 DebugClientMessage::maybe_send(&debug_client_tx, || DebugClientMessage::UpdateFilter { id, filter: Box::new(filter.clone()) });
-                                info!("client #{id}: filter updated");
+                                client_log!(info, "filter updated");
+                                if !subscribe_accepted {
+                                    subscribe_accepted = true;
+                                    match stream_tx.send(Ok(filter.get_subscribe_accepted_msg())).await {
+                                        Ok(()) => {}
+                                        Err(mpsc::error::SendError(_)) => {
+                                            client_log!(error, "stream closed");
+                                            break 'outer;
+                                        }
+                                    }
+                                }
+                                if let Some(interval) = filter.get_stats_interval() {
+                                    stats_sleep.as_mut().reset(Instant::now() + interval);
+                                }
+                                if rate_limit_rx.has_changed().unwrap_or(false) {
+                                    rate_limiter = (*rate_limit_rx.borrow_and_update())
+                                        .map(|rate| UpdatesRateLimiter::new(rate, rate_limit_policy));
+                                }
+                                if channel_capacity_rx.has_changed().unwrap_or(false) {
+                                    channel_capacity = *channel_capacity_rx.borrow_and_update();
+                                }
 
-                                if let Some(from_slot) = from_slot {
+                                if let Some(replay_from) = replay_from {
                                     let Some(replay_stored_slots_tx) = &replay_stored_slots_tx else {
-                                        info!("client #{id}: from_slot is not supported");
+                                        client_log!(info, "from_slot is not supported");
                                         tokio::spawn(async move {
                                             let _ = stream_tx.send(Err(Status::internal("from_slot is not supported"))).await;
                                         });
                                         break 'outer;
                                     };
 
+                                    let position = match replay_from {
+                                        ReplayFrom::Slot(slot) => format!("broadcast from {slot}"),
+                                        ReplayFrom::Token(token) => {
+                                            // message ids of other server instances are unknown
+                                            if resume_tokens.as_ref().map(|tokens| tokens.instance) != Some(token.instance) {
+                                                client_log!(info, "resume_token of other server instance");
+                                                tokio::spawn(async move {
+                                                    let _ = stream_tx.send(Err(Status::out_of_range("resume_token is issued by other server instance"))).await;
+                                                });
+                                                break 'outer;
+                                            }
+                                            "resume_token position".to_owned()
+                                        }
+                                    };
+
                                     let (tx, rx) = oneshot::channel();
                                     let commitment = filter.get_commitment_level();
-                                    if let Err(_error) = replay_stored_slots_tx.send((commitment, from_slot, tx)).await {
-                                        error!("client #{id}: failed to send from_slot request");
+                                    if let Err(_error) = replay_stored_slots_tx.send((commitment, replay_from, tx)).await {
+                                        client_log!(error, "failed to send from_slot request");
                                         tokio::spawn(async move {
                                             let _ = stream_tx.send(Err(Status::internal("failed to send from_slot request"))).await;
                                         });
@@ -916,17 +2645,17 @@ impl GrpcService {
                                     let mut messages = match rx.await {
                                         Ok(ReplayedResponse::Messages(messages)) => messages,
                                         Ok(ReplayedResponse::Lagged(slot)) => {
-                                            info!("client #{id}: broadcast from {from_slot} is not available");
+                                            client_log!(info, "{position} is not available");
                                             tokio::spawn(async move {
                                                 let message = format!(
-                                                    "broadcast from {from_slot} is not available, last available: {slot}"
+                                                    "{position} is not available, last available: {slot}"
                                                 );
-                                                let _ = stream_tx.send(Err(Status::internal(message))).await;
+                                                let _ = stream_tx.send(Err(Status::out_of_range(message))).await;
                                             });
                                             break 'outer;
                                         },
                                         Err(_error) => {
-                                            error!("client #{id}: failed to get replay response");
+                                            client_log!(error, "failed to get replay response");
                                             tokio::spawn(async move {
                                                 let _ = stream_tx.send(Err(Status::internal("failed to get replay response"))).await;
                                             });
@@ -936,11 +2665,65 @@ impl GrpcService {
 
                                     messages.sort_by_key(|msg| msg.0);
                                     for (_msgid, message) in messages.iter() {
-                                        for message in filter.get_updates(message, Some(commitment)) {
+                                        let mut updates = filter.get_updates(message, Some(commitment));
+                                        if let Some(gate) = &mut strict_commitment_gate {
+                                            updates = gate.push(message, updates);
+                                        }
+                                        for mut message in updates {
+                                            message.seq = Some(seq);
+                                            seq += 1;
+                                            slot_seqs.set(&mut message);
+                                            Self::set_account_data_out_of_band(
+                                                &mut message,
+                                                account_data_threshold,
+                                            );
+                                            filter.wrap_update(&mut message);
+                                            match stream_tx.send(Ok(message)).await {
+                                                Ok(()) => {}
+                                                Err(mpsc::error::SendError(_)) => {
+                                                    client_log!(error, "stream closed");
+                                                    break 'outer;
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+
+                                // current state is sent after replayed messages, before live updates
+                                if filter.is_accounts_snapshot() {
+                                    if let Some(accounts_snapshot) = &accounts_snapshot {
+                                        let mut reader = accounts_snapshot.reader(filter.get_commitment_level()).await;
+                                        while let Some(updates) = reader.next(&filter).await {
+                                            for mut message in updates {
+                                                message.seq = Some(seq);
+                                                seq += 1;
+                                                slot_seqs.set(&mut message);
+                                                Self::set_account_data_out_of_band(
+                                                    &mut message,
+                                                    account_data_threshold,
+                                                );
+                                                filter.wrap_update(&mut message);
+                                                match stream_tx.send(Ok(message)).await {
+                                                    Ok(()) => {}
+                                                    Err(mpsc::error::SendError(_)) => {
+                                                        client_log!(error, "stream closed");
+                                                        break 'outer;
+                                                    }
+                                                }
+                                            }
+                                        }
+                                        client_log!(info, "accounts snapshot sent");
+                                    }
+                                }
+
+                                // latest finalized slots are sent before live updates
+                                if filter.get_slots_replay_finalized() > 0 {
+                                    if let Some(slots_finalized) = &slots_finalized {
+                                        for message in slots_finalized.get_updates(&filter).await {
                                             match stream_tx.send(Ok(message)).await {
                                                 Ok(()) => {}
                                                 Err(mpsc::error::SendError(_)) => {
-                                                    error!("client #{id}: stream closed");
+                                                    client_log!(error, "stream closed");
                                                     break 'outer;
                                                 }
                                             }
@@ -963,7 +2746,7 @@ impl GrpcService {
                                 break 'outer;
                             },
                             Err(broadcast::error::RecvError::Lagged(_)) => {
-                                info!("client #{id}: lagged to receive geyser messages");
+                                client_log!(info, "lagged to receive geyser messages");
                                 tokio::spawn(async move {
                                     let _ = stream_tx.send(Err(Status::internal("lagged to receive geyser messages"))).await;
                                 });
@@ -971,25 +2754,86 @@ impl GrpcService {
                             }
                         };
 
-                        if commitment == filter.get_commitment_level() {
-                            for (_msgid, message) in messages.iter() {
-                                for message in filter.get_updates(message, Some(commitment)) {
-                                    match stream_tx.try_send(Ok(message)) {
+                        if filter.is_commitment_level_used(commitment) {
+                            // permit is held until all updates are sent to the stream or the rate limit is reached
+                            let mut permit = match &fair_scheduler {
+                                Some(fair_scheduler) => {
+                                    let ts = Instant::now();
+                                    let permit = fair_scheduler.acquire(messages.len()).await;
+                                    metrics::observe_scheduling_delay(&endpoint, ts.elapsed());
+                                    Some(permit)
+                                }
+                                None => None,
+                            };
+
+                            let now = Instant::now();
+                            let is_request_commitment = commitment == filter.get_commitment_level();
+                            for (msgid, message) in messages.iter() {
+                                let mut updates = filter.get_updates(message, Some(commitment));
+                                // updates with raised commitment are produced only for slots which reached it
+                                let mut is_gate_empty = true;
+                                if let Some(gate) = strict_commitment_gate.as_mut().filter(|_| is_request_commitment) {
+                                    updates = gate.push(message, updates);
+                                    is_gate_empty = gate.is_empty();
+                                }
+                                // position of the token should not skip buffered updates
+                                if let Some(resume_tokens) = resume_tokens.as_mut().filter(|_| is_request_commitment && is_gate_empty) {
+                                    resume_tokens.maybe_set(&mut updates, commitment, message.get_slot(), *msgid, now);
+                                }
+                                for mut message in updates {
+                                    if let Some(rate_limiter) = &mut rate_limiter {
+                                        if !rate_limiter.acquire(&queue_metrics, &message.message, &mut permit).await {
+                                            continue;
+                                        }
+                                    }
+                                    message.seq = Some(seq);
+                                    seq += 1;
+                                    slot_seqs.set(&mut message);
+                                    Self::set_account_data_out_of_band(
+                                        &mut message,
+                                        account_data_threshold,
+                                    );
+
+                                    // labeled by the kind of the plain update
+                                    metrics::observe_message_send_latency(&message);
+                                    filter.wrap_update(&mut message);
+                                    if channel_full_policy == ConfigGrpcClientChannelFullPolicy::DropOldest {
+                                        pending.push_back(message);
+                                        if pending.len() > channel_capacity {
+                                            pending.pop_front();
+                                            queue_metrics.dropped_inc();
+                                        }
+                                        continue;
+                                    }
+
+                                    let queued = stream_tx.max_capacity() - stream_tx.capacity();
+                                    let result = if queued < channel_capacity {
+                                        stream_tx.try_send(Ok(message))
+                                    } else {
+                                        Err(mpsc::error::TrySendError::Full(Ok(message)))
+                                    };
+                                    match result {
                                         Ok(()) => {}
                                         Err(mpsc::error::TrySendError::Full(_)) => {
-                                            error!("client #{id}: lagged to send an update");
+                                            queue_metrics.dropped_inc();
+                                            client_log!(error, "lagged to send an update");
                                             tokio::spawn(async move {
                                                 let _ = stream_tx.send(Err(Status::internal("lagged to send an update"))).await;
                                             });
                                             break 'outer;
                                         }
                                         Err(mpsc::error::TrySendError::Closed(_)) => {
-                                            error!("client #{id}: stream closed");
+                                            client_log!(error, "stream closed");
                                             break 'outer;
                                         }
                                     }
                                 }
                             }
+                            metrics::update_filter_caches_memory(&caches_memory);
+                            if !Self::client_loop_flush(&stream_tx, &mut pending) {
+                                break 'outer;
+                            }
+                            queue_metrics.set_depth(stream_tx.max_capacity() - stream_tx.capacity() + pending.len());
                         }
 
                         if commitment == CommitmentLevel::Processed && debug_client_tx.is_some() {
@@ -1000,35 +2844,149 @@ impl GrpcService {
                             }
                         }
                     }
+                    is_open = Self::client_loop_send_pending(&stream_tx, &mut pending), if !pending.is_empty() => {
+                        if !is_open {
+                            break 'outer;
+                        }
+                        queue_metrics.set_depth(stream_tx.max_capacity() - stream_tx.capacity() + pending.len());
+                    }
+                    Ok(_) = drained_rx.wait_for(|drained| *drained), if drain_deadline.is_none() => {
+                        client_log!(info, "draining");
+                        drain_deadline = Some(Instant::now() + shutdown_timeout);
+                    }
+                    () = &mut stats_sleep, if filter.get_stats_interval().is_some() => {
+                        if let Some(interval) = filter.get_stats_interval() {
+                            stats_sleep.as_mut().reset(Instant::now() + interval);
+                        }
+                        if let Some(message) = filter.get_stats_msg() {
+                            match stream_tx.send(Ok(message)).await {
+                                Ok(()) => {}
+                                Err(mpsc::error::SendError(_)) => {
+                                    client_log!(error, "stream closed");
+                                    break 'outer;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if let Some(deadline) = drain_deadline {
+                    if messages_rx.is_empty() || Instant::now() >= deadline {
+                        Self::client_loop_drain(
+                            &stream_tx,
+                            &filter,
+                            std::mem::take(&mut pending),
+                            deadline,
+                        )
+                        .await;
+                        break 'outer;
+                    }
                 }
             }
         }
 
+        if let Some(rate_limiter) = rate_limiter.filter(|rate_limiter| rate_limiter.throttled > 0) {
+            client_log!(info, "{} updates throttled", rate_limiter.throttled);
+        }
         metrics::connections_total_dec();
         DebugClientMessage::maybe_send(&debug_client_tx, || DebugClientMessage::Removed { id });
         metrics::update_subscriptions(&endpoint, Some(&filter), None);
-        info!("client #{id}: removed");
+        drop(filter);
+        metrics::update_filter_caches_memory(&caches_memory);
+        client_log!(info, "removed");
         drop_client();
     }
 
+    async fn client_loop_drain(
+        stream_tx: &mpsc::Sender<TonicResult<FilteredUpdate>>,
+        filter: &Filter,
+        pending: VecDeque<FilteredUpdate>,
+        deadline: Instant,
+    ) {
+        let messages = pending
+            .into_iter()
+            .chain(std::iter::once(filter.get_server_shutdown_msg()));
+        for message in messages {
+            match timeout_at(deadline, stream_tx.send(Ok(message))).await {
+                Ok(Ok(())) => {}
+                Ok(Err(mpsc::error::SendError(_))) => {
+                    client_log!(error, "stream closed");
+                    return;
+                }
+                Err(_elapsed) => {
+                    client_log!(error, "failed to send shutdown message");
+                    return;
+                }
+            }
+        }
+
+        // wait until queued updates are consumed by the stream
+        while stream_tx.capacity() < stream_tx.max_capacity() && !stream_tx.is_closed() {
+            if Instant::now() >= deadline {
+                client_log!(error, "failed to drain queued updates");
+                return;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        client_log!(info, "drained");
+    }
+
+    /// Moves queued updates to the stream until it's full, returns `false` if the stream is closed
+    fn client_loop_flush(
+        stream_tx: &mpsc::Sender<TonicResult<FilteredUpdate>>,
+        pending: &mut VecDeque<FilteredUpdate>,
+    ) -> bool {
+        while let Some(message) = pending.pop_front() {
+            match stream_tx.try_send(Ok(message)) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(value)) => {
+                    if let Ok(message) = value {
+                        pending.push_front(message);
+                    }
+                    break;
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    client_log!(error, "stream closed");
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Waits for free capacity in the stream and moves queued updates to it,
+    /// returns `false` if the stream is closed
+    async fn client_loop_send_pending(
+        stream_tx: &mpsc::Sender<TonicResult<FilteredUpdate>>,
+        pending: &mut VecDeque<FilteredUpdate>,
+    ) -> bool {
+        let Ok(permit) = stream_tx.reserve().await else {
+            client_log!(error, "stream closed");
+            return false;
+        };
+        if let Some(message) = pending.pop_front() {
+            permit.send(Ok(message));
+        }
+        Self::client_loop_flush(stream_tx, pending)
+    }
+
     async fn client_loop_snapshot(
-        id: usize,
         endpoint: &str,
         stream_tx: &mpsc::Sender<TonicResult<FilteredUpdate>>,
-        client_rx: &mut mpsc::UnboundedReceiver<Option<(Option<u64>, Filter)>>,
+        client_rx: &mut mpsc::UnboundedReceiver<Option<(Option<ReplayFrom>, Filter)>>,
         snapshot_rx: crossbeam_channel::Receiver<Box<Message>>,
         is_alive: &mut bool,
         filter: &mut Filter,
     ) {
-        info!("client #{id}: going to receive snapshot data");
+        client_log!(info, "going to receive snapshot data");
 
         // we start with default filter, for snapshot we need wait actual filter first
         while *is_alive {
             match client_rx.recv().await {
-                Some(Some((_from_slot, filter_new))) => {
+                Some(Some((_replay_from, filter_new))) => {
                     if let Some(msg) = filter_new.get_pong_msg() {
                         if stream_tx.send(Ok(msg)).await.is_err() {
-                            error!("client #{id}: stream closed");
+                            client_log!(error, "stream closed");
                             *is_alive = false;
                         }
                         continue;
@@ -1036,7 +2994,15 @@ impl GrpcService {
 
                     metrics::update_subscriptions(endpoint, Some(filter), Some(&filter_new));
                     *filter = filter_new;
-                    info!("client #{id}: filter updated");
+                    client_log!(info, "filter updated");
+                    if stream_tx
+                        .send(Ok(filter.get_subscribe_accepted_msg()))
+                        .await
+                        .is_err()
+                    {
+                        client_log!(error, "stream closed");
+                        *is_alive = false;
+                    }
                     break;
                 }
                 Some(None) => {
@@ -1059,31 +3025,283 @@ impl GrpcService {
                     continue;
                 }
                 Err(crossbeam_channel::TryRecvError::Disconnected) => {
-                    info!("client #{id}: end of startup");
+                    client_log!(info, "end of startup");
                     break;
                 }
             };
 
-            for message in filter.get_updates(&message, None) {
+            for mut message in filter.get_updates(&message, None) {
+                filter.wrap_update(&mut message);
                 if stream_tx.send(Ok(message)).await.is_err() {
-                    error!("client #{id}: stream closed");
+                    client_log!(error, "stream closed");
                     *is_alive = false;
                     break;
                 }
             }
         }
     }
+
+    /// Reads requests of the connection and sends created filters to the client loop, invalid
+    /// requests are answered with an error without closing the stream
+    async fn incoming_loop(
+        mut requests: impl Stream<Item = Result<SubscribeRequest, Status>> + Unpin,
+        stream_tx: mpsc::Sender<TonicResult<FilteredUpdate>>,
+        client_tx: mpsc::UnboundedSender<Option<(Option<ReplayFrom>, Filter)>>,
+        exit: Arc<Notify>,
+        context: IncomingContext,
+    ) {
+        let IncomingContext {
+            filter_names,
+            filter_cache,
+            filter_limits: config_filter_limits,
+            ping_interval: config_ping_interval,
+            ping_interval_tx,
+            rate_limit: config_rate_limit,
+            rate_limit_tx,
+            channel_capacity: config_channel_capacity,
+            channel_capacity_tx,
+            accounts_snapshot_enabled,
+            slots_replay_finalized_enabled,
+            json_encoding_enabled,
+            resume_tokens_enabled,
+        } = context;
+        let exit = exit.notified();
+        tokio::pin!(exit);
+
+        loop {
+            tokio::select! {
+                _ = &mut exit => {
+                    break;
+                }
+                message = requests.next() => match message {
+                    Some(Ok(request)) => {
+                        let mut filter_names = filter_names.lock().await;
+                        filter_names.try_clean();
+
+                        // names are locked first, cache is used only with names
+                        let mut filter_cache = filter_cache.lock().await;
+                        let filter = filter_cache
+                            .get_or_create(&request, &config_filter_limits, &mut filter_names)
+                            .map(Arc::unwrap_or_clone);
+                        metrics::update_filter_cache(&mut filter_cache);
+                        drop(filter_cache);
+                        metrics::update_filter_names(&mut filter_names);
+                        drop(filter_names);
+
+                        if let Err(error) = match filter {
+                            Ok(filter) => {
+                                if let Some(msg) = filter.get_pong_msg() {
+                                    if stream_tx.send(Ok(msg)).await.is_err() {
+                                        client_log!(error, "stream closed");
+                                        let _ = client_tx.send(None);
+                                        break;
+                                    }
+                                    continue;
+                                }
+
+                                let ping_interval = request.ping_interval_ms.map(Duration::from_millis);
+                                let replay_from = Self::get_replay_from(&request, &filter, resume_tokens_enabled);
+                                if ping_interval.is_some_and(|interval| interval < PING_INTERVAL_MIN) {
+                                    Err(format!("ping_interval_ms should be at least {}", PING_INTERVAL_MIN.as_millis()))
+                                } else if request.max_updates_per_second == Some(0) {
+                                    Err("max_updates_per_second should be greater than 0".to_owned())
+                                } else if request.channel_capacity == Some(0) {
+                                    Err("channel_capacity should be greater than 0".to_owned())
+                                } else if filter.is_accounts_snapshot() && !accounts_snapshot_enabled {
+                                    Err("accounts snapshot is not enabled on the server".to_owned())
+                                } else if filter.get_slots_replay_finalized() > 0 && !slots_replay_finalized_enabled {
+                                    Err("replay of finalized slots is not enabled on the server".to_owned())
+                                } else if filter.is_json() && !json_encoding_enabled {
+                                    Err("JSON encoding is not enabled on the server".to_owned())
+                                } else if let Err(error) = &replay_from {
+                                    Err(error.clone())
+                                } else {
+                                    let ping_interval = ping_interval.unwrap_or(config_ping_interval);
+                                    ping_interval_tx.send_if_modified(|value| {
+                                        let modified = *value != ping_interval;
+                                        *value = ping_interval;
+                                        modified
+                                    });
+                                    let rate_limit = config_rate_limit.get_updates_per_second(request.max_updates_per_second);
+                                    rate_limit_tx.send_if_modified(|value| {
+                                        let modified = *value != rate_limit;
+                                        *value = rate_limit;
+                                        modified
+                                    });
+                                    let channel_capacity = request
+                                        .channel_capacity
+                                        .and_then(|value| usize::try_from(value).ok())
+                                        .map_or(config_channel_capacity, |value| value.min(config_channel_capacity));
+                                    channel_capacity_tx.send_if_modified(|value| {
+                                        let modified = *value != channel_capacity;
+                                        *value = channel_capacity;
+                                        modified
+                                    });
+                                    metrics::observe_subscription_filters(&filter);
+                                    match client_tx.send(Some((replay_from.unwrap_or_default(), filter))) {
+                                        Ok(()) => Ok(()),
+                                        Err(error) => Err(error.to_string()),
+                                    }
+                                }
+                            },
+                            Err(error) => {
+                                metrics::subscribe_rejections_inc(&error);
+                                Err(error.to_string())
+                            }
+                        } {
+                            client_log!(error, "failed to create filter: {error}");
+                            let err = Err(Status::invalid_argument(format!(
+                                "failed to create filter: {error}"
+                            )));
+                            if stream_tx.send(err).await.is_err() {
+                                let _ = client_tx.send(None);
+                            }
+                        }
+                    }
+                    None => {
+                        break;
+                    }
+                    Some(Err(error)) => {
+                        client_log!(error, "failed to receive request: {error}");
+                        let _ = client_tx.send(None);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn ping_loop(
+        stream_tx: mpsc::Sender<TonicResult<FilteredUpdate>>,
+        client_tx: mpsc::UnboundedSender<Option<(Option<ReplayFrom>, Filter)>>,
+        exit: Arc<Notify>,
+        mut ping_interval_rx: watch::Receiver<Duration>,
+    ) {
+        let exit = exit.notified();
+        tokio::pin!(exit);
+
+        // sender is dropped once client stops sending requests, keep the last interval
+        let mut ping_interval_updates = true;
+        loop {
+            let ping_interval = *ping_interval_rx.borrow_and_update();
+            tokio::select! {
+                _ = &mut exit => {
+                    break;
+                }
+                result = ping_interval_rx.changed(), if ping_interval_updates => {
+                    ping_interval_updates = result.is_ok();
+                }
+                _ = sleep(ping_interval) => {
+                    let msg = FilteredUpdate::new_empty(FilteredUpdateOneof::ping());
+                    match stream_tx.try_send(Ok(msg)) {
+                        Ok(()) => {}
+                        Err(mpsc::error::TrySendError::Full(_)) => {}
+                        Err(mpsc::error::TrySendError::Closed(_)) => {
+                            let _ = client_tx.send(None);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn stale_stream_loop(
+        stream_tx: mpsc::Sender<TonicResult<FilteredUpdate>>,
+        stream_activity: Arc<StreamActivity>,
+        client_tx: mpsc::UnboundedSender<Option<(Option<ReplayFrom>, Filter)>>,
+        exit: Arc<Notify>,
+        timeout: Duration,
+    ) {
+        let exit = exit.notified();
+        tokio::pin!(exit);
+
+        loop {
+            let idle = stream_activity.idle();
+            if idle >= timeout {
+                client_log!(info, "no updates consumed from the stream for {idle:?}");
+                stream_activity.set_stale();
+                let _ = client_tx.send(None);
+                // wake up the idle response stream, full channel is terminated on the next read
+                let _ = stream_tx.try_send(Err(Status::deadline_exceeded("stream is stale")));
+                break;
+            }
+
+            tokio::select! {
+                _ = &mut exit => {
+                    break;
+                }
+                _ = sleep(timeout - idle) => {}
+            }
+        }
+    }
+
+    /// Replay position of the request, `resume_token` is accepted only if tokens are enabled
+    fn get_replay_from(
+        request: &SubscribeRequest,
+        filter: &Filter,
+        resume_tokens_enabled: bool,
+    ) -> Result<Option<ReplayFrom>, String> {
+        let Some(data) = &request.resume_token else {
+            return Ok(request.from_slot.map(ReplayFrom::Slot));
+        };
+        if !resume_tokens_enabled {
+            return Err("resume_token is not enabled on the server".to_owned());
+        }
+        if request.from_slot.is_some() {
+            return Err("from_slot and resume_token can not be combined".to_owned());
+        }
+        let token = ResumeToken::decode(data).ok_or_else(|| "invalid resume_token".to_owned())?;
+        if token.commitment != filter.get_commitment_level() {
+            return Err(
+                "commitment of resume_token should match the request commitment".to_owned(),
+            );
+        }
+        Ok(Some(ReplayFrom::Token(token)))
+    }
+
+    /// Key of per-client limits: identity from the client certificate or `x-token`
+    fn get_client_token<T>(request: &Request<T>) -> Option<&str> {
+        request
+            .extensions()
+            .get::<ClientIdentity>()
+            .map(|identity| identity.0.as_str())
+            .or_else(|| {
+                request
+                    .metadata()
+                    .get("x-token")
+                    .and_then(|value| value.to_str().ok())
+            })
+    }
 }
 
 #[tonic::async_trait]
 impl Geyser for GrpcService {
-    type SubscribeStream = ReceiverStream<TonicResult<FilteredUpdate>>;
+    type SubscribeStream = SubscribeStream;
 
     async fn subscribe(
         &self,
         mut request: Request<Streaming<SubscribeRequest>>,
     ) -> TonicResult<Response<Self::SubscribeStream>> {
+        let Some(client_guard) = self
+            .clients_tx
+            .upgrade()
+            .filter(|_| !*self.drained_rx.borrow())
+        else {
+            return Err(Status::unavailable("server is shutting down"));
+        };
         let id = self.subscribe_id.fetch_add(1, Ordering::Relaxed);
+        let span = ConnectionSpan::new(id, request.remote_addr(), Self::get_client_token(&request));
+        let subscription_guard = match self
+            .subscriptions
+            .try_acquire(Self::get_client_token(&request))
+        {
+            Ok(guard) => guard,
+            Err(status) => {
+                span.in_scope(|| client_log!(error, "{}", status.message()));
+                return Err(status);
+            }
+        };
 
         let x_request_snapshot = request.metadata().contains_key("x-request-snapshot");
         let snapshot_rx = if x_request_snapshot {
@@ -1093,39 +3311,34 @@ impl Geyser for GrpcService {
         };
         let (stream_tx, stream_rx) = mpsc::channel(if snapshot_rx.is_some() {
             self.config_snapshot_client_channel_capacity
+        } else if self.config_channel_full_policy == ConfigGrpcClientChannelFullPolicy::DropOldest {
+            self.config_channel_capacity.min(CHANNEL_DROP_OLDEST_WINDOW)
         } else {
             self.config_channel_capacity
         });
         let (client_tx, client_rx) = mpsc::unbounded_channel();
         let notify_exit1 = Arc::new(Notify::new());
         let notify_exit2 = Arc::new(Notify::new());
+        let notify_exit3 = Arc::new(Notify::new());
+        let stream_activity = Arc::new(StreamActivity::new());
 
-        let ping_stream_tx = stream_tx.clone();
-        let ping_client_tx = client_tx.clone();
-        let ping_exit = Arc::clone(&notify_exit1);
-        tokio::spawn(async move {
-            let exit = ping_exit.notified();
-            tokio::pin!(exit);
+        let (ping_interval_tx, ping_interval_rx) = watch::channel(self.config_ping_interval);
+        tokio::spawn(Self::ping_loop(
+            stream_tx.clone(),
+            client_tx.clone(),
+            Arc::clone(&notify_exit1),
+            ping_interval_rx,
+        ));
 
-            loop {
-                tokio::select! {
-                    _ = &mut exit => {
-                        break;
-                    }
-                    _ = sleep(Duration::from_secs(10)) => {
-                        let msg = FilteredUpdate::new_empty(FilteredUpdateOneof::ping());
-                        match ping_stream_tx.try_send(Ok(msg)) {
-                            Ok(()) => {}
-                            Err(mpsc::error::TrySendError::Full(_)) => {}
-                            Err(mpsc::error::TrySendError::Closed(_)) => {
-                                let _ = ping_client_tx.send(None);
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-        });
+        if let Some(timeout) = self.config_stale_stream_timeout {
+            tokio::spawn(span.clone().instrument(Self::stale_stream_loop(
+                stream_tx.clone(),
+                Arc::clone(&stream_activity),
+                client_tx.clone(),
+                Arc::clone(&notify_exit3),
+                timeout,
+            )));
+        }
 
         let endpoint = request
             .metadata()
@@ -1133,79 +3346,81 @@ impl Geyser for GrpcService {
             .and_then(|h| h.to_str().ok().map(|s| s.to_string()))
             .unwrap_or_else(|| "".to_owned());
 
-        let config_filter_limits = Arc::clone(&self.config_filter_limits);
-        let filter_names = Arc::clone(&self.filter_names);
-        let incoming_stream_tx = stream_tx.clone();
-        let incoming_client_tx = client_tx;
-        let incoming_exit = Arc::clone(&notify_exit2);
-        tokio::spawn(async move {
-            let exit = incoming_exit.notified();
-            tokio::pin!(exit);
-
-            loop {
-                tokio::select! {
-                    _ = &mut exit => {
-                        break;
-                    }
-                    message = request.get_mut().message() => match message {
-                        Ok(Some(request)) => {
-                            let mut filter_names = filter_names.lock().await;
-                            filter_names.try_clean();
-
-                            if let Err(error) = match Filter::new(&request, &config_filter_limits, &mut filter_names) {
-                                Ok(filter) => {
-                                    if let Some(msg) = filter.get_pong_msg() {
-                                        if incoming_stream_tx.send(Ok(msg)).await.is_err() {
-                                            error!("client #{id}: stream closed");
-                                            let _ = incoming_client_tx.send(None);
-                                            break;
-                                        }
-                                        continue;
-                                    }
-
-                                    match incoming_client_tx.send(Some((request.from_slot, filter))) {
-                                        Ok(()) => Ok(()),
-                                        Err(error) => Err(error.to_string()),
-                                    }
-                                },
-                                Err(error) => Err(error.to_string()),
-                            } {
-                                let err = Err(Status::invalid_argument(format!(
-                                    "failed to create filter: {error}"
-                                )));
-                                if incoming_stream_tx.send(err).await.is_err() {
-                                    let _ = incoming_client_tx.send(None);
-                                }
-                            }
-                        }
-                        Ok(None) => {
-                            break;
-                        }
-                        Err(_error) => {
-                            let _ = incoming_client_tx.send(None);
-                            break;
-                        }
-                    }
-                }
-            }
+        let fair_scheduler = self.fair_scheduler.as_ref().map(|(scheduler, config)| {
+            scheduler.client(id, config.get_weight(Self::get_client_token(&request)))
         });
 
-        tokio::spawn(Self::client_loop(
-            id,
-            endpoint,
-            stream_tx,
-            client_rx,
-            snapshot_rx,
-            self.broadcast_tx.subscribe(),
-            self.replay_stored_slots_tx.clone(),
-            self.debug_clients_tx.clone(),
-            move || {
-                notify_exit1.notify_one();
-                notify_exit2.notify_one();
+        let config_filter_limits = Arc::clone(
+            self.config_filter_limits
+                .get(Self::get_client_token(&request)),
+        );
+        let (rate_limit_tx, rate_limit_rx) =
+            watch::channel(self.config_rate_limit.get_updates_per_second(None));
+        let (channel_capacity_tx, channel_capacity_rx) =
+            watch::channel(self.config_channel_capacity);
+        tokio::spawn(span.clone().instrument(Self::incoming_loop(
+            request.into_inner(),
+            stream_tx.clone(),
+            client_tx,
+            Arc::clone(&notify_exit2),
+            IncomingContext {
+                filter_names: Arc::clone(&self.filter_names),
+                filter_cache: Arc::clone(&self.filter_cache),
+                filter_limits: config_filter_limits,
+                ping_interval: self.config_ping_interval,
+                ping_interval_tx,
+                rate_limit: self.config_rate_limit,
+                rate_limit_tx,
+                channel_capacity: self.config_channel_capacity,
+                channel_capacity_tx,
+                accounts_snapshot_enabled: self.accounts_snapshot.is_some(),
+                slots_replay_finalized_enabled: self.slots_finalized.is_some(),
+                json_encoding_enabled: self.config_json_encoding,
+                resume_tokens_enabled: self.config_resume_token_interval.is_some(),
             },
-        ));
+        )));
 
-        Ok(Response::new(ReceiverStream::new(stream_rx)))
+        tokio::spawn(
+            span.instrument(Self::client_loop(
+                id,
+                stream_tx,
+                client_rx,
+                self.broadcast_tx.subscribe(),
+                ClientLoopContext {
+                    endpoint,
+                    snapshot_rx,
+                    replay_stored_slots_tx: self.replay_stored_slots_tx.clone(),
+                    resume_tokens: self
+                        .config_resume_token_interval
+                        .map(|interval| ResumeTokens::new(self.resume_token_instance, interval)),
+                    strict_commitment: self.config_strict_commitment,
+                    accounts_snapshot: self.accounts_snapshot.as_ref().map(Arc::clone),
+                    slots_finalized: self.slots_finalized.as_ref().map(Arc::clone),
+                    account_data_threshold: self.config_account_data_threshold,
+                    debug_client_tx: self.debug_clients_tx.clone(),
+                    fair_scheduler,
+                    rate_limit_rx,
+                    rate_limit_policy: self.config_rate_limit.policy,
+                    channel_capacity_rx,
+                    channel_full_policy: self.config_channel_full_policy,
+                    drained_rx: self.drained_rx.clone(),
+                    shutdown_timeout: self.config_shutdown_timeout,
+                    caches_memory: self.config_filter_limits.default.caches.memory.clone(),
+                },
+                client_guard,
+                move || {
+                    drop(subscription_guard);
+                    notify_exit1.notify_one();
+                    notify_exit2.notify_one();
+                    notify_exit3.notify_one();
+                },
+            )),
+        );
+
+        Ok(Response::new(SubscribeStream::new(
+            stream_rx,
+            stream_activity,
+        )))
     }
 
     async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
@@ -1295,4 +3510,2256 @@ impl Geyser for GrpcService {
             version: serde_json::to_string(&GrpcVersionInfo::default()).unwrap(),
         }))
     }
+
+    async fn get_block_meta_range(
+        &self,
+        request: Request<GetBlockMetaRangeRequest>,
+    ) -> Result<Response<GetBlockMetaRangeResponse>, Status> {
+        if let Some(blocks_meta) = &self.blocks_meta {
+            let req = request.get_ref();
+            blocks_meta
+                .get_block_meta_range(req.from_slot, req.to_slot)
+                .await
+        } else {
+            Err(Status::unimplemented("method disabled"))
+        }
+    }
+
+    async fn get_filter_limits(
+        &self,
+        request: Request<GetFilterLimitsRequest>,
+    ) -> Result<Response<GetFilterLimitsResponse>, Status> {
+        let limits = self
+            .config_filter_limits
+            .get(Self::get_client_token(&request));
+        Ok(Response::new(limits.as_ref().into()))
+    }
+
+    async fn get_transaction_accounts(
+        &self,
+        request: Request<GetTransactionAccountsRequest>,
+    ) -> Result<Response<GetTransactionAccountsResponse>, Status> {
+        if let Some(transaction_accounts) = &self.transaction_accounts {
+            transaction_accounts
+                .get_transaction_accounts(&request.get_ref().signature)
+                .await
+        } else {
+            Err(Status::unimplemented("method disabled"))
+        }
+    }
+
+    async fn get_account_data(
+        &self,
+        request: Request<GetAccountDataRequest>,
+    ) -> Result<Response<GetAccountDataResponse>, Status> {
+        if let Some(account_data) = &self.account_data {
+            account_data
+                .get_account_data(request.into_inner(), self.accounts_snapshot.as_deref())
+                .await
+        } else {
+            Err(Status::unimplemented("method disabled"))
+        }
+    }
+
+    async fn explain_filter(
+        &self,
+        request: Request<ExplainFilterRequest>,
+    ) -> Result<Response<ExplainFilterResponse>, Status> {
+        let limits = Arc::clone(
+            self.config_filter_limits
+                .get(Self::get_client_token(&request)),
+        );
+        let ExplainFilterRequest { request, update } = request.into_inner();
+
+        let mut filter_names = self.filter_names.lock().await;
+        filter_names.try_clean();
+        let filter = Filter::new(&request.unwrap_or_default(), &limits, &mut filter_names);
+        metrics::update_filter_names(&mut filter_names);
+        drop(filter_names);
+        let filter = filter.map_err(|error| {
+            Status::invalid_argument(format!("failed to create filter: {error}"))
+        })?;
+
+        let update = update
+            .and_then(|update| update.update_oneof)
+            .ok_or_else(|| Status::invalid_argument("update should be defined"))?;
+        let message = Message::from_update_oneof(update, Timestamp::from(SystemTime::now()))
+            .map_err(|error| Status::invalid_argument(format!("invalid update: {error}")))?;
+        let explains = filter.explain(&message).ok_or_else(|| {
+            Status::invalid_argument("only account and transaction updates can be explained")
+        })?;
+
+        Ok(Response::new(ExplainFilterResponse {
+            filters: explains
+                .into_iter()
+                .map(|explain| explain_filter_response::Filter {
+                    kind: explain.kind.to_owned(),
+                    name: explain.name.as_ref().to_owned(),
+                    matched: explain.mismatch.is_none(),
+                    reason: explain.mismatch.map(|mismatch| mismatch.to_string()),
+                })
+                .collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            token_hash, AccountDataStorageInner, AccountsSnapshotStorage,
+            AccountsSnapshotStorageInner, BlockMetaStorageInner, ClientLoopContext, ConnectionSpan,
+            FilterLimitsProfiles, GeyserMessageSender, GrpcService, IncomingContext, ReplayFrom,
+            ReplayedResponse, ResumeToken, ResumeTokens, SlotLiveness, SlotMessages, SlotSeqs,
+            SlotsFinalizedStorage, SlotsFinalizedStorageInner, StreamActivity,
+            StrictCommitmentGate, SubscribeStream, Subscriptions, TransactionAccountsStorageInner,
+            UpdatesRateLimiter,
+        },
+        crate::{
+            config::{
+                ConfigGrpcChannelFullPolicy, ConfigGrpcClientChannelFullPolicy,
+                ConfigGrpcRateLimit, ConfigGrpcRateLimitPolicy,
+            },
+            metrics,
+            scheduler::FairScheduler,
+            tls::ClientIdentity,
+        },
+        log::{LevelFilter, Log, Metadata, Record},
+        prost_types::Timestamp,
+        solana_sdk::{pubkey::Pubkey, signature::Signature},
+        std::{
+            cell::RefCell,
+            collections::{BTreeMap, HashMap},
+            future::Future,
+            sync::{atomic::Ordering, Arc},
+            time::SystemTime,
+        },
+        tokio::{
+            sync::{broadcast, mpsc, watch, Mutex, Notify, RwLock},
+            time::{sleep, timeout, Duration, Instant},
+        },
+        tokio_stream::StreamExt,
+        tonic::{Code, Request, Result as TonicResult},
+        yellowstone_grpc_proto::{
+            plugin::{
+                filter::{
+                    cache::FilterCache,
+                    limits::FilterLimits,
+                    message::{
+                        FilteredUpdate, FilteredUpdateAccount, FilteredUpdateOneof, FilteredUpdates,
+                    },
+                    name::{FilterName, FilterNames},
+                    Filter, FilterAccountsDataSlice,
+                },
+                fixtures,
+                message::{
+                    CommitmentLevel, Message, MessageAccount, MessageAccountInfo, MessageBlockMeta,
+                    MessageSlot, SlotStatus,
+                },
+            },
+            prelude::{
+                subscribe_update::UpdateOneof, AccountsDataEncoding,
+                CommitmentLevel as CommitmentLevelProto, SubscribeRequest,
+                SubscribeRequestFilterAccounts, SubscribeRequestFilterBlocks,
+                SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterSlots,
+                SubscribeRequestFilterTransactions, SubscribeUpdateBlockMeta,
+                SubscribeUpdateSnapshotComplete, SubscribeUpdateSubscribeAccepted, UpdatesEncoding,
+            },
+        },
+    };
+
+    /// Tests checking `connection_dropped_messages_total` counter are not run concurrently
+    static DROPPED_MESSAGES_LOCK: Mutex<()> = Mutex::const_new(());
+
+    fn create_slot_messages(slots: std::ops::RangeInclusive<u64>) -> BTreeMap<u64, SlotMessages> {
+        let mut messages = BTreeMap::new();
+        for slot in slots {
+            let mut slot_messages = SlotMessages::default();
+            slot_messages.messages_slots.push((
+                slot,
+                Message::Slot(MessageSlot {
+                    slot,
+                    parent: slot.checked_sub(1),
+                    status: SlotStatus::Processed,
+                    dead_error: None,
+                    raw_status: None,
+                    created_at: Timestamp::from(SystemTime::now()),
+                }),
+            ));
+            messages.insert(slot, slot_messages);
+        }
+        messages
+    }
+
+    fn create_block_meta_storage(slots: &[u64]) -> BlockMetaStorageInner {
+        let mut storage = BlockMetaStorageInner::default();
+        for slot in slots {
+            let block_meta = MessageBlockMeta {
+                block_meta: SubscribeUpdateBlockMeta {
+                    slot: *slot,
+                    ..Default::default()
+                },
+                created_at: Timestamp::from(SystemTime::now()),
+            };
+            storage.blocks.insert(*slot, Arc::new(block_meta));
+        }
+        storage
+    }
+
+    #[test]
+    fn test_block_meta_range_empty() {
+        let storage = create_block_meta_storage(&[]);
+        let blocks_meta = storage.get_block_meta_range(10, 20, 100).unwrap();
+        assert!(blocks_meta.is_empty());
+
+        let storage = create_block_meta_storage(&[5, 25]);
+        let blocks_meta = storage.get_block_meta_range(10, 20, 100).unwrap();
+        assert!(blocks_meta.is_empty());
+    }
+
+    #[test]
+    fn test_block_meta_range_partial() {
+        let storage = create_block_meta_storage(&[8, 9, 10, 12, 13]);
+        let blocks_meta = storage.get_block_meta_range(10, 20, 100).unwrap();
+        let slots = blocks_meta
+            .iter()
+            .map(|block| block.slot)
+            .collect::<Vec<_>>();
+        assert_eq!(slots, vec![10, 12, 13]);
+
+        // single slot range
+        let blocks_meta = storage.get_block_meta_range(9, 9, 1).unwrap();
+        assert_eq!(blocks_meta.len(), 1);
+    }
+
+    #[test]
+    fn test_block_meta_range_invalid() {
+        let storage = create_block_meta_storage(&[10]);
+        let error = storage.get_block_meta_range(11, 10, 100).unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+
+        let blocks_meta = storage.get_block_meta_range(10, 109, 100).unwrap();
+        assert_eq!(blocks_meta.len(), 1);
+        let error = storage.get_block_meta_range(10, 110, 100).unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+    }
+
+    fn create_message_account(signature: Option<Signature>, write_version: u64) -> MessageAccount {
+        MessageAccount {
+            account: Arc::new(MessageAccountInfo {
+                pubkey: Pubkey::new_unique(),
+                lamports: 0,
+                owner: Pubkey::new_unique(),
+                executable: false,
+                rent_epoch: 0,
+                data: vec![1, 2, 3],
+                write_version,
+                txn_signature: signature,
+                data_checksum: None,
+            }),
+            slot: 42,
+            is_startup: false,
+            created_at: Timestamp::from(SystemTime::now()),
+        }
+    }
+
+    #[test]
+    fn test_transaction_accounts() {
+        let signature1 = Signature::new_unique();
+        let signature2 = Signature::new_unique();
+
+        let mut storage = TransactionAccountsStorageInner::default();
+        storage.push(create_message_account(Some(signature1), 3), 10, usize::MAX);
+        storage.push(create_message_account(Some(signature2), 2), 10, usize::MAX);
+        storage.push(create_message_account(Some(signature1), 1), 10, usize::MAX);
+        storage.push(create_message_account(None, 4), 10, usize::MAX);
+
+        let accounts = storage.get(&signature1).unwrap();
+        let write_versions = accounts
+            .iter()
+            .map(|msg| msg.account.as_ref().unwrap().write_version)
+            .collect::<Vec<_>>();
+        assert_eq!(write_versions, vec![1, 3]);
+        for msg in accounts {
+            let account = msg.account.unwrap();
+            assert_eq!(account.txn_signature, Some(signature1.as_ref().to_vec()));
+            assert_eq!(account.data, vec![1, 2, 3]);
+        }
+        assert_eq!(storage.get(&signature2).unwrap().len(), 1);
+        assert!(storage.get(&Signature::new_unique()).is_none());
+    }
+
+    #[test]
+    fn test_account_data_storage() {
+        let accounts = (0..3)
+            .map(|write_version| create_message_account(None, write_version))
+            .collect::<Vec<_>>();
+        let get = |storage: &AccountDataStorageInner, message: &MessageAccount| {
+            storage.get(
+                message.account.pubkey,
+                message.slot,
+                message.account.write_version,
+            )
+        };
+
+        // every account has 3 bytes of data, the oldest is evicted first
+        let mut storage = AccountDataStorageInner::default();
+        for message in accounts.iter() {
+            storage.push(message, 6);
+        }
+        assert_eq!(storage.bytes, 6);
+        assert!(get(&storage, &accounts[0]).is_none());
+        assert_eq!(get(&storage, &accounts[1]), Some(vec![1, 2, 3]));
+        assert_eq!(get(&storage, &accounts[2]), Some(vec![1, 2, 3]));
+
+        // duplicates are not stored twice
+        storage.push(&accounts[2], 6);
+        assert_eq!(storage.keys.len(), 2);
+        assert!(storage
+            .get(accounts[2].account.pubkey, accounts[2].slot + 1, 2)
+            .is_none());
+    }
+
+    #[test]
+    fn test_set_account_data_out_of_band() {
+        let message = create_message_account(None, 1);
+        let mut update = FilteredUpdate::new_empty(FilteredUpdateOneof::account(
+            &message,
+            FilterAccountsDataSlice::default(),
+        ));
+
+        GrpcService::set_account_data_out_of_band(&mut update, None);
+        GrpcService::set_account_data_out_of_band(&mut update, Some(3));
+        let FilteredUpdateOneof::Account(account) = &update.message else {
+            unreachable!();
+        };
+        assert!(account.data_ref.is_none());
+
+        GrpcService::set_account_data_out_of_band(&mut update, Some(2));
+        let Some(UpdateOneof::Account(account)) = update.as_subscribe_update().update_oneof else {
+            panic!("expected account update");
+        };
+        assert!(account.account.expect("account").data.is_empty());
+        let data_ref = account.data_ref.expect("data_ref");
+        assert_eq!(data_ref.slot, message.slot);
+        assert_eq!(data_ref.write_version, 1);
+        assert_eq!(data_ref.data_len, 3);
+
+        // sliced data within the threshold is sent inline
+        let mut update = FilteredUpdate::new_empty(FilteredUpdateOneof::account(
+            &message,
+            FilterAccountsDataSlice::new_unchecked(Arc::new(vec![0..1])),
+        ));
+        GrpcService::set_account_data_out_of_band(&mut update, Some(2));
+        let FilteredUpdateOneof::Account(account) = &update.message else {
+            unreachable!();
+        };
+        assert!(account.data_ref.is_none());
+    }
+
+    #[test]
+    fn test_account_data_out_of_band_fetch() {
+        let threshold = 100;
+        let create_message = |data_len: usize| {
+            let message = create_message_account(None, data_len as u64);
+            MessageAccount {
+                account: Arc::new(MessageAccountInfo {
+                    data: vec![42; data_len],
+                    ..(*message.account).clone()
+                }),
+                ..message
+            }
+        };
+        // data is kept with the same check as in the geyser loop, returns fetched data
+        let send = |message: &MessageAccount, mut update: FilteredUpdate| {
+            let mut storage = AccountDataStorageInner::default();
+            if FilteredUpdateAccount::is_data_out_of_band(message.account.data.len(), threshold) {
+                storage.push(message, usize::MAX);
+            }
+            GrpcService::set_account_data_out_of_band(&mut update, Some(threshold));
+            let FilteredUpdateOneof::Account(account) = update.message else {
+                unreachable!();
+            };
+            account.data_ref.map(|data_ref| {
+                let data = storage
+                    .get(
+                        account.account.pubkey,
+                        data_ref.slot,
+                        data_ref.write_version,
+                    )
+                    .expect("data is kept for the reference");
+                assert_eq!(data.len() as u64, data_ref.data_len);
+                data
+            })
+        };
+        let base64 = FilterAccountsDataSlice::default().with_encoding(AccountsDataEncoding::Base64);
+
+        // encoded data is bigger than the threshold, but received data is not kept
+        let message = create_message(80);
+        let update = FilteredUpdateOneof::account(&message, base64.clone());
+        assert_eq!(send(&message, FilteredUpdate::new_empty(update)), None);
+
+        let message = create_message(120);
+        let update = FilteredUpdateOneof::account(&message, base64);
+        assert_eq!(
+            send(&message, FilteredUpdate::new_empty(update)),
+            Some(vec![42; 120])
+        );
+
+        // decompressed data is sent by the reference to the received data
+        for (compressed_len, expected) in [(60, None), (120, Some(vec![42; 120]))] {
+            let message = create_message(compressed_len);
+            let mut update = FilteredUpdateOneof::account(
+                &MessageAccount {
+                    account: Arc::new(MessageAccountInfo {
+                        data: vec![7; 200],
+                        ..(*message.account).clone()
+                    }),
+                    ..message.clone()
+                },
+                FilterAccountsDataSlice::default(),
+            );
+            if let FilteredUpdateOneof::Account(account) = &mut update {
+                account.data_compressed_len = Some(compressed_len);
+            }
+            assert_eq!(send(&message, FilteredUpdate::new_empty(update)), expected);
+        }
+    }
+
+    #[test]
+    fn test_transaction_accounts_eviction() {
+        let signatures = (0..3).map(|_| Signature::new_unique()).collect::<Vec<_>>();
+
+        let mut storage = TransactionAccountsStorageInner::default();
+        for (write_version, signature) in signatures.iter().enumerate() {
+            storage.push(
+                create_message_account(Some(*signature), write_version as u64),
+                2,
+                usize::MAX,
+            );
+        }
+
+        assert!(storage.get(&signatures[0]).is_none());
+        assert!(storage.get(&signatures[1]).is_some());
+        assert!(storage.get(&signatures[2]).is_some());
+        assert_eq!(storage.accounts.len(), 2);
+        assert_eq!(storage.signatures.len(), 2);
+        assert_eq!(storage.bytes, 6);
+
+        // oldest signatures are evicted above the size of data, every update has 3 bytes
+        let mut storage = TransactionAccountsStorageInner::default();
+        storage.push(create_message_account(Some(signatures[0]), 0), 10, 7);
+        storage.push(create_message_account(Some(signatures[1]), 1), 10, 7);
+        storage.push(create_message_account(Some(signatures[1]), 2), 10, 7);
+        assert!(storage.get(&signatures[0]).is_none());
+        assert_eq!(
+            storage.get(&signatures[1]).map(|accounts| accounts.len()),
+            Some(2)
+        );
+        assert_eq!(storage.bytes, 6);
+        storage.push(create_message_account(Some(signatures[2]), 3), 10, 7);
+        assert!(storage.get(&signatures[1]).is_none());
+        assert!(storage.get(&signatures[2]).is_some());
+        assert_eq!(storage.bytes, 3);
+    }
+
+    #[tokio::test]
+    async fn test_accounts_snapshot() {
+        let owner = Pubkey::new_unique();
+        let create_account = |pubkey, owner, slot, write_version, lamports| MessageAccount {
+            account: Arc::new(MessageAccountInfo {
+                pubkey,
+                lamports,
+                owner,
+                executable: false,
+                rent_epoch: 0,
+                data: vec![],
+                write_version,
+                txn_signature: None,
+                data_checksum: None,
+            }),
+            slot,
+            is_startup: false,
+            created_at: Timestamp::from(SystemTime::now()),
+        };
+        let create_write = |pubkey, owner, slot, write_version, lamports| {
+            Message::Account(create_account(pubkey, owner, slot, write_version, lamports))
+        };
+        let create_status = |slot, status| {
+            Message::Slot(MessageSlot {
+                slot,
+                parent: None,
+                status,
+                dead_error: None,
+                raw_status: None,
+                created_at: Timestamp::from(SystemTime::now()),
+            })
+        };
+
+        let mut storage = AccountsSnapshotStorageInner::default();
+        // startup accounts are finalized
+        let startup = Pubkey::new_unique();
+        storage.push(Message::Account(MessageAccount {
+            is_startup: true,
+            ..create_account(startup, owner, 5, 0, 1)
+        }));
+        let pubkey = Pubkey::new_unique();
+        storage.push(create_write(pubkey, owner, 10, 1, 1));
+        storage.push(create_write(pubkey, owner, 11, 3, 5));
+        // older write is ignored
+        storage.push(create_write(pubkey, owner, 11, 2, 7));
+        storage.push(create_write(pubkey, owner, 13, 6, 8));
+        // closed account is removed
+        let closed = Pubkey::new_unique();
+        storage.push(create_write(closed, owner, 10, 4, 1));
+        storage.push(create_write(closed, owner, 12, 5, 0));
+        // not matched by owner
+        storage.push(create_write(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            13,
+            6,
+            1,
+        ));
+        // writes of dead slots and slots on other forks are dropped
+        storage.push(create_write(Pubkey::new_unique(), owner, 9, 7, 1));
+        storage.push(create_write(Pubkey::new_unique(), owner, 14, 8, 1));
+        storage.push(create_status(14, SlotStatus::Dead));
+        storage.push(create_status(10, SlotStatus::Confirmed));
+        storage.push(create_status(10, SlotStatus::Finalized));
+        storage.push(create_status(11, SlotStatus::Confirmed));
+        storage.push(create_status(12, SlotStatus::Confirmed));
+        assert_eq!(storage.accounts.len(), 3);
+        // data of sent accounts is available for `GetAccountData`
+        assert_eq!(storage.get(pubkey, 10, 1), Some(vec![]));
+        assert_eq!(storage.get(pubkey, 11, 3), Some(vec![]));
+        assert_eq!(storage.get(pubkey, 11, 2), None);
+        assert_eq!(
+            storage.slots.keys().copied().collect::<Vec<_>>(),
+            vec![11, 12, 13]
+        );
+
+        let mut accounts = HashMap::new();
+        for (name, snapshot) in [("snapshot", Some(true)), ("live", None)] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    owner: vec![owner.to_string()],
+                    snapshot,
+                    ..Default::default()
+                },
+            );
+        }
+        let filter = Filter::new(
+            &SubscribeRequest {
+                accounts,
+                ..Default::default()
+            },
+            &FilterLimits::default(),
+            &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+        )
+        .unwrap();
+        assert!(filter.is_accounts_snapshot());
+
+        let storage = AccountsSnapshotStorage {
+            inner: Arc::new(RwLock::new(storage)),
+        };
+        let get_accounts = |updates: &[FilteredUpdate]| {
+            let mut accounts = updates
+                .iter()
+                .filter_map(|update| match &update.message {
+                    FilteredUpdateOneof::Account(account) => {
+                        assert!(account.is_startup);
+                        assert_eq!(
+                            update
+                                .filters
+                                .iter()
+                                .map(|name| name.as_ref())
+                                .collect::<Vec<_>>(),
+                            vec!["snapshot"]
+                        );
+                        Some((account.account.pubkey, account.account.lamports))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            accounts.sort();
+            accounts
+        };
+        let sorted = |mut accounts: Vec<(Pubkey, u64)>| {
+            accounts.sort();
+            accounts
+        };
+
+        // state at the commitment of the request, completion is the last message
+        for (commitment, expected, slot) in [
+            (
+                CommitmentLevel::Finalized,
+                vec![(startup, 1), (pubkey, 1), (closed, 1)],
+                10,
+            ),
+            (
+                CommitmentLevel::Confirmed,
+                vec![(startup, 1), (pubkey, 5)],
+                11,
+            ),
+            (
+                CommitmentLevel::Processed,
+                vec![(startup, 1), (pubkey, 8)],
+                13,
+            ),
+        ] {
+            let updates = read_accounts_snapshot(&storage, &filter, commitment).await;
+            assert_eq!(get_accounts(&updates), sorted(expected.clone()));
+            assert_eq!(
+                updates.last().map(|update| update.message.clone()),
+                Some(FilteredUpdateOneof::SnapshotComplete(
+                    SubscribeUpdateSnapshotComplete {
+                        accounts: expected.len() as u64,
+                        slot,
+                    }
+                ))
+            );
+        }
+
+        // writes are applied once the slot is finalized
+        {
+            let mut storage = storage.inner.write().await;
+            storage.push(create_status(11, SlotStatus::Finalized));
+            storage.push(create_status(12, SlotStatus::Finalized));
+            assert_eq!(storage.slots.keys().copied().collect::<Vec<_>>(), vec![13]);
+        }
+        let updates = read_accounts_snapshot(&storage, &filter, CommitmentLevel::Finalized).await;
+        assert_eq!(
+            get_accounts(&updates),
+            sorted(vec![(startup, 1), (pubkey, 5)])
+        );
+
+        // finalized accounts are read in chunks
+        let mut storage = AccountsSnapshotStorageInner::default();
+        for _ in 0..=AccountsSnapshotStorageInner::CHUNK_SIZE {
+            storage.push(create_write(Pubkey::new_unique(), owner, 1, 0, 1));
+        }
+        storage.push(create_status(1, SlotStatus::Finalized));
+        let storage = AccountsSnapshotStorage {
+            inner: Arc::new(RwLock::new(storage)),
+        };
+        let mut reader = storage.reader(CommitmentLevel::Processed).await;
+        let chunk = reader.next(&filter).await.expect("first chunk");
+        assert_eq!(chunk.len(), AccountsSnapshotStorageInner::CHUNK_SIZE);
+        let chunk = reader.next(&filter).await.expect("last chunk");
+        assert_eq!(chunk.len(), 2);
+        assert!(matches!(
+            chunk[1].message,
+            FilteredUpdateOneof::SnapshotComplete(_)
+        ));
+        assert!(reader.next(&filter).await.is_none());
+
+        // completion is sent for empty snapshot
+        let storage = AccountsSnapshotStorage {
+            inner: Arc::new(RwLock::new(AccountsSnapshotStorageInner::default())),
+        };
+        let updates = read_accounts_snapshot(&storage, &filter, CommitmentLevel::Processed).await;
+        assert_eq!(
+            updates
+                .iter()
+                .map(|update| update.message.clone())
+                .collect::<Vec<_>>(),
+            vec![FilteredUpdateOneof::snapshot_complete(0, 0)]
+        );
+    }
+
+    async fn read_accounts_snapshot(
+        storage: &AccountsSnapshotStorage,
+        filter: &Filter,
+        commitment: CommitmentLevel,
+    ) -> Vec<FilteredUpdate> {
+        let mut reader = storage.reader(commitment).await;
+        let mut updates = vec![];
+        while let Some(chunk) = reader.next(filter).await {
+            updates.extend(chunk);
+        }
+        updates
+    }
+
+    fn create_message_slot(slot: u64) -> Message {
+        Message::Slot(MessageSlot {
+            slot,
+            parent: None,
+            status: SlotStatus::Processed,
+            dead_error: None,
+            raw_status: None,
+            created_at: Timestamp::from(SystemTime::now()),
+        })
+    }
+
+    #[test]
+    fn test_geyser_message_sender_startup_account() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let (accounts_snapshot_tx, mut accounts_snapshot_rx) = mpsc::unbounded_channel();
+        let sender = GeyserMessageSender {
+            tx,
+            full_policy: ConfigGrpcChannelFullPolicy::Drop,
+            accounts_snapshot_tx: Some(accounts_snapshot_tx),
+        };
+        assert!(sender.is_startup_accounts_used());
+
+        // startup accounts are stored but not sent to clients
+        let mut message = create_message_account(None, 1);
+        message.is_startup = true;
+        sender.send_startup_account(&message);
+        let Ok(Message::Account(stored)) = accounts_snapshot_rx.try_recv() else {
+            panic!("expected stored account");
+        };
+        assert_eq!(stored.account.pubkey, message.account.pubkey);
+        assert!(rx.try_recv().is_err());
+
+        let (tx, _rx) = mpsc::channel(1);
+        let sender = GeyserMessageSender {
+            tx,
+            full_policy: ConfigGrpcChannelFullPolicy::Drop,
+            accounts_snapshot_tx: None,
+        };
+        assert!(!sender.is_startup_accounts_used());
+    }
+
+    #[test]
+    fn test_geyser_message_sender_drop() {
+        let (tx, mut rx) = mpsc::channel(2);
+        let sender = GeyserMessageSender {
+            tx,
+            full_policy: ConfigGrpcChannelFullPolicy::Drop,
+            accounts_snapshot_tx: None,
+        };
+
+        // third message is dropped at capacity
+        assert!(sender.send(create_message_slot(0)));
+        assert!(sender.send(create_message_slot(1)));
+        assert!(!sender.send(create_message_slot(2)));
+        assert_eq!(rx.try_recv().unwrap().get_slot(), 0);
+        assert_eq!(rx.try_recv().unwrap().get_slot(), 1);
+        assert!(rx.try_recv().is_err());
+
+        // queue accepts messages once there is free capacity
+        assert!(sender.send(create_message_slot(3)));
+        assert_eq!(rx.try_recv().unwrap().get_slot(), 3);
+
+        drop(rx);
+        assert!(!sender.send(create_message_slot(4)));
+    }
+
+    #[test]
+    fn test_geyser_message_sender_block() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let sender = GeyserMessageSender {
+            tx,
+            full_policy: ConfigGrpcChannelFullPolicy::Block,
+            accounts_snapshot_tx: None,
+        };
+
+        // sender is blocked at capacity, but nothing is lost
+        let handle = std::thread::spawn(move || {
+            (0..3)
+                .map(|slot| sender.send(create_message_slot(slot)))
+                .collect::<Vec<_>>()
+        });
+        for slot in 0..3 {
+            assert_eq!(rx.blocking_recv().unwrap().get_slot(), slot);
+        }
+        assert_eq!(handle.join().unwrap(), vec![true, true, true]);
+    }
+
+    #[test]
+    fn test_evict_block_buffers() {
+        let mut messages = BTreeMap::new();
+        for slot in 1..=3 {
+            messages.insert(slot, SlotMessages::default());
+        }
+        messages.get_mut(&2).unwrap().sealed = true;
+
+        assert_eq!(SlotMessages::evict_block_buffers(&mut messages, 2), 0);
+        assert_eq!(SlotMessages::evict_block_buffers(&mut messages, 1), 1);
+        assert!(messages[&1].evicted);
+        assert!(!messages[&2].evicted);
+        assert!(!messages[&3].evicted);
+
+        messages.insert(4, SlotMessages::default());
+        assert_eq!(SlotMessages::evict_block_buffers(&mut messages, 1), 1);
+        assert!(messages[&3].evicted);
+        assert!(messages[&4].is_buffered());
+    }
+
+    #[test]
+    fn test_replay_buffer_boundary() {
+        let messages = create_slot_messages(10..=20);
+
+        assert!(matches!(
+            SlotMessages::replay(&messages, CommitmentLevel::Processed, 9),
+            ReplayedResponse::Lagged(10)
+        ));
+
+        let ReplayedResponse::Messages(replayed) =
+            SlotMessages::replay(&messages, CommitmentLevel::Processed, 10)
+        else {
+            panic!("expected replayed messages");
+        };
+        assert_eq!(replayed.len(), 11);
+
+        let ReplayedResponse::Messages(replayed) =
+            SlotMessages::replay(&messages, CommitmentLevel::Processed, 20)
+        else {
+            panic!("expected replayed messages");
+        };
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].1.get_slot(), 20);
+    }
+
+    #[test]
+    fn test_replay_live_handoff() {
+        let messages = create_slot_messages(10..=20);
+
+        // slot in the future: nothing to replay, client continue with live messages
+        let ReplayedResponse::Messages(replayed) =
+            SlotMessages::replay(&messages, CommitmentLevel::Processed, 21)
+        else {
+            panic!("expected replayed messages");
+        };
+        assert!(replayed.is_empty());
+
+        // replayed messages ordered and continuous up to the live tip
+        let ReplayedResponse::Messages(replayed) =
+            SlotMessages::replay(&messages, CommitmentLevel::Processed, 15)
+        else {
+            panic!("expected replayed messages");
+        };
+        let slots = replayed
+            .iter()
+            .map(|(_msgid, message)| message.get_slot())
+            .collect::<Vec<_>>();
+        assert_eq!(slots, (15..=20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_resume_token_encoding() {
+        let token = ResumeToken {
+            instance: 42,
+            commitment: CommitmentLevel::Confirmed,
+            slot: 300_000_000,
+            msgid: u64::MAX,
+        };
+        let data = token.encode();
+        assert_eq!(data.len(), ResumeToken::SIZE);
+        assert_eq!(ResumeToken::decode(&data), Some(token));
+
+        assert_eq!(ResumeToken::decode(&data[1..]), None);
+        let mut invalid = data.clone();
+        invalid[0] = 0;
+        assert_eq!(ResumeToken::decode(&invalid), None);
+        let mut invalid = data;
+        invalid[9] = 3;
+        assert_eq!(ResumeToken::decode(&invalid), None);
+    }
+
+    #[test]
+    fn test_resume_token_set() {
+        let mut updates = FilteredUpdates::new();
+        updates.push(FilteredUpdate::new_empty(FilteredUpdateOneof::ping()));
+        updates.push(FilteredUpdate::new_empty(FilteredUpdateOneof::ping()));
+        let mut tokens = ResumeTokens::new(42, Duration::from_secs(1));
+        let now = Instant::now();
+
+        // not set before the interval
+        tokens.maybe_set(&mut updates, CommitmentLevel::Processed, 10, 1, now);
+        assert!(updates.iter().all(|update| update.resume_token.is_none()));
+
+        // postponed until the message with updates
+        let now = now + Duration::from_secs(1);
+        tokens.maybe_set(
+            &mut FilteredUpdates::new(),
+            CommitmentLevel::Processed,
+            10,
+            2,
+            now,
+        );
+        tokens.maybe_set(&mut updates, CommitmentLevel::Processed, 10, 3, now);
+        assert_eq!(updates[0].resume_token, None);
+        let token = ResumeToken::decode(updates[1].resume_token.as_deref().unwrap());
+        assert_eq!(
+            token,
+            Some(ResumeToken {
+                instance: 42,
+                commitment: CommitmentLevel::Processed,
+                slot: 10,
+                msgid: 3,
+            })
+        );
+
+        // next token after the interval
+        updates[1].resume_token = None;
+        tokens.maybe_set(&mut updates, CommitmentLevel::Processed, 10, 4, now);
+        assert_eq!(updates[1].resume_token, None);
+    }
+
+    #[test]
+    fn test_subscriptions_limit() {
+        let subscriptions = Arc::new(Subscriptions::new(Some(3), Some(2)));
+
+        // subscriptions up to the limit per token
+        let guard1 = subscriptions.try_acquire(Some("token1")).unwrap();
+        let guard2 = subscriptions.try_acquire(Some("token1")).unwrap();
+        let error = subscriptions.try_acquire(Some("token1")).unwrap_err();
+        assert_eq!(error.code(), Code::ResourceExhausted);
+
+        // subscriptions up to the total limit
+        let guard3 = subscriptions.try_acquire(None).unwrap();
+        for token in [None, Some("token2")] {
+            let error = subscriptions.try_acquire(token).unwrap_err();
+            assert_eq!(error.code(), Code::ResourceExhausted);
+        }
+        assert_eq!(subscriptions.total.load(Ordering::Relaxed), 3);
+
+        // disconnect frees the slot
+        drop(guard1);
+        let _guard4 = subscriptions.try_acquire(Some("token1")).unwrap();
+        drop(guard3);
+        let _guard5 = subscriptions.try_acquire(Some("token2")).unwrap();
+        assert_eq!(subscriptions.total.load(Ordering::Relaxed), 3);
+
+        // tokens without subscriptions are removed
+        drop(guard2);
+        let per_token = subscriptions.per_token.lock().unwrap().clone();
+        assert_eq!(
+            per_token,
+            HashMap::from([("token1".to_owned(), 1), ("token2".to_owned(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_slot_liveness() {
+        let now = Instant::now();
+        let staleness = Duration::from_secs(5);
+        let liveness = SlotLiveness::new(now);
+        assert!(liveness.is_serving(staleness, now + Duration::from_secs(5)));
+        assert!(!liveness.is_serving(staleness, now + Duration::from_secs(6)));
+
+        // slots advance
+        for (slot, secs) in [(10, 4), (11, 8), (12, 12)] {
+            let ts = now + Duration::from_secs(secs);
+            liveness.update(slot, ts);
+            assert!(liveness.is_serving(staleness, ts + Duration::from_secs(1)));
+        }
+
+        // statuses of known slots do not advance liveness
+        liveness.update(12, now + Duration::from_secs(16));
+        liveness.update(11, now + Duration::from_secs(16));
+        assert!(liveness.is_serving(staleness, now + Duration::from_secs(17)));
+        assert!(!liveness.is_serving(staleness, now + Duration::from_secs(18)));
+
+        // healthy again on the next slot
+        liveness.update(13, now + Duration::from_secs(19));
+        assert!(liveness.is_serving(staleness, now + Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_slot_seqs() {
+        let mut slot_seqs = SlotSeqs::default();
+        let mut set = |message| {
+            let mut update = FilteredUpdate::new_empty(message);
+            slot_seqs.set(&mut update);
+            update.slot_seq
+        };
+        let create_account = |slot| {
+            let mut message = create_message_account(None, 1);
+            message.slot = slot;
+            FilteredUpdateOneof::account(&message, FilterAccountsDataSlice::default())
+        };
+        let create_slot = |slot| {
+            let Message::Slot(message) = create_message_slot(slot) else {
+                unreachable!();
+            };
+            FilteredUpdateOneof::slot(message)
+        };
+
+        // mixed updates of interleaved slots are numbered per slot
+        let mut seqs = vec![];
+        for (slot, message) in [
+            (42, create_account(42)),
+            (42, create_slot(42)),
+            (43, create_account(43)),
+            (42, create_account(42)),
+            (43, create_slot(43)),
+            (42, create_account(42)),
+        ] {
+            seqs.push((slot, set(message).expect("slot_seq")));
+        }
+        for slot in [42, 43] {
+            let slot_seqs = seqs
+                .iter()
+                .filter(|(seq_slot, _seq)| *seq_slot == slot)
+                .map(|(_slot, seq)| *seq)
+                .collect::<Vec<_>>();
+            assert_eq!(slot_seqs, (0..slot_seqs.len() as u64).collect::<Vec<_>>());
+        }
+
+        // updates without slot are not numbered and do not consume numbers
+        assert_eq!(set(FilteredUpdateOneof::ping()), None);
+        assert_eq!(set(FilteredUpdateOneof::server_shutdown()), None);
+        assert_eq!(set(create_account(42)), Some(4));
+
+        // numbers of evicted slots start again
+        for slot in 0..SlotSeqs::SLOTS_MAX as u64 {
+            set(create_slot(1_000 + slot));
+        }
+        assert_eq!(set(create_account(1_000)), Some(1));
+        assert_eq!(set(create_account(43)), Some(0));
+    }
+
+    #[test]
+    fn test_strict_commitment_gate() {
+        let slot_status = |slot, status| {
+            Message::Slot(MessageSlot {
+                slot,
+                parent: None,
+                status,
+                dead_error: None,
+                raw_status: None,
+                created_at: Timestamp::from(SystemTime::now()),
+            })
+        };
+        let account = |slot| {
+            let mut message = create_message_account(None, 1);
+            message.slot = slot;
+            Message::Account(message)
+        };
+        let updates = |kinds: &[&str]| {
+            kinds
+                .iter()
+                .map(|kind| {
+                    let mut update = FilteredUpdate::new_empty(FilteredUpdateOneof::ping());
+                    update.filters.push(FilterName::new(*kind));
+                    update
+                })
+                .collect::<FilteredUpdates>()
+        };
+        let kinds = |updates: FilteredUpdates| {
+            updates
+                .iter()
+                .map(|update| update.filters[0].as_ref().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let mut gate = StrictCommitmentGate::new(CommitmentLevel::Confirmed);
+
+        // processed data is buffered until the slot is confirmed
+        assert!(kinds(gate.push(&account(42), updates(&["account"]))).is_empty());
+        let message = slot_status(42, SlotStatus::Processed);
+        assert!(kinds(gate.push(&message, updates(&["processed"]))).is_empty());
+        assert!(!gate.is_empty());
+        let message = slot_status(42, SlotStatus::Confirmed);
+        assert_eq!(
+            kinds(gate.push(&message, updates(&["confirmed"]))),
+            vec!["account", "processed", "confirmed"]
+        );
+        assert!(gate.is_empty());
+        assert_eq!(
+            kinds(gate.push(&account(42), updates(&["account"]))),
+            vec!["account"]
+        );
+
+        // dead slot cancels buffered data
+        assert!(kinds(gate.push(&account(43), updates(&["account"]))).is_empty());
+        let message = slot_status(43, SlotStatus::Dead);
+        assert_eq!(kinds(gate.push(&message, updates(&["dead"]))), vec!["dead"]);
+        assert!(gate.is_empty());
+
+        // finalized slot cancels buffered data of abandoned forks
+        assert!(kinds(gate.push(&account(44), updates(&["account"]))).is_empty());
+        let message = slot_status(45, SlotStatus::Finalized);
+        assert_eq!(
+            kinds(gate.push(&message, updates(&["finalized"]))),
+            vec!["finalized"]
+        );
+        assert!(gate.is_empty());
+        let message = slot_status(44, SlotStatus::Confirmed);
+        assert!(kinds(gate.push(&message, FilteredUpdates::new())).is_empty());
+
+        // processed commitment is not gated
+        let mut gate = StrictCommitmentGate::new(CommitmentLevel::Processed);
+        assert_eq!(
+            kinds(gate.push(&account(46), updates(&["account"]))),
+            vec!["account"]
+        );
+    }
+
+    #[test]
+    fn test_replay_resume_token() {
+        // ids of messages with processed commitment are interleaved across slots
+        let mut messages = BTreeMap::new();
+        for (slot, slot_msgid, msgids, confirmed) in [
+            (10, 1, vec![3, 6], true),
+            (11, 2, vec![4, 7], true),
+            (12, 5, vec![8], false),
+        ] {
+            let mut slot_messages = SlotMessages {
+                confirmed,
+                ..Default::default()
+            };
+            slot_messages
+                .messages_slots
+                .push((slot_msgid, create_message_slot(slot)));
+            for msgid in msgids {
+                slot_messages
+                    .messages
+                    .push(Some((msgid, create_message_slot(slot))));
+            }
+            messages.insert(slot, slot_messages);
+        }
+        let replay = |commitment, slot, msgid| {
+            let token = ResumeToken {
+                instance: 0,
+                commitment,
+                slot,
+                msgid,
+            };
+            match SlotMessages::replay_after(&messages, token) {
+                ReplayedResponse::Messages(messages) => {
+                    let mut msgids = messages
+                        .iter()
+                        .map(|(msgid, _message)| *msgid)
+                        .collect::<Vec<_>>();
+                    msgids.sort_unstable();
+                    Ok(msgids)
+                }
+                ReplayedResponse::Lagged(slot) => Err(slot),
+            }
+        };
+
+        // processed: messages after the id, including older slots
+        assert_eq!(
+            replay(CommitmentLevel::Processed, 11, 4),
+            Ok(vec![5, 6, 7, 8])
+        );
+        assert_eq!(replay(CommitmentLevel::Processed, 12, 8), Ok(vec![]));
+        // confirmed: messages after the id in the slot and all messages of the next slots
+        assert_eq!(
+            replay(CommitmentLevel::Confirmed, 10, 3),
+            Ok(vec![2, 4, 5, 6, 7])
+        );
+        assert_eq!(replay(CommitmentLevel::Confirmed, 11, 7), Ok(vec![5]));
+        // position out of stored slots
+        assert_eq!(replay(CommitmentLevel::Processed, 9, 0), Err(10));
+        assert_eq!(replay(CommitmentLevel::Confirmed, 9, 0), Err(10));
+    }
+
+    #[test]
+    fn test_replay_from_request() {
+        let filter = Filter::default();
+        let token = ResumeToken {
+            instance: 0,
+            commitment: CommitmentLevel::Processed,
+            slot: 10,
+            msgid: 1,
+        };
+        let replay_from = |from_slot, resume_token, enabled| {
+            let request = SubscribeRequest {
+                from_slot,
+                resume_token,
+                ..Default::default()
+            };
+            GrpcService::get_replay_from(&request, &filter, enabled)
+        };
+
+        assert_eq!(replay_from(None, None, false), Ok(None));
+        assert_eq!(
+            replay_from(Some(10), None, false),
+            Ok(Some(ReplayFrom::Slot(10)))
+        );
+        assert_eq!(
+            replay_from(None, Some(token.encode()), true),
+            Ok(Some(ReplayFrom::Token(token)))
+        );
+        assert!(replay_from(None, Some(token.encode()), false).is_err());
+        assert!(replay_from(Some(10), Some(token.encode()), true).is_err());
+        assert!(replay_from(None, Some(vec![1, 2, 3]), true).is_err());
+        let token = ResumeToken {
+            commitment: CommitmentLevel::Finalized,
+            ..token
+        };
+        assert!(replay_from(None, Some(token.encode()), true).is_err());
+    }
+
+    #[test]
+    fn test_replay_retention_duration() {
+        let now = SystemTime::now();
+        let mut messages = create_slot_messages(1..=30);
+        for (slot, slot_messages) in messages.iter_mut() {
+            let age = if *slot <= 5 { 120 } else { 0 };
+            slot_messages.last_created_at = Timestamp::from(now - Duration::from_secs(age));
+        }
+
+        // only slot count bound
+        assert_eq!(
+            SlotMessages::retention_first_slot(&messages, 30, 100, None, now),
+            0
+        );
+        // expired slots removed within slot count bound
+        assert_eq!(
+            SlotMessages::retention_first_slot(
+                &messages,
+                30,
+                100,
+                Some(Duration::from_secs(60)),
+                now
+            ),
+            6
+        );
+        // slot count bound is still applied
+        assert_eq!(
+            SlotMessages::retention_first_slot(
+                &messages,
+                30,
+                5,
+                Some(Duration::from_secs(60)),
+                now
+            ),
+            15
+        );
+        // extra slots for block reconstruction are kept even if expired
+        assert_eq!(
+            SlotMessages::retention_first_slot(
+                &messages,
+                30,
+                100,
+                Some(Duration::from_secs(1)),
+                now + Duration::from_secs(10)
+            ),
+            20
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ping_loop() {
+        let (stream_tx, mut stream_rx) = mpsc::channel(1);
+        let (client_tx, _client_rx) = mpsc::unbounded_channel();
+        let exit = Arc::new(Notify::new());
+        let (ping_interval_tx, ping_interval_rx) = watch::channel(Duration::from_millis(50));
+        let ping_loop = tokio::spawn(GrpcService::ping_loop(
+            stream_tx,
+            client_tx,
+            Arc::clone(&exit),
+            ping_interval_rx,
+        ));
+
+        // idle stream receives ping within the interval
+        let msg = timeout(Duration::from_millis(500), stream_rx.recv())
+            .await
+            .expect("ping within the interval")
+            .expect("stream is open")
+            .expect("ping message");
+        assert!(matches!(msg.message, FilteredUpdateOneof::Ping));
+
+        // updated interval is applied
+        ping_interval_tx.send(Duration::from_secs(3_600)).unwrap();
+        assert!(timeout(Duration::from_millis(200), stream_rx.recv())
+            .await
+            .is_err());
+
+        // loop is stopped on exit, even without interval updates
+        drop(ping_interval_tx);
+        exit.notify_one();
+        timeout(Duration::from_secs(1), ping_loop)
+            .await
+            .expect("ping loop stopped")
+            .expect("ping loop not panicked");
+    }
+
+    tokio::task_local! {
+        static CAPTURED_LOGS: RefCell<Vec<String>>;
+    }
+
+    /// Logger of the test process, lines are kept only within `capture_logs`
+    struct CaptureLogger;
+
+    impl Log for CaptureLogger {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            let _ = CAPTURED_LOGS.try_with(|lines| {
+                lines.borrow_mut().push(record.args().to_string());
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE_LOGGER: CaptureLogger = CaptureLogger;
+
+    /// Runs the future in the current task and returns log lines emitted by it, lines of
+    /// other tests are not captured
+    async fn capture_logs<F: Future>(future: F) -> (F::Output, Vec<String>) {
+        if log::set_logger(&CAPTURE_LOGGER).is_ok() {
+            log::set_max_level(LevelFilter::Info);
+        }
+        CAPTURED_LOGS
+            .scope(RefCell::new(Vec::new()), async {
+                let output = future.await;
+                (output, CAPTURED_LOGS.with(RefCell::take))
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_connection_span() {
+        let span = ConnectionSpan::new(
+            42,
+            Some("127.0.0.1:10000".parse().unwrap()),
+            Some("secret-token"),
+        );
+        let context = span.to_string();
+        assert_eq!(
+            context,
+            format!(
+                "client #42 peer=127.0.0.1:10000 token={:016x}",
+                token_hash("secret-token")
+            )
+        );
+        assert_eq!(
+            ConnectionSpan::new(42, None, None).to_string(),
+            "client #42"
+        );
+        assert_eq!(ConnectionSpan::current(), "client");
+
+        // lines of the client loop are prefixed with the context of the connection
+        let (stream_tx, _stream_rx) = mpsc::channel(64);
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let (_broadcast_tx, messages_rx) = broadcast::channel(64);
+        let (clients_tx, _clients_rx) = mpsc::channel(1);
+        client_tx.send(None).unwrap();
+        let client_loop = span.instrument(GrpcService::client_loop(
+            42,
+            stream_tx,
+            client_rx,
+            messages_rx,
+            ClientLoopContext::default(),
+            clients_tx,
+            || {},
+        ));
+        let ((), lines) = timeout(Duration::from_secs(1), capture_logs(client_loop))
+            .await
+            .expect("client loop stopped");
+        assert_eq!(
+            lines,
+            [format!("{context}: new"), format!("{context}: removed")]
+        );
+        assert!(lines.iter().all(|line| !line.contains("secret-token")));
+    }
+
+    #[tokio::test]
+    async fn test_incoming_loop_span() {
+        let span = ConnectionSpan::new(42, None, Some("secret-token"));
+        let context = span.to_string();
+        let (stream_tx, mut stream_rx) = mpsc::channel(64);
+        let (client_tx, mut client_rx) = mpsc::unbounded_channel();
+        let (ping_interval_tx, _ping_interval_rx) = watch::channel(Duration::from_secs(10));
+        let (rate_limit_tx, _rate_limit_rx) = watch::channel(None);
+        let (channel_capacity_tx, _channel_capacity_rx) = watch::channel(64);
+        let requests = tokio_stream::iter([Ok(SubscribeRequest {
+            channel_capacity: Some(0),
+            ..Default::default()
+        })]);
+        let incoming_loop = span.instrument(GrpcService::incoming_loop(
+            requests,
+            stream_tx,
+            client_tx,
+            Arc::new(Notify::new()),
+            IncomingContext {
+                filter_names: Arc::new(Mutex::new(FilterNames::new(
+                    64,
+                    1024,
+                    Duration::from_secs(1),
+                ))),
+                filter_cache: Arc::new(Mutex::new(FilterCache::new(0, Duration::from_secs(1)))),
+                filter_limits: Arc::new(FilterLimits::default()),
+                ping_interval: Duration::from_secs(10),
+                ping_interval_tx,
+                rate_limit: ConfigGrpcRateLimit::default(),
+                rate_limit_tx,
+                channel_capacity: 64,
+                channel_capacity_tx,
+                accounts_snapshot_enabled: false,
+                slots_replay_finalized_enabled: false,
+                json_encoding_enabled: false,
+                resume_tokens_enabled: false,
+            },
+        ));
+        let ((), lines) = timeout(Duration::from_secs(1), capture_logs(incoming_loop))
+            .await
+            .expect("incoming loop stopped");
+
+        // invalid request is rejected with the context of the connection, stream is kept open
+        assert_eq!(
+            lines,
+            [format!(
+                "{context}: failed to create filter: channel_capacity should be greater than 0"
+            )]
+        );
+        let status = stream_rx
+            .try_recv()
+            .expect("error is sent")
+            .expect_err("invalid request");
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(client_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stale_stream_loop() {
+        let (stream_tx, stream_rx) = mpsc::channel(1);
+        let (client_tx, mut client_rx) = mpsc::unbounded_channel();
+        let stream_activity = Arc::new(StreamActivity::new());
+        let mut stream = SubscribeStream::new(stream_rx, Arc::clone(&stream_activity));
+
+        // consumed updates are recorded as activity
+        sleep(Duration::from_millis(50)).await;
+        stream_tx
+            .try_send(Ok(FilteredUpdate::new_empty(FilteredUpdateOneof::ping())))
+            .unwrap();
+        assert!(stream.next().await.expect("stream is open").is_ok());
+        assert!(stream_activity.idle() < Duration::from_millis(50));
+
+        // stalled stream, channel is full and nothing is consumed
+        stream_tx
+            .try_send(Ok(FilteredUpdate::new_empty(FilteredUpdateOneof::ping())))
+            .unwrap();
+        let stale_stream_loop = tokio::spawn(GrpcService::stale_stream_loop(
+            stream_tx.clone(),
+            Arc::clone(&stream_activity),
+            client_tx,
+            Arc::new(Notify::new()),
+            Duration::from_millis(100),
+        ));
+
+        // loop is not blocked by the full channel and client loop is stopped
+        timeout(Duration::from_secs(1), stale_stream_loop)
+            .await
+            .expect("stale stream loop stopped")
+            .expect("stale stream loop not panicked");
+        assert!(matches!(client_rx.recv().await, Some(None)));
+        assert!(stream_activity.idle() >= Duration::from_millis(100));
+        assert!(!stream_tx.is_closed());
+
+        // stream is terminated with `DeadlineExceeded` on the next read
+        let status = stream
+            .next()
+            .await
+            .expect("stream is open")
+            .expect_err("status");
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+        assert!(stream.next().await.is_none());
+        assert!(stream_tx.is_closed());
+    }
+
+    #[test]
+    fn test_updates_rate_limiter_rate() {
+        let mut limiter = UpdatesRateLimiter::new(100, ConfigGrpcRateLimitPolicy::Drop);
+        let started_at = limiter.updated_at;
+
+        // 1,000 updates per second during 5 seconds
+        let mut sent = 0;
+        for i in 1..=5_000 {
+            let now = started_at + Duration::from_millis(i);
+            if limiter.try_acquire(now).is_ok() {
+                sent += 1;
+            }
+        }
+        // rate plus initial burst
+        assert!((500..=600).contains(&sent), "sent {sent}");
+
+        // tokens are refilled after idle time, but not above the burst
+        let now = started_at + Duration::from_secs(60);
+        let sent = (0..1_000)
+            .filter(|_| limiter.try_acquire(now).is_ok())
+            .count();
+        assert_eq!(sent, 100);
+        let wait = limiter.try_acquire(now).expect_err("limit is reached");
+        assert!(wait <= Duration::from_millis(10));
+    }
+
+    #[tokio::test]
+    async fn test_updates_rate_limiter_policy() {
+        let block_meta = FilteredUpdateOneof::block_meta(Arc::new(MessageBlockMeta {
+            block_meta: SubscribeUpdateBlockMeta::default(),
+            created_at: Timestamp::from(SystemTime::now()),
+        }));
+        let ping = FilteredUpdateOneof::ping();
+        let queue_metrics = metrics::ConnectionQueueMetrics::new(usize::MAX - 1);
+
+        // updates over the limit are dropped, pings are not limited
+        let mut limiter = UpdatesRateLimiter::new(10, ConfigGrpcRateLimitPolicy::Drop);
+        let mut sent = 0;
+        for _ in 0..20 {
+            if limiter
+                .acquire(&queue_metrics, &block_meta, &mut None)
+                .await
+            {
+                sent += 1;
+            }
+            assert!(limiter.acquire(&queue_metrics, &ping, &mut None).await);
+        }
+        assert!((10..=11).contains(&sent), "sent {sent}");
+        assert_eq!(limiter.throttled, 20 - sent);
+
+        // updates over the limit are delayed
+        let mut limiter = UpdatesRateLimiter::new(50, ConfigGrpcRateLimitPolicy::Backpressure);
+        let ts = Instant::now();
+        for _ in 0..60 {
+            assert!(
+                limiter
+                    .acquire(&queue_metrics, &block_meta, &mut None)
+                    .await
+            );
+        }
+        assert!(ts.elapsed() >= Duration::from_millis(150));
+        assert!(limiter.throttled > 0 && limiter.throttled <= 10);
+    }
+
+    #[tokio::test]
+    async fn test_updates_rate_limiter_fair_scheduling() {
+        let block_meta = FilteredUpdateOneof::block_meta(Arc::new(MessageBlockMeta {
+            block_meta: SubscribeUpdateBlockMeta::default(),
+            created_at: Timestamp::from(SystemTime::now()),
+        }));
+        let queue_metrics = metrics::ConnectionQueueMetrics::new(usize::MAX - 2);
+        let scheduler = FairScheduler::new(1);
+        let throttled = scheduler.client(0, 1);
+        let other = scheduler.client(1, 1);
+
+        // throttled client holds the only permit and used all tokens
+        let mut limiter = UpdatesRateLimiter::new(10, ConfigGrpcRateLimitPolicy::Backpressure);
+        let mut permit = Some(throttled.acquire(20).await);
+        for _ in 0..10 {
+            assert!(
+                limiter
+                    .acquire(&queue_metrics, &block_meta, &mut permit)
+                    .await
+            );
+        }
+        assert!(permit.is_some());
+
+        // permit is released while the throttled client waits for the next token
+        let (sent, other_permit) = tokio::join!(
+            limiter.acquire(&queue_metrics, &block_meta, &mut permit),
+            timeout(Duration::from_millis(50), other.acquire(1)),
+        );
+        assert!(sent);
+        assert!(permit.is_none());
+        other_permit.expect("other client is scheduled while throttled client waits");
+    }
+
+    async fn wait_subscribe_accepted(
+        stream_rx: &mut mpsc::Receiver<TonicResult<FilteredUpdate>>,
+    ) -> SubscribeUpdateSubscribeAccepted {
+        let update = timeout(Duration::from_secs(1), stream_rx.recv())
+            .await
+            .expect("subscription accepted within the deadline")
+            .expect("stream is not closed")
+            .expect("update");
+        match update.message {
+            FilteredUpdateOneof::SubscribeAccepted(message) => message,
+            message => panic!("unexpected first message: {message:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_loop_subscribe_accepted() {
+        let (stream_tx, mut stream_rx) = mpsc::channel(64);
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let (broadcast_tx, messages_rx) = broadcast::channel(64);
+        let (clients_tx, _clients_rx) = mpsc::channel(1);
+        let client_loop = tokio::spawn(GrpcService::client_loop(
+            0,
+            stream_tx,
+            client_rx,
+            messages_rx,
+            ClientLoopContext::default(),
+            clients_tx,
+            || {},
+        ));
+
+        let mut names = FilterNames::new(64, 1024, Duration::from_secs(1));
+        let mut slots = HashMap::new();
+        slots.insert("slots".to_owned(), SubscribeRequestFilterSlots::default());
+        let mut accounts = HashMap::new();
+        accounts.insert("a".to_owned(), SubscribeRequestFilterAccounts::default());
+        accounts.insert("b".to_owned(), SubscribeRequestFilterAccounts::default());
+        let request = SubscribeRequest {
+            accounts,
+            slots,
+            commitment: Some(CommitmentLevelProto::Confirmed as i32),
+            ..Default::default()
+        };
+        let filter = Filter::new(&request, &FilterLimits::default(), &mut names).unwrap();
+        client_tx.send(Some((None, filter))).unwrap();
+
+        let message = wait_subscribe_accepted(&mut stream_rx).await;
+        assert_eq!(message.commitment, CommitmentLevelProto::Confirmed as i32);
+        let counts = message
+            .filters
+            .iter()
+            .map(|entry| (entry.filter_type.as_str(), entry.count))
+            .filter(|(_filter_type, count)| *count > 0)
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![("accounts", 2), ("slots", 1), ("all", 3)]);
+
+        // filter updates are not confirmed again
+        let filter = Filter::new(&request, &FilterLimits::default(), &mut names).unwrap();
+        client_tx.send(Some((None, filter))).unwrap();
+        broadcast_tx
+            .send((
+                CommitmentLevel::Confirmed,
+                Arc::new(vec![(0, create_message_slot(0))]),
+            ))
+            .unwrap();
+        let update = timeout(Duration::from_secs(1), stream_rx.recv())
+            .await
+            .expect("update within the deadline")
+            .expect("stream is not closed")
+            .expect("update");
+        assert!(matches!(update.message, FilteredUpdateOneof::Slot(_)));
+
+        drop(client_tx);
+        client_loop.await.expect("client loop not panicked");
+    }
+
+    /// Kind, value and `slot_seq` of the next update rendered as JSON
+    async fn recv_json(
+        stream_rx: &mut mpsc::Receiver<TonicResult<FilteredUpdate>>,
+    ) -> (String, serde_json::Value, Option<u64>) {
+        let update = timeout(Duration::from_secs(1), stream_rx.recv())
+            .await
+            .expect("update within the deadline")
+            .expect("stream is not closed")
+            .expect("update");
+        let FilteredUpdateOneof::Json(msg) = update.message else {
+            panic!("expected json update: {:?}", update.message);
+        };
+        let value = serde_json::from_str(&msg.value).expect("valid json");
+        (msg.kind, value, update.slot_seq)
+    }
+
+    #[tokio::test]
+    async fn test_client_loop_wrap_updates() {
+        let (stream_tx, mut stream_rx) = mpsc::channel(64);
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let (broadcast_tx, messages_rx) = broadcast::channel(64);
+        let (clients_tx, _clients_rx) = mpsc::channel(1);
+        let client_loop = tokio::spawn(GrpcService::client_loop(
+            0,
+            stream_tx,
+            client_rx,
+            messages_rx,
+            ClientLoopContext {
+                account_data_threshold: Some(2),
+                rate_limit_rx: watch::channel(Some(1)).1,
+                rate_limit_policy: ConfigGrpcRateLimitPolicy::Drop,
+                ..Default::default()
+            },
+            clients_tx,
+            || {},
+        ));
+
+        let mut slots = HashMap::new();
+        slots.insert("slots".to_owned(), SubscribeRequestFilterSlots::default());
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "accounts".to_owned(),
+            SubscribeRequestFilterAccounts::default(),
+        );
+        let request = SubscribeRequest {
+            accounts,
+            slots,
+            encoding: Some(UpdatesEncoding::Json as i32),
+            ..Default::default()
+        };
+        let filter = Filter::new(
+            &request,
+            &FilterLimits::default(),
+            &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+        )
+        .unwrap();
+        client_tx.send(Some((None, filter))).unwrap();
+
+        assert_eq!(recv_json(&mut stream_rx).await.0, "subscribeAccepted");
+
+        // slots are not throttled, the second account is over the rate limit
+        broadcast_tx
+            .send((
+                CommitmentLevel::Processed,
+                Arc::new(vec![
+                    (0, create_message_slot(42)),
+                    (1, Message::Account(create_message_account(None, 1))),
+                    (2, Message::Account(create_message_account(None, 2))),
+                    (3, create_message_slot(43)),
+                ]),
+            ))
+            .unwrap();
+        let (kind, value, slot_seq) = recv_json(&mut stream_rx).await;
+        assert_eq!((kind.as_str(), slot_seq), ("slot", Some(0)));
+        assert_eq!(value["slot"], 42);
+        // data is sent out-of-band before the update is rendered
+        let (kind, value, slot_seq) = recv_json(&mut stream_rx).await;
+        assert_eq!((kind.as_str(), slot_seq), ("account", Some(1)));
+        assert_eq!(value["writeVersion"], 1);
+        assert_eq!(value["dataRef"]["dataLen"], 3);
+        let (kind, value, slot_seq) = recv_json(&mut stream_rx).await;
+        assert_eq!((kind.as_str(), slot_seq), ("slot", Some(0)));
+        assert_eq!(value["slot"], 43);
+
+        drop(client_tx);
+        client_loop.await.expect("client loop not panicked");
+    }
+
+    fn create_message_slot_finalized(slot: u64) -> MessageSlot {
+        let Message::Slot(message) = create_message_slot(slot) else {
+            unreachable!();
+        };
+        MessageSlot {
+            status: SlotStatus::Finalized,
+            ..message
+        }
+    }
+
+    #[test]
+    fn test_slots_finalized_storage() {
+        let mut storage = SlotsFinalizedStorageInner::new(3);
+        for slot in 10..15 {
+            storage.push(create_message_slot_finalized(slot));
+            // other statuses and repeated or older finalized slots are not stored
+            let Message::Slot(message) = create_message_slot(slot + 100) else {
+                unreachable!();
+            };
+            storage.push(message);
+            storage.push(create_message_slot_finalized(slot - 1));
+        }
+        let slots = storage
+            .slots
+            .iter()
+            .map(|message| message.slot)
+            .collect::<Vec<_>>();
+        assert_eq!(slots, [12, 13, 14]);
+    }
+
+    #[tokio::test]
+    async fn test_client_loop_slots_replay_finalized() {
+        let mut storage = SlotsFinalizedStorageInner::new(8);
+        for slot in 10..13 {
+            storage.push(create_message_slot_finalized(slot));
+        }
+        let slots_finalized = SlotsFinalizedStorage {
+            inner: Arc::new(RwLock::new(storage)),
+        };
+
+        let (stream_tx, mut stream_rx) = mpsc::channel(64);
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let (broadcast_tx, messages_rx) = broadcast::channel(64);
+        let (clients_tx, _clients_rx) = mpsc::channel(1);
+        let client_loop = tokio::spawn(GrpcService::client_loop(
+            0,
+            stream_tx,
+            client_rx,
+            messages_rx,
+            ClientLoopContext {
+                slots_finalized: Some(Arc::new(slots_finalized)),
+                ..Default::default()
+            },
+            clients_tx,
+            || {},
+        ));
+        fn recv_slot(
+            stream_rx: &mut mpsc::Receiver<TonicResult<FilteredUpdate>>,
+        ) -> (u64, SlotStatus) {
+            let update = stream_rx.try_recv().expect("update");
+            match update.expect("update").message {
+                FilteredUpdateOneof::Slot(message) => (message.slot, message.status),
+                message => panic!("unexpected message: {message:?}"),
+            }
+        }
+
+        let mut names = FilterNames::new(64, 1024, Duration::from_secs(1));
+        let create_filter = |names: &mut FilterNames, filter_by_commitment, commitment| {
+            let mut slots = HashMap::new();
+            slots.insert(
+                "slots".to_owned(),
+                SubscribeRequestFilterSlots {
+                    filter_by_commitment: Some(filter_by_commitment),
+                    replay_finalized: Some(2),
+                    ..Default::default()
+                },
+            );
+            let request = SubscribeRequest {
+                slots,
+                commitment: Some(commitment as i32),
+                ..Default::default()
+            };
+            Filter::new(&request, &FilterLimits::default(), names).unwrap()
+        };
+
+        // replayed slots precede live updates
+        client_tx
+            .send(Some((
+                None,
+                create_filter(&mut names, false, CommitmentLevelProto::Processed),
+            )))
+            .unwrap();
+        wait_subscribe_accepted(&mut stream_rx).await;
+        broadcast_tx
+            .send((
+                CommitmentLevel::Processed,
+                Arc::new(vec![(0, create_message_slot(20))]),
+            ))
+            .unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(recv_slot(&mut stream_rx), (11, SlotStatus::Finalized));
+        assert_eq!(recv_slot(&mut stream_rx), (12, SlotStatus::Finalized));
+        assert_eq!(recv_slot(&mut stream_rx), (20, SlotStatus::Processed));
+
+        // finalized slots are not replayed to `filter_by_commitment` with other commitment
+        client_tx
+            .send(Some((
+                None,
+                create_filter(&mut names, true, CommitmentLevelProto::Processed),
+            )))
+            .unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert!(stream_rx.try_recv().is_err());
+        client_tx
+            .send(Some((
+                None,
+                create_filter(&mut names, true, CommitmentLevelProto::Finalized),
+            )))
+            .unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(recv_slot(&mut stream_rx), (11, SlotStatus::Finalized));
+        assert_eq!(recv_slot(&mut stream_rx), (12, SlotStatus::Finalized));
+
+        drop(client_tx);
+        client_loop.await.expect("client loop not panicked");
+    }
+
+    #[tokio::test]
+    async fn test_client_loop_drain() {
+        let (stream_tx, mut stream_rx) = mpsc::channel(64);
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let (broadcast_tx, messages_rx) = broadcast::channel(64);
+        let (drained_tx, drained_rx) = watch::channel(false);
+        let (clients_tx, mut clients_rx) = mpsc::channel(1);
+        let client_loop = tokio::spawn(GrpcService::client_loop(
+            0,
+            stream_tx,
+            client_rx,
+            messages_rx,
+            ClientLoopContext {
+                drained_rx,
+                ..Default::default()
+            },
+            clients_tx,
+            || {},
+        ));
+
+        let mut slots = HashMap::new();
+        slots.insert("slots".to_owned(), SubscribeRequestFilterSlots::default());
+        let filter = Filter::new(
+            &SubscribeRequest {
+                slots,
+                ..Default::default()
+            },
+            &FilterLimits::default(),
+            &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+        )
+        .unwrap();
+        client_tx.send(Some((None, filter))).unwrap();
+
+        let send_slots = |slots: std::ops::Range<u64>| {
+            let messages = slots
+                .map(|slot| (slot, create_message_slot(slot)))
+                .collect::<Vec<_>>();
+            broadcast_tx
+                .send((CommitmentLevel::Processed, Arc::new(messages)))
+                .unwrap();
+        };
+
+        // filter is applied once the subscription is accepted
+        wait_subscribe_accepted(&mut stream_rx).await;
+
+        // queued updates are followed by shutdown message and stream close
+        let ts = Instant::now();
+        for slot in (100..140).step_by(10) {
+            send_slots(slot..slot + 10);
+        }
+        drained_tx.send(true).unwrap();
+        let mut updates = vec![];
+        while let Some(update) = timeout(Duration::from_secs(1), stream_rx.recv())
+            .await
+            .expect("stream closed within the deadline")
+        {
+            match update.expect("update").message {
+                FilteredUpdateOneof::Slot(message) if message.slot < 100 => {}
+                FilteredUpdateOneof::Slot(message) => updates.push(Some(message.slot)),
+                FilteredUpdateOneof::ServerShutdown => updates.push(None),
+                message => panic!("unexpected message: {message:?}"),
+            }
+        }
+        let mut expected = (100..140).map(Some).collect::<Vec<_>>();
+        expected.push(None);
+        assert_eq!(updates, expected);
+
+        // client is removed within the deadline
+        assert!(timeout(Duration::from_secs(1), clients_rx.recv())
+            .await
+            .expect("client removed")
+            .is_none());
+        assert!(ts.elapsed() < Duration::from_secs(1));
+        client_loop.await.expect("client loop not panicked");
+    }
+
+    #[tokio::test]
+    async fn test_client_loop_dropped_messages() {
+        let dropped = || {
+            metrics::CONNECTION_DROPPED_MESSAGES
+                .with_label_values(&["all"])
+                .get()
+        };
+        let _lock = DROPPED_MESSAGES_LOCK.lock().await;
+        let dropped_before = dropped();
+
+        let (stream_tx, mut stream_rx) = mpsc::channel(4);
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let (broadcast_tx, messages_rx) = broadcast::channel(64);
+        let (clients_tx, mut clients_rx) = mpsc::channel(1);
+        let client_loop = tokio::spawn(GrpcService::client_loop(
+            0,
+            stream_tx,
+            client_rx,
+            messages_rx,
+            ClientLoopContext {
+                channel_capacity_rx: watch::channel(4).1,
+                ..Default::default()
+            },
+            clients_tx,
+            || {},
+        ));
+
+        let mut slots = HashMap::new();
+        slots.insert("slots".to_owned(), SubscribeRequestFilterSlots::default());
+        let filter = Filter::new(
+            &SubscribeRequest {
+                slots,
+                ..Default::default()
+            },
+            &FilterLimits::default(),
+            &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+        )
+        .unwrap();
+        client_tx.send(Some((None, filter))).unwrap();
+
+        let send_slots = |slots: std::ops::Range<u64>| {
+            let messages = slots
+                .map(|slot| (slot, create_message_slot(slot)))
+                .collect::<Vec<_>>();
+            broadcast_tx
+                .send((CommitmentLevel::Processed, Arc::new(messages)))
+                .unwrap();
+        };
+
+        // filter is applied once the subscription is accepted
+        wait_subscribe_accepted(&mut stream_rx).await;
+
+        // slow consumer, the fifth update overflows the stream queue
+        send_slots(100..110);
+        assert!(timeout(Duration::from_secs(1), clients_rx.recv())
+            .await
+            .expect("client removed")
+            .is_none());
+        client_loop.await.expect("client loop not panicked");
+        assert_eq!(dropped() - dropped_before, 1);
+
+        let mut updates = vec![];
+        while let Some(update) = stream_rx.recv().await {
+            updates.push(
+                update
+                    .map(|update| update.message)
+                    .map_err(|status| status.code()),
+            );
+        }
+        assert_eq!(updates.len(), 5);
+        assert_eq!(updates[4], Err(Code::Internal));
+    }
+
+    /// Sends slots to the client loop while the consumer is stalled, returns received slots
+    async fn client_loop_stalled(
+        stream_capacity: usize,
+        channel_capacity: usize,
+        channel_full_policy: ConfigGrpcClientChannelFullPolicy,
+        stalled_slots: std::ops::Range<u64>,
+    ) -> Vec<Result<u64, Code>> {
+        let (stream_tx, mut stream_rx) = mpsc::channel(stream_capacity);
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let (broadcast_tx, messages_rx) = broadcast::channel(64);
+        let (clients_tx, _clients_rx) = mpsc::channel(1);
+        let client_loop = tokio::spawn(GrpcService::client_loop(
+            0,
+            stream_tx,
+            client_rx,
+            messages_rx,
+            ClientLoopContext {
+                channel_capacity_rx: watch::channel(channel_capacity).1,
+                channel_full_policy,
+                ..Default::default()
+            },
+            clients_tx,
+            || {},
+        ));
+
+        let mut slots = HashMap::new();
+        slots.insert("slots".to_owned(), SubscribeRequestFilterSlots::default());
+        let filter = Filter::new(
+            &SubscribeRequest {
+                slots,
+                ..Default::default()
+            },
+            &FilterLimits::default(),
+            &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+        )
+        .unwrap();
+        client_tx.send(Some((None, filter))).unwrap();
+
+        let send_slots = |slots: std::ops::Range<u64>| {
+            let messages = slots
+                .map(|slot| (slot, create_message_slot(slot)))
+                .collect::<Vec<_>>();
+            broadcast_tx
+                .send((CommitmentLevel::Processed, Arc::new(messages)))
+                .unwrap();
+        };
+
+        // filter is applied once the subscription is accepted
+        wait_subscribe_accepted(&mut stream_rx).await;
+
+        send_slots(stalled_slots);
+        sleep(Duration::from_millis(100)).await;
+
+        let mut updates = vec![];
+        while let Ok(Some(update)) = timeout(Duration::from_millis(100), stream_rx.recv()).await {
+            match update {
+                Ok(update) => match update.message {
+                    FilteredUpdateOneof::Slot(message) if message.slot < 100 => {}
+                    FilteredUpdateOneof::Slot(message) => updates.push(Ok(message.slot)),
+                    message => panic!("unexpected message: {message:?}"),
+                },
+                Err(status) => updates.push(Err(status.code())),
+            }
+        }
+
+        drop(client_tx);
+        client_loop.await.expect("client loop not panicked");
+        updates
+    }
+
+    #[tokio::test]
+    async fn test_client_loop_channel_capacity() {
+        let _lock = DROPPED_MESSAGES_LOCK.lock().await;
+
+        // small buffer overflows during the stall, the stream is closed
+        let updates = client_loop_stalled(
+            64,
+            4,
+            ConfigGrpcClientChannelFullPolicy::Disconnect,
+            100..108,
+        )
+        .await;
+        assert_eq!(
+            updates,
+            vec![Ok(100), Ok(101), Ok(102), Ok(103), Err(Code::Internal)]
+        );
+
+        // larger buffer tolerates the same stall
+        let updates = client_loop_stalled(
+            64,
+            16,
+            ConfigGrpcClientChannelFullPolicy::Disconnect,
+            100..108,
+        )
+        .await;
+        assert_eq!(updates, (100..108).map(Ok).collect::<Vec<_>>());
+
+        // oldest updates are dropped, the stream is alive
+        let updates = client_loop_stalled(
+            2,
+            4,
+            ConfigGrpcClientChannelFullPolicy::DropOldest,
+            100..108,
+        )
+        .await;
+        assert_eq!(updates, (104..108).map(Ok).collect::<Vec<_>>());
+
+        let updates = client_loop_stalled(
+            2,
+            16,
+            ConfigGrpcClientChannelFullPolicy::DropOldest,
+            100..108,
+        )
+        .await;
+        assert_eq!(updates, (100..108).map(Ok).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_filter_limits_profiles() {
+        let limits = |max| {
+            let mut limits = FilterLimits::default();
+            limits.accounts.max = max;
+            limits
+        };
+        let profiles = FilterLimitsProfiles::new(
+            limits(1),
+            HashMap::from([
+                ("free".to_owned(), limits(1)),
+                ("paid".to_owned(), limits(4)),
+            ]),
+            HashMap::from([
+                ("token-free".to_owned(), "free".to_owned()),
+                ("token-paid".to_owned(), "paid".to_owned()),
+            ]),
+        )
+        .unwrap();
+
+        let subscribe = SubscribeRequest {
+            accounts: ["a", "b"]
+                .into_iter()
+                .map(|name| (name.to_owned(), SubscribeRequestFilterAccounts::default()))
+                .collect(),
+            ..Default::default()
+        };
+        let create_filter = |token: Option<&str>| {
+            let mut request = Request::new(());
+            if let Some(token) = token {
+                request
+                    .metadata_mut()
+                    .insert("x-token", token.parse().unwrap());
+            }
+            let limits = profiles.get(GrpcService::get_client_token(&request));
+            Filter::new(
+                &subscribe,
+                limits,
+                &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+            )
+        };
+        assert!(create_filter(Some("token-paid")).is_ok());
+        assert!(create_filter(Some("token-free")).is_err());
+        // unknown tokens use default limits
+        assert!(create_filter(Some("token-unknown")).is_err());
+        assert!(create_filter(None).is_err());
+
+        // client certificate identity is used before x-token
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("x-token", "token-free".parse().unwrap());
+        request
+            .extensions_mut()
+            .insert(ClientIdentity("token-paid".to_owned()));
+        assert_eq!(GrpcService::get_client_token(&request), Some("token-paid"));
+
+        // profile should exist
+        assert!(FilterLimitsProfiles::new(
+            limits(1),
+            HashMap::new(),
+            HashMap::from([("token".to_owned(), "unknown".to_owned())]),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_filter_predefined_blocks() {
+        let messages = fixtures::load_predefined_messages();
+
+        let mut transactions = HashMap::new();
+        for (name, vote, failed) in [
+            ("all", None, None),
+            ("non_vote", Some(false), None),
+            ("failed", None, Some(true)),
+        ] {
+            transactions.insert(
+                name.to_owned(),
+                SubscribeRequestFilterTransactions {
+                    vote,
+                    failed,
+                    ..Default::default()
+                },
+            );
+        }
+        let request = SubscribeRequest {
+            transactions,
+            blocks: HashMap::from([(
+                "blocks".to_owned(),
+                SubscribeRequestFilterBlocks {
+                    include_transactions: Some(true),
+                    ..Default::default()
+                },
+            )]),
+            blocks_meta: HashMap::from([(
+                "blocks_meta".to_owned(),
+                SubscribeRequestFilterBlocksMeta::default(),
+            )]),
+            ..Default::default()
+        };
+        let mut filter = Filter::new(
+            &request,
+            &FilterLimits::default(),
+            &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+        )
+        .unwrap();
+
+        let mut counts = HashMap::<String, usize>::new();
+        let mut blocks_transactions = 0;
+        for message in messages.iter() {
+            for update in filter.get_updates(message, None) {
+                for name in update.filters.iter() {
+                    *counts.entry(name.as_ref().to_owned()).or_default() += 1;
+                }
+                if let FilteredUpdateOneof::Block(block) = &update.message {
+                    assert_eq!(
+                        block.transactions.len() as u64,
+                        block.meta.executed_transaction_count
+                    );
+                    blocks_transactions += block.transactions.len();
+                }
+            }
+        }
+
+        let txs = messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::Transaction(message) => Some(&message.transaction),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let blocks = messages
+            .iter()
+            .filter(|message| matches!(message, Message::Block(_)))
+            .count();
+        assert_eq!(blocks, 3);
+        assert!(txs.iter().any(|tx| tx.is_vote) && txs.iter().any(|tx| !tx.is_vote));
+
+        let get = |name: &str| counts.get(name).copied().unwrap_or_default();
+        assert_eq!(get("all"), txs.len());
+        assert_eq!(get("non_vote"), txs.iter().filter(|tx| !tx.is_vote).count());
+        assert_eq!(
+            get("failed"),
+            txs.iter().filter(|tx| tx.meta.err.is_some()).count()
+        );
+        assert_eq!(get("blocks"), blocks);
+        assert_eq!(get("blocks_meta"), blocks);
+        assert_eq!(blocks_transactions, txs.len());
+    }
 }
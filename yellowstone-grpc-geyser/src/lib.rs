@@ -2,6 +2,8 @@ pub mod config;
 pub mod grpc;
 pub mod metrics;
 pub mod plugin;
+pub mod scheduler;
+pub mod tls;
 pub mod version;
 
 pub fn get_thread_name() -> String {
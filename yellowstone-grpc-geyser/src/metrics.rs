@@ -12,19 +12,29 @@ use {
         server::conn::auto::Builder as ServerBuilder,
     },
     log::{error, info},
-    prometheus::{IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder},
+    prometheus::{
+        Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+        Opts, Registry, TextEncoder,
+    },
     solana_sdk::clock::Slot,
     std::{
-        collections::{hash_map::Entry as HashMapEntry, HashMap},
+        collections::{hash_map::Entry as HashMapEntry, HashMap, HashSet},
         convert::Infallible,
-        sync::{Arc, Once},
+        sync::{Arc, Mutex, Once},
+        time::{Duration, SystemTime},
     },
     tokio::{
         net::TcpListener,
         sync::{mpsc, oneshot, Notify},
         task::JoinHandle,
     },
-    yellowstone_grpc_proto::plugin::{filter::Filter, message::SlotStatus},
+    yellowstone_grpc_proto::plugin::{
+        filter::{
+            cache::FilterCache, limits::FilterCachesMemory, message::FilteredUpdate,
+            name::FilterNames, Filter, FilterError,
+        },
+        message::SlotStatus,
+    },
 };
 
 lazy_static::lazy_static! {
@@ -54,6 +64,32 @@ lazy_static::lazy_static! {
         "message_queue_size", "Size of geyser message queue"
     ).unwrap();
 
+    static ref GEYSER_MESSAGES_DROPPED: IntCounter = IntCounter::new(
+        "geyser_messages_dropped_total", "Number of geyser messages dropped on full queue"
+    ).unwrap();
+
+    static ref FILTER_NAMES_CACHE_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("filter_names_cache_total", "Filter names cache lookups and evictions"),
+        &["kind"]
+    ).unwrap();
+
+    static ref FILTER_NAMES_SIZE: IntGauge = IntGauge::new(
+        "filter_names_size", "Number of cached filter names"
+    ).unwrap();
+
+    static ref FILTER_CACHE_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("filter_cache_total", "Decoded filters cache lookups"),
+        &["kind"]
+    ).unwrap();
+
+    static ref FILTER_CACHE_SIZE: IntGauge = IntGauge::new(
+        "filter_cache_size", "Number of cached decoded filters"
+    ).unwrap();
+
+    static ref FILTER_CACHES_MEMORY: IntGauge = IntGauge::new(
+        "filter_caches_memory_bytes", "Approximate memory used by per-connection filter caches"
+    ).unwrap();
+
     static ref CONNECTIONS_TOTAL: IntGauge = IntGauge::new(
         "connections_total", "Total number of connections to gRPC service"
     ).unwrap();
@@ -63,10 +99,65 @@ lazy_static::lazy_static! {
         &["endpoint", "subscription"]
     ).unwrap();
 
+    static ref SUBSCRIPTIONS_ACTIVE: IntGauge = IntGauge::new(
+        "subscriptions_active", "Number of active subscribe streams"
+    ).unwrap();
+
+    static ref SUBSCRIPTIONS_ACTIVE_PER_TOKEN: IntGaugeVec = IntGaugeVec::new(
+        Opts::new("subscriptions_active_per_token", "Number of active subscribe streams by hash of client token"),
+        &["token"]
+    ).unwrap();
+
+    static ref SUBSCRIPTION_FILTERS: Histogram = Histogram::with_opts(
+        HistogramOpts::new("subscription_filters", "Number of filters in applied subscribe requests")
+            .buckets(vec![1.0, 5.0, 10.0, 50.0, 100.0, 500.0])
+    ).unwrap();
+
     static ref MISSED_STATUS_MESSAGE: IntCounterVec = IntCounterVec::new(
         Opts::new("missed_status_message_total", "Number of missed messages by commitment"),
         &["status"]
     ).unwrap();
+
+    static ref BLOCK_BUFFERS_EVICTED: IntCounter = IntCounter::new(
+        "block_buffers_evicted_total", "Number of partial blocks dropped due to max_concurrent_block_buffers"
+    ).unwrap();
+
+    static ref SCHEDULING_DELAY: HistogramVec = HistogramVec::new(
+        HistogramOpts::new("scheduling_delay_seconds", "Delay of updates processing by fair scheduler")
+            .buckets(vec![0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0]),
+        &["endpoint"]
+    ).unwrap();
+
+    static ref MESSAGE_SEND_LATENCY: HistogramVec = HistogramVec::new(
+        HistogramOpts::new("message_send_latency_seconds", "Time from message creation to queueing the update to the client stream")
+            .buckets(vec![
+                0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5,
+                1.0, 2.5, 5.0, 10.0,
+            ]),
+        &["kind"]
+    ).unwrap();
+
+    static ref CONNECTION_QUEUE_DEPTH: IntGaugeVec = IntGaugeVec::new(
+        Opts::new("connection_queue_depth", "Number of updates queued to the connection stream"),
+        &["connection"]
+    ).unwrap();
+
+    pub(crate) static ref CONNECTION_DROPPED_MESSAGES: IntCounterVec = IntCounterVec::new(
+        Opts::new("connection_dropped_messages_total", "Number of updates not queued on full connection stream"),
+        &["connection"]
+    ).unwrap();
+
+    static ref CONNECTION_LABELS: Mutex<HashSet<usize>> = Mutex::new(HashSet::new());
+
+    static ref UPDATES_THROTTLED: IntCounterVec = IntCounterVec::new(
+        Opts::new("updates_throttled_total", "Number of updates delayed or dropped by connection rate limit"),
+        &["connection"]
+    ).unwrap();
+
+    static ref SUBSCRIBE_REJECTIONS: IntCounterVec = IntCounterVec::new(
+        Opts::new("subscribe_rejections_total", "Number of subscribe requests rejected by filter creation"),
+        &["reason"]
+    ).unwrap();
 }
 
 #[derive(Debug)]
@@ -196,9 +287,25 @@ impl PrometheusService {
             register!(SLOT_STATUS_PLUGIN);
             register!(INVALID_FULL_BLOCKS);
             register!(MESSAGE_QUEUE_SIZE);
+            register!(GEYSER_MESSAGES_DROPPED);
+            register!(FILTER_NAMES_CACHE_TOTAL);
+            register!(FILTER_NAMES_SIZE);
+            register!(FILTER_CACHE_TOTAL);
+            register!(FILTER_CACHE_SIZE);
+            register!(FILTER_CACHES_MEMORY);
             register!(CONNECTIONS_TOTAL);
             register!(SUBSCRIPTIONS_TOTAL);
+            register!(SUBSCRIPTIONS_ACTIVE);
+            register!(SUBSCRIPTIONS_ACTIVE_PER_TOKEN);
+            register!(SUBSCRIPTION_FILTERS);
             register!(MISSED_STATUS_MESSAGE);
+            register!(BLOCK_BUFFERS_EVICTED);
+            register!(SCHEDULING_DELAY);
+            register!(MESSAGE_SEND_LATENCY);
+            register!(CONNECTION_QUEUE_DEPTH);
+            register!(CONNECTION_DROPPED_MESSAGES);
+            register!(UPDATES_THROTTLED);
+            register!(SUBSCRIBE_REJECTIONS);
 
             VERSION
                 .with_label_values(&[
@@ -341,6 +448,36 @@ pub fn message_queue_size_dec() {
     MESSAGE_QUEUE_SIZE.dec()
 }
 
+pub fn geyser_messages_dropped_inc() {
+    GEYSER_MESSAGES_DROPPED.inc()
+}
+
+pub fn update_filter_names(filter_names: &mut FilterNames) {
+    let stats = filter_names.take_stats();
+    for (kind, value) in [
+        ("hit", stats.hits),
+        ("miss", stats.misses),
+        ("eviction", stats.evictions),
+    ] {
+        FILTER_NAMES_CACHE_TOTAL
+            .with_label_values(&[kind])
+            .inc_by(value);
+    }
+    FILTER_NAMES_SIZE.set(filter_names.len() as i64);
+}
+
+pub fn update_filter_cache(filter_cache: &mut FilterCache) {
+    let stats = filter_cache.take_stats();
+    for (kind, value) in [("hit", stats.hits), ("miss", stats.misses)] {
+        FILTER_CACHE_TOTAL.with_label_values(&[kind]).inc_by(value);
+    }
+    FILTER_CACHE_SIZE.set(filter_cache.len() as i64);
+}
+
+pub fn update_filter_caches_memory(memory: &FilterCachesMemory) {
+    FILTER_CACHES_MEMORY.set(memory.used() as i64);
+}
+
 pub fn connections_total_inc() {
     CONNECTIONS_TOTAL.inc()
 }
@@ -349,6 +486,23 @@ pub fn connections_total_dec() {
     CONNECTIONS_TOTAL.dec()
 }
 
+pub fn set_subscriptions_active(count: usize) {
+    SUBSCRIPTIONS_ACTIVE.set(count as i64)
+}
+
+/// Tokens are labeled by hash to keep secrets out of `/metrics`, label is removed once
+/// the token has no active subscriptions
+pub fn set_subscriptions_active_per_token(token_hash: u64, count: usize) {
+    let label = format!("{token_hash:016x}");
+    if count == 0 {
+        let _ = SUBSCRIPTIONS_ACTIVE_PER_TOKEN.remove_label_values(&[&label]);
+    } else {
+        SUBSCRIPTIONS_ACTIVE_PER_TOKEN
+            .with_label_values(&[&label])
+            .set(count as i64);
+    }
+}
+
 pub fn update_subscriptions(endpoint: &str, old: Option<&Filter>, new: Option<&Filter>) {
     for (multiplier, filter) in [(-1, old), (1, new)] {
         if let Some(filter) = filter {
@@ -365,8 +519,363 @@ pub fn update_subscriptions(endpoint: &str, old: Option<&Filter>, new: Option<&F
     }
 }
 
+pub fn observe_subscription_filters(filter: &Filter) {
+    let all = filter
+        .get_metrics()
+        .into_iter()
+        .find_map(|(name, value)| (name == "all").then_some(value))
+        .unwrap_or_default();
+    SUBSCRIPTION_FILTERS.observe(all as f64)
+}
+
 pub fn missed_status_message_inc(status: SlotStatus) {
     MISSED_STATUS_MESSAGE
         .with_label_values(&[status.as_str()])
         .inc()
 }
+
+pub fn block_buffers_evicted_inc(count: usize) {
+    BLOCK_BUFFERS_EVICTED.inc_by(count as u64)
+}
+
+pub fn observe_scheduling_delay(endpoint: &str, delay: Duration) {
+    SCHEDULING_DELAY
+        .with_label_values(&[endpoint])
+        .observe(delay.as_secs_f64())
+}
+
+pub fn subscribe_rejections_inc(error: &FilterError) {
+    SUBSCRIBE_REJECTIONS
+        .with_label_values(&[error.reason()])
+        .inc()
+}
+
+/// Queue and rate limit metrics of the connection, only the first connections up to the limit
+/// get own label, the rest are reported under `other`
+#[derive(Debug)]
+pub struct ConnectionQueueMetrics {
+    id: Option<usize>,
+    label: String,
+    depth: i64,
+}
+
+impl Drop for ConnectionQueueMetrics {
+    fn drop(&mut self) {
+        self.set_depth(0);
+        if let Some(id) = self.id {
+            let _ = CONNECTION_QUEUE_DEPTH.remove_label_values(&[&self.label]);
+            let _ = CONNECTION_DROPPED_MESSAGES.remove_label_values(&[&self.label]);
+            let _ = UPDATES_THROTTLED.remove_label_values(&[&self.label]);
+            CONNECTION_LABELS
+                .lock()
+                .expect("unpoisoned mutex")
+                .remove(&id);
+        }
+    }
+}
+
+impl ConnectionQueueMetrics {
+    const LABELS_MAX: usize = 100;
+
+    pub fn new(id: usize) -> Self {
+        let mut labels = CONNECTION_LABELS.lock().expect("unpoisoned mutex");
+        let id = (labels.len() < Self::LABELS_MAX && labels.insert(id)).then_some(id);
+        Self {
+            id,
+            label: id.map_or_else(|| "other".to_owned(), |id| id.to_string()),
+            depth: 0,
+        }
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        let depth = depth as i64;
+        CONNECTION_QUEUE_DEPTH
+            .with_label_values(&[&self.label])
+            .add(depth - self.depth);
+        self.depth = depth;
+    }
+
+    pub fn dropped_inc(&self) {
+        CONNECTION_DROPPED_MESSAGES
+            .with_label_values(&[&self.label])
+            .inc();
+        CONNECTION_DROPPED_MESSAGES
+            .with_label_values(&["all"])
+            .inc();
+    }
+
+    pub fn throttled_inc(&self) {
+        UPDATES_THROTTLED.with_label_values(&[&self.label]).inc();
+        UPDATES_THROTTLED.with_label_values(&["all"]).inc();
+    }
+}
+
+pub fn observe_message_send_latency(update: &FilteredUpdate) {
+    // clock of the node can go backwards, such updates are counted with zero latency
+    let latency = SystemTime::try_from(update.created_at)
+        .ok()
+        .and_then(|created_at| SystemTime::now().duration_since(created_at).ok())
+        .unwrap_or_default();
+    MESSAGE_SEND_LATENCY
+        .with_label_values(&[update.message.kind()])
+        .observe(latency.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            observe_message_send_latency, observe_subscription_filters,
+            set_subscriptions_active_per_token, subscribe_rejections_inc, ConnectionQueueMetrics,
+            MESSAGE_SEND_LATENCY, SUBSCRIBE_REJECTIONS, SUBSCRIPTIONS_ACTIVE_PER_TOKEN,
+            SUBSCRIPTION_FILTERS, UPDATES_THROTTLED,
+        },
+        prometheus::core::Metric,
+        prost_types::Timestamp,
+        std::{
+            collections::HashMap,
+            time::{Duration, SystemTime},
+        },
+        yellowstone_grpc_proto::{
+            plugin::filter::{
+                limits::FilterLimits,
+                message::{FilteredUpdate, FilteredUpdateOneof},
+                name::FilterNames,
+                Filter,
+            },
+            prelude::{
+                SubscribeRequest, SubscribeRequestAccountsDataSlice,
+                SubscribeRequestFilterAccounts, SubscribeRequestFilterEntry,
+                SubscribeRequestFilterSlots, SubscribeRequestFilterTransactions,
+            },
+        },
+    };
+
+    #[test]
+    fn test_message_send_latency() {
+        let created_at = SystemTime::now() - Duration::from_millis(3_000);
+        let update = FilteredUpdate::new(
+            Default::default(),
+            FilteredUpdateOneof::snapshot_complete(0, 0),
+            Timestamp::from(created_at),
+        );
+        observe_message_send_latency(&update);
+
+        let histogram = MESSAGE_SEND_LATENCY.with_label_values(&["snapshot_complete"]);
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert!(histogram.get_sample_sum() >= 3.0);
+        let buckets = histogram
+            .metric()
+            .get_histogram()
+            .get_bucket()
+            .iter()
+            .map(|bucket| (bucket.get_upper_bound(), bucket.get_cumulative_count()))
+            .collect::<Vec<_>>();
+        for (upper_bound, count) in buckets {
+            assert_eq!(count, u64::from(upper_bound >= 5.0), "bucket {upper_bound}");
+        }
+    }
+
+    #[test]
+    fn test_subscriptions_active_per_token() {
+        set_subscriptions_active_per_token(0x2a, 2);
+        let gauge = SUBSCRIPTIONS_ACTIVE_PER_TOKEN.with_label_values(&["000000000000002a"]);
+        assert_eq!(gauge.get(), 2);
+
+        // label is removed with the last subscription
+        set_subscriptions_active_per_token(0x2a, 0);
+        assert!(SUBSCRIPTIONS_ACTIVE_PER_TOKEN
+            .remove_label_values(&["000000000000002a"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_updates_throttled() {
+        let id = usize::MAX;
+        let label = id.to_string();
+        let metrics = ConnectionQueueMetrics::new(id);
+        metrics.throttled_inc();
+        metrics.throttled_inc();
+        assert_eq!(UPDATES_THROTTLED.with_label_values(&[&label]).get(), 2);
+        assert!(UPDATES_THROTTLED.with_label_values(&["all"]).get() >= 2);
+
+        // label is removed on disconnect
+        drop(metrics);
+        assert!(UPDATES_THROTTLED.remove_label_values(&[&label]).is_err());
+    }
+
+    #[test]
+    fn test_subscription_filters() {
+        let mut request = SubscribeRequest::default();
+        for name in ["a", "b", "c"] {
+            request.accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    account: vec!["11111111111111111111111111111111".to_owned()],
+                    ..Default::default()
+                },
+            );
+        }
+        request
+            .slots
+            .insert("slots".to_owned(), SubscribeRequestFilterSlots::default());
+        request.transactions.insert(
+            "transactions".to_owned(),
+            SubscribeRequestFilterTransactions {
+                vote: Some(false),
+                ..Default::default()
+            },
+        );
+        let filter = Filter::new(
+            &request,
+            &FilterLimits::default(),
+            &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+        )
+        .unwrap();
+
+        let count = SUBSCRIPTION_FILTERS.get_sample_count();
+        let sum = SUBSCRIPTION_FILTERS.get_sample_sum();
+        observe_subscription_filters(&filter);
+        assert_eq!(SUBSCRIPTION_FILTERS.get_sample_count() - count, 1);
+        assert_eq!(SUBSCRIPTION_FILTERS.get_sample_sum() - sum, 5.0);
+
+        let buckets = SUBSCRIPTION_FILTERS
+            .metric()
+            .get_histogram()
+            .get_bucket()
+            .iter()
+            .map(|bucket| (bucket.get_upper_bound(), bucket.get_cumulative_count()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            buckets,
+            [
+                (1.0, 0),
+                (5.0, 1),
+                (10.0, 1),
+                (50.0, 1),
+                (100.0, 1),
+                (500.0, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subscribe_rejections() {
+        let mut limits = FilterLimits::default();
+        limits.slots.max = 1;
+
+        let cases = [
+            (
+                "invalid_commitment",
+                SubscribeRequest {
+                    commitment: Some(100),
+                    ..Default::default()
+                },
+            ),
+            (
+                "invalid_pubkey",
+                SubscribeRequest {
+                    accounts: [(
+                        "accounts".to_owned(),
+                        SubscribeRequestFilterAccounts {
+                            account: vec!["invalid".to_owned()],
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "invalid_signature",
+                SubscribeRequest {
+                    transactions: [(
+                        "transactions".to_owned(),
+                        SubscribeRequestFilterTransactions {
+                            signature: Some("invalid".to_owned()),
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "invalid_slot_status",
+                SubscribeRequest {
+                    slots: [(
+                        "slots".to_owned(),
+                        SubscribeRequestFilterSlots {
+                            statuses: vec![100],
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "limits_max",
+                SubscribeRequest {
+                    slots: [
+                        ("a".to_owned(), SubscribeRequestFilterSlots::default()),
+                        ("b".to_owned(), SubscribeRequestFilterSlots::default()),
+                    ]
+                    .into(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "name_oversized",
+                SubscribeRequest {
+                    slots: [("a".repeat(65), SubscribeRequestFilterSlots::default())].into(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "data_slice_overlap",
+                SubscribeRequest {
+                    accounts_data_slice: vec![
+                        SubscribeRequestAccountsDataSlice {
+                            offset: 0,
+                            length: 10,
+                        },
+                        SubscribeRequestAccountsDataSlice {
+                            offset: 5,
+                            length: 10,
+                        },
+                    ],
+                    ..Default::default()
+                },
+            ),
+            (
+                "entries_slot_range",
+                SubscribeRequest {
+                    entry: [(
+                        "entries".to_owned(),
+                        SubscribeRequestFilterEntry {
+                            slot_from: Some(2),
+                            slot_to: Some(1),
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                },
+            ),
+        ];
+        for (reason, request) in cases {
+            let error = Filter::new(
+                &request,
+                &limits,
+                &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+            )
+            .expect_err(reason);
+
+            let counter = SUBSCRIBE_REJECTIONS.with_label_values(&[reason]);
+            let value = counter.get();
+            subscribe_rejections_inc(&error);
+            assert_eq!(counter.get() - value, 1, "reason {reason}");
+        }
+    }
+}
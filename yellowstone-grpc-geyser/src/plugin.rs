@@ -1,7 +1,7 @@
 use {
     crate::{
         config::Config,
-        grpc::GrpcService,
+        grpc::{GeyserMessageSender, GrpcService, GrpcShutdown},
         metrics::{self, PrometheusService},
     },
     agave_geyser_plugin_interface::geyser_plugin_interface::{
@@ -19,7 +19,7 @@ use {
     },
     tokio::{
         runtime::{Builder, Runtime},
-        sync::{mpsc, Notify},
+        sync::mpsc,
     },
     yellowstone_grpc_proto::plugin::message::{
         Message, MessageAccount, MessageBlockMeta, MessageEntry, MessageSlot, MessageTransaction,
@@ -31,14 +31,15 @@ pub struct PluginInner {
     runtime: Runtime,
     snapshot_channel: Mutex<Option<crossbeam_channel::Sender<Box<Message>>>>,
     snapshot_channel_closed: AtomicBool,
-    grpc_channel: mpsc::UnboundedSender<Message>,
-    grpc_shutdown: Arc<Notify>,
+    grpc_channel: GeyserMessageSender,
+    grpc_shutdown: GrpcShutdown,
     prometheus: PrometheusService,
+    accounts_data_checksum: bool,
 }
 
 impl PluginInner {
     fn send_message(&self, message: Message) {
-        if self.grpc_channel.send(message).is_ok() {
+        if self.grpc_channel.send(message) {
             metrics::message_queue_size_inc();
         }
     }
@@ -80,6 +81,7 @@ impl GeyserPlugin for Plugin {
                 affinity::set_thread_affinity(&tokio_cpus).expect("failed to set affinity")
             });
         }
+        let accounts_data_checksum = config.grpc.accounts_data_checksum;
         let runtime = builder
             .thread_name_fn(crate::get_thread_name)
             .enable_all()
@@ -118,6 +120,7 @@ impl GeyserPlugin for Plugin {
             grpc_channel,
             grpc_shutdown,
             prometheus,
+            accounts_data_checksum,
         });
 
         Ok(())
@@ -125,8 +128,8 @@ impl GeyserPlugin for Plugin {
 
     fn on_unload(&mut self) {
         if let Some(inner) = self.inner.take() {
-            inner.grpc_shutdown.notify_one();
             drop(inner.grpc_channel);
+            inner.runtime.block_on(inner.grpc_shutdown.drain());
             inner.prometheus.shutdown();
             inner.runtime.shutdown_timeout(Duration::from_secs(30));
         }
@@ -150,10 +153,20 @@ impl GeyserPlugin for Plugin {
             };
 
             if is_startup {
-                if let Some(channel) = inner.snapshot_channel.lock().unwrap().as_ref() {
-                    let message =
-                        Message::Account(MessageAccount::from_geyser(account, slot, is_startup));
-                    match channel.send(Box::new(message)) {
+                let snapshot_channel = inner.snapshot_channel.lock().unwrap();
+                if snapshot_channel.is_none() && !inner.grpc_channel.is_startup_accounts_used() {
+                    return Ok(());
+                }
+
+                let message = MessageAccount::from_geyser(
+                    account,
+                    slot,
+                    is_startup,
+                    inner.accounts_data_checksum,
+                );
+                inner.grpc_channel.send_startup_account(&message);
+                if let Some(channel) = snapshot_channel.as_ref() {
+                    match channel.send(Box::new(Message::Account(message))) {
                         Ok(()) => metrics::message_queue_size_inc(),
                         Err(_) => {
                             if !inner.snapshot_channel_closed.swap(true, Ordering::Relaxed) {
@@ -165,8 +178,12 @@ impl GeyserPlugin for Plugin {
                     }
                 }
             } else {
-                let message =
-                    Message::Account(MessageAccount::from_geyser(account, slot, is_startup));
+                let message = Message::Account(MessageAccount::from_geyser(
+                    account,
+                    slot,
+                    is_startup,
+                    inner.accounts_data_checksum,
+                ));
                 inner.send_message(message);
             }
 
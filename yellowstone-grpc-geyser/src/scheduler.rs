@@ -0,0 +1,274 @@
+use {
+    std::{
+        cmp::{Ordering, Reverse},
+        collections::{BinaryHeap, HashMap},
+        future::Future,
+        sync::{Arc, Mutex},
+    },
+    tokio::sync::oneshot,
+};
+
+// virtual time is integer, scale cost to keep precision for big weights
+const COST_SCALE: u64 = 1_000;
+
+/// Start-time fair queuing of updates processing across subscribers.
+///
+/// Every subscriber requests a permit with the cost of the work, permits are limited by
+/// `concurrency` and waiting subscribers are served in the order of virtual finish time
+/// (`start + cost / weight`), so a subscriber with big batches can't starve others.
+#[derive(Debug)]
+pub struct FairScheduler {
+    inner: Mutex<FairSchedulerInner>,
+}
+
+#[derive(Debug)]
+struct FairSchedulerInner {
+    concurrency: usize,
+    in_flight: usize,
+    virtual_time: u64,
+    finish_tags: HashMap<usize, u64>,
+    waiters: BinaryHeap<Reverse<FairSchedulerWaiter>>,
+    seq: u64,
+}
+
+#[derive(Debug)]
+struct FairSchedulerWaiter {
+    start: u64,
+    finish: u64,
+    seq: u64,
+    tx: oneshot::Sender<FairSchedulerPermit>,
+}
+
+impl PartialEq for FairSchedulerWaiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FairSchedulerWaiter {}
+
+impl PartialOrd for FairSchedulerWaiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FairSchedulerWaiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.finish, self.seq).cmp(&(other.finish, other.seq))
+    }
+}
+
+impl FairScheduler {
+    pub fn new(concurrency: usize) -> Arc<Self> {
+        Arc::new(Self {
+            inner: Mutex::new(FairSchedulerInner {
+                concurrency: concurrency.max(1),
+                in_flight: 0,
+                virtual_time: 0,
+                finish_tags: HashMap::new(),
+                waiters: BinaryHeap::new(),
+                seq: 0,
+            }),
+        })
+    }
+
+    pub fn client(self: &Arc<Self>, id: usize, weight: u32) -> FairSchedulerClient {
+        FairSchedulerClient {
+            scheduler: Arc::clone(self),
+            id,
+            weight: weight.max(1),
+        }
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut inner = self.inner.lock().unwrap();
+        while let Some(Reverse(waiter)) = inner.waiters.pop() {
+            inner.virtual_time = inner.virtual_time.max(waiter.start);
+            // permit is passed to the waiter, if waiter is dropped after send
+            // permit would be released on channel drop
+            let permit = FairSchedulerPermit {
+                scheduler: Some(Arc::clone(self)),
+            };
+            match waiter.tx.send(permit) {
+                Ok(()) => return,
+                // skip dropped waiters, permit should not be released under the lock
+                Err(mut permit) => {
+                    permit.scheduler = None;
+                }
+            }
+        }
+        inner.in_flight -= 1;
+    }
+}
+
+#[derive(Debug)]
+pub struct FairSchedulerClient {
+    scheduler: Arc<FairScheduler>,
+    id: usize,
+    weight: u32,
+}
+
+impl Drop for FairSchedulerClient {
+    fn drop(&mut self) {
+        let mut inner = self.scheduler.inner.lock().unwrap();
+        inner.finish_tags.remove(&self.id);
+    }
+}
+
+impl FairSchedulerClient {
+    /// Request is queued on call, returned future resolves once the permit is granted
+    pub fn acquire(&self, cost: usize) -> impl Future<Output = FairSchedulerPermit> {
+        let rx = {
+            let mut inner = self.scheduler.inner.lock().unwrap();
+            let start = inner
+                .finish_tags
+                .get(&self.id)
+                .copied()
+                .unwrap_or_default()
+                .max(inner.virtual_time);
+            let finish = start + (cost.max(1) as u64) * COST_SCALE / self.weight as u64;
+            inner.finish_tags.insert(self.id, finish);
+
+            if inner.in_flight < inner.concurrency && inner.waiters.is_empty() {
+                inner.in_flight += 1;
+                inner.virtual_time = inner.virtual_time.max(start);
+                None
+            } else {
+                let (tx, rx) = oneshot::channel();
+                inner.seq += 1;
+                let seq = inner.seq;
+                inner.waiters.push(Reverse(FairSchedulerWaiter {
+                    start,
+                    finish,
+                    seq,
+                    tx,
+                }));
+                Some(rx)
+            }
+        };
+
+        let scheduler = Arc::clone(&self.scheduler);
+        async move {
+            match rx {
+                Some(rx) => rx.await.expect("scheduler alive while permit is waited"),
+                None => FairSchedulerPermit {
+                    scheduler: Some(scheduler),
+                },
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FairSchedulerPermit {
+    scheduler: Option<Arc<FairScheduler>>,
+}
+
+impl Drop for FairSchedulerPermit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::FairScheduler,
+        futures::FutureExt,
+        std::{sync::Arc, time::Duration},
+        tokio::{
+            sync::{mpsc, Barrier},
+            time::{sleep, timeout},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_slow_subscriber_not_starve_fast() {
+        let scheduler = FairScheduler::new(1);
+        let slow = scheduler.client(0, 1);
+        let fast = scheduler.client(1, 1);
+
+        // slow subscriber holds the permit and has big batches in the queue
+        let permit = slow.acquire(1_000).await;
+        let mut slow_next = Box::pin(slow.acquire(1_000));
+        let mut fast_next = Box::pin(fast.acquire(1));
+        assert!((&mut fast_next).now_or_never().is_none());
+
+        drop(permit);
+        let permit = timeout(Duration::from_secs(1), &mut fast_next)
+            .await
+            .expect("fast subscriber starved");
+        assert!((&mut slow_next).now_or_never().is_none());
+
+        drop(permit);
+        timeout(Duration::from_secs(1), slow_next)
+            .await
+            .expect("slow subscriber should get permit");
+    }
+
+    #[tokio::test]
+    async fn test_mock_slow_subscriber() {
+        let scheduler = FairScheduler::new(1);
+        let barrier = Arc::new(Barrier::new(2));
+
+        // slow subscriber always has work and holds permit for a long time
+        let slow = scheduler.client(0, 1);
+        let slow_barrier = Arc::clone(&barrier);
+        let slow_task = tokio::spawn(async move {
+            slow_barrier.wait().await;
+            for _ in 0..50 {
+                let _permit = slow.acquire(100).await;
+                sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        let fast = scheduler.client(1, 1);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let fast_task = tokio::spawn(async move {
+            barrier.wait().await;
+            for _ in 0..10 {
+                let _permit = fast.acquire(1).await;
+                let _ = tx.send(());
+            }
+        });
+
+        // 10 fast batches should be done before 50 slow batches (500ms)
+        for _ in 0..10 {
+            timeout(Duration::from_secs(1), rx.recv())
+                .await
+                .expect("fast subscriber starved");
+        }
+        fast_task.await.unwrap();
+        assert!(!slow_task.is_finished());
+        slow_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_weights() {
+        let scheduler = FairScheduler::new(1);
+        let permit = scheduler.client(100, 1).acquire(1).await;
+
+        let light = scheduler.client(0, 1);
+        let heavy = scheduler.client(1, 3);
+        let mut queue = vec![];
+        for _ in 0..8 {
+            queue.push((0, Box::pin(light.acquire(1))));
+            queue.push((1, Box::pin(heavy.acquire(1))));
+        }
+        drop(permit);
+
+        // with weights 1:3 first 8 permits should be granted as 2:6
+        let mut granted = [0; 2];
+        for _ in 0..8 {
+            let index = queue
+                .iter_mut()
+                .position(|(_client, fut)| fut.now_or_never().is_some())
+                .expect("permit should be granted");
+            granted[queue.remove(index).0] += 1;
+        }
+        assert_eq!(granted, [2, 6]);
+    }
+}
@@ -0,0 +1,419 @@
+use {
+    crate::config::{ConfigGrpcServerTls, ConfigGrpcServerTlsClientIdentity},
+    anyhow::Context,
+    log::{error, info},
+    std::{
+        io,
+        sync::{Arc, RwLock},
+        time::SystemTime,
+    },
+    tokio::{
+        fs,
+        net::TcpStream,
+        sync::mpsc,
+        time::{interval, timeout, Duration, MissedTickBehavior},
+    },
+    tokio_rustls::{
+        rustls::{
+            crypto::ring,
+            pki_types::CertificateDer,
+            server::{
+                danger::ClientCertVerifier, ClientHello, ResolvesServerCert, WebPkiClientVerifier,
+            },
+            sign::CertifiedKey,
+            RootCertStore, ServerConfig,
+        },
+        server::TlsStream,
+        TlsAcceptor,
+    },
+    tokio_stream::{wrappers::ReceiverStream, StreamExt},
+    tonic::transport::server::TcpIncoming,
+    x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer},
+};
+
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Server certificate which can be replaced without restart, established connections keep
+/// the certificate used on handshake
+#[derive(Debug)]
+pub struct CertResolver {
+    certified_key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.get())
+    }
+}
+
+impl CertResolver {
+    pub fn new(certified_key: CertifiedKey) -> Self {
+        Self {
+            certified_key: RwLock::new(Arc::new(certified_key)),
+        }
+    }
+
+    pub fn get(&self) -> Arc<CertifiedKey> {
+        Arc::clone(&self.certified_key.read().expect("unpoisoned rwlock"))
+    }
+
+    /// Loads cert and key from disk, the previous certificate is kept on failure
+    pub async fn reload(&self, config: &ConfigGrpcServerTls) -> anyhow::Result<()> {
+        match load_certified_key(config).await {
+            Ok(certified_key) => {
+                *self.certified_key.write().expect("unpoisoned rwlock") = Arc::new(certified_key);
+                info!("tls certificate reloaded from {}", config.cert_path);
+                Ok(())
+            }
+            Err(error) => {
+                error!("failed to reload tls certificate, previous one is used: {error:?}");
+                Err(error)
+            }
+        }
+    }
+}
+
+pub async fn load_certified_key(config: &ConfigGrpcServerTls) -> anyhow::Result<CertifiedKey> {
+    let (cert, key) = tokio::try_join!(fs::read(&config.cert_path), fs::read(&config.key_path))
+        .context("failed to load tls_config files")?;
+
+    let certs = rustls_pemfile::certs(&mut cert.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to parse certificate")?;
+    anyhow::ensure!(!certs.is_empty(), "no certificate found");
+
+    let key = rustls_pemfile::private_key(&mut key.as_slice())
+        .context("failed to parse private key")?
+        .ok_or_else(|| anyhow::anyhow!("no private key found"))?;
+    let key = ring::sign::any_supported_type(&key).context("unsupported private key")?;
+
+    // cert and key files are not replaced at once on reload
+    let certified_key = CertifiedKey::new(certs, key);
+    certified_key
+        .keys_match()
+        .context("private key does not match certificate")?;
+    Ok(certified_key)
+}
+
+/// Loads CA certificates from `client_ca_path`, client certificate is not required without it
+pub async fn load_client_verifier(
+    config: &ConfigGrpcServerTls,
+) -> anyhow::Result<Option<Arc<dyn ClientCertVerifier>>> {
+    let Some(path) = &config.client_ca_path else {
+        return Ok(None);
+    };
+    let ca = fs::read(path)
+        .await
+        .context("failed to load client_ca_path")?;
+
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut ca.as_slice()) {
+        let cert = cert.context("failed to parse client CA certificate")?;
+        roots.add(cert).context("invalid client CA certificate")?;
+    }
+    anyhow::ensure!(!roots.is_empty(), "no client CA certificate found");
+
+    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), Arc::new(ring::default_provider()))
+        .build()
+        .map(Some)
+        .context("failed to create client certificate verifier")
+}
+
+pub fn create_acceptor(
+    resolver: Arc<CertResolver>,
+    client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+) -> anyhow::Result<TlsAcceptor> {
+    let builder = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .context("failed to set tls protocol versions")?;
+    let builder = match client_verifier {
+        Some(verifier) => builder.with_client_cert_verifier(verifier),
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder.with_cert_resolver(resolver);
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Identity of the client from the verified certificate, inserted into request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity(pub String);
+
+impl ClientIdentity {
+    pub fn from_cert(
+        cert: &CertificateDer<'_>,
+        field: ConfigGrpcServerTlsClientIdentity,
+    ) -> Option<Self> {
+        let (_rem, cert) = X509Certificate::from_der(cert.as_ref()).ok()?;
+        let identity = match field {
+            ConfigGrpcServerTlsClientIdentity::CommonName => cert
+                .subject()
+                .iter_common_name()
+                .next()
+                .and_then(|name| name.as_str().ok())?,
+            ConfigGrpcServerTlsClientIdentity::SubjectAltName => cert
+                .subject_alternative_name()
+                .ok()
+                .flatten()?
+                .value
+                .general_names
+                .iter()
+                .find_map(|name| match name {
+                    GeneralName::DNSName(name)
+                    | GeneralName::RFC822Name(name)
+                    | GeneralName::URI(name) => Some(*name),
+                    _ => None,
+                })?,
+        };
+        Some(Self(identity.to_owned()))
+    }
+}
+
+/// Accepts TLS connections, handshakes are made concurrently. With `reload_interval` cert and
+/// key files are checked for changes and reloaded, new connections use the new certificate
+pub fn incoming(
+    incoming: TcpIncoming,
+    acceptor: TlsAcceptor,
+    resolver: Arc<CertResolver>,
+    config: ConfigGrpcServerTls,
+) -> ReceiverStream<io::Result<TlsStream<TcpStream>>> {
+    let (tx, rx) = mpsc::channel(128);
+    if let Some(period) = config.reload_interval {
+        tokio::spawn(reload_loop(tx.clone(), resolver, config, period));
+    }
+    tokio::spawn(async move {
+        tokio::pin!(incoming);
+        loop {
+            let stream = tokio::select! {
+                () = tx.closed() => break,
+                stream = incoming.next() => match stream {
+                    Some(Ok(stream)) => stream,
+                    Some(Err(error)) => {
+                        error!("failed to accept connection: {error}");
+                        continue;
+                    }
+                    None => break,
+                },
+            };
+
+            let acceptor = acceptor.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                match timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let _ = tx.send(Ok(stream)).await;
+                    }
+                    Ok(Err(error)) => info!("tls handshake failed: {error}"),
+                    Err(_elapsed) => info!("tls handshake timed out"),
+                }
+            });
+        }
+    });
+    ReceiverStream::new(rx)
+}
+
+/// Reloads certificate on modification of cert or key file until the server is stopped
+async fn reload_loop(
+    tx: mpsc::Sender<io::Result<TlsStream<TcpStream>>>,
+    resolver: Arc<CertResolver>,
+    config: ConfigGrpcServerTls,
+    period: Duration,
+) {
+    let mut reload_interval = interval(period);
+    reload_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut modified = get_modified(&config).await.ok();
+    loop {
+        tokio::select! {
+            () = tx.closed() => break,
+            _ = reload_interval.tick() => {
+                let current = get_modified(&config).await.ok();
+                if current.is_some() && current != modified {
+                    modified = current;
+                    let _ = resolver.reload(&config).await;
+                }
+            }
+        }
+    }
+}
+
+async fn get_modified(config: &ConfigGrpcServerTls) -> io::Result<(SystemTime, SystemTime)> {
+    let (cert, key) = tokio::try_join!(
+        fs::metadata(&config.cert_path),
+        fs::metadata(&config.key_path)
+    )?;
+    Ok((cert.modified()?, key.modified()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            create_acceptor, incoming, load_certified_key, load_client_verifier, CertResolver,
+            ClientIdentity,
+        },
+        crate::config::{ConfigGrpcServerTls, ConfigGrpcServerTlsClientIdentity},
+        anyhow::Context,
+        std::{
+            net::SocketAddr,
+            path::{Path, PathBuf},
+            sync::Arc,
+        },
+        tokio::{
+            fs,
+            io::AsyncReadExt,
+            net::{TcpListener, TcpStream},
+            time::{timeout, Duration},
+        },
+        tokio_rustls::{
+            client::TlsStream,
+            rustls::{crypto::ring, pki_types::ServerName, ClientConfig, RootCertStore},
+            TlsConnector,
+        },
+        tokio_stream::StreamExt,
+        tonic::transport::server::TcpIncoming,
+    };
+
+    fn get_fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/tls")
+    }
+
+    fn get_fixture(name: &str) -> String {
+        get_fixtures().join(name).to_string_lossy().into_owned()
+    }
+
+    async fn connect(
+        addr: SocketAddr,
+        client_cert: Option<(&str, &str)>,
+    ) -> anyhow::Result<TlsStream<TcpStream>> {
+        let mut roots = RootCertStore::empty();
+        let server_cert = fs::read(get_fixture("cert1.pem")).await?;
+        for cert in rustls_pemfile::certs(&mut server_cert.as_slice()) {
+            roots.add(cert?)?;
+        }
+
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots);
+        let config = match client_cert {
+            Some((cert, key)) => {
+                let cert = fs::read(get_fixture(cert)).await?;
+                let key = fs::read(get_fixture(key)).await?;
+                builder.with_client_auth_cert(
+                    rustls_pemfile::certs(&mut cert.as_slice()).collect::<Result<_, _>>()?,
+                    rustls_pemfile::private_key(&mut key.as_slice())?.context("no key")?,
+                )?
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let stream = TcpStream::connect(addr).await?;
+        let server_name = ServerName::try_from("localhost")?;
+        Ok(TlsConnector::from(Arc::new(config))
+            .connect(server_name, stream)
+            .await?)
+    }
+
+    #[tokio::test]
+    async fn test_reload() -> anyhow::Result<()> {
+        let fixtures = get_fixtures();
+        let dir = std::env::temp_dir().join(format!("grpc-geyser-tls-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir).await;
+        fs::create_dir_all(&dir).await?;
+        let config = ConfigGrpcServerTls {
+            cert_path: dir.join("cert.pem").to_string_lossy().into_owned(),
+            key_path: dir.join("key.pem").to_string_lossy().into_owned(),
+            reload_interval: None,
+            client_ca_path: None,
+            client_identity: None,
+        };
+        let install = |id: usize| {
+            let config = config.clone();
+            let fixtures = fixtures.clone();
+            async move {
+                fs::copy(fixtures.join(format!("cert{id}.pem")), &config.cert_path).await?;
+                fs::copy(fixtures.join(format!("key{id}.pem")), &config.key_path).await?;
+                anyhow::Ok(())
+            }
+        };
+
+        install(1).await?;
+        let resolver = Arc::new(CertResolver::new(load_certified_key(&config).await?));
+        create_acceptor(Arc::clone(&resolver), None)?;
+        let cert1 = resolver.get().cert.clone();
+
+        // valid certificate is picked up
+        install(2).await?;
+        resolver.reload(&config).await?;
+        let cert2 = resolver.get().cert.clone();
+        assert_ne!(cert1, cert2);
+        assert_eq!(cert2, load_certified_key(&config).await?.cert);
+
+        // key of other certificate is rejected, previous one is kept
+        fs::copy(fixtures.join("key1.pem"), &config.key_path).await?;
+        assert!(load_certified_key(&config).await.is_err());
+        assert!(resolver.reload(&config).await.is_err());
+        assert_eq!(resolver.get().cert, cert2);
+
+        // invalid certificate is rejected, previous one is kept
+        fs::write(&config.cert_path, "invalid").await?;
+        assert!(resolver.reload(&config).await.is_err());
+        assert_eq!(resolver.get().cert, cert2);
+
+        fs::remove_dir_all(&dir).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_auth() -> anyhow::Result<()> {
+        let config = ConfigGrpcServerTls {
+            cert_path: get_fixture("cert1.pem"),
+            key_path: get_fixture("key1.pem"),
+            reload_interval: None,
+            client_ca_path: Some(get_fixture("client-ca.pem")),
+            client_identity: Some(ConfigGrpcServerTlsClientIdentity::CommonName),
+        };
+        let resolver = Arc::new(CertResolver::new(load_certified_key(&config).await?));
+        let acceptor =
+            create_acceptor(Arc::clone(&resolver), load_client_verifier(&config).await?)?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let listener = TcpIncoming::from_listener(listener, true, None)
+            .map_err(|error| anyhow::anyhow!(error))?;
+        let mut incoming = incoming(listener, acceptor, resolver, config);
+
+        // certificate signed by the client CA
+        let _client = connect(addr, Some(("client.pem", "client-key.pem"))).await?;
+        let stream = timeout(Duration::from_secs(5), incoming.next())
+            .await?
+            .context("incoming closed")??;
+        let certs = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .context("no client certificate")?;
+        assert_eq!(
+            ClientIdentity::from_cert(&certs[0], ConfigGrpcServerTlsClientIdentity::CommonName),
+            Some(ClientIdentity("client-a".to_owned()))
+        );
+        assert_eq!(
+            ClientIdentity::from_cert(&certs[0], ConfigGrpcServerTlsClientIdentity::SubjectAltName),
+            Some(ClientIdentity("client-a.example".to_owned()))
+        );
+
+        // untrusted or missed certificate, with TLS 1.3 client can finish the handshake before
+        // the server rejects the certificate
+        for client_cert in [
+            Some(("client-untrusted.pem", "client-untrusted-key.pem")),
+            None,
+        ] {
+            if let Ok(mut client) = connect(addr, client_cert).await {
+                assert!(client.read_u8().await.is_err());
+            }
+            assert!(timeout(Duration::from_millis(500), incoming.next())
+                .await
+                .is_err());
+        }
+
+        Ok(())
+    }
+}
@@ -2,16 +2,27 @@ use {
     criterion::{criterion_group, criterion_main, BenchmarkId, Criterion},
     prost::Message as _,
     prost_types::Timestamp,
-    std::time::{Duration, SystemTime},
-    yellowstone_grpc_proto::plugin::{
-        filter::message::{
-            tests::{
-                create_accounts, create_message_filters, load_predefined_blocks,
-                load_predefined_transactions,
+    std::{
+        collections::HashMap,
+        time::{Duration, SystemTime},
+    },
+    yellowstone_grpc_proto::{
+        geyser::{SubscribeRequest, SubscribeRequestFilterTransactions},
+        plugin::{
+            filter::{
+                limits::FilterLimits,
+                message::{
+                    tests::{
+                        create_accounts, create_message_filters, load_predefined_blocks,
+                        load_predefined_transactions,
+                    },
+                    FilteredUpdate, FilteredUpdateOneof,
+                },
+                name::FilterNames,
+                Filter,
             },
-            FilteredUpdate, FilteredUpdateOneof,
+            message::{Message, MessageTransaction},
         },
-        message::MessageTransaction,
     },
 };
 
@@ -43,6 +54,9 @@ fn bench_account(c: &mut Criterion) {
             filters: filters.clone(),
             message: FilteredUpdateOneof::account(&msg, data_slice),
             created_at: Timestamp::from(SystemTime::now()),
+            seq: None,
+            resume_token: None,
+            slot_seq: None,
         })
         .collect::<Vec<_>>();
     bench!(&updates, "accounts");
@@ -57,26 +71,96 @@ fn bench_account(c: &mut Criterion) {
                 created_at: Timestamp::from(SystemTime::now()),
             }),
             created_at: Timestamp::from(SystemTime::now()),
+            seq: None,
+            resume_token: None,
+            slot_seq: None,
         })
         .collect::<Vec<_>>();
     bench!(&updates, "transactions");
 
+    let updates = load_predefined_transactions()
+        .into_iter()
+        .map(|transaction| FilteredUpdate {
+            filters: filters.clone(),
+            message: FilteredUpdateOneof::transaction_status(&MessageTransaction {
+                transaction,
+                slot: 42,
+                created_at: Timestamp::from(SystemTime::now()),
+            }),
+            created_at: Timestamp::from(SystemTime::now()),
+            seq: None,
+            resume_token: None,
+            slot_seq: None,
+        })
+        .collect::<Vec<_>>();
+    bench!(&updates, "transactions_status");
+
     let updates = load_predefined_blocks()
         .into_iter()
         .map(|block| FilteredUpdate {
             filters: filters.clone(),
             message: FilteredUpdateOneof::block(Box::new(block)),
             created_at: Timestamp::from(SystemTime::now()),
+            seq: None,
+            resume_token: None,
+            slot_seq: None,
         })
         .collect::<Vec<_>>();
     bench!(&updates, "blocks");
 }
 
+fn bench_transactions_status(c: &mut Criterion) {
+    let messages = load_predefined_transactions()
+        .into_iter()
+        .map(|transaction| {
+            Message::Transaction(MessageTransaction {
+                transaction,
+                slot: 42,
+                created_at: Timestamp::from(SystemTime::now()),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // `failed` only filter skips successful transactions before matching
+    for (kind, vote) in [("failed-only", None), ("failed-non-vote", Some(false))] {
+        let request = SubscribeRequest {
+            transactions_status: HashMap::from([(
+                "failed".to_owned(),
+                SubscribeRequestFilterTransactions {
+                    vote,
+                    failed: Some(true),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        let mut filter = Filter::new(
+            &request,
+            &FilterLimits::default(),
+            &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+        )
+        .expect("valid filter");
+        c.bench_with_input(
+            BenchmarkId::new("transactions_status_stream", kind),
+            &messages,
+            |b, messages| {
+                b.iter(|| {
+                    for message in messages.iter() {
+                        for update in filter.get_updates(message, None).iter() {
+                            update.encode_to_vec().len();
+                        }
+                    }
+                })
+            },
+        );
+    }
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default()
         .warm_up_time(Duration::from_secs(3)) // default 3
         .measurement_time(Duration::from_secs(5)); // default 5
-    targets = bench_account
+    targets = bench_account, bench_transactions_status
 );
 criterion_main!(benches);
@@ -0,0 +1,201 @@
+use {
+    criterion::{criterion_group, criterion_main, BenchmarkId, Criterion},
+    solana_sdk::pubkey::Pubkey,
+    std::{collections::HashMap, time::Duration},
+    yellowstone_grpc_proto::{
+        geyser::{
+            subscribe_request_filter_accounts_filter::Filter as AccountsFilterDataOneof,
+            subscribe_request_filter_accounts_filter_memcmp::Data as AccountsFilterMemcmpOneof,
+            SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterAccountsFilter,
+            SubscribeRequestFilterAccountsFilterChecksum,
+            SubscribeRequestFilterAccountsFilterMemcmp,
+        },
+        plugin::{
+            filter::{
+                limits::FilterLimits, message::tests::create_accounts, name::FilterNames, Filter,
+            },
+            message::{CommitmentLevel, Message},
+        },
+    },
+};
+
+const OWNER: &str = "5jrPJWVGrFvQ2V9wRZC3kHEZhxo9pmMir15x73oHT6mn";
+const PUBKEY: &str = "28Dncoh8nmzXYEGLUcBA5SUw5WDwDBn15uUCwrWBbyuu";
+
+fn create_filter(
+    account: Vec<String>,
+    owner: Vec<String>,
+    filters: Vec<AccountsFilterDataOneof>,
+) -> SubscribeRequestFilterAccounts {
+    SubscribeRequestFilterAccounts {
+        nonempty_txn_signature: None,
+        account,
+        owner,
+        filters: filters
+            .into_iter()
+            .map(|filter| SubscribeRequestFilterAccountsFilter {
+                filter: Some(filter),
+            })
+            .collect(),
+        dedup_by_slot: None,
+        deliver_at_commitment: None,
+        data_fields: vec![],
+        only_from_successful_tx: None,
+        new_accounts_only: None,
+        data_size_max: None,
+        decompress_data: None,
+        snapshot: None,
+        on_owner_change: None,
+        native_program_owned: None,
+        on_data_change_only: None,
+        on_data_change_include_lamports_owner: None,
+        diff: None,
+        rent_epoch_eq: None,
+        rent_epoch_gt: None,
+        rent_epoch_lt: None,
+        executable: None,
+    }
+}
+
+fn create_request(accounts: HashMap<String, SubscribeRequestFilterAccounts>) -> SubscribeRequest {
+    SubscribeRequest {
+        accounts,
+        slots: HashMap::new(),
+        transactions: HashMap::new(),
+        transactions_status: HashMap::new(),
+        blocks: HashMap::new(),
+        blocks_meta: HashMap::new(),
+        entry: HashMap::new(),
+        commitment: None,
+        accounts_data_slice: vec![],
+        ping: None,
+        from_slot: None,
+        ping_interval_ms: None,
+        envelope: None,
+        accounts_data_encoding: None,
+        stats_interval_ms: None,
+        max_updates_per_second: None,
+        program_events: HashMap::new(),
+        channel_capacity: None,
+        exclude_votes: None,
+        accounts_data_segments: None,
+        resume_token: None,
+        encoding: None,
+    }
+}
+
+fn bench_filter(c: &mut Criterion) {
+    let memcmp = || {
+        AccountsFilterDataOneof::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+            offset: 0,
+            data: Some(AccountsFilterMemcmpOneof::Bytes(vec![42; 32])),
+        })
+    };
+    let checksum = || {
+        AccountsFilterDataOneof::Checksum(SubscribeRequestFilterAccountsFilterChecksum {
+            crc_offset: 0,
+            body_offset: 4,
+            body_length: 1024,
+        })
+    };
+
+    let requests = [
+        (
+            "pubkey",
+            create_request(HashMap::from([
+                (
+                    "account".to_owned(),
+                    create_filter(vec![PUBKEY.to_owned()], vec![], vec![]),
+                ),
+                (
+                    "owner".to_owned(),
+                    create_filter(vec![], vec![OWNER.to_owned()], vec![]),
+                ),
+            ])),
+        ),
+        (
+            "mixed",
+            create_request(HashMap::from([
+                (
+                    "account".to_owned(),
+                    create_filter(vec![PUBKEY.to_owned()], vec![], vec![]),
+                ),
+                (
+                    "memcmp".to_owned(),
+                    create_filter(vec![], vec![OWNER.to_owned()], vec![memcmp()]),
+                ),
+                (
+                    "token".to_owned(),
+                    create_filter(
+                        vec![],
+                        vec![OWNER.to_owned()],
+                        vec![AccountsFilterDataOneof::TokenAccountState(true)],
+                    ),
+                ),
+                (
+                    "checksum".to_owned(),
+                    create_filter(vec![], vec![OWNER.to_owned()], vec![memcmp(), checksum()]),
+                ),
+                (
+                    "checksum-only".to_owned(),
+                    create_filter(vec![], vec![OWNER.to_owned()], vec![checksum()]),
+                ),
+            ])),
+        ),
+        (
+            // only filters of the account owner are evaluated
+            "owners-500",
+            create_request(
+                (0..500)
+                    .map(|index| {
+                        let owner = if index == 0 {
+                            OWNER.to_owned()
+                        } else {
+                            Pubkey::new_unique().to_string()
+                        };
+                        (
+                            format!("owner-{index}"),
+                            create_filter(vec![], vec![owner], vec![memcmp()]),
+                        )
+                    })
+                    .collect(),
+            ),
+        ),
+    ];
+
+    let messages = create_accounts()
+        .into_iter()
+        .map(|(msg, _data_slice)| Message::Account(msg))
+        .collect::<Vec<_>>();
+
+    for (kind, request) in requests.iter() {
+        let mut filter = Filter::new(
+            request,
+            &FilterLimits::default(),
+            &mut FilterNames::new(64, 1024, Duration::from_secs(1)),
+        )
+        .expect("valid filter");
+        c.bench_with_input(
+            BenchmarkId::new("accounts", kind),
+            &messages,
+            |b, messages| {
+                b.iter(|| {
+                    for message in messages.iter() {
+                        filter
+                            .get_updates(message, Some(CommitmentLevel::Processed))
+                            .len();
+                    }
+                })
+            },
+        );
+    }
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_secs(3)) // default 3
+        .measurement_time(Duration::from_secs(5)); // default 5
+    targets = bench_filter
+);
+criterion_main!(benches);
@@ -90,6 +90,51 @@ fn main() -> anyhow::Result<()> {
                 .codec_path("tonic::codec::ProstCodec")
                 .build(),
         )
+        .method(
+            Method::builder()
+                .name("get_filter_limits")
+                .route_name("GetFilterLimits")
+                .input_type("crate::geyser::GetFilterLimitsRequest")
+                .output_type("crate::geyser::GetFilterLimitsResponse")
+                .codec_path("tonic::codec::ProstCodec")
+                .build(),
+        )
+        .method(
+            Method::builder()
+                .name("get_block_meta_range")
+                .route_name("GetBlockMetaRange")
+                .input_type("crate::geyser::GetBlockMetaRangeRequest")
+                .output_type("crate::geyser::GetBlockMetaRangeResponse")
+                .codec_path("tonic::codec::ProstCodec")
+                .build(),
+        )
+        .method(
+            Method::builder()
+                .name("get_transaction_accounts")
+                .route_name("GetTransactionAccounts")
+                .input_type("crate::geyser::GetTransactionAccountsRequest")
+                .output_type("crate::geyser::GetTransactionAccountsResponse")
+                .codec_path("tonic::codec::ProstCodec")
+                .build(),
+        )
+        .method(
+            Method::builder()
+                .name("explain_filter")
+                .route_name("ExplainFilter")
+                .input_type("crate::geyser::ExplainFilterRequest")
+                .output_type("crate::geyser::ExplainFilterResponse")
+                .codec_path("tonic::codec::ProstCodec")
+                .build(),
+        )
+        .method(
+            Method::builder()
+                .name("get_account_data")
+                .route_name("GetAccountData")
+                .input_type("crate::geyser::GetAccountDataRequest")
+                .output_type("crate::geyser::GetAccountDataResponse")
+                .codec_path("tonic::codec::ProstCodec")
+                .build(),
+        )
         .build();
     Builder::new()
         .build_client(false)
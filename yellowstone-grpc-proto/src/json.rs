@@ -0,0 +1,380 @@
+//! JSON rendering of updates, used by the example client for printing and by the plugin for
+//! `UPDATES_ENCODING_JSON` subscriptions
+
+use {
+    crate::{
+        convert_from,
+        prelude::{
+            subscribe_update::UpdateOneof, CommitmentLevel, SlotStatus, SubscribeUpdateAccountInfo,
+            SubscribeUpdateEntry, SubscribeUpdateTransactionInfo,
+        },
+        slot_delta,
+    },
+    serde_json::{json, Value},
+    solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature},
+    solana_transaction_status::UiTransactionEncoding,
+    std::collections::HashMap,
+};
+
+type JsonResult<T> = Result<T, &'static str>;
+
+pub fn create_pretty_account(account: SubscribeUpdateAccountInfo) -> JsonResult<Value> {
+    Ok(json!({
+        "pubkey": Pubkey::try_from(account.pubkey).map_err(|_| "invalid account pubkey")?.to_string(),
+        "lamports": account.lamports,
+        "owner": Pubkey::try_from(account.owner).map_err(|_| "invalid account owner")?.to_string(),
+        "executable": account.executable,
+        "rentEpoch": account.rent_epoch,
+        "data": account.data_encoded.unwrap_or_else(|| hex::encode(account.data)),
+        "writeVersion": account.write_version,
+        "txnSignature": account.txn_signature.map(|sig| bs58::encode(sig).into_string()),
+        "dataChecksum": account.data_checksum,
+        "dataSegments": account.data_segments.into_iter().map(|segment| json!({
+            "offset": segment.offset,
+            "data": hex::encode(segment.data),
+        })).collect::<Vec<_>>(),
+    }))
+}
+
+pub fn create_pretty_transaction(tx: SubscribeUpdateTransactionInfo) -> JsonResult<Value> {
+    Ok(json!({
+        "signature": create_signature(&tx.signature)?,
+        "isVote": tx.is_vote,
+        "tx": convert_from::create_tx_with_meta(tx)?
+            .encode(UiTransactionEncoding::Base64, Some(u8::MAX), true)
+            .map_err(|_| "failed to encode transaction")?,
+    }))
+}
+
+pub fn create_pretty_entry(msg: SubscribeUpdateEntry) -> JsonResult<Value> {
+    Ok(json!({
+        "slot": msg.slot,
+        "index": msg.index,
+        "numHashes": msg.num_hashes,
+        "hash": Hash::new_from_array(<[u8; 32]>::try_from(msg.hash.as_slice()).map_err(|_| "invalid entry hash")?).to_string(),
+        "executedTransactionCount": msg.executed_transaction_count,
+        "startingTransactionIndex": msg.starting_transaction_index,
+    }))
+}
+
+fn create_signature(signature: &[u8]) -> JsonResult<String> {
+    Signature::try_from(signature)
+        .map(|signature| signature.to_string())
+        .map_err(|_| "invalid signature")
+}
+
+fn create_commitment(commitment: i32) -> JsonResult<&'static str> {
+    CommitmentLevel::try_from(commitment)
+        .map(|commitment| commitment.as_str_name())
+        .map_err(|_| "failed to decode commitment")
+}
+
+fn create_slot_status(status: i32) -> JsonResult<&'static str> {
+    SlotStatus::try_from(status)
+        .map(|status| status.as_str_name())
+        .map_err(|_| "failed to decode slot status")
+}
+
+/// Type and JSON object of the update, `None` for pings, pongs and already rendered updates
+pub fn create_pretty_update(update: UpdateOneof) -> JsonResult<Option<(&'static str, Value)>> {
+    Ok(Some(match update {
+        UpdateOneof::Account(msg) => {
+            let account = msg.account.ok_or("no account in the message")?;
+            let mut value = create_pretty_account(account)?;
+            value["isStartup"] = json!(msg.is_startup);
+            value["slot"] = json!(msg.slot);
+            if let Some(commitment) = msg.commitment {
+                value["commitment"] = json!(create_commitment(commitment)?);
+            }
+            if msg.data_decompress_failed {
+                value["dataDecompressFailed"] = json!(true);
+            }
+            if let Some(data_diff) = msg.data_diff {
+                value["dataDiff"] = json!({
+                    "baseWriteVersion": data_diff.base_write_version,
+                    "dataLen": data_diff.data_len,
+                });
+            }
+            if let Some(data_ref) = msg.data_ref {
+                value["dataRef"] = json!({
+                    "pubkey": bs58::encode(data_ref.pubkey).into_string(),
+                    "slot": data_ref.slot,
+                    "writeVersion": data_ref.write_version,
+                    "dataLen": data_ref.data_len,
+                });
+            }
+            ("account", value)
+        }
+        UpdateOneof::Slot(msg) => (
+            "slot",
+            json!({
+                "slot": msg.slot,
+                "parent": msg.parent,
+                "status": create_slot_status(msg.status)?,
+                "deadError": msg.dead_error,
+                "rawStatus": msg.raw_status,
+            }),
+        ),
+        UpdateOneof::SlotGap(msg) => (
+            "slotGap",
+            json!({
+                "fromSlot": msg.from_slot,
+                "toSlot": msg.to_slot,
+                "commitment": create_commitment(msg.commitment)?,
+            }),
+        ),
+        UpdateOneof::SlotDeltaBatch(msg) => (
+            "slotDeltaBatch",
+            json!(slot_delta::decode(&msg)?
+                .into_iter()
+                .map(|(slot, status)| json!({
+                    "slot": slot,
+                    "status": status.as_str_name(),
+                }))
+                .collect::<Vec<_>>()),
+        ),
+        UpdateOneof::Transaction(msg) => {
+            let tx = msg.transaction.ok_or("no transaction in the message")?;
+            let mut value = create_pretty_transaction(tx)?;
+            value["slot"] = json!(msg.slot);
+            ("transaction", value)
+        }
+        UpdateOneof::TransactionStatus(msg) => (
+            "transactionStatus",
+            json!({
+                "slot": msg.slot,
+                "signature": create_signature(&msg.signature)?,
+                "isVote": msg.is_vote,
+                "index": msg.index,
+                "err": convert_from::create_tx_error(msg.err.as_ref())?,
+            }),
+        ),
+        UpdateOneof::Entry(msg) => ("entry", create_pretty_entry(msg)?),
+        UpdateOneof::BlockMeta(msg) => (
+            "blockmeta",
+            json!({
+                "slot": msg.slot,
+                "blockhash": msg.blockhash,
+                "rewards": msg.rewards.map(convert_from::create_rewards_obj).transpose()?,
+                "blockTime": msg.block_time.map(|obj| obj.timestamp),
+                "blockHeight": msg.block_height.map(|obj| obj.block_height),
+                "parentSlot": msg.parent_slot,
+                "parentBlockhash": msg.parent_blockhash,
+                "executedTransactionCount": msg.executed_transaction_count,
+                "entriesCount": msg.entries_count,
+            }),
+        ),
+        UpdateOneof::Block(msg) => (
+            "block",
+            json!({
+                "slot": msg.slot,
+                "blockhash": msg.blockhash,
+                "rewards": msg.rewards.map(convert_from::create_rewards_obj).transpose()?,
+                "blockTime": msg.block_time.map(|obj| obj.timestamp),
+                "blockHeight": msg.block_height.map(|obj| obj.block_height),
+                "parentSlot": msg.parent_slot,
+                "parentBlockhash": msg.parent_blockhash,
+                "executedTransactionCount": msg.executed_transaction_count,
+                "transactions": msg.transactions.into_iter().map(create_pretty_transaction).collect::<JsonResult<Value>>()?,
+                "updatedAccountCount": msg.updated_account_count,
+                "accounts": msg.accounts.into_iter().map(create_pretty_account).collect::<JsonResult<Value>>()?,
+                "entriesCount": msg.entries_count,
+                "entries": msg.entries.into_iter().map(create_pretty_entry).collect::<JsonResult<Value>>()?,
+            }),
+        ),
+        UpdateOneof::Envelope(msg) => (
+            "envelope",
+            json!({
+                "typeUrl": msg.type_url,
+                "value": hex::encode(msg.value),
+            }),
+        ),
+        UpdateOneof::FilterStats(msg) => (
+            "filterStats",
+            json!({
+                "intervalMs": msg.interval_ms,
+                "filters": msg.filters.into_iter().map(|entry| json!({
+                    "filter": entry.filter,
+                    "count": entry.count,
+                    "bytes": entry.bytes,
+                    "uniquePubkeys": entry.unique_pubkeys,
+                })).collect::<Vec<_>>(),
+            }),
+        ),
+        UpdateOneof::SnapshotComplete(msg) => (
+            "snapshotComplete",
+            json!({
+                "accounts": msg.accounts,
+                "slot": msg.slot,
+            }),
+        ),
+        UpdateOneof::ServerShutdown(_) => ("serverShutdown", json!({})),
+        UpdateOneof::SubscribeAccepted(msg) => (
+            "subscribeAccepted",
+            json!({
+                "filters": msg.filters.into_iter().map(|entry| (entry.filter_type, entry.count)).collect::<HashMap<_, _>>(),
+                "commitment": create_commitment(msg.commitment)?,
+            }),
+        ),
+        UpdateOneof::ProgramEvent(msg) => (
+            "programEvent",
+            json!({
+                "slot": msg.slot,
+                "signature": create_signature(&msg.signature)?,
+                "transactionIndex": msg.transaction_index,
+                "program": Pubkey::try_from(msg.program).map_err(|_| "invalid program")?.to_string(),
+                "logIndex": msg.log_index,
+                "data": msg.data.iter().map(hex::encode).collect::<Vec<_>>(),
+            }),
+        ),
+        UpdateOneof::Ping(_) | UpdateOneof::Pong(_) | UpdateOneof::Json(_) => return Ok(None),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::create_pretty_update,
+        crate::prelude::{
+            subscribe_update::UpdateOneof, CommitmentLevel, SlotStatus, SubscribeUpdateAccount,
+            SubscribeUpdateAccountInfo, SubscribeUpdatePing, SubscribeUpdateSlot,
+            SubscribeUpdateTransactionStatus,
+        },
+        serde_json::json,
+        solana_sdk::{pubkey::Pubkey, signature::Signature},
+    };
+
+    #[test]
+    fn test_pretty_update() {
+        let pubkey = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let update = UpdateOneof::Account(SubscribeUpdateAccount {
+            account: Some(SubscribeUpdateAccountInfo {
+                pubkey: pubkey.to_bytes().to_vec(),
+                lamports: 42,
+                owner: owner.to_bytes().to_vec(),
+                data: vec![1, 2, 255],
+                write_version: 7,
+                ..Default::default()
+            }),
+            slot: 100,
+            commitment: Some(CommitmentLevel::Confirmed as i32),
+            ..Default::default()
+        });
+        assert_eq!(
+            create_pretty_update(update),
+            Ok(Some((
+                "account",
+                json!({
+                    "pubkey": pubkey.to_string(),
+                    "lamports": 42,
+                    "owner": owner.to_string(),
+                    "executable": false,
+                    "rentEpoch": 0,
+                    "data": "0102ff",
+                    "writeVersion": 7,
+                    "txnSignature": null,
+                    "dataChecksum": null,
+                    "dataSegments": [],
+                    "isStartup": false,
+                    "slot": 100,
+                    "commitment": "CONFIRMED",
+                })
+            )))
+        );
+
+        let update = UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot: 100,
+            parent: Some(99),
+            status: SlotStatus::SlotFinalized as i32,
+            ..Default::default()
+        });
+        assert_eq!(
+            create_pretty_update(update),
+            Ok(Some((
+                "slot",
+                json!({
+                    "slot": 100,
+                    "parent": 99,
+                    "status": "SLOT_FINALIZED",
+                    "deadError": null,
+                    "rawStatus": null,
+                })
+            )))
+        );
+
+        for (status, name, dead_error) in [
+            (
+                SlotStatus::SlotFirstShredReceived,
+                "SLOT_FIRST_SHRED_RECEIVED",
+                None,
+            ),
+            (SlotStatus::SlotCompleted, "SLOT_COMPLETED", None),
+            (SlotStatus::SlotCreatedBank, "SLOT_CREATED_BANK", None),
+            (SlotStatus::SlotDead, "SLOT_DEAD", Some("dead".to_owned())),
+        ] {
+            let update = UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot: 101,
+                parent: Some(100),
+                status: status as i32,
+                dead_error: dead_error.clone(),
+                ..Default::default()
+            });
+            assert_eq!(
+                create_pretty_update(update),
+                Ok(Some((
+                    "slot",
+                    json!({
+                        "slot": 101,
+                        "parent": 100,
+                        "status": name,
+                        "deadError": dead_error,
+                        "rawStatus": null,
+                    })
+                )))
+            );
+        }
+
+        let update = UpdateOneof::Slot(SubscribeUpdateSlot {
+            status: 42,
+            ..Default::default()
+        });
+        assert_eq!(
+            create_pretty_update(update),
+            Err("failed to decode slot status")
+        );
+
+        let signature = Signature::new_unique();
+        let update = UpdateOneof::TransactionStatus(SubscribeUpdateTransactionStatus {
+            slot: 100,
+            signature: signature.as_ref().to_vec(),
+            is_vote: true,
+            index: 3,
+            err: None,
+        });
+        assert_eq!(
+            create_pretty_update(update),
+            Ok(Some((
+                "transactionStatus",
+                json!({
+                    "slot": 100,
+                    "signature": signature.to_string(),
+                    "isVote": true,
+                    "index": 3,
+                    "err": null,
+                })
+            )))
+        );
+
+        let update = UpdateOneof::TransactionStatus(SubscribeUpdateTransactionStatus {
+            signature: vec![1, 2, 3],
+            ..Default::default()
+        });
+        assert_eq!(create_pretty_update(update), Err("invalid signature"));
+
+        assert_eq!(
+            create_pretty_update(UpdateOneof::Ping(SubscribeUpdatePing {})),
+            Ok(None)
+        );
+    }
+}
@@ -37,9 +37,182 @@ pub mod prelude {
 pub use tonic;
 pub use {prost, prost_types};
 
+#[cfg(feature = "json")]
+pub mod json;
+
 #[cfg(feature = "plugin")]
 pub mod plugin;
 
+/// Encoding of `SubscribeUpdateSlotDeltaBatch`: consecutive transitions of the same slot are
+/// stored as a run of zigzag varint slot delta from the previous run (from `base_slot` for the
+/// first one) and varint run length, statuses are packed by 4 bits per transition
+pub mod slot_delta {
+    use {
+        super::geyser::{SlotStatus, SubscribeUpdateSlotDeltaBatch},
+        prost::encoding::{decode_varint, encode_varint},
+    };
+
+    const fn zigzag_encode(value: i64) -> u64 {
+        ((value << 1) ^ (value >> 63)) as u64
+    }
+
+    const fn zigzag_decode(value: u64) -> i64 {
+        ((value >> 1) as i64) ^ -((value & 1) as i64)
+    }
+
+    fn encode_run(delta: u64, length: u64, buf: &mut Vec<u8>) {
+        encode_varint(zigzag_encode(delta as i64), buf);
+        encode_varint(length, buf);
+    }
+
+    pub fn encode(transitions: &[(u64, SlotStatus)]) -> SubscribeUpdateSlotDeltaBatch {
+        let base_slot = transitions
+            .first()
+            .map(|(slot, _)| *slot)
+            .unwrap_or_default();
+        let mut batch = SubscribeUpdateSlotDeltaBatch {
+            base_slot,
+            slot_runs: vec![],
+            statuses: vec![0; transitions.len().div_ceil(2)],
+        };
+
+        let mut prev_slot = base_slot;
+        let mut run: Option<(u64, u64)> = None;
+        for (index, (slot, status)) in transitions.iter().copied().enumerate() {
+            match run.as_mut() {
+                Some((run_slot, length)) if *run_slot == slot => *length += 1,
+                _ => {
+                    if let Some((run_slot, length)) = run.replace((slot, 1)) {
+                        encode_run(
+                            run_slot.wrapping_sub(prev_slot),
+                            length,
+                            &mut batch.slot_runs,
+                        );
+                        prev_slot = run_slot;
+                    }
+                }
+            }
+            batch.statuses[index / 2] |= (status as u8 & 0x0f) << (index % 2 * 4);
+        }
+        if let Some((run_slot, length)) = run {
+            encode_run(
+                run_slot.wrapping_sub(prev_slot),
+                length,
+                &mut batch.slot_runs,
+            );
+        }
+
+        batch
+    }
+
+    pub fn decode(
+        batch: &SubscribeUpdateSlotDeltaBatch,
+    ) -> Result<Vec<(u64, SlotStatus)>, &'static str> {
+        let mut slots = vec![];
+        let mut slot = batch.base_slot;
+        let mut buf = batch.slot_runs.as_slice();
+        while !buf.is_empty() {
+            let delta = decode_varint(&mut buf).map_err(|_error| "invalid slot delta")?;
+            slot = slot.wrapping_add(zigzag_decode(delta) as u64);
+            let length = decode_varint(&mut buf).map_err(|_error| "invalid slot run length")?;
+            // every transition takes 4 bits in statuses, do not trust the length
+            let length = usize::try_from(length)
+                .ok()
+                .filter(|length| slots.len() + length <= batch.statuses.len() * 2)
+                .ok_or("more slots than statuses")?;
+            slots.resize(slots.len() + length, slot);
+        }
+        if slots.len().div_ceil(2) != batch.statuses.len() {
+            return Err("less slots than statuses");
+        }
+
+        slots
+            .into_iter()
+            .enumerate()
+            .map(|(index, slot)| {
+                let status = (batch.statuses[index / 2] >> (index % 2 * 4)) & 0x0f;
+                SlotStatus::try_from(i32::from(status))
+                    .map(|status| (slot, status))
+                    .map_err(|_error| "unknown status")
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use {
+            super::{decode, encode},
+            crate::geyser::{SlotStatus, SubscribeUpdateSlot, SubscribeUpdateSlotDeltaBatch},
+            prost::Message,
+        };
+
+        #[test]
+        fn test_round_trip() {
+            let mut transitions = vec![];
+            for slot in 300_000_000..300_000_064 {
+                transitions.push((slot, SlotStatus::SlotFirstShredReceived));
+                transitions.push((slot, SlotStatus::SlotCreatedBank));
+                transitions.push((slot, SlotStatus::SlotCompleted));
+                transitions.push((slot, SlotStatus::SlotProcessed));
+                transitions.push((slot - 1, SlotStatus::SlotConfirmed));
+                transitions.push((slot - 32, SlotStatus::SlotFinalized));
+            }
+            transitions.push((300_000_100, SlotStatus::SlotDead));
+            transitions.push((0, SlotStatus::SlotDead));
+            transitions.push((u64::MAX, SlotStatus::SlotProcessed));
+
+            for transitions in [&transitions[..], &transitions[..1], &[]] {
+                let batch = encode(transitions);
+                let batch = SubscribeUpdateSlotDeltaBatch::decode(batch.encode_to_vec().as_slice())
+                    .expect("failed to decode");
+                assert_eq!(decode(&batch).as_deref(), Ok(transitions));
+            }
+
+            // per-transition messages take at least 4x more bytes
+            let per_transition_len = transitions
+                .iter()
+                .map(|(slot, status)| {
+                    SubscribeUpdateSlot {
+                        slot: *slot,
+                        status: *status as i32,
+                        ..Default::default()
+                    }
+                    .encoded_len()
+                })
+                .sum::<usize>();
+            assert!(encode(&transitions).encoded_len() * 4 < per_transition_len);
+        }
+
+        #[test]
+        fn test_decode_invalid() {
+            let transitions = [
+                (42, SlotStatus::SlotProcessed),
+                (42, SlotStatus::SlotConfirmed),
+                (43, SlotStatus::SlotProcessed),
+            ];
+            let batch = encode(&transitions);
+            assert_eq!(batch.slot_runs, [0, 2, 2, 1]);
+            assert_eq!(batch.statuses, [0x10, 0x00]);
+
+            let mut invalid = batch.clone();
+            invalid.statuses.push(0);
+            assert_eq!(decode(&invalid), Err("less slots than statuses"));
+
+            let mut invalid = batch.clone();
+            invalid.slot_runs[3] = 3;
+            assert_eq!(decode(&invalid), Err("more slots than statuses"));
+
+            let mut invalid = batch.clone();
+            invalid.slot_runs.push(0x80);
+            assert_eq!(decode(&invalid), Err("invalid slot delta"));
+
+            let mut invalid = batch;
+            invalid.statuses[1] = 0x0f;
+            assert_eq!(decode(&invalid), Err("unknown status"));
+        }
+    }
+}
+
 #[cfg(feature = "convert")]
 pub mod convert_to {
     use {
@@ -300,7 +473,8 @@ pub mod convert_to {
 #[cfg(feature = "convert")]
 pub mod convert_from {
     use {
-        super::prelude as proto,
+        super::{prelude as proto, slot_delta},
+        base64::{engine::general_purpose::STANDARD as base64_engine, Engine},
         solana_account_decoder::parse_token::UiTokenAmount,
         solana_sdk::{
             account::Account,
@@ -311,7 +485,7 @@ pub mod convert_from {
                 Message, MessageHeader, VersionedMessage,
             },
             pubkey::Pubkey,
-            signature::Signature,
+            signature::{Signature, SIGNATURE_BYTES},
             transaction::{TransactionError, VersionedTransaction},
             transaction_context::TransactionReturnData,
         },
@@ -320,6 +494,7 @@ pub mod convert_from {
             RewardsAndNumPartitions, TransactionStatusMeta, TransactionTokenBalance,
             TransactionWithStatusMeta, VersionedTransactionWithStatusMeta,
         },
+        std::str::FromStr,
     };
 
     type CreateResult<T> = Result<T, &'static str>;
@@ -640,4 +815,404 @@ pub mod convert_from {
         };
         Ok((pubkey, account))
     }
+    /// Checks that the update can be converted by `create_*` functions: lengths of pubkeys,
+    /// signatures and hashes, base58 strings, encoded account data and required fields.
+    /// The error contains the path of the invalid field. Updates in `envelope` are not unpacked.
+    pub fn validate_subscribe_update(update: &proto::SubscribeUpdate) -> Result<(), String> {
+        use proto::subscribe_update::UpdateOneof;
+
+        match update
+            .update_oneof
+            .as_ref()
+            .ok_or("failed to get update_oneof")?
+        {
+            UpdateOneof::Account(msg) => validate_account(
+                msg.account
+                    .as_ref()
+                    .ok_or("account: failed to get account")?,
+                "account.account",
+            ),
+            UpdateOneof::Transaction(msg) => validate_tx_info(
+                msg.transaction
+                    .as_ref()
+                    .ok_or("transaction: failed to get transaction")?,
+                "transaction.transaction",
+            ),
+            UpdateOneof::TransactionStatus(msg) => {
+                validate_signature(&msg.signature, "transaction_status.signature")?;
+                create_tx_error(msg.err.as_ref())
+                    .map(|_| ())
+                    .map_err(|error| format!("transaction_status.err: {error}"))
+            }
+            UpdateOneof::Block(msg) => {
+                validate_hash_str(&msg.blockhash, "block.blockhash")?;
+                validate_hash_str(&msg.parent_blockhash, "block.parent_blockhash")?;
+                validate_rewards(msg.rewards.as_ref(), "block.rewards")?;
+                if msg.block_time.is_none() {
+                    return Err("block: failed to get block_time".to_owned());
+                }
+                if msg.block_height.is_none() {
+                    return Err("block: failed to get block_height".to_owned());
+                }
+                for (index, tx) in msg.transactions.iter().enumerate() {
+                    validate_tx_info(tx, &format!("block.transactions[{index}]"))?;
+                }
+                for (index, account) in msg.accounts.iter().enumerate() {
+                    validate_account(account, &format!("block.accounts[{index}]"))?;
+                }
+                for (index, entry) in msg.entries.iter().enumerate() {
+                    validate_hash(&entry.hash, &format!("block.entries[{index}].hash"))?;
+                }
+                Ok(())
+            }
+            UpdateOneof::BlockMeta(msg) => {
+                validate_hash_str(&msg.blockhash, "block_meta.blockhash")?;
+                validate_hash_str(&msg.parent_blockhash, "block_meta.parent_blockhash")?;
+                validate_rewards(msg.rewards.as_ref(), "block_meta.rewards")
+            }
+            UpdateOneof::Entry(msg) => validate_hash(&msg.hash, "entry.hash"),
+            UpdateOneof::ProgramEvent(msg) => {
+                validate_signature(&msg.signature, "program_event.signature")?;
+                validate_pubkey(&msg.program, "program_event.program")
+            }
+            UpdateOneof::SlotDeltaBatch(msg) => slot_delta::decode(msg)
+                .map(|_| ())
+                .map_err(|error| format!("slot_delta_batch: {error}")),
+            UpdateOneof::Slot(_)
+            | UpdateOneof::Ping(_)
+            | UpdateOneof::Pong(_)
+            | UpdateOneof::SlotGap(_)
+            | UpdateOneof::Envelope(_)
+            | UpdateOneof::FilterStats(_)
+            | UpdateOneof::SnapshotComplete(_)
+            | UpdateOneof::ServerShutdown(_)
+            | UpdateOneof::SubscribeAccepted(_)
+            | UpdateOneof::Json(_) => Ok(()),
+        }
+    }
+
+    fn validate_account(
+        account: &proto::SubscribeUpdateAccountInfo,
+        path: &str,
+    ) -> Result<(), String> {
+        validate_pubkey(&account.pubkey, &format!("{path}.pubkey"))?;
+        validate_pubkey(&account.owner, &format!("{path}.owner"))?;
+        if let Some(signature) = &account.txn_signature {
+            validate_signature(signature, &format!("{path}.txn_signature"))?;
+        }
+        // requested encoding is not a part of the update, any of supported is accepted
+        if let Some(data) = &account.data_encoded {
+            if base64_engine.decode(data).is_err() && bs58::decode(data).into_vec().is_err() {
+                return Err(format!(
+                    "{path}.data_encoded: failed to decode base64 or base58"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_tx_info(
+        tx: &proto::SubscribeUpdateTransactionInfo,
+        path: &str,
+    ) -> Result<(), String> {
+        validate_signature(&tx.signature, &format!("{path}.signature"))?;
+        if let Some(meta) = &tx.meta {
+            for (index, reward) in meta.rewards.iter().enumerate() {
+                validate_reward(reward, &format!("{path}.meta.rewards[{index}]"))?;
+            }
+            for (name, balances) in [
+                ("pre_token_balances", &meta.pre_token_balances),
+                ("post_token_balances", &meta.post_token_balances),
+            ] {
+                for (index, balance) in balances.iter().enumerate() {
+                    validate_pubkey_str(
+                        &balance.mint,
+                        &format!("{path}.meta.{name}[{index}].mint"),
+                    )?;
+                }
+            }
+        }
+        create_tx_with_meta(tx.clone())
+            .map(|_| ())
+            .map_err(|error| format!("{path}: {error}"))
+    }
+
+    fn validate_rewards(rewards: Option<&proto::Rewards>, path: &str) -> Result<(), String> {
+        let rewards = rewards.ok_or_else(|| format!("{path}: failed to get rewards"))?;
+        for (index, reward) in rewards.rewards.iter().enumerate() {
+            validate_reward(reward, &format!("{path}[{index}]"))?;
+        }
+        Ok(())
+    }
+
+    fn validate_reward(reward: &proto::Reward, path: &str) -> Result<(), String> {
+        validate_pubkey_str(&reward.pubkey, &format!("{path}.pubkey"))?;
+        create_reward(reward.clone())
+            .map(|_| ())
+            .map_err(|error| format!("{path}: {error}"))
+    }
+
+    fn validate_pubkey(pubkey: &[u8], path: &str) -> Result<(), String> {
+        create_pubkey(pubkey)
+            .map(|_| ())
+            .map_err(|error| format!("{path}: {error}"))
+    }
+
+    fn validate_pubkey_str(pubkey: &str, path: &str) -> Result<(), String> {
+        Pubkey::from_str(pubkey)
+            .map(|_| ())
+            .map_err(|_| format!("{path}: failed to parse Pubkey from base58"))
+    }
+
+    fn validate_signature(signature: &[u8], path: &str) -> Result<(), String> {
+        if signature.len() != SIGNATURE_BYTES {
+            return Err(format!("{path}: failed to parse Signature"));
+        }
+        Ok(())
+    }
+
+    fn validate_hash(hash: &[u8], path: &str) -> Result<(), String> {
+        if hash.len() != HASH_BYTES {
+            return Err(format!("{path}: failed to parse hash"));
+        }
+        Ok(())
+    }
+
+    fn validate_hash_str(hash: &str, path: &str) -> Result<(), String> {
+        Hash::from_str(hash)
+            .map(|_| ())
+            .map_err(|_| format!("{path}: failed to parse hash from base58"))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use {
+            super::validate_subscribe_update,
+            crate::{
+                convert_to,
+                prelude::{
+                    subscribe_update::UpdateOneof, Reward, Rewards, SubscribeUpdate,
+                    SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdateBlockMeta,
+                    SubscribeUpdateEntry, SubscribeUpdateTransaction,
+                    SubscribeUpdateTransactionInfo,
+                },
+            },
+            solana_sdk::{
+                hash::Hash,
+                message::{v0::LoadedAddresses, Message},
+                pubkey::Pubkey,
+                signer::{keypair::Keypair, Signer},
+                transaction::{SanitizedTransaction, Transaction},
+            },
+            solana_transaction_status::TransactionStatusMeta,
+        };
+
+        fn create_update(update: UpdateOneof) -> SubscribeUpdate {
+            SubscribeUpdate {
+                update_oneof: Some(update),
+                ..Default::default()
+            }
+        }
+
+        fn create_transaction() -> SubscribeUpdateTransactionInfo {
+            let keypair = Keypair::new();
+            let message = Message::new(&[], Some(&keypair.pubkey()));
+            let tx = SanitizedTransaction::from_transaction_for_tests(Transaction::new(
+                &[&keypair],
+                message,
+                Hash::new_unique(),
+            ));
+            SubscribeUpdateTransactionInfo {
+                signature: tx.signature().as_ref().to_vec(),
+                is_vote: false,
+                transaction: Some(convert_to::create_transaction(&tx)),
+                meta: Some(convert_to::create_transaction_meta(
+                    &TransactionStatusMeta {
+                        status: Ok(()),
+                        fee: 5_000,
+                        pre_balances: vec![10_000],
+                        post_balances: vec![5_000],
+                        inner_instructions: None,
+                        log_messages: None,
+                        pre_token_balances: None,
+                        post_token_balances: None,
+                        rewards: None,
+                        loaded_addresses: LoadedAddresses::default(),
+                        return_data: None,
+                        compute_units_consumed: None,
+                    },
+                )),
+                index: 0,
+            }
+        }
+
+        #[test]
+        fn test_validate_transaction() {
+            let tx = create_transaction();
+            let update = |tx| {
+                create_update(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+                    transaction: Some(tx),
+                    slot: 42,
+                }))
+            };
+            assert_eq!(validate_subscribe_update(&update(tx.clone())), Ok(()));
+
+            let mut invalid = tx.clone();
+            invalid.signature.pop();
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err("transaction.transaction.signature: failed to parse Signature".to_owned())
+            );
+
+            let mut invalid = tx.clone();
+            let message = invalid
+                .transaction
+                .as_mut()
+                .unwrap()
+                .message
+                .as_mut()
+                .unwrap();
+            message.account_keys[0].truncate(31);
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err("transaction.transaction: failed to parse Pubkey".to_owned())
+            );
+
+            let mut invalid = tx.clone();
+            invalid.meta = None;
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err("transaction.transaction: failed to get transaction meta".to_owned())
+            );
+
+            let mut invalid = tx;
+            invalid.meta.as_mut().unwrap().rewards.push(Reward {
+                pubkey: "not a pubkey".to_owned(),
+                ..Default::default()
+            });
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err(
+                    "transaction.transaction.meta.rewards[0].pubkey: failed to parse Pubkey from base58"
+                        .to_owned()
+                )
+            );
+        }
+
+        #[test]
+        fn test_validate_account() {
+            let account = SubscribeUpdateAccountInfo {
+                pubkey: Pubkey::new_unique().to_bytes().to_vec(),
+                lamports: 1,
+                owner: Pubkey::new_unique().to_bytes().to_vec(),
+                data: vec![1, 2, 3],
+                txn_signature: Some(vec![1; 64]),
+                ..Default::default()
+            };
+            let update = |account| {
+                create_update(UpdateOneof::Account(SubscribeUpdateAccount {
+                    account: Some(account),
+                    ..Default::default()
+                }))
+            };
+            assert_eq!(validate_subscribe_update(&update(account.clone())), Ok(()));
+
+            let mut encoded = account.clone();
+            encoded.data_encoded = Some("AQID".to_owned());
+            assert_eq!(validate_subscribe_update(&update(encoded)), Ok(()));
+
+            let mut invalid = account.clone();
+            invalid.owner = vec![];
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err("account.account.owner: failed to parse Pubkey".to_owned())
+            );
+
+            let mut invalid = account.clone();
+            invalid.txn_signature = Some(vec![1; 63]);
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err("account.account.txn_signature: failed to parse Signature".to_owned())
+            );
+
+            let mut invalid = account;
+            invalid.data_encoded = Some("!!!".to_owned());
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err("account.account.data_encoded: failed to decode base64 or base58".to_owned())
+            );
+
+            assert_eq!(
+                validate_subscribe_update(&create_update(UpdateOneof::Account(
+                    SubscribeUpdateAccount::default()
+                ))),
+                Err("account: failed to get account".to_owned())
+            );
+        }
+
+        #[test]
+        fn test_validate_block_meta_and_entry() {
+            let block_meta = SubscribeUpdateBlockMeta {
+                slot: 42,
+                blockhash: Hash::new_unique().to_string(),
+                parent_blockhash: Hash::new_unique().to_string(),
+                rewards: Some(Rewards {
+                    rewards: vec![Reward {
+                        pubkey: Pubkey::new_unique().to_string(),
+                        lamports: 1,
+                        commission: "5".to_owned(),
+                        ..Default::default()
+                    }],
+                    num_partitions: None,
+                }),
+                ..Default::default()
+            };
+            let update = |block_meta| create_update(UpdateOneof::BlockMeta(block_meta));
+            assert_eq!(
+                validate_subscribe_update(&update(block_meta.clone())),
+                Ok(())
+            );
+
+            let mut invalid = block_meta.clone();
+            invalid.blockhash = "0OIl".to_owned();
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err("block_meta.blockhash: failed to parse hash from base58".to_owned())
+            );
+
+            let mut invalid = block_meta.clone();
+            invalid.rewards.as_mut().unwrap().rewards[0].commission = "x".to_owned();
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err("block_meta.rewards[0]: failed to parse reward commission".to_owned())
+            );
+
+            let mut invalid = block_meta;
+            invalid.rewards = None;
+            assert_eq!(
+                validate_subscribe_update(&update(invalid)),
+                Err("block_meta.rewards: failed to get rewards".to_owned())
+            );
+
+            let entry = SubscribeUpdateEntry {
+                hash: Hash::new_unique().to_bytes().to_vec(),
+                ..Default::default()
+            };
+            assert_eq!(
+                validate_subscribe_update(&create_update(UpdateOneof::Entry(entry.clone()))),
+                Ok(())
+            );
+            let mut invalid = entry;
+            invalid.hash.push(0);
+            assert_eq!(
+                validate_subscribe_update(&create_update(UpdateOneof::Entry(invalid))),
+                Err("entry.hash: failed to parse hash".to_owned())
+            );
+
+            assert_eq!(
+                validate_subscribe_update(&SubscribeUpdate::default()),
+                Err("failed to get update_oneof".to_owned())
+            );
+        }
+    }
 }
@@ -0,0 +1,287 @@
+use {
+    crate::{
+        geyser::SubscribeRequest,
+        plugin::filter::{limits::FilterLimits, name::FilterNames, Filter, FilterResult},
+    },
+    prost::Message,
+    std::{
+        collections::{hash_map::DefaultHasher, HashMap},
+        hash::{Hash, Hasher},
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FilterCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug)]
+struct FilterCacheEntry {
+    request: SubscribeRequest,
+    limits: Arc<FilterLimits>,
+    filter: Arc<Filter>,
+    created_at: Instant,
+}
+
+/// Decoded filters of recent requests, reconnecting clients usually resubmit the same request.
+///
+/// Cached filters hold their names, so `FilterNames::try_clean` does not evict names of cached
+/// filters until the entry expires after `ttl`. `FilterNames` is used only on cache miss, names
+/// stats do not include requests served from the cache.
+#[derive(Debug)]
+pub struct FilterCache {
+    max: usize,
+    ttl: Duration,
+    entries: HashMap<u64, FilterCacheEntry>,
+    stats: FilterCacheStats,
+}
+
+impl FilterCache {
+    /// Cache is disabled with `max` equal to 0
+    pub fn new(max: usize, ttl: Duration) -> Self {
+        Self {
+            max,
+            ttl,
+            entries: HashMap::new(),
+            stats: FilterCacheStats::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns stats collected since the previous call
+    pub fn take_stats(&mut self) -> FilterCacheStats {
+        std::mem::take(&mut self.stats)
+    }
+
+    pub fn get_or_create(
+        &mut self,
+        request: &SubscribeRequest,
+        limits: &Arc<FilterLimits>,
+        names: &mut FilterNames,
+    ) -> FilterResult<Arc<Filter>> {
+        self.get_or_create_at(request, limits, names, Instant::now())
+    }
+
+    /// Filter is shared only for the same request with the same limits, errors are not cached
+    pub fn get_or_create_at(
+        &mut self,
+        request: &SubscribeRequest,
+        limits: &Arc<FilterLimits>,
+        names: &mut FilterNames,
+        now: Instant,
+    ) -> FilterResult<Arc<Filter>> {
+        if self.max == 0 {
+            return Filter::new(request, limits, names).map(Arc::new);
+        }
+
+        let key = Self::get_key(request);
+        if let Some(entry) = self.entries.get(&key) {
+            if now.saturating_duration_since(entry.created_at) <= self.ttl
+                && Arc::ptr_eq(&entry.limits, limits)
+                && entry.request == *request
+            {
+                self.stats.hits += 1;
+                return Ok(Arc::clone(&entry.filter));
+            }
+        }
+
+        self.stats.misses += 1;
+        let filter = Arc::new(Filter::new(request, limits, names)?);
+        self.entries.remove(&key);
+        self.clean(now);
+        self.entries.insert(
+            key,
+            FilterCacheEntry {
+                request: request.clone(),
+                limits: Arc::clone(limits),
+                filter: Arc::clone(&filter),
+                created_at: now,
+            },
+        );
+        Ok(filter)
+    }
+
+    /// Removes expired entries and the oldest ones to fit a new entry
+    fn clean(&mut self, now: Instant) {
+        self.entries
+            .retain(|_key, entry| now.saturating_duration_since(entry.created_at) <= self.ttl);
+        while self.entries.len() >= self.max {
+            let Some(key) = self
+                .entries
+                .iter()
+                .min_by_key(|(_key, entry)| entry.created_at)
+                .map(|(key, _entry)| *key)
+            else {
+                break;
+            };
+            self.entries.remove(&key);
+        }
+    }
+
+    /// Hash of the request with filters sorted by name, equality is checked on lookup
+    fn get_key(request: &SubscribeRequest) -> u64 {
+        fn hash_filters<T: Message>(hasher: &mut DefaultHasher, filters: &HashMap<String, T>) {
+            let mut filters = filters.iter().collect::<Vec<_>>();
+            filters.sort_unstable_by_key(|(name, _filter)| *name);
+            filters.len().hash(hasher);
+            for (name, filter) in filters {
+                name.hash(hasher);
+                filter.encode_to_vec().hash(hasher);
+            }
+        }
+
+        let mut hasher = DefaultHasher::new();
+        hash_filters(&mut hasher, &request.accounts);
+        hash_filters(&mut hasher, &request.slots);
+        hash_filters(&mut hasher, &request.transactions);
+        hash_filters(&mut hasher, &request.transactions_status);
+        hash_filters(&mut hasher, &request.blocks);
+        hash_filters(&mut hasher, &request.blocks_meta);
+        hash_filters(&mut hasher, &request.entry);
+        hash_filters(&mut hasher, &request.program_events);
+        SubscribeRequest {
+            accounts: HashMap::new(),
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            program_events: HashMap::new(),
+            ..request.clone()
+        }
+        .encode_to_vec()
+        .hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{FilterCache, FilterCacheStats},
+        crate::{
+            geyser::{
+                SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterSlots,
+            },
+            plugin::filter::{limits::FilterLimits, name::FilterNames},
+        },
+        std::{
+            collections::HashMap,
+            sync::Arc,
+            time::{Duration, Instant},
+        },
+    };
+
+    fn create_request(names: &[&str], owner: &str) -> SubscribeRequest {
+        let mut accounts = HashMap::new();
+        for name in names {
+            accounts.insert(
+                (*name).to_owned(),
+                SubscribeRequestFilterAccounts {
+                    owner: vec![owner.to_owned()],
+                    ..Default::default()
+                },
+            );
+        }
+        let mut slots = HashMap::new();
+        slots.insert("slots".to_owned(), SubscribeRequestFilterSlots::default());
+        SubscribeRequest {
+            accounts,
+            slots,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identical_requests() {
+        let owner = "5jrPJWVGrFvQ2V9wRZC3kHEZhxo9pmMir15x73oHT6mn";
+        let limits = Arc::new(FilterLimits::default());
+        let mut names = FilterNames::new(64, 1024, Duration::from_secs(1));
+        let mut cache = FilterCache::new(8, Duration::from_secs(10));
+        let now = Instant::now();
+
+        // filters inserted in different order
+        let request = create_request(&["a", "b", "c"], owner);
+        let filter = cache
+            .get_or_create_at(&request, &limits, &mut names, now)
+            .unwrap();
+        let filter2 = cache
+            .get_or_create_at(
+                &create_request(&["c", "b", "a"], owner),
+                &limits,
+                &mut names,
+                now,
+            )
+            .unwrap();
+        assert!(Arc::ptr_eq(&filter, &filter2));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.take_stats(), FilterCacheStats { hits: 1, misses: 1 });
+
+        // expired entry is replaced
+        let filter3 = cache
+            .get_or_create_at(&request, &limits, &mut names, now + Duration::from_secs(11))
+            .unwrap();
+        assert!(!Arc::ptr_eq(&filter, &filter3));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_different_requests() {
+        let owner = "5jrPJWVGrFvQ2V9wRZC3kHEZhxo9pmMir15x73oHT6mn";
+        let limits = Arc::new(FilterLimits::default());
+        let mut names = FilterNames::new(64, 1024, Duration::from_secs(1));
+        let mut cache = FilterCache::new(2, Duration::from_secs(10));
+        let now = Instant::now();
+
+        let request = create_request(&["a"], owner);
+        let filter = cache
+            .get_or_create_at(&request, &limits, &mut names, now)
+            .unwrap();
+
+        // other filter, other request field and other limits miss the cache
+        let mut request_commitment = request.clone();
+        request_commitment.commitment = Some(1);
+        for (request, limits) in [
+            (
+                create_request(&["a"], "28Dncoh8nmzXYEGLUcBA5SUw5WDwDBn15uUCwrWBbyuu"),
+                Arc::clone(&limits),
+            ),
+            (create_request(&["b"], owner), Arc::clone(&limits)),
+            (request_commitment, Arc::clone(&limits)),
+            (request.clone(), Arc::new(FilterLimits::default())),
+        ] {
+            let filter2 = cache
+                .get_or_create_at(&request, &limits, &mut names, now)
+                .unwrap();
+            assert!(!Arc::ptr_eq(&filter, &filter2));
+        }
+        assert_eq!(cache.take_stats(), FilterCacheStats { hits: 0, misses: 5 });
+        assert_eq!(cache.len(), 2);
+
+        // invalid requests are not cached
+        let request = create_request(&["a"], "invalid");
+        assert!(cache
+            .get_or_create_at(&request, &limits, &mut names, now)
+            .is_err());
+        assert_eq!(cache.len(), 2);
+
+        // disabled cache
+        let mut cache = FilterCache::new(0, Duration::from_secs(10));
+        let request = create_request(&["a"], owner);
+        let filter = cache.get_or_create(&request, &limits, &mut names).unwrap();
+        let filter2 = cache.get_or_create(&request, &limits, &mut names).unwrap();
+        assert!(!Arc::ptr_eq(&filter, &filter2));
+        assert!(cache.is_empty());
+    }
+}
@@ -1,48 +1,77 @@
 use {
     crate::{
+        convert_from,
         geyser::{
             subscribe_request_filter_accounts_filter::Filter as AccountsFilterDataOneof,
             subscribe_request_filter_accounts_filter_lamports::Cmp as AccountsFilterLamports,
             subscribe_request_filter_accounts_filter_memcmp::Data as AccountsFilterMemcmpOneof,
-            CommitmentLevel as CommitmentLevelProto, SubscribeRequest,
-            SubscribeRequestAccountsDataSlice, SubscribeRequestFilterAccounts,
-            SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterAccountsFilterLamports,
-            SubscribeRequestFilterBlocks, SubscribeRequestFilterBlocksMeta,
-            SubscribeRequestFilterEntry, SubscribeRequestFilterSlots,
-            SubscribeRequestFilterTransactions,
+            AccountsDataEncoding, CommitmentLevel as CommitmentLevelProto, DecompressCodec,
+            SlotStatus as SlotStatusProto, SubscribeRequest, SubscribeRequestAccountsDataSlice,
+            SubscribeRequestFilterAccounts, SubscribeRequestFilterAccountsFilter,
+            SubscribeRequestFilterAccountsFilterChecksum,
+            SubscribeRequestFilterAccountsFilterLamports, SubscribeRequestFilterBlocks,
+            SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterEntry,
+            SubscribeRequestFilterProgramEvents, SubscribeRequestFilterSlots,
+            SubscribeRequestFilterTransactions, SubscribeRequestFilterTransactionsError,
+            SubscribeUpdateAccountDataDiff, SubscribeUpdateBlockMeta, SubscribeUpdateFilterStats,
+            SubscribeUpdateFilterStatsEntry, UpdatesEncoding,
         },
         plugin::{
             filter::{
                 limits::{
-                    FilterLimits, FilterLimitsAccounts, FilterLimitsBlocks, FilterLimitsBlocksMeta,
-                    FilterLimitsCheckError, FilterLimitsEntries, FilterLimitsSlots,
-                    FilterLimitsTransactions,
+                    FilterCachesMemory, FilterLimits, FilterLimitsAccounts, FilterLimitsBlocks,
+                    FilterLimitsBlocksMeta, FilterLimitsCaches, FilterLimitsCheckError,
+                    FilterLimitsEntries, FilterLimitsMinCommitment, FilterLimitsProgramEvents,
+                    FilterLimitsSlots, FilterLimitsTransactions,
                 },
                 message::{
-                    FilteredUpdate, FilteredUpdateBlock, FilteredUpdateFilters,
-                    FilteredUpdateOneof, FilteredUpdates,
+                    prost_field_encoded_len, FilteredUpdate, FilteredUpdateBlock,
+                    FilteredUpdateFilters, FilteredUpdateOneof, FilteredUpdates,
                 },
                 name::{FilterName, FilterNameError, FilterNames},
             },
             message::{
-                CommitmentLevel, Message, MessageAccount, MessageBlock, MessageBlockMeta,
-                MessageEntry, MessageSlot, MessageTransaction, SlotStatus,
+                CommitmentLevel, Message, MessageAccount, MessageAccountInfo, MessageBlock,
+                MessageBlockMeta, MessageEntry, MessageSlot, MessageTransaction,
+                MessageTransactionInfo, SlotStatus,
             },
         },
     },
     base64::{engine::general_purpose::STANDARD as base64_engine, Engine},
     bytes::buf::BufMut,
-    prost::encoding::{encode_key, encode_varint, WireType},
+    prost::{
+        encoding::{encode_key, encode_varint, WireType},
+        Message as _,
+    },
     solana_sdk::{
+        address_lookup_table, bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable,
+        clock::Slot,
+        compute_budget, config, ed25519_program, feature,
+        instruction::InstructionError,
+        loader_v4, native_loader,
         pubkey::{ParsePubkeyError, Pubkey},
+        secp256k1_program,
         signature::{ParseSignatureError, Signature},
+        stake,
+        system_instruction::MAX_PERMITTED_DATA_LENGTH,
+        system_program, sysvar,
+        transaction::TransactionError,
+        vote,
     },
     spl_token_2022::{generic_token_account::GenericTokenAccount, state::Account as TokenAccount},
     std::{
-        collections::{HashMap, HashSet},
-        ops::Range,
+        collections::{
+            btree_map::Entry as BTreeMapEntry,
+            hash_map::{DefaultHasher, Entry as HashMapEntry},
+            BTreeMap, HashMap, HashSet, VecDeque,
+        },
+        hash::{Hash, Hasher},
+        io::Read,
+        mem::{self, size_of},
+        ops::{Range, RangeInclusive},
         str::FromStr,
         sync::Arc,
+        time::Duration,
     },
 };
 
@@ -55,6 +84,14 @@ pub enum FilterError {
 
     #[error("failed to create CommitmentLevel from {commitment}")]
     InvalidCommitment { commitment: i32 },
+    #[error("failed to create AccountsDataEncoding from {encoding}")]
+    InvalidAccountsDataEncoding { encoding: i32 },
+    #[error("failed to create UpdatesEncoding from {encoding}")]
+    InvalidUpdatesEncoding { encoding: i32 },
+    #[error("failed to create DecompressCodec from {codec}")]
+    InvalidDecompressCodec { codec: i32 },
+    #[error("failed to create SlotStatus from {status}")]
+    InvalidSlotStatus { status: i32 },
     #[error(transparent)]
     InvalidPubkey(#[from] ParsePubkeyError),
     #[error(transparent)]
@@ -62,18 +99,150 @@ pub enum FilterError {
 
     #[error("Too many filters provided; max {max}")]
     CreateAccountStateMaxFilters { max: usize },
+    #[error("`data_equals` is too large; max {max} bytes")]
+    CreateAccountStateDataEqualsMax { max: usize },
+    #[error("memcmp at offset {offset} with {length} bytes is out of datasize {datasize}")]
+    CreateAccountStateMemcmpOutOfBounds {
+        offset: usize,
+        length: usize,
+        datasize: usize,
+    },
     #[error("{0}")]
     CreateAccountState(&'static str),
     #[error("`include_{0}` is not allowed")]
     CreateBlocksNotAllowed(&'static str),
+    #[error("{0}")]
+    CreateBlocks(&'static str),
     #[error("failed to create filter: data slices out of order")]
     CreateDataSliceOutOfOrder,
     #[error("failed to create filter: data slices overlapped")]
     CreateDataSliceOverlap,
+    #[error("failed to create filter: `accounts_data_segments` can't be used with `accounts_data_encoding`")]
+    CreateDataSegmentsWithEncoding,
+    #[error("failed to create filter: accounts `diff` can't be used with `accounts_data_slice`, `accounts_data_encoding` or `accounts_data_segments`")]
+    CreateAccountsDiffWithDataOptions,
+    #[error("failed to create filter: JSON `encoding` can't be used with `envelope`")]
+    CreateJsonWithEnvelope,
+    #[error("failed to create filter: `data_fields` requires `owner`")]
+    CreateDataFieldsWithoutOwner,
+    #[error("failed to create filter: unknown data field `{field}` for owner {owner}")]
+    CreateDataFieldUnknown { owner: Pubkey, field: String },
+    #[error("failed to create filter: data field `{0}` is different for owners")]
+    CreateDataFieldMismatch(String),
+    #[error("failed to create filter: `stats_interval_ms` should be at least {min}")]
+    CreateStatsInterval { min: u64 },
+    #[error("failed to create filter: `account_count_min` is greater than `account_count_max`")]
+    CreateTransactionsAccountCountRange,
+    #[error("failed to create filter: invalid `error_match`, {0}")]
+    CreateTransactionsErrorMatch(&'static str),
+    #[error("failed to create filter: entries `slot_from` is greater than `slot_to`")]
+    CreateEntriesSlotRange,
+}
+
+impl FilterError {
+    /// Short name of the error for metrics labels, one per variant
+    pub const fn reason(&self) -> &'static str {
+        match self {
+            Self::Name(FilterNameError::Oversized { .. }) => "name_oversized",
+            Self::LimitsCheck(error) => error.reason(),
+            Self::InvalidCommitment { .. } => "invalid_commitment",
+            Self::InvalidAccountsDataEncoding { .. } => "invalid_accounts_data_encoding",
+            Self::InvalidUpdatesEncoding { .. } => "invalid_updates_encoding",
+            Self::InvalidDecompressCodec { .. } => "invalid_decompress_codec",
+            Self::InvalidSlotStatus { .. } => "invalid_slot_status",
+            Self::InvalidPubkey(_) => "invalid_pubkey",
+            Self::InvalidSignature(_) => "invalid_signature",
+            Self::CreateAccountStateMaxFilters { .. } => "accounts_max_filters",
+            Self::CreateAccountStateDataEqualsMax { .. } => "accounts_data_equals_max",
+            Self::CreateAccountStateMemcmpOutOfBounds { .. } => "accounts_memcmp_out_of_bounds",
+            Self::CreateAccountState(_) => "accounts_invalid",
+            Self::CreateBlocksNotAllowed(_) => "blocks_not_allowed",
+            Self::CreateBlocks(_) => "blocks_invalid",
+            Self::CreateDataSliceOutOfOrder => "data_slice_out_of_order",
+            Self::CreateDataSliceOverlap => "data_slice_overlap",
+            Self::CreateDataSegmentsWithEncoding => "data_segments_with_encoding",
+            Self::CreateAccountsDiffWithDataOptions => "accounts_diff_with_data_options",
+            Self::CreateJsonWithEnvelope => "json_with_envelope",
+            Self::CreateDataFieldsWithoutOwner => "data_fields_without_owner",
+            Self::CreateDataFieldUnknown { .. } => "data_field_unknown",
+            Self::CreateDataFieldMismatch(_) => "data_field_mismatch",
+            Self::CreateStatsInterval { .. } => "stats_interval",
+            Self::CreateTransactionsAccountCountRange => "transactions_account_count_range",
+            Self::CreateTransactionsErrorMatch(_) => "transactions_error_match",
+            Self::CreateEntriesSlotRange => "entries_slot_range",
+        }
+    }
 }
 
 pub type FilterResult<T> = Result<T, FilterError>;
 
+/// First failed predicate of the filter for the update
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum FilterMismatch {
+    #[error("nonempty_txn_signature not satisfied")]
+    NonemptyTxnSignature,
+    #[error("account not satisfied")]
+    Account,
+    #[error("owner is not allowed by the server")]
+    OwnerAllowlist,
+    #[error("owner not satisfied")]
+    Owner,
+    #[error("data_size_max exceeded")]
+    DataSizeMax,
+    #[error("native_program_owned not satisfied")]
+    NativeProgramOwned,
+    #[error("executable not satisfied")]
+    Executable,
+    #[error("datasize mismatch")]
+    Datasize,
+    #[error("lamports mismatch")]
+    Lamports,
+    #[error("rent_epoch mismatch")]
+    RentEpoch,
+    #[error("data_equals mismatch")]
+    DataEquals,
+    #[error("memcmp mismatch at offset {offset}")]
+    Memcmp { offset: usize },
+    #[error("token_account_state not satisfied")]
+    TokenAccountState,
+    #[error("checksum mismatch")]
+    Checksum,
+    #[error("vote mismatch")]
+    Vote,
+    #[error("failed mismatch")]
+    Failed,
+    #[error("signature mismatch")]
+    Signature,
+    #[error("account_include not satisfied")]
+    AccountInclude,
+    #[error("account_exclude matched")]
+    AccountExclude,
+    #[error("account_required not satisfied")]
+    AccountRequired,
+    #[error("signer_include not satisfied")]
+    SignerInclude,
+    #[error("program_include not satisfied")]
+    ProgramInclude,
+    #[error("cpi_program_include not satisfied")]
+    CpiProgramInclude,
+    #[error("account_count_min not satisfied")]
+    AccountCountMin,
+    #[error("account_count_max exceeded")]
+    AccountCountMax,
+    #[error("error_match not satisfied")]
+    ErrorMatch,
+    #[error("vote transaction excluded by exclude_votes")]
+    ExcludeVotes,
+}
+
+/// Result of the named filter evaluation against a sample update, see `Filter::explain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterExplain {
+    pub kind: &'static str,
+    pub name: FilterName,
+    pub mismatch: Option<FilterMismatch>,
+}
+
 macro_rules! filtered_updates_once_owned {
     ($filters:ident, $message:expr, $created_at:expr) => {{
         let mut messages = FilteredUpdates::new();
@@ -107,9 +276,15 @@ pub struct Filter {
     entries: FilterEntries,
     blocks: FilterBlocks,
     blocks_meta: FilterBlocksMeta,
+    program_events: FilterProgramEvents,
     commitment: CommitmentLevel,
     accounts_data_slice: FilterAccountsDataSlice,
     ping: Option<i32>,
+    envelope: bool,
+    json: bool,
+    stats: Option<FilterStats>,
+    min_commitment: FilterLimitsMinCommitment,
+    exclude_votes: bool,
 }
 
 impl Default for Filter {
@@ -120,17 +295,25 @@ impl Default for Filter {
             transactions: FilterTransactions {
                 filter_type: FilterTransactionsType::Transaction,
                 filters: HashMap::new(),
+                dedup: FilterTransactionsDedup::default(),
             },
             transactions_status: FilterTransactions {
                 filter_type: FilterTransactionsType::TransactionStatus,
                 filters: HashMap::new(),
+                dedup: FilterTransactionsDedup::default(),
             },
             entries: FilterEntries::default(),
             blocks: FilterBlocks::default(),
             blocks_meta: FilterBlocksMeta::default(),
+            program_events: FilterProgramEvents::default(),
             commitment: CommitmentLevel::Processed,
             accounts_data_slice: FilterAccountsDataSlice::default(),
             ping: None,
+            envelope: false,
+            json: false,
+            stats: None,
+            min_commitment: FilterLimitsMinCommitment::default(),
+            exclude_votes: false,
         }
     }
 }
@@ -141,33 +324,95 @@ impl Filter {
         limits: &FilterLimits,
         names: &mut FilterNames,
     ) -> FilterResult<Self> {
+        let accounts_data_encoding =
+            Self::decode_accounts_data_encoding(config.accounts_data_encoding)?;
+        if config.accounts_data_segments.unwrap_or_default()
+            && accounts_data_encoding != AccountsDataEncoding::Raw
+        {
+            return Err(FilterError::CreateDataSegmentsWithEncoding);
+        }
+        if config
+            .accounts
+            .values()
+            .any(|filter| filter.diff.unwrap_or_default())
+            && (!config.accounts_data_slice.is_empty()
+                || accounts_data_encoding != AccountsDataEncoding::Raw
+                || config.accounts_data_segments.unwrap_or_default())
+        {
+            return Err(FilterError::CreateAccountsDiffWithDataOptions);
+        }
+        let json = Self::decode_updates_encoding(config.encoding)? == UpdatesEncoding::Json;
+        if json && config.envelope.unwrap_or_default() {
+            return Err(FilterError::CreateJsonWithEnvelope);
+        }
         Ok(Self {
-            accounts: FilterAccounts::new(&config.accounts, &limits.accounts, names)?,
+            accounts: FilterAccounts::new(
+                &config.accounts,
+                &limits.accounts,
+                &limits.caches,
+                accounts_data_encoding,
+                config.accounts_data_segments.unwrap_or_default(),
+                names,
+            )?,
             slots: FilterSlots::new(&config.slots, &limits.slots, names)?,
             transactions: FilterTransactions::new(
                 &config.transactions,
                 &limits.transactions,
+                &limits.caches,
                 FilterTransactionsType::Transaction,
                 names,
             )?,
             transactions_status: FilterTransactions::new(
                 &config.transactions_status,
                 &limits.transactions_status,
+                &limits.caches,
                 FilterTransactionsType::TransactionStatus,
                 names,
             )?,
             entries: FilterEntries::new(&config.entry, &limits.entries, names)?,
-            blocks: FilterBlocks::new(&config.blocks, &limits.blocks, names)?,
+            blocks: FilterBlocks::new(&config.blocks, &limits.blocks, &limits.transactions, names)?,
             blocks_meta: FilterBlocksMeta::new(&config.blocks_meta, &limits.blocks_meta, names)?,
+            program_events: FilterProgramEvents::new(
+                &config.program_events,
+                &limits.program_events,
+                names,
+            )?,
             commitment: Self::decode_commitment(config.commitment)?,
             accounts_data_slice: FilterAccountsDataSlice::new(
                 &config.accounts_data_slice,
                 limits.accounts.data_slice_max,
-            )?,
+            )?
+            .with_encoding(accounts_data_encoding)
+            .with_segments(config.accounts_data_segments.unwrap_or_default()),
             ping: config.ping.as_ref().map(|msg| msg.id),
+            envelope: config.envelope.unwrap_or_default(),
+            json,
+            stats: config.stats_interval_ms.map(FilterStats::new).transpose()?,
+            min_commitment: limits.min_commitment_per_type,
+            exclude_votes: config.exclude_votes.unwrap_or_default(),
         })
     }
 
+    fn decode_accounts_data_encoding(encoding: Option<i32>) -> FilterResult<AccountsDataEncoding> {
+        encoding
+            .map(|encoding| {
+                AccountsDataEncoding::try_from(encoding)
+                    .map_err(|_error| FilterError::InvalidAccountsDataEncoding { encoding })
+            })
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    fn decode_updates_encoding(encoding: Option<i32>) -> FilterResult<UpdatesEncoding> {
+        encoding
+            .map(|encoding| {
+                UpdatesEncoding::try_from(encoding)
+                    .map_err(|_error| FilterError::InvalidUpdatesEncoding { encoding })
+            })
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
     fn decode_commitment(commitment: Option<i32>) -> FilterResult<CommitmentLevel> {
         let commitment = commitment.unwrap_or(CommitmentLevelProto::Processed as i32);
         let commitment = CommitmentLevelProto::try_from(commitment)
@@ -203,7 +448,7 @@ impl Filter {
         Self::decode_pubkeys(pubkeys, limit).collect::<FilterResult<_>>()
     }
 
-    pub fn get_metrics(&self) -> [(&'static str, usize); 8] {
+    pub fn get_metrics(&self) -> [(&'static str, usize); 9] {
         [
             ("accounts", self.accounts.filters.len()),
             ("slots", self.slots.filters.len()),
@@ -215,6 +460,7 @@ impl Filter {
             ("entries", self.entries.filters.len()),
             ("blocks", self.blocks.filters.len()),
             ("blocks_meta", self.blocks_meta.filters.len()),
+            ("program_events", self.program_events.filters_len()),
             (
                 "all",
                 self.accounts.filters.len()
@@ -223,7 +469,8 @@ impl Filter {
                     + self.transactions_status.filters.len()
                     + self.entries.filters.len()
                     + self.blocks.filters.len()
-                    + self.blocks_meta.filters.len(),
+                    + self.blocks_meta.filters.len()
+                    + self.program_events.filters_len(),
             ),
         ]
     }
@@ -232,30 +479,318 @@ impl Filter {
         self.commitment
     }
 
-    pub fn get_updates(
+    /// Returns `true` if messages broadcasted with the commitment produce updates,
+    /// commitment of some updates can be raised by the server
+    pub fn is_commitment_level_used(&self, commitment: CommitmentLevel) -> bool {
+        commitment == self.commitment
+            || ((!self.transactions.filters.is_empty()
+                || !self.transactions_status.filters.is_empty()
+                || !self.program_events.filters.is_empty())
+                && self.get_min_commitment(self.min_commitment.transaction) == commitment)
+            || (!self.blocks.filters.is_empty()
+                && self.get_min_commitment(self.min_commitment.block) == commitment)
+            || (!self.blocks_meta.filters.is_empty()
+                && self.get_min_commitment(self.min_commitment.block_meta) == commitment)
+    }
+
+    fn get_min_commitment(&self, min: Option<CommitmentLevel>) -> CommitmentLevel {
+        min.map_or(self.commitment, |min| min.max(self.commitment))
+    }
+
+    fn is_commitment_match(
         &self,
+        min: Option<CommitmentLevel>,
+        commitment: Option<CommitmentLevel>,
+    ) -> bool {
+        commitment.unwrap_or(self.commitment) == self.get_min_commitment(min)
+    }
+
+    pub fn get_updates(
+        &mut self,
         message: &Message,
         commitment: Option<CommitmentLevel>,
     ) -> FilteredUpdates {
-        match message {
-            Message::Account(message) => self
-                .accounts
-                .get_updates(message, &self.accounts_data_slice),
-            Message::Slot(message) => self.slots.get_updates(message, commitment),
+        // messages are broadcasted for every commitment, updates with raised commitment are
+        // produced only from messages of that commitment, the rest from the request commitment
+        let is_request_commitment = self.is_commitment_match(None, commitment);
+        let mut updates = match message {
+            Message::Account(message) if is_request_commitment => {
+                self.accounts
+                    .get_updates(message, self.commitment, &self.accounts_data_slice)
+            }
+            Message::Slot(message) if is_request_commitment => {
+                self.entries.update_slot(message);
+                self.accounts.update_slot_tx_statuses(message);
+                let mut updates = self.accounts.get_dedup_by_slot_updates(
+                    message,
+                    self.commitment,
+                    &self.accounts_data_slice,
+                );
+                updates.append(
+                    &mut self
+                        .accounts
+                        .get_held_updates(message, &self.accounts_data_slice),
+                );
+                updates.append(&mut self.slots.get_updates(message, commitment));
+                updates
+            }
             Message::Transaction(message) => {
-                let mut updates = self.transactions.get_updates(message);
-                updates.append(&mut self.transactions_status.get_updates(message));
+                let mut updates = if is_request_commitment {
+                    self.accounts
+                        .get_transaction_updates(message, &self.accounts_data_slice)
+                } else {
+                    FilteredUpdates::new()
+                };
+                if self.is_commitment_match(self.min_commitment.transaction, commitment) {
+                    let is_excluded_vote = self.exclude_votes && message.transaction.is_vote;
+                    if !is_excluded_vote {
+                        updates.append(&mut self.transactions.get_updates(message));
+                    }
+                    updates.append(&mut self.transactions_status.get_updates(message));
+                    if !is_excluded_vote {
+                        updates.append(&mut self.program_events.get_updates(message));
+                    }
+                }
+                updates
+            }
+            Message::Entry(message) if is_request_commitment => self.entries.get_updates(message),
+            Message::Block(message)
+                if self.is_commitment_match(self.min_commitment.block, commitment) =>
+            {
+                self.blocks
+                    .get_updates(message, &self.accounts_data_slice, self.exclude_votes)
+            }
+            Message::BlockMeta(message) => {
+                let mut updates = if is_request_commitment {
+                    self.entries.get_block_meta_updates(message)
+                } else {
+                    FilteredUpdates::new()
+                };
+                if self.is_commitment_match(self.min_commitment.block_meta, commitment) {
+                    updates.append(&mut self.blocks_meta.get_updates(message));
+                }
                 updates
             }
-            Message::Entry(message) => self.entries.get_updates(message),
-            Message::Block(message) => self.blocks.get_updates(message, &self.accounts_data_slice),
-            Message::BlockMeta(message) => self.blocks_meta.get_updates(message),
+            _ => FilteredUpdates::new(),
+        };
+        if let Some(stats) = &mut self.stats {
+            updates.retain(|update| !stats.push(update));
+        }
+        updates
+    }
+
+    /// Evaluates account and transaction filters against the update without changing the filter
+    /// state, `None` for other updates. Stateful options (`dedup_by_slot`, `new_accounts_only`,
+    /// `dedup_signatures` and etc) and commitment are not taken into account
+    pub fn explain(&self, message: &Message) -> Option<Vec<FilterExplain>> {
+        let mut explains = match message {
+            Message::Account(message) => self.accounts.explain(message),
+            Message::Transaction(message) => {
+                let mut explains = self.transactions.explain(message);
+                if self.exclude_votes && message.transaction.is_vote {
+                    for explain in explains.iter_mut() {
+                        explain.mismatch = Some(FilterMismatch::ExcludeVotes);
+                    }
+                }
+                explains.append(&mut self.transactions_status.explain(message));
+                explains
+            }
+            _ => return None,
+        };
+        explains.sort_by(|a, b| (a.kind, a.name.as_ref()).cmp(&(b.kind, b.name.as_ref())));
+        Some(explains)
+    }
+
+    /// Wraps the update into `envelope` or renders it as JSON if requested
+    fn wrap_message(&self, message: FilteredUpdateOneof) -> FilteredUpdateOneof {
+        if self.envelope {
+            message.as_envelope()
+        } else if self.json {
+            message.as_json()
+        } else {
+            message
+        }
+    }
+
+    /// Wraps the update from [`Filter::get_updates`], applied once rate limiting, sequence
+    /// numbers and out-of-band data are set on the plain update
+    pub fn wrap_update(&self, update: &mut FilteredUpdate) {
+        if self.envelope || self.json {
+            let message = mem::replace(&mut update.message, FilteredUpdateOneof::Ping);
+            update.message = self.wrap_message(message);
+        }
+    }
+
+    fn wrap_updates(&self, updates: &mut FilteredUpdates) {
+        for update in updates.iter_mut() {
+            self.wrap_update(update);
         }
     }
 
     pub fn get_pong_msg(&self) -> Option<FilteredUpdate> {
         self.ping
-            .map(|id| FilteredUpdate::new_empty(FilteredUpdateOneof::pong(id)))
+            .map(|id| FilteredUpdate::new_empty(self.wrap_message(FilteredUpdateOneof::pong(id))))
+    }
+
+    /// Max number of the latest finalized slots requested with `replay_finalized`
+    pub fn get_slots_replay_finalized(&self) -> usize {
+        self.slots
+            .filters
+            .values()
+            .filter_map(|inner| inner.replay_finalized)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Updates of the latest finalized slots (sorted by slot) sent before live updates
+    pub fn get_slots_replay_finalized_updates(&self, messages: &[MessageSlot]) -> FilteredUpdates {
+        let mut updates = self
+            .slots
+            .get_replay_finalized_updates(messages, self.commitment);
+        self.wrap_updates(&mut updates);
+        updates
+    }
+
+    pub const fn is_json(&self) -> bool {
+        self.json
+    }
+
+    pub fn is_accounts_snapshot(&self) -> bool {
+        !self.accounts.snapshot.is_empty()
+    }
+
+    /// Updates for an account from the snapshot, only filters with `snapshot` are matched,
+    /// updates are wrapped with [`Filter::wrap_update`] as live updates
+    pub fn get_snapshot_updates(&self, message: &MessageAccount) -> FilteredUpdates {
+        let mut filters = self.accounts.get_filters(message);
+        filters.retain(|name| self.accounts.snapshot.contains(name));
+
+        let mut updates = FilteredUpdates::new();
+        // `snapshot` can't be used with `diff`
+        self.accounts.push_updates_sliced(
+            &mut updates,
+            filters,
+            message,
+            &self.accounts_data_slice,
+            None,
+        );
+        updates
+    }
+
+    /// Completion of the snapshot, wrapped with [`Filter::wrap_update`] as other snapshot updates
+    pub fn get_snapshot_complete_msg(&self, accounts: u64, slot: Slot) -> FilteredUpdate {
+        let mut filters = self
+            .accounts
+            .snapshot
+            .iter()
+            .cloned()
+            .collect::<FilteredUpdateFilters>();
+        filters.sort_unstable_by(|a, b| a.as_ref().cmp(b.as_ref()));
+
+        FilteredUpdate {
+            filters,
+            ..FilteredUpdate::new_empty(FilteredUpdateOneof::snapshot_complete(accounts, slot))
+        }
+    }
+
+    /// Filter counts and commitment of the accepted request, sent once at stream start
+    pub fn get_subscribe_accepted_msg(&self) -> FilteredUpdate {
+        let message = FilteredUpdateOneof::subscribe_accepted(&self.get_metrics(), self.commitment);
+        FilteredUpdate::new_empty(self.wrap_message(message))
+    }
+
+    pub fn get_server_shutdown_msg(&self) -> FilteredUpdate {
+        FilteredUpdate::new_empty(self.wrap_message(FilteredUpdateOneof::server_shutdown()))
+    }
+
+    pub fn get_stats_interval(&self) -> Option<Duration> {
+        self.stats.as_ref().map(|stats| stats.interval)
+    }
+
+    /// Aggregates collected since the previous call, sent even if nothing matched
+    pub fn get_stats_msg(&mut self) -> Option<FilteredUpdate> {
+        let message = FilteredUpdateOneof::FilterStats(self.stats.as_mut()?.flush());
+        Some(FilteredUpdate::new_empty(self.wrap_message(message)))
+    }
+}
+
+/// Per filter aggregates of accounts and transactions matches, updates are not sent
+#[derive(Debug, Clone)]
+struct FilterStats {
+    interval: Duration,
+    filters: HashMap<FilterName, FilterStatsEntry>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct FilterStatsEntry {
+    count: u64,
+    bytes: u64,
+    pubkeys: HashSet<Pubkey>,
+}
+
+impl FilterStats {
+    const INTERVAL_MIN_MS: u64 = 1_000;
+
+    fn new(interval_ms: u64) -> FilterResult<Self> {
+        if interval_ms < Self::INTERVAL_MIN_MS {
+            return Err(FilterError::CreateStatsInterval {
+                min: Self::INTERVAL_MIN_MS,
+            });
+        }
+        Ok(Self {
+            interval: Duration::from_millis(interval_ms),
+            filters: HashMap::new(),
+        })
+    }
+
+    /// Accumulate accounts and transactions updates, returns `false` for other updates
+    fn push(&mut self, update: &FilteredUpdate) -> bool {
+        if !matches!(
+            update.message,
+            FilteredUpdateOneof::Account(_)
+                | FilteredUpdateOneof::Transaction(_)
+                | FilteredUpdateOneof::TransactionStatus(_)
+        ) {
+            return false;
+        }
+
+        let bytes = update.message.encoded_len() as u64;
+        for name in update.filters.iter() {
+            let entry = self.filters.entry(name.clone()).or_default();
+            entry.count += 1;
+            entry.bytes += bytes;
+            match &update.message {
+                FilteredUpdateOneof::Account(msg) => {
+                    entry.pubkeys.insert(msg.account.pubkey);
+                }
+                FilteredUpdateOneof::Transaction(msg) => {
+                    entry.pubkeys.extend(&msg.transaction.account_keys);
+                }
+                FilteredUpdateOneof::TransactionStatus(msg) => {
+                    entry.pubkeys.extend(&msg.transaction.account_keys);
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+
+    fn flush(&mut self) -> SubscribeUpdateFilterStats {
+        let mut filters = self
+            .filters
+            .drain()
+            .map(|(name, entry)| SubscribeUpdateFilterStatsEntry {
+                filter: name.as_ref().to_owned(),
+                count: entry.count,
+                bytes: entry.bytes,
+                unique_pubkeys: entry.pubkeys.len() as u64,
+            })
+            .collect::<Vec<_>>();
+        filters.sort_unstable_by(|a, b| a.filter.cmp(&b.filter));
+        SubscribeUpdateFilterStats {
+            interval_ms: self.interval.as_millis() as u64,
+            filters,
+        }
     }
 }
 
@@ -267,18 +802,252 @@ struct FilterAccounts {
     account_required: HashSet<FilterName>,
     owner: HashMap<Pubkey, HashSet<FilterName>>,
     owner_required: HashSet<FilterName>,
+    // server policy from limits, empty set allows any owner
+    owner_allowlist: HashSet<Pubkey>,
     filters: Vec<(FilterName, FilterAccountsState)>,
+    // indexes of `filters` scoped on the owner and without owner constraint, sorted
+    filters_by_owner: HashMap<Pubkey, Vec<usize>>,
+    filters_any_owner: Vec<usize>,
+    dedup_by_slot: HashSet<FilterName>,
+    dedup_by_slot_buffer: BTreeMap<Slot, HashMap<Pubkey, MessageAccount>>,
+    deliver_at_commitment: HashMap<FilterName, CommitmentLevel>,
+    held_buffer: BTreeMap<Slot, Vec<FilterAccountsHeld>>,
+    data_slices: HashMap<FilterName, FilterAccountsDataSlice>,
+    only_from_successful_tx: HashSet<FilterName>,
+    // transactions statuses and updates waiting for the transaction, kept until slot is finalized
+    tx_statuses: BTreeMap<Slot, HashMap<Signature, bool>>,
+    tx_pending: BTreeMap<Slot, HashMap<Signature, Vec<(MessageAccount, FilteredUpdateFilters)>>>,
+    new_accounts_only: HashSet<FilterName>,
+    new_accounts_seen: FilterAccountsSeen<()>,
+    on_owner_change: HashSet<FilterName>,
+    owners_seen: FilterAccountsSeen<Pubkey>,
+    // filters with `on_data_change_only`, value is `on_data_change_include_lamports_owner`
+    on_data_change_only: HashMap<FilterName, bool>,
+    data_seen: FilterAccountsSeen<FilterAccountsDataSeen>,
+    data_size_max: Vec<(FilterName, u64)>,
+    native_program_owned: Vec<(FilterName, bool)>,
+    executable: Vec<(FilterName, bool)>,
+    decompress_data: HashMap<FilterName, DecompressCodec>,
+    snapshot: HashSet<FilterName>,
+    diff: HashSet<FilterName>,
+    diff_seen: FilterAccountsSeen<FilterAccountsDiffSeen>,
+    diff_full_update_interval: usize,
+}
+
+/// Approximate memory used by one cache, accounted in the budget shared by all connections
+#[derive(Debug)]
+struct FilterCacheMemory {
+    memory: FilterCachesMemory,
+    memory_max: usize,
+    used: usize,
+}
+
+impl Default for FilterCacheMemory {
+    fn default() -> Self {
+        Self::new(&FilterLimitsCaches::default())
+    }
+}
+
+impl Clone for FilterCacheMemory {
+    fn clone(&self) -> Self {
+        self.memory.add(self.used);
+        if self.used > 0 {
+            self.memory.cache_inc();
+        }
+        Self {
+            memory: self.memory.clone(),
+            memory_max: self.memory_max,
+            used: self.used,
+        }
+    }
+}
+
+impl Drop for FilterCacheMemory {
+    fn drop(&mut self) {
+        self.memory.sub(self.used);
+        if self.used > 0 {
+            self.memory.cache_dec();
+        }
+    }
+}
+
+impl FilterCacheMemory {
+    fn new(limits: &FilterLimitsCaches) -> Self {
+        Self {
+            memory: limits.memory.clone(),
+            memory_max: limits.memory_max,
+            used: 0,
+        }
+    }
+
+    fn add(&mut self, bytes: usize) {
+        if self.used == 0 && bytes > 0 {
+            self.memory.cache_inc();
+        }
+        self.used += bytes;
+        self.memory.add(bytes);
+    }
+
+    fn sub(&mut self, bytes: usize) {
+        self.used -= bytes;
+        self.memory.sub(bytes);
+        if self.used == 0 && bytes > 0 {
+            self.memory.cache_dec();
+        }
+    }
+
+    /// Shared budget is exceeded and the cache uses more than its even share of the budget,
+    /// so the biggest caches are evicted first and small caches of other connections are kept
+    fn is_exceeded(&self) -> bool {
+        self.memory.used() > self.memory_max && self.used > self.memory.fair_share(self.memory_max)
+    }
+}
+
+/// Accounts already seen on the connection with a value from the last write,
+/// least recently written are evicted first
+#[derive(Debug, Clone)]
+struct FilterAccountsSeen<T> {
+    max: usize,
+    seq: u64,
+    accounts: HashMap<Pubkey, (u64, T)>,
+    order: BTreeMap<u64, Pubkey>,
+    memory: FilterCacheMemory,
+    // heap memory owned by the value
+    value_size: fn(&T) -> usize,
+}
+
+impl<T> Default for FilterAccountsSeen<T> {
+    fn default() -> Self {
+        Self::new(0, &FilterLimitsCaches::default())
+    }
+}
+
+impl<T> FilterAccountsSeen<T> {
+    // entry in both maps, without allocator and hashbrown overhead
+    const ENTRY_SIZE: usize = size_of::<(Pubkey, (u64, T))>() + size_of::<(u64, Pubkey)>();
+
+    fn new(max: usize, limits: &FilterLimitsCaches) -> Self {
+        Self::with_value_size(max, limits, |_value| 0)
+    }
+
+    fn with_value_size(
+        max: usize,
+        limits: &FilterLimitsCaches,
+        value_size: fn(&T) -> usize,
+    ) -> Self {
+        Self {
+            max,
+            seq: 0,
+            accounts: HashMap::new(),
+            order: BTreeMap::new(),
+            memory: FilterCacheMemory::new(limits),
+            value_size,
+        }
+    }
+
+    fn get(&self, pubkey: &Pubkey) -> Option<&T> {
+        self.accounts.get(pubkey).map(|(_seq, value)| value)
+    }
+
+    /// Returns value of the previous write, `None` if the account was not seen before
+    fn insert(&mut self, pubkey: Pubkey, value: T) -> Option<T> {
+        self.seq += 1;
+        self.memory.add((self.value_size)(&value));
+        let prev = self
+            .accounts
+            .insert(pubkey, (self.seq, value))
+            .map(|(seq, value)| {
+                self.order.remove(&seq);
+                value
+            });
+        self.order.insert(self.seq, pubkey);
+        match &prev {
+            Some(prev) => self.memory.sub((self.value_size)(prev)),
+            None => self.memory.add(Self::ENTRY_SIZE),
+        }
+
+        // the inserted account is the last in the order and is not evicted by the memory budget
+        while self.accounts.len() > self.max
+            || (self.accounts.len() > 1 && self.memory.is_exceeded())
+        {
+            let Some((_seq, pubkey)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some((_seq, value)) = self.accounts.remove(&pubkey) {
+                self.memory
+                    .sub(Self::ENTRY_SIZE + (self.value_size)(&value));
+            }
+        }
+
+        prev
+    }
+}
+
+/// Last write of the account tracked by `on_data_change_only`
+#[derive(Debug, Clone, Copy)]
+struct FilterAccountsDataSeen {
+    data_hash: u64,
+    lamports: u64,
+    owner: Pubkey,
+}
+
+impl FilterAccountsDataSeen {
+    fn new(account: &MessageAccountInfo) -> Self {
+        let mut hasher = DefaultHasher::new();
+        account.data.hash(&mut hasher);
+        Self {
+            data_hash: hasher.finish(),
+            lamports: account.lamports,
+            owner: account.owner,
+        }
+    }
+}
+
+/// Last update of the account sent to filters with `diff`
+#[derive(Debug, Clone)]
+struct FilterAccountsDiffSeen {
+    account: Arc<MessageAccountInfo>,
+    // diffs sent since the last update with the whole data
+    diffs: usize,
+}
+
+impl FilterAccountsDiffSeen {
+    fn data_size(&self) -> usize {
+        self.account.data.len()
+    }
+}
+
+/// Account update held until the slot reaches commitment required by filters
+#[derive(Debug, Clone)]
+struct FilterAccountsHeld {
+    message: MessageAccount,
+    filters: Vec<(CommitmentLevel, FilterName)>,
 }
 
 impl FilterAccounts {
     fn new(
         configs: &HashMap<String, SubscribeRequestFilterAccounts>,
         limits: &FilterLimitsAccounts,
+        limits_caches: &FilterLimitsCaches,
+        data_encoding: AccountsDataEncoding,
+        data_segments: bool,
         names: &mut FilterNames,
     ) -> FilterResult<Self> {
         FilterLimits::check_max(configs.len(), limits.max)?;
 
-        let mut this = Self::default();
+        let mut this = Self {
+            new_accounts_seen: FilterAccountsSeen::new(limits.new_accounts_seen_max, limits_caches),
+            owners_seen: FilterAccountsSeen::new(limits.owner_change_seen_max, limits_caches),
+            data_seen: FilterAccountsSeen::new(limits.data_change_seen_max, limits_caches),
+            diff_seen: FilterAccountsSeen::with_value_size(
+                limits.diff_seen_max,
+                limits_caches,
+                FilterAccountsDiffSeen::data_size,
+            ),
+            diff_full_update_interval: limits.diff_full_update_interval,
+            owner_allowlist: limits.owner_allowlist.clone(),
+            ..Default::default()
+        };
         for (name, filter) in configs {
             this.nonempty_txn_signature
                 .push((names.get(name)?, filter.nonempty_txn_signature));
@@ -310,12 +1079,196 @@ impl FilterAccounts {
                 Filter::decode_pubkeys(&filter.owner, &limits.owner_reject),
             )?;
 
-            this.filters
-                .push((names.get(name)?, FilterAccountsState::new(&filter.filters)?));
+            let mut state = FilterAccountsState::new(&filter.filters, limits)?;
+            state.rent_epoch = FilterAccountsRentEpoch::new(
+                filter.rent_epoch_eq,
+                filter.rent_epoch_gt,
+                filter.rent_epoch_lt,
+            )?;
+            this.filters.push((names.get(name)?, state));
+            if filter.dedup_by_slot.unwrap_or_default() {
+                this.dedup_by_slot.insert(names.get(name)?);
+            }
+            if let Some(commitment) = filter.deliver_at_commitment {
+                if filter.dedup_by_slot.unwrap_or_default() {
+                    return Err(FilterError::CreateAccountState(
+                        "`dedup_by_slot` can't be used with `deliver_at_commitment`",
+                    ));
+                }
+                this.deliver_at_commitment.insert(
+                    names.get(name)?,
+                    Filter::decode_commitment(Some(commitment))?,
+                );
+            }
+            if filter.only_from_successful_tx.unwrap_or_default() {
+                if filter.dedup_by_slot.unwrap_or_default()
+                    || filter.deliver_at_commitment.is_some()
+                {
+                    return Err(FilterError::CreateAccountState(
+                        "`only_from_successful_tx` can't be used with `dedup_by_slot` or `deliver_at_commitment`",
+                    ));
+                }
+                this.only_from_successful_tx.insert(names.get(name)?);
+            }
+            if filter.new_accounts_only.unwrap_or_default() {
+                if filter.dedup_by_slot.unwrap_or_default()
+                    || filter.deliver_at_commitment.is_some()
+                    || filter.only_from_successful_tx.unwrap_or_default()
+                {
+                    return Err(FilterError::CreateAccountState(
+                        "`new_accounts_only` can't be used with `dedup_by_slot`, `deliver_at_commitment` or `only_from_successful_tx`",
+                    ));
+                }
+                this.new_accounts_only.insert(names.get(name)?);
+            }
+            if filter.on_owner_change.unwrap_or_default() {
+                if filter.dedup_by_slot.unwrap_or_default()
+                    || filter.deliver_at_commitment.is_some()
+                    || filter.only_from_successful_tx.unwrap_or_default()
+                    || filter.new_accounts_only.unwrap_or_default()
+                {
+                    return Err(FilterError::CreateAccountState(
+                        "`on_owner_change` can't be used with `dedup_by_slot`, `deliver_at_commitment`, `only_from_successful_tx` or `new_accounts_only`",
+                    ));
+                }
+                this.on_owner_change.insert(names.get(name)?);
+            }
+            if filter.on_data_change_only.unwrap_or_default() {
+                if filter.dedup_by_slot.unwrap_or_default()
+                    || filter.deliver_at_commitment.is_some()
+                    || filter.only_from_successful_tx.unwrap_or_default()
+                    || filter.new_accounts_only.unwrap_or_default()
+                    || filter.on_owner_change.unwrap_or_default()
+                {
+                    return Err(FilterError::CreateAccountState(
+                        "`on_data_change_only` can't be used with `dedup_by_slot`, `deliver_at_commitment`, `only_from_successful_tx`, `new_accounts_only` or `on_owner_change`",
+                    ));
+                }
+                this.on_data_change_only.insert(
+                    names.get(name)?,
+                    filter
+                        .on_data_change_include_lamports_owner
+                        .unwrap_or_default(),
+                );
+            } else if filter
+                .on_data_change_include_lamports_owner
+                .unwrap_or_default()
+            {
+                return Err(FilterError::CreateAccountState(
+                    "`on_data_change_include_lamports_owner` requires `on_data_change_only`",
+                ));
+            }
+            if let Some(data_size_max) = filter.data_size_max {
+                this.data_size_max.push((names.get(name)?, data_size_max));
+            }
+            if let Some(native_program_owned) = filter.native_program_owned {
+                this.native_program_owned
+                    .push((names.get(name)?, native_program_owned));
+            }
+            if let Some(executable) = filter.executable {
+                this.executable.push((names.get(name)?, executable));
+            }
+            if let Some(codec) = filter.decompress_data {
+                let codec = DecompressCodec::try_from(codec)
+                    .map_err(|_error| FilterError::InvalidDecompressCodec { codec })?;
+                this.decompress_data.insert(names.get(name)?, codec);
+            }
+            if filter.snapshot.unwrap_or_default() {
+                this.snapshot.insert(names.get(name)?);
+            }
+            if filter.diff.unwrap_or_default() {
+                if !filter.data_fields.is_empty()
+                    || filter.decompress_data.is_some()
+                    || filter.snapshot.unwrap_or_default()
+                {
+                    return Err(FilterError::CreateAccountState(
+                        "`diff` can't be used with `data_fields`, `decompress_data` or `snapshot`",
+                    ));
+                }
+                this.diff.insert(names.get(name)?);
+            }
+            if !filter.data_fields.is_empty() {
+                this.data_slices.insert(
+                    names.get(name)?,
+                    Self::decode_data_fields(&filter.data_fields, &filter.owner, limits)?
+                        .with_encoding(data_encoding)
+                        .with_segments(data_segments),
+                );
+            }
         }
+        this.build_owner_index();
         Ok(this)
     }
 
+    /// Data filters are evaluated only for filters of the account owner and filters
+    /// without owners, order of `filters` is kept
+    fn build_owner_index(&mut self) {
+        self.filters_any_owner = self
+            .filters
+            .iter()
+            .enumerate()
+            .filter(|(_index, (name, _state))| !self.owner_required.contains(name))
+            .map(|(index, _filter)| index)
+            .collect();
+        self.filters_by_owner = self
+            .owner
+            .iter()
+            .map(|(owner, names)| {
+                let indexes = self
+                    .filters
+                    .iter()
+                    .enumerate()
+                    .filter(|(_index, (name, _state))| names.contains(name))
+                    .map(|(index, _filter)| index)
+                    .collect();
+                (*owner, indexes)
+            })
+            .collect();
+    }
+
+    /// Expand named fields from owners schema into data slices
+    fn decode_data_fields(
+        fields: &[String],
+        owners: &[String],
+        limits: &FilterLimitsAccounts,
+    ) -> FilterResult<FilterAccountsDataSlice> {
+        if owners.is_empty() {
+            return Err(FilterError::CreateDataFieldsWithoutOwner);
+        }
+
+        let mut slices = Vec::with_capacity(fields.len());
+        for field in fields {
+            let mut slice = None;
+            for owner in owners {
+                let owner = Pubkey::from_str(owner)?;
+                let value = limits
+                    .data_fields
+                    .get(&owner)
+                    .and_then(|fields| fields.get(field))
+                    .ok_or_else(|| FilterError::CreateDataFieldUnknown {
+                        owner,
+                        field: field.clone(),
+                    })?;
+                match slice {
+                    Some(slice) if slice != *value => {
+                        return Err(FilterError::CreateDataFieldMismatch(field.clone()));
+                    }
+                    _ => slice = Some(*value),
+                }
+            }
+            if let Some(slice) = slice {
+                slices.push(SubscribeRequestAccountsDataSlice {
+                    offset: slice.offset,
+                    length: slice.length,
+                });
+            }
+        }
+        slices.sort_unstable_by_key(|slice| slice.offset);
+        slices.dedup();
+
+        FilterAccountsDataSlice::new(&slices, limits.data_slice_max)
+    }
+
     fn set(
         map: &mut HashMap<Pubkey, HashSet<FilterName>>,
         map_required: &mut HashSet<FilterName>,
@@ -336,22 +1289,531 @@ impl FilterAccounts {
         Ok(required)
     }
 
-    fn get_updates(
-        &self,
-        message: &MessageAccount,
-        accounts_data_slice: &FilterAccountsDataSlice,
-    ) -> FilteredUpdates {
+    fn is_owner_allowed(&self, owner: &Pubkey) -> bool {
+        self.owner_allowlist.is_empty() || self.owner_allowlist.contains(owner)
+    }
+
+    fn get_filters(&self, message: &MessageAccount) -> FilteredUpdateFilters {
+        if !self.is_owner_allowed(&message.account.owner) {
+            return FilteredUpdateFilters::new();
+        }
+
         let mut filter = FilterAccountsMatch::new(self);
         filter.match_txn_signature(&message.account.txn_signature);
         filter.match_account(&message.account.pubkey);
         filter.match_owner(&message.account.owner);
-        filter.match_data_lamports(&message.account.data, message.account.lamports);
-        let filters = filter.get_filters();
-        filtered_updates_once_owned!(
-            filters,
-            FilteredUpdateOneof::account(message, accounts_data_slice.clone()),
-            message.created_at
-        )
+        filter.match_data_size_max(message.account.data.len());
+        filter.match_native_program_owned(&message.account.owner);
+        filter.match_executable(message.account.executable);
+        filter.get_filters(&message.account)
+    }
+
+    fn explain(&self, message: &MessageAccount) -> Vec<FilterExplain> {
+        let mut filter = FilterAccountsMatch::new(self);
+        filter.match_txn_signature(&message.account.txn_signature);
+        filter.match_account(&message.account.pubkey);
+        filter.match_owner(&message.account.owner);
+        filter.match_data_size_max(message.account.data.len());
+        filter.match_native_program_owned(&message.account.owner);
+        filter.match_executable(message.account.executable);
+        self.filters
+            .iter()
+            .map(|(name, state)| FilterExplain {
+                kind: "accounts",
+                name: name.clone(),
+                mismatch: filter.check(name, state, &message.account).err(),
+            })
+            .collect()
+    }
+
+    /// Filters with `diff` share one update, other filters are grouped by data slices
+    fn push_updates(
+        &mut self,
+        updates: &mut FilteredUpdates,
+        mut filters: FilteredUpdateFilters,
+        message: &MessageAccount,
+        accounts_data_slice: &FilterAccountsDataSlice,
+        commitment: Option<CommitmentLevel>,
+    ) {
+        if !self.diff.is_empty() {
+            let mut filters_diff = FilteredUpdateFilters::new();
+            filters.retain(|name| {
+                if self.diff.contains(name) {
+                    filters_diff.push(name.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+            if !filters_diff.is_empty() {
+                self.push_diff_update(updates, filters_diff, message, commitment);
+            }
+        }
+        self.push_updates_sliced(updates, filters, message, accounts_data_slice, commitment);
+    }
+
+    /// The whole data is sent on the first update of the account, after eviction, every
+    /// `diff_full_update_interval` update and if the diff is not smaller than the data
+    fn push_diff_update(
+        &mut self,
+        updates: &mut FilteredUpdates,
+        filters: FilteredUpdateFilters,
+        message: &MessageAccount,
+        commitment: Option<CommitmentLevel>,
+    ) {
+        let diff = self
+            .diff_seen
+            .get(&message.account.pubkey)
+            .filter(|seen| seen.diffs + 1 < self.diff_full_update_interval)
+            .and_then(|seen| {
+                Self::get_data_diff(&seen.account.data, &message.account.data).map(|slices| {
+                    let data_diff = SubscribeUpdateAccountDataDiff {
+                        base_write_version: seen.account.write_version,
+                        data_len: message.account.data.len() as u64,
+                    };
+                    (seen.diffs + 1, data_diff, slices)
+                })
+            });
+        self.diff_seen.insert(
+            message.account.pubkey,
+            FilterAccountsDiffSeen {
+                account: Arc::clone(&message.account),
+                diffs: diff.as_ref().map_or(0, |(diffs, _, _)| *diffs),
+            },
+        );
+
+        let (data_slice, data_diff) = match diff {
+            Some((_diffs, data_diff, slices)) => (
+                FilterAccountsDataSlice::new_unchecked(Arc::new(slices)).with_segments(true),
+                Some(data_diff),
+            ),
+            None => (FilterAccountsDataSlice::default(), None),
+        };
+        let mut update = match commitment {
+            Some(commitment) => {
+                FilteredUpdateOneof::account_at_commitment(message, data_slice, commitment)
+            }
+            None => FilteredUpdateOneof::account(message, data_slice),
+        };
+        if let FilteredUpdateOneof::Account(account) = &mut update {
+            account.data_diff = data_diff;
+        }
+        updates.push(FilteredUpdate::new(filters, update, message.created_at));
+    }
+
+    /// Ranges of `data` changed against `base` including the tail beyond the base, ranges
+    /// separated by a few equal bytes are merged as every segment costs a few bytes to encode.
+    /// Returns `None` if the diff is not smaller than the data.
+    fn get_data_diff(base: &[u8], data: &[u8]) -> Option<Vec<Range<usize>>> {
+        const SEGMENT_OVERHEAD: usize = 8;
+
+        let mut slices: Vec<Range<usize>> = vec![];
+        let mut push = |slice: Range<usize>| match slices.last_mut() {
+            Some(last) if slice.start - last.end <= SEGMENT_OVERHEAD => last.end = slice.end,
+            _ => slices.push(slice),
+        };
+        let common = base.len().min(data.len());
+        let mut offset = 0;
+        while offset < common {
+            if base[offset] == data[offset] {
+                offset += 1;
+                continue;
+            }
+            let start = offset;
+            while offset < common && base[offset] != data[offset] {
+                offset += 1;
+            }
+            push(start..offset);
+        }
+        if data.len() > common {
+            push(common..data.len());
+        }
+        // segments are sent only for non-empty slices
+        if slices.is_empty() {
+            slices.push(0..0);
+        }
+
+        let size = slices
+            .iter()
+            .map(|slice| slice.len() + SEGMENT_OVERHEAD)
+            .sum::<usize>();
+        (size < data.len()).then_some(slices)
+    }
+
+    /// Push account update per distinct data slice and decompress codec of matched filters
+    fn push_updates_sliced(
+        &self,
+        updates: &mut FilteredUpdates,
+        filters: FilteredUpdateFilters,
+        message: &MessageAccount,
+        accounts_data_slice: &FilterAccountsDataSlice,
+        commitment: Option<CommitmentLevel>,
+    ) {
+        type Group<'a> = (
+            &'a FilterAccountsDataSlice,
+            Option<DecompressCodec>,
+            FilteredUpdateFilters,
+        );
+        let mut groups: Vec<Group> = vec![];
+        for name in filters {
+            let data_slice = self.data_slices.get(&name).unwrap_or(accounts_data_slice);
+            let codec = self.decompress_data.get(&name).copied();
+            match groups
+                .iter_mut()
+                .find(|(value, value_codec, _)| *value == data_slice && *value_codec == codec)
+            {
+                Some((_, _, filters)) => filters.push(name),
+                None => groups.push((
+                    data_slice,
+                    codec,
+                    FilteredUpdateFilters::from_vec(vec![name]),
+                )),
+            }
+        }
+
+        let data_len = message.account.data.len();
+        for (data_slice, codec, filters) in groups {
+            let (decompressed, decompress_failed) = match codec {
+                Some(codec) => match Self::decompress_data(codec, &message.account.data) {
+                    Some(data) => (
+                        Some(MessageAccount {
+                            account: Arc::new(MessageAccountInfo {
+                                pubkey: message.account.pubkey,
+                                lamports: message.account.lamports,
+                                owner: message.account.owner,
+                                executable: message.account.executable,
+                                rent_epoch: message.account.rent_epoch,
+                                data,
+                                write_version: message.account.write_version,
+                                txn_signature: message.account.txn_signature,
+                                // checksum of the raw data
+                                data_checksum: message.account.data_checksum,
+                            }),
+                            slot: message.slot,
+                            is_startup: message.is_startup,
+                            created_at: message.created_at,
+                        }),
+                        false,
+                    ),
+                    None => (None, true),
+                },
+                None => (None, false),
+            };
+            let message = decompressed.as_ref().unwrap_or(message);
+
+            let mut update = match commitment {
+                Some(commitment) => FilteredUpdateOneof::account_at_commitment(
+                    message,
+                    data_slice.clone(),
+                    commitment,
+                ),
+                None => FilteredUpdateOneof::account(message, data_slice.clone()),
+            };
+            if let FilteredUpdateOneof::Account(account) = &mut update {
+                account.data_decompress_failed = decompress_failed;
+                account.data_compressed_len = decompressed.is_some().then_some(data_len);
+            }
+            updates.push(FilteredUpdate::new(filters, update, message.created_at));
+        }
+    }
+
+    /// Decompressed data is limited by max account size
+    fn decompress_data(codec: DecompressCodec, data: &[u8]) -> Option<Vec<u8>> {
+        let max = MAX_PERMITTED_DATA_LENGTH as usize;
+        match codec {
+            DecompressCodec::Zstd => zstd::bulk::decompress(data, max).ok(),
+            DecompressCodec::Gzip => {
+                let mut decompressed = vec![];
+                flate2::read::GzDecoder::new(data)
+                    .take(max as u64 + 1)
+                    .read_to_end(&mut decompressed)
+                    .ok()?;
+                (decompressed.len() <= max).then_some(decompressed)
+            }
+        }
+    }
+
+    fn get_updates(
+        &mut self,
+        message: &MessageAccount,
+        commitment: CommitmentLevel,
+        accounts_data_slice: &FilterAccountsDataSlice,
+    ) -> FilteredUpdates {
+        let mut filters = self.get_filters(message);
+        let mut updates = FilteredUpdates::new();
+        if filters
+            .iter()
+            .any(|name| self.new_accounts_only.contains(name))
+            && self
+                .new_accounts_seen
+                .insert(message.account.pubkey, ())
+                .is_some()
+        {
+            filters.retain(|name| !self.new_accounts_only.contains(name));
+        }
+        if filters
+            .iter()
+            .any(|name| self.on_owner_change.contains(name))
+        {
+            let owner = self
+                .owners_seen
+                .insert(message.account.pubkey, message.account.owner);
+            if owner.map_or(true, |owner| owner == message.account.owner) {
+                filters.retain(|name| !self.on_owner_change.contains(name));
+            }
+        }
+        if filters
+            .iter()
+            .any(|name| self.on_data_change_only.contains_key(name))
+        {
+            let seen = FilterAccountsDataSeen::new(&message.account);
+            let (data_changed, lamports_owner_changed) = self
+                .data_seen
+                .insert(message.account.pubkey, seen)
+                .map_or((true, true), |prev| {
+                    (
+                        prev.data_hash != seen.data_hash,
+                        prev.lamports != seen.lamports || prev.owner != seen.owner,
+                    )
+                });
+            if !data_changed {
+                filters.retain(|name| match self.on_data_change_only.get(name) {
+                    Some(include_lamports_owner) => {
+                        *include_lamports_owner && lamports_owner_changed
+                    }
+                    None => true,
+                });
+            }
+        }
+        if !self.dedup_by_slot.is_empty() {
+            // once buffered, later writes are kept even if they do not match anymore,
+            // filters are evaluated again against the final state
+            let is_buffered = self
+                .dedup_by_slot_buffer
+                .get(&message.slot)
+                .map(|accounts| accounts.contains_key(&message.account.pubkey))
+                .unwrap_or(false);
+            if is_buffered || filters.iter().any(|name| self.dedup_by_slot.contains(name)) {
+                match self
+                    .dedup_by_slot_buffer
+                    .entry(message.slot)
+                    .or_default()
+                    .entry(message.account.pubkey)
+                {
+                    HashMapEntry::Occupied(mut entry) => {
+                        if entry.get().account.write_version < message.account.write_version {
+                            entry.insert(message.clone());
+                        }
+                    }
+                    HashMapEntry::Vacant(entry) => {
+                        entry.insert(message.clone());
+                    }
+                }
+            }
+            filters.retain(|name| !self.dedup_by_slot.contains(name));
+        }
+        if !self.deliver_at_commitment.is_empty() {
+            let mut filters_tagged = FilteredUpdateFilters::new();
+            let mut filters_held = vec![];
+            filters.retain(|name| match self.deliver_at_commitment.get(name) {
+                Some(filter_commitment) if *filter_commitment > commitment => {
+                    filters_held.push((*filter_commitment, name.clone()));
+                    false
+                }
+                Some(_) => {
+                    filters_tagged.push(name.clone());
+                    false
+                }
+                None => true,
+            });
+            self.push_updates(
+                &mut updates,
+                filters_tagged,
+                message,
+                accounts_data_slice,
+                Some(commitment),
+            );
+            if !filters_held.is_empty() {
+                self.held_buffer
+                    .entry(message.slot)
+                    .or_default()
+                    .push(FilterAccountsHeld {
+                        message: message.clone(),
+                        filters: filters_held,
+                    });
+            }
+        }
+        if let Some(signature) = message
+            .account
+            .txn_signature
+            .filter(|_signature| !self.only_from_successful_tx.is_empty())
+        {
+            let mut filters_tx = FilteredUpdateFilters::new();
+            filters.retain(|name| {
+                if self.only_from_successful_tx.contains(name) {
+                    filters_tx.push(name.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+            if !filters_tx.is_empty() {
+                let status = self
+                    .tx_statuses
+                    .get(&message.slot)
+                    .and_then(|statuses| statuses.get(&signature))
+                    .copied();
+                match status {
+                    Some(true) => filters.extend(filters_tx),
+                    Some(false) => {}
+                    None => self
+                        .tx_pending
+                        .entry(message.slot)
+                        .or_default()
+                        .entry(signature)
+                        .or_default()
+                        .push((message.clone(), filters_tx)),
+                }
+            }
+        }
+        self.push_updates(&mut updates, filters, message, accounts_data_slice, None);
+        updates
+    }
+
+    /// Emit accounts waiting for the transaction if it succeeded
+    fn get_transaction_updates(
+        &mut self,
+        message: &MessageTransaction,
+        accounts_data_slice: &FilterAccountsDataSlice,
+    ) -> FilteredUpdates {
+        let mut updates = FilteredUpdates::new();
+        if self.only_from_successful_tx.is_empty() {
+            return updates;
+        }
+
+        let signature = message.transaction.signature;
+        let is_success = message.transaction.meta.err.is_none();
+        self.tx_statuses
+            .entry(message.slot)
+            .or_default()
+            .insert(signature, is_success);
+
+        let pending = self
+            .tx_pending
+            .get_mut(&message.slot)
+            .and_then(|pending| pending.remove(&signature));
+        if let Some(pending) = pending.filter(|_pending| is_success) {
+            for (message, filters) in pending {
+                self.push_updates(&mut updates, filters, &message, accounts_data_slice, None);
+            }
+        }
+        updates
+    }
+
+    fn update_slot_tx_statuses(&mut self, message: &MessageSlot) {
+        if self.only_from_successful_tx.is_empty() {
+            return;
+        }
+
+        match message.status {
+            SlotStatus::Dead => {
+                self.tx_statuses.remove(&message.slot);
+                self.tx_pending.remove(&message.slot);
+            }
+            SlotStatus::Finalized => {
+                self.tx_statuses = self.tx_statuses.split_off(&(message.slot + 1));
+                self.tx_pending = self.tx_pending.split_off(&(message.slot + 1));
+            }
+            _ => {}
+        }
+    }
+
+    /// Emit buffered accounts once the slot reaches subscription commitment
+    fn get_dedup_by_slot_updates(
+        &mut self,
+        message: &MessageSlot,
+        commitment: CommitmentLevel,
+        accounts_data_slice: &FilterAccountsDataSlice,
+    ) -> FilteredUpdates {
+        let mut updates = FilteredUpdates::new();
+        if self.dedup_by_slot_buffer.is_empty() {
+            return updates;
+        }
+
+        if message.status == SlotStatus::Dead {
+            self.dedup_by_slot_buffer.remove(&message.slot);
+        } else if commitment == message.status {
+            if let Some(accounts) = self.dedup_by_slot_buffer.remove(&message.slot) {
+                let mut accounts = accounts.into_values().collect::<Vec<_>>();
+                accounts.sort_unstable_by_key(|message| message.account.write_version);
+                for message in accounts {
+                    let mut filters = self.get_filters(&message);
+                    filters.retain(|name| self.dedup_by_slot.contains(name));
+                    self.push_updates(&mut updates, filters, &message, accounts_data_slice, None);
+                }
+            }
+        }
+
+        // slots from abandoned forks never reach commitment
+        if message.status == SlotStatus::Finalized {
+            self.dedup_by_slot_buffer = self.dedup_by_slot_buffer.split_off(&(message.slot + 1));
+        }
+
+        updates
+    }
+
+    /// Emit held accounts tagged with the slot commitment once required commitment is reached
+    fn get_held_updates(
+        &mut self,
+        message: &MessageSlot,
+        accounts_data_slice: &FilterAccountsDataSlice,
+    ) -> FilteredUpdates {
+        let mut updates = FilteredUpdates::new();
+        if self.held_buffer.is_empty() {
+            return updates;
+        }
+
+        let commitment = match message.status {
+            SlotStatus::Confirmed => CommitmentLevel::Confirmed,
+            SlotStatus::Finalized => CommitmentLevel::Finalized,
+            SlotStatus::Dead => {
+                self.held_buffer.remove(&message.slot);
+                return updates;
+            }
+            _ => return updates,
+        };
+
+        if let Some(mut held_accounts) = self.held_buffer.remove(&message.slot) {
+            held_accounts.retain_mut(|held| {
+                let mut filters = FilteredUpdateFilters::new();
+                held.filters.retain(|(filter_commitment, name)| {
+                    if *filter_commitment <= commitment {
+                        filters.push(name.clone());
+                        false
+                    } else {
+                        true
+                    }
+                });
+                self.push_updates(
+                    &mut updates,
+                    filters,
+                    &held.message,
+                    accounts_data_slice,
+                    Some(commitment),
+                );
+                !held.filters.is_empty()
+            });
+            if !held_accounts.is_empty() {
+                self.held_buffer.insert(message.slot, held_accounts);
+            }
+        }
+
+        // slots from abandoned forks never reach commitment
+        if commitment == CommitmentLevel::Finalized {
+            self.held_buffer = self.held_buffer.split_off(&(message.slot + 1));
+        }
+
+        updates
     }
 }
 
@@ -361,10 +1823,16 @@ struct FilterAccountsState {
     datasize: Option<usize>,
     token_account_state: bool,
     lamports: Vec<FilterAccountsLamports>,
+    rent_epoch: Option<FilterAccountsRentEpoch>,
+    checksum: Option<FilterAccountsChecksum>,
+    data_equals: Option<Vec<u8>>,
 }
 
 impl FilterAccountsState {
-    fn new(filters: &[SubscribeRequestFilterAccountsFilter]) -> FilterResult<Self> {
+    fn new(
+        filters: &[SubscribeRequestFilterAccountsFilter],
+        limits: &FilterLimitsAccounts,
+    ) -> FilterResult<Self> {
         const MAX_FILTERS: usize = 4;
         const MAX_DATA_SIZE: usize = 128;
         const MAX_DATA_BASE58_SIZE: usize = 175;
@@ -432,11 +1900,45 @@ impl FilterAccountsState {
                     };
                     this.lamports.push(cmp.into());
                 }
+                Some(AccountsFilterDataOneof::Checksum(checksum)) => {
+                    let checksum = FilterAccountsChecksum::new(checksum)?;
+                    if this.checksum.replace(checksum).is_some() {
+                        return Err(FilterError::CreateAccountState(
+                            "checksum used more than once",
+                        ));
+                    }
+                }
+                Some(AccountsFilterDataOneof::DataEquals(data)) => {
+                    if data.len() > limits.data_equals_max {
+                        return Err(FilterError::CreateAccountStateDataEqualsMax {
+                            max: limits.data_equals_max,
+                        });
+                    }
+                    if this.data_equals.replace(data.clone()).is_some() {
+                        return Err(FilterError::CreateAccountState(
+                            "data_equals used more than once",
+                        ));
+                    }
+                }
                 None => {
                     return Err(FilterError::CreateAccountState("filter should be defined"));
                 }
             }
         }
+
+        // memcmp out of declared datasize would never match
+        if let Some(datasize) = this.datasize {
+            for (offset, data) in this.memcmp.iter() {
+                if offset.saturating_add(data.len()) > datasize {
+                    return Err(FilterError::CreateAccountStateMemcmpOutOfBounds {
+                        offset: *offset,
+                        length: data.len(),
+                        datasize,
+                    });
+                }
+            }
+        }
+
         Ok(this)
     }
 
@@ -445,28 +1947,40 @@ impl FilterAccountsState {
             && self.datasize.is_none()
             && !self.token_account_state
             && self.lamports.is_empty()
+            && self.rent_epoch.is_none()
+            && self.checksum.is_none()
+            && self.data_equals.is_none()
     }
 
-    fn is_match(&self, data: &[u8], lamports: u64) -> bool {
+    /// Checks are ordered by cost: size, lamports and rent epoch, data equality, memcmp, token
+    /// account parse, checksum
+    fn check(&self, data: &[u8], lamports: u64, rent_epoch: u64) -> Result<(), FilterMismatch> {
         if matches!(self.datasize, Some(datasize) if data.len() != datasize) {
-            return false;
-        }
-        if self.token_account_state && !TokenAccount::valid_account_data(data) {
-            return false;
+            return Err(FilterMismatch::Datasize);
         }
         if self.lamports.iter().any(|f| !f.is_match(lamports)) {
-            return false;
+            return Err(FilterMismatch::Lamports);
         }
-        for (offset, bytes) in self.memcmp.iter() {
-            if data.len() < *offset + bytes.len() {
-                return false;
+        if matches!(self.rent_epoch, Some(f) if !f.is_match(rent_epoch)) {
+            return Err(FilterMismatch::RentEpoch);
+        }
+        if let Some(data_equals) = &self.data_equals {
+            if data.len() != data_equals.len() || data != data_equals.as_slice() {
+                return Err(FilterMismatch::DataEquals);
             }
-            let data = &data[*offset..*offset + bytes.len()];
-            if data != bytes {
-                return false;
+        }
+        for (offset, bytes) in self.memcmp.iter() {
+            if data.get(*offset..offset.saturating_add(bytes.len())) != Some(bytes.as_slice()) {
+                return Err(FilterMismatch::Memcmp { offset: *offset });
             }
         }
-        true
+        if self.token_account_state && !TokenAccount::valid_account_data(data) {
+            return Err(FilterMismatch::TokenAccountState);
+        }
+        if matches!(&self.checksum, Some(checksum) if !checksum.is_match(data)) {
+            return Err(FilterMismatch::Checksum);
+        }
+        Ok(())
     }
 }
 
@@ -500,23 +2014,110 @@ impl FilterAccountsLamports {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FilterAccountsRentEpoch {
+    Eq(u64),
+    Gt(u64),
+    Lt(u64),
+}
+
+impl FilterAccountsRentEpoch {
+    fn new(eq: Option<u64>, gt: Option<u64>, lt: Option<u64>) -> FilterResult<Option<Self>> {
+        match (eq, gt, lt) {
+            (None, None, None) => Ok(None),
+            (Some(value), None, None) => Ok(Some(Self::Eq(value))),
+            (None, Some(value), None) => Ok(Some(Self::Gt(value))),
+            (None, None, Some(value)) => Ok(Some(Self::Lt(value))),
+            _ => Err(FilterError::CreateAccountState(
+                "only one of rent_epoch_eq, rent_epoch_gt and rent_epoch_lt can be used",
+            )),
+        }
+    }
+
+    const fn is_match(self, rent_epoch: u64) -> bool {
+        match self {
+            Self::Eq(value) => value == rent_epoch,
+            Self::Gt(value) => value < rent_epoch,
+            Self::Lt(value) => value > rent_epoch,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FilterAccountsChecksum {
+    crc: Range<usize>,
+    body: Range<usize>,
+}
+
+impl FilterAccountsChecksum {
+    fn new(checksum: &SubscribeRequestFilterAccountsFilterChecksum) -> FilterResult<Self> {
+        let crc_offset = checksum.crc_offset as usize;
+        let body_offset = checksum.body_offset as usize;
+        match (
+            crc_offset.checked_add(4),
+            body_offset.checked_add(checksum.body_length as usize),
+        ) {
+            (Some(crc_end), Some(body_end)) => Ok(Self {
+                crc: crc_offset..crc_end,
+                body: body_offset..body_end,
+            }),
+            _ => Err(FilterError::CreateAccountState("checksum range overflow")),
+        }
+    }
+
+    fn is_match(&self, data: &[u8]) -> bool {
+        match (data.get(self.crc.clone()), data.get(self.body.clone())) {
+            (Some(crc), Some(body)) => {
+                let crc = u32::from_le_bytes(crc.try_into().expect("valid crc length"));
+                crc == crc32fast::hash(body)
+            }
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct FilterAccountsMatch<'a> {
     filter: &'a FilterAccounts,
     nonempty_txn_signature: HashSet<&'a str>,
     account: HashSet<&'a str>,
     owner: HashSet<&'a str>,
-    data: HashSet<&'a str>,
+    owner_allowed: bool,
+    data_size_exceeded: HashSet<&'a str>,
+    native_program_owned_mismatch: HashSet<&'a str>,
+    executable_mismatch: HashSet<&'a str>,
 }
 
 impl<'a> FilterAccountsMatch<'a> {
+    /// Owners of accounts created by the runtime and builtin programs
+    const NATIVE_PROGRAM_IDS: [Pubkey; 15] = [
+        system_program::ID,
+        stake::program::ID,
+        vote::program::ID,
+        bpf_loader::ID,
+        bpf_loader_deprecated::ID,
+        bpf_loader_upgradeable::ID,
+        loader_v4::ID,
+        native_loader::ID,
+        config::program::ID,
+        compute_budget::ID,
+        address_lookup_table::program::ID,
+        ed25519_program::ID,
+        secp256k1_program::ID,
+        sysvar::ID,
+        feature::ID,
+    ];
+
     fn new(filter: &'a FilterAccounts) -> Self {
         Self {
             filter,
             nonempty_txn_signature: Default::default(),
             account: Default::default(),
             owner: Default::default(),
-            data: Default::default(),
+            owner_allowed: true,
+            data_size_exceeded: Default::default(),
+            native_program_owned_mismatch: Default::default(),
+            executable_mismatch: Default::default(),
         }
     }
 
@@ -547,65 +2148,241 @@ impl<'a> FilterAccountsMatch<'a> {
     }
 
     fn match_owner(&mut self, pubkey: &Pubkey) {
+        self.owner_allowed = self.filter.is_owner_allowed(pubkey);
         Self::extend(&mut self.owner, &self.filter.owner, pubkey)
     }
 
-    fn match_data_lamports(&mut self, data: &[u8], lamports: u64) {
-        for (name, filter) in self.filter.filters.iter() {
-            if filter.is_match(data, lamports) {
-                self.data.insert(name.as_ref());
+    fn match_data_size_max(&mut self, data_len: usize) {
+        for (name, data_size_max) in self.filter.data_size_max.iter() {
+            if data_len as u64 > *data_size_max {
+                self.data_size_exceeded.insert(name.as_ref());
             }
         }
     }
 
-    fn get_filters(&self) -> FilteredUpdateFilters {
-        self.filter
-            .filters
-            .iter()
-            .filter_map(|(filter_name, filter)| {
-                let name = filter_name.as_ref();
-                let af = &self.filter;
+    fn match_native_program_owned(&mut self, owner: &Pubkey) {
+        if self.filter.native_program_owned.is_empty() {
+            return;
+        }
+        let is_native = Self::NATIVE_PROGRAM_IDS.contains(owner);
+        for (name, native_program_owned) in self.filter.native_program_owned.iter() {
+            if *native_program_owned != is_native {
+                self.native_program_owned_mismatch.insert(name.as_ref());
+            }
+        }
+    }
 
-                // If filter name in required but not in matched => return `false`
-                if af.nonempty_txn_signature_required.contains(name)
-                    && !self.nonempty_txn_signature.contains(name)
-                {
-                    return None;
-                }
-                if af.account_required.contains(name) && !self.account.contains(name) {
-                    return None;
-                }
-                if af.owner_required.contains(name) && !self.owner.contains(name) {
-                    return None;
-                }
-                if !filter.is_empty() && !self.data.contains(name) {
-                    return None;
-                }
+    fn match_executable(&mut self, executable: bool) {
+        for (name, filter_executable) in self.filter.executable.iter() {
+            if *filter_executable != executable {
+                self.executable_mismatch.insert(name.as_ref());
+            }
+        }
+    }
 
-                Some(filter_name.clone())
-            })
+    /// Pubkey lookups are already done, data filters are evaluated only for filters
+    /// that passed them. Filters of other owners are skipped with the owner index
+    fn get_filters(&self, account: &MessageAccountInfo) -> FilteredUpdateFilters {
+        let af = self.filter;
+        let by_owner = af
+            .filters_by_owner
+            .get(&account.owner)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        Self::merge_indexes(&af.filters_any_owner, by_owner)
+            .map(|index| &af.filters[index])
+            .filter(|(name, filter)| self.check(name, filter, account).is_ok())
+            .map(|(name, _filter)| name.clone())
             .collect()
     }
+
+    /// Merge two sorted lists of indexes into one sorted sequence
+    fn merge_indexes<'b>(a: &'b [usize], b: &'b [usize]) -> impl Iterator<Item = usize> + 'b {
+        let mut a = a.iter().copied().peekable();
+        let mut b = b.iter().copied().peekable();
+        std::iter::from_fn(move || match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) if x <= y => a.next(),
+            (Some(_), Some(_)) => b.next(),
+            (Some(_), None) => a.next(),
+            (None, _) => b.next(),
+        })
+    }
+
+    fn check(
+        &self,
+        name: &str,
+        filter: &FilterAccountsState,
+        account: &MessageAccountInfo,
+    ) -> Result<(), FilterMismatch> {
+        let af = &self.filter;
+
+        if !self.owner_allowed {
+            return Err(FilterMismatch::OwnerAllowlist);
+        }
+        // If filter name in required but not in matched => return `false`
+        if af.nonempty_txn_signature_required.contains(name)
+            && !self.nonempty_txn_signature.contains(name)
+        {
+            return Err(FilterMismatch::NonemptyTxnSignature);
+        }
+        if af.account_required.contains(name) && !self.account.contains(name) {
+            return Err(FilterMismatch::Account);
+        }
+        if af.owner_required.contains(name) && !self.owner.contains(name) {
+            return Err(FilterMismatch::Owner);
+        }
+        if self.data_size_exceeded.contains(name) {
+            return Err(FilterMismatch::DataSizeMax);
+        }
+        if self.native_program_owned_mismatch.contains(name) {
+            return Err(FilterMismatch::NativeProgramOwned);
+        }
+        if self.executable_mismatch.contains(name) {
+            return Err(FilterMismatch::Executable);
+        }
+        if filter.is_empty() {
+            Ok(())
+        } else {
+            filter.check(&account.data, account.lamports, account.rent_epoch)
+        }
+    }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone)]
 struct FilterSlotsInner {
     filter_by_commitment: bool,
     interslot_updates: bool,
+    slot_gaps: bool,
+    tip_only: bool,
+    statuses: HashSet<SlotStatus>,
+    delta_batch_interval_ms: Option<i64>,
+    replay_finalized: Option<usize>,
 }
 
 impl FilterSlotsInner {
-    fn new(filter: SubscribeRequestFilterSlots) -> Self {
-        Self {
+    fn new(filter: &SubscribeRequestFilterSlots) -> FilterResult<Self> {
+        Ok(Self {
             filter_by_commitment: filter.filter_by_commitment.unwrap_or_default(),
             interslot_updates: filter.interslot_updates.unwrap_or_default(),
-        }
+            slot_gaps: filter.slot_gaps.unwrap_or_default(),
+            tip_only: filter.tip_only.unwrap_or_default(),
+            statuses: filter
+                .statuses
+                .iter()
+                .map(|status| {
+                    SlotStatusProto::try_from(*status)
+                        .map(Into::into)
+                        .map_err(|_error| FilterError::InvalidSlotStatus { status: *status })
+                })
+                .collect::<FilterResult<_>>()?,
+            delta_batch_interval_ms: filter.delta_batch_interval_ms.map(i64::from),
+            replay_finalized: filter.replay_finalized.map(|count| count as usize),
+        })
+    }
+
+    fn is_match_commitment(
+        &self,
+        message: &MessageSlot,
+        commitment: Option<CommitmentLevel>,
+    ) -> bool {
+        !self.filter_by_commitment
+            || commitment
+                .map(|commitment| commitment == message.status)
+                .unwrap_or(false)
+    }
+
+    /// Explicit statuses replace the default of confirmation levels only
+    fn is_match_status(&self, status: SlotStatus) -> bool {
+        if self.statuses.is_empty() {
+            self.interslot_updates
+                || matches!(
+                    status,
+                    SlotStatus::Processed | SlotStatus::Confirmed | SlotStatus::Finalized
+                )
+        } else {
+            self.statuses.contains(&status)
+        }
+    }
+}
+
+/// Detects catch-up by the rate of processed slots and tracks the highest seen slot
+#[derive(Debug, Default, Clone)]
+struct FilterSlotsTip {
+    highest_slot: Slot,
+    processed: VecDeque<(Slot, i64)>,
+}
+
+impl FilterSlotsTip {
+    const RATE_WINDOW: usize = 16;
+    const CATCH_UP_SLOTS_PER_SEC: u64 = 10;
+    const MAX_DISTANCE: u64 = 32;
+
+    fn update(&mut self, message: &MessageSlot) {
+        self.highest_slot = self.highest_slot.max(message.slot);
+        if message.status == SlotStatus::Processed {
+            if self.processed.len() == Self::RATE_WINDOW {
+                self.processed.pop_front();
+            }
+            let created_at = message.created_at.seconds * 1_000
+                + i64::from(message.created_at.nanos) / 1_000_000;
+            self.processed.push_back((message.slot, created_at));
+        }
+    }
+
+    fn is_catching_up(&self) -> bool {
+        if self.processed.len() < Self::RATE_WINDOW {
+            return false;
+        }
+        match (self.processed.front(), self.processed.back()) {
+            (Some((first_slot, first_ms)), Some((last_slot, last_ms))) => {
+                let slots = last_slot.saturating_sub(*first_slot);
+                let elapsed_ms = last_ms.saturating_sub(*first_ms).max(1) as u64;
+                slots * 1_000 > Self::CATCH_UP_SLOTS_PER_SEC * elapsed_ms
+            }
+            _ => false,
+        }
+    }
+
+    fn is_near_tip(&self, slot: Slot) -> bool {
+        slot + Self::MAX_DISTANCE >= self.highest_slot || !self.is_catching_up()
+    }
+}
+
+/// Slot transitions matched by a filter with `delta_batch_interval_ms`
+#[derive(Debug, Default, Clone)]
+struct FilterSlotsDeltaBatch {
+    transitions: Vec<(Slot, SlotStatus)>,
+    started_at_ms: i64,
+}
+
+impl FilterSlotsDeltaBatch {
+    const MAX_TRANSITIONS: usize = 1_024;
+
+    fn push(&mut self, message: &MessageSlot, created_at_ms: i64) {
+        if self.transitions.is_empty() {
+            self.started_at_ms = created_at_ms;
+        }
+        self.transitions.push((message.slot, message.status));
+    }
+
+    fn take_ready(&mut self, interval_ms: i64, created_at_ms: i64) -> Option<FilteredUpdateOneof> {
+        let is_ready = !self.transitions.is_empty()
+            && (created_at_ms.saturating_sub(self.started_at_ms) >= interval_ms
+                || self.transitions.len() >= Self::MAX_TRANSITIONS);
+        is_ready.then(|| {
+            let message = FilteredUpdateOneof::slot_delta_batch(&self.transitions);
+            self.transitions.clear();
+            message
+        })
     }
 }
 
 #[derive(Debug, Default, Clone)]
 struct FilterSlots {
     filters: HashMap<FilterName, FilterSlotsInner>,
+    last_slots: BTreeMap<CommitmentLevel, Slot>,
+    tip: FilterSlotsTip,
+    delta_batches: HashMap<FilterName, FilterSlotsDeltaBatch>,
 }
 
 impl FilterSlots {
@@ -619,34 +2396,132 @@ impl FilterSlots {
         Ok(Self {
             filters: configs
                 .iter()
-                .map(|(name, filter)| {
-                    names
-                        .get(name)
-                        .map(|name| (name, FilterSlotsInner::new(*filter)))
-                })
-                .collect::<Result<_, _>>()?,
+                .map(|(name, filter)| Ok((names.get(name)?, FilterSlotsInner::new(filter)?)))
+                .collect::<FilterResult<_>>()?,
+            last_slots: BTreeMap::new(),
+            tip: FilterSlotsTip::default(),
+            delta_batches: HashMap::new(),
         })
     }
 
     fn get_updates(
+        &mut self,
+        message: &MessageSlot,
+        commitment: Option<CommitmentLevel>,
+    ) -> FilteredUpdates {
+        let mut updates = self.get_slot_gap_updates(message, commitment);
+
+        let is_near_tip = if self.filters.values().any(|inner| inner.tip_only) {
+            self.tip.update(message);
+            self.tip.is_near_tip(message.slot)
+        } else {
+            true
+        };
+
+        let created_at_ms =
+            message.created_at.seconds * 1_000 + i64::from(message.created_at.nanos) / 1_000_000;
+        let mut filters = FilteredUpdateFilters::new();
+        for (name, inner) in self.filters.iter() {
+            let is_match = inner.is_match_commitment(message, commitment)
+                && (!inner.tip_only || is_near_tip)
+                && inner.is_match_status(message.status);
+            let Some(interval_ms) = inner.delta_batch_interval_ms else {
+                if is_match {
+                    filters.push(name.clone());
+                }
+                continue;
+            };
+
+            // batches are flushed by any slot update, not only by matched
+            let batch = self.delta_batches.entry(name.clone()).or_default();
+            if is_match {
+                batch.push(message, created_at_ms);
+            }
+            if let Some(batch_message) = batch.take_ready(interval_ms, created_at_ms) {
+                updates.push(FilteredUpdate::new(
+                    FilteredUpdateFilters::from_elem(name.clone(), 1),
+                    batch_message,
+                    message.created_at,
+                ));
+            }
+        }
+        updates.append(&mut filtered_updates_once_owned!(
+            filters,
+            FilteredUpdateOneof::slot(message.clone()),
+            message.created_at
+        ));
+        updates
+    }
+
+    /// `messages` are the latest finalized slots sorted by slot
+    fn get_replay_finalized_updates(
         &self,
+        messages: &[MessageSlot],
+        commitment: CommitmentLevel,
+    ) -> FilteredUpdates {
+        let mut updates = FilteredUpdates::new();
+        for (index, message) in messages.iter().enumerate() {
+            // `1` for the latest slot
+            let position = messages.len() - index;
+            let mut filters = FilteredUpdateFilters::new();
+            for (name, inner) in self.filters.iter() {
+                if inner
+                    .replay_finalized
+                    .is_some_and(|count| position <= count)
+                    && inner.is_match_commitment(message, Some(commitment))
+                    && inner.is_match_status(message.status)
+                {
+                    filters.push(name.clone());
+                }
+            }
+            updates.append(&mut filtered_updates_once_owned!(
+                filters,
+                FilteredUpdateOneof::slot(message.clone()),
+                message.created_at
+            ));
+        }
+        updates
+    }
+
+    fn get_slot_gap_updates(
+        &mut self,
         message: &MessageSlot,
         commitment: Option<CommitmentLevel>,
     ) -> FilteredUpdates {
+        let slot_commitment = match message.status {
+            SlotStatus::Processed => CommitmentLevel::Processed,
+            SlotStatus::Confirmed => CommitmentLevel::Confirmed,
+            SlotStatus::Finalized => CommitmentLevel::Finalized,
+            _ => return FilteredUpdates::new(),
+        };
+        if !self.filters.values().any(|inner| inner.slot_gaps) {
+            return FilteredUpdates::new();
+        }
+
+        // out of order updates (forks, missed statuses) do not move the last slot back
+        let from_slot = match self.last_slots.entry(slot_commitment) {
+            BTreeMapEntry::Vacant(entry) => {
+                entry.insert(message.slot);
+                return FilteredUpdates::new();
+            }
+            BTreeMapEntry::Occupied(mut entry) => {
+                let last_slot = *entry.get();
+                if message.slot <= last_slot {
+                    return FilteredUpdates::new();
+                }
+                entry.insert(message.slot);
+                last_slot
+            }
+        };
+        if message.slot == from_slot + 1 || message.parent == Some(from_slot) {
+            return FilteredUpdates::new();
+        }
+
         let filters = self
             .filters
             .iter()
             .filter_map(|(name, inner)| {
-                if (!inner.filter_by_commitment
-                    || commitment
-                        .map(|commitment| commitment == message.status)
-                        .unwrap_or(false))
-                    && (inner.interslot_updates
-                        || matches!(
-                            message.status,
-                            SlotStatus::Processed | SlotStatus::Confirmed | SlotStatus::Finalized
-                        ))
-                {
+                if inner.slot_gaps && inner.is_match_commitment(message, commitment) {
                     Some(name.clone())
                 } else {
                     None
@@ -655,7 +2530,7 @@ impl FilterSlots {
             .collect::<FilteredUpdateFilters>();
         filtered_updates_once_owned!(
             filters,
-            FilteredUpdateOneof::slot(message.clone()),
+            FilteredUpdateOneof::slot_gap(from_slot, message.slot, slot_commitment),
             message.created_at
         )
     }
@@ -675,18 +2550,380 @@ struct FilterTransactionsInner {
     account_include: HashSet<Pubkey>,
     account_exclude: HashSet<Pubkey>,
     account_required: HashSet<Pubkey>,
+    program_include: HashSet<Pubkey>,
+    signer_include: HashSet<Pubkey>,
+    cpi_program_include: HashSet<Pubkey>,
+    account_count_min: Option<u64>,
+    account_count_max: Option<u64>,
+    error_match: Option<FilterTransactionsErrorMatch>,
+    dedup_signatures: bool,
+}
+
+impl FilterTransactionsInner {
+    fn new(
+        filter: &SubscribeRequestFilterTransactions,
+        limits: &FilterLimitsTransactions,
+    ) -> FilterResult<Self> {
+        FilterLimits::check_any(
+            filter.vote.is_none()
+                && filter.failed.is_none()
+                && filter.account_include.is_empty()
+                && filter.account_exclude.is_empty()
+                && filter.account_required.is_empty()
+                && filter.program_include.is_empty()
+                && filter.signer_include.is_empty()
+                && filter.cpi_program_include.is_empty()
+                && filter.account_count_min.is_none()
+                && filter.account_count_max.is_none()
+                && filter.error_match.is_none(),
+            limits.any,
+        )?;
+        FilterLimits::check_pubkey_max(filter.account_include.len(), limits.account_include_max)?;
+        FilterLimits::check_pubkey_max(filter.account_exclude.len(), limits.account_exclude_max)?;
+        FilterLimits::check_pubkey_max(filter.account_required.len(), limits.account_required_max)?;
+        FilterLimits::check_pubkey_max(filter.program_include.len(), limits.program_include_max)?;
+        FilterLimits::check_pubkey_max(filter.signer_include.len(), limits.signer_include_max)?;
+        FilterLimits::check_pubkey_max(
+            filter.cpi_program_include.len(),
+            limits.cpi_program_include_max,
+        )?;
+        if let (Some(min), Some(max)) = (filter.account_count_min, filter.account_count_max) {
+            if min > max {
+                return Err(FilterError::CreateTransactionsAccountCountRange);
+            }
+        }
+
+        Ok(Self {
+            vote: filter.vote,
+            failed: filter.failed,
+            signature: filter
+                .signature
+                .as_ref()
+                .map(|signature_str| signature_str.parse().map_err(FilterError::InvalidSignature))
+                .transpose()?,
+            account_include: Filter::decode_pubkeys_into_set(
+                &filter.account_include,
+                &limits.account_include_reject,
+            )?,
+            account_exclude: Filter::decode_pubkeys_into_set(
+                &filter.account_exclude,
+                &HashSet::new(),
+            )?,
+            account_required: Filter::decode_pubkeys_into_set(
+                &filter.account_required,
+                &HashSet::new(),
+            )?,
+            program_include: Filter::decode_pubkeys_into_set(
+                &filter.program_include,
+                &HashSet::new(),
+            )?,
+            signer_include: Filter::decode_pubkeys_into_set(
+                &filter.signer_include,
+                &HashSet::new(),
+            )?,
+            cpi_program_include: Filter::decode_pubkeys_into_set(
+                &filter.cpi_program_include,
+                &HashSet::new(),
+            )?,
+            account_count_min: filter.account_count_min,
+            account_count_max: filter.account_count_max,
+            error_match: filter
+                .error_match
+                .as_ref()
+                .map(FilterTransactionsErrorMatch::new)
+                .transpose()?,
+            dedup_signatures: filter.dedup_signatures.unwrap_or_default(),
+        })
+    }
+
+    fn is_match(&self, transaction: &MessageTransactionInfo) -> bool {
+        self.check(transaction).is_ok()
+    }
+
+    /// Matches failed transactions without any other predicate
+    fn is_failed_only(&self) -> bool {
+        self.failed == Some(true)
+            && self.vote.is_none()
+            && self.signature.is_none()
+            && self.account_include.is_empty()
+            && self.account_exclude.is_empty()
+            && self.account_required.is_empty()
+            && self.program_include.is_empty()
+            && self.signer_include.is_empty()
+            && self.cpi_program_include.is_empty()
+            && self.account_count_min.is_none()
+            && self.account_count_max.is_none()
+            && self.error_match.is_none()
+    }
+
+    /// Checks are ordered by cost, instructions are scanned last
+    fn check(&self, transaction: &MessageTransactionInfo) -> Result<(), FilterMismatch> {
+        if let Some(is_vote) = self.vote {
+            if is_vote != transaction.is_vote {
+                return Err(FilterMismatch::Vote);
+            }
+        }
+
+        if let Some(is_failed) = self.failed {
+            if is_failed != transaction.meta.err.is_some() {
+                return Err(FilterMismatch::Failed);
+            }
+        }
+
+        if let Some(signature) = &self.signature {
+            let tx_sig = transaction.transaction.signatures.first();
+            if Some(signature.as_ref()) != tx_sig.map(|sig| sig.as_ref()) {
+                return Err(FilterMismatch::Signature);
+            }
+        }
+
+        let account_count = transaction.account_keys.len() as u64;
+        if matches!(self.account_count_min, Some(min) if account_count < min) {
+            return Err(FilterMismatch::AccountCountMin);
+        }
+        if matches!(self.account_count_max, Some(max) if account_count > max) {
+            return Err(FilterMismatch::AccountCountMax);
+        }
+
+        if let Some(error_match) = &self.error_match {
+            if !error_match.is_match(transaction) {
+                return Err(FilterMismatch::ErrorMatch);
+            }
+        }
+
+        if !self.account_include.is_empty()
+            && self
+                .account_include
+                .intersection(&transaction.account_keys)
+                .next()
+                .is_none()
+        {
+            return Err(FilterMismatch::AccountInclude);
+        }
+
+        if !self.account_exclude.is_empty()
+            && self
+                .account_exclude
+                .intersection(&transaction.account_keys)
+                .next()
+                .is_some()
+        {
+            return Err(FilterMismatch::AccountExclude);
+        }
+
+        if !self.account_required.is_empty()
+            && !self.account_required.is_subset(&transaction.account_keys)
+        {
+            return Err(FilterMismatch::AccountRequired);
+        }
+
+        if !self.signer_include.is_empty()
+            && !transaction
+                .signers()
+                .any(|signer| self.signer_include.contains(&signer))
+        {
+            return Err(FilterMismatch::SignerInclude);
+        }
+
+        if !self.program_include.is_empty()
+            && !transaction
+                .program_ids()
+                .any(|program_id| self.program_include.contains(&program_id))
+        {
+            return Err(FilterMismatch::ProgramInclude);
+        }
+
+        if !self.cpi_program_include.is_empty()
+            && !transaction
+                .inner_program_ids()
+                .any(|program_id| self.cpi_program_include.contains(&program_id))
+        {
+            return Err(FilterMismatch::CpiProgramInclude);
+        }
+
+        Ok(())
+    }
+}
+
+/// Error of failed transaction matched by `TransactionError` variant name, for
+/// `InstructionError` optionally by instruction index and `InstructionError` variant name
+#[derive(Debug, Clone)]
+struct FilterTransactionsErrorMatch {
+    error: String,
+    instruction_index: Option<u8>,
+    instruction_error: Option<String>,
+}
+
+impl FilterTransactionsErrorMatch {
+    const INSTRUCTION_ERROR: &'static str = "InstructionError";
+
+    fn new(filter: &SubscribeRequestFilterTransactionsError) -> FilterResult<Self> {
+        if filter.error.is_empty() {
+            return Err(FilterError::CreateTransactionsErrorMatch(
+                "`error` should be defined",
+            ));
+        }
+        if filter.error != Self::INSTRUCTION_ERROR
+            && (filter.instruction_index.is_some() || filter.instruction_error.is_some())
+        {
+            return Err(FilterError::CreateTransactionsErrorMatch(
+                "`instruction_index` and `instruction_error` require `InstructionError`",
+            ));
+        }
+        if !Self::get_variant_names::<TransactionError>().contains(&filter.error.as_str()) {
+            return Err(FilterError::CreateTransactionsErrorMatch(
+                "`error` is not a `TransactionError` variant",
+            ));
+        }
+        if let Some(instruction_error) = &filter.instruction_error {
+            if !Self::get_variant_names::<InstructionError>().contains(&instruction_error.as_str())
+            {
+                return Err(FilterError::CreateTransactionsErrorMatch(
+                    "`instruction_error` is not an `InstructionError` variant",
+                ));
+            }
+        }
+        Ok(Self {
+            error: filter.error.clone(),
+            instruction_index: filter
+                .instruction_index
+                .map(u8::try_from)
+                .transpose()
+                .map_err(|_error| {
+                    FilterError::CreateTransactionsErrorMatch("`instruction_index` is out of range")
+                })?,
+            instruction_error: filter.instruction_error.clone(),
+        })
+    }
+
+    /// Errors which can not be decoded never match
+    fn is_match(&self, transaction: &MessageTransactionInfo) -> bool {
+        match convert_from::create_tx_error(transaction.meta.err.as_ref()) {
+            Ok(Some(TransactionError::InstructionError(index, error))) => {
+                self.error == Self::INSTRUCTION_ERROR
+                    && self.instruction_index.map_or(true, |value| value == index)
+                    && self
+                        .instruction_error
+                        .as_ref()
+                        .map_or(true, |value| *value == Self::get_variant_name(&error))
+            }
+            Ok(Some(error)) => self.error == Self::get_variant_name(&error),
+            Ok(None) | Err(_) => false,
+        }
+    }
+
+    /// Variant names of the enum, collected from its `Deserialize` implementation
+    fn get_variant_names<T: serde::de::DeserializeOwned>() -> &'static [&'static str] {
+        struct VariantNames<'a>(&'a mut &'static [&'static str]);
+
+        impl<'de> serde::Deserializer<'de> for VariantNames<'_> {
+            type Error = serde::de::value::Error;
+
+            fn deserialize_any<V: serde::de::Visitor<'de>>(
+                self,
+                _visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                Err(serde::de::Error::custom("expected enum"))
+            }
+
+            fn deserialize_enum<V: serde::de::Visitor<'de>>(
+                self,
+                _name: &'static str,
+                variants: &'static [&'static str],
+                _visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                *self.0 = variants;
+                Err(serde::de::Error::custom("variant names collected"))
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+                byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map
+                struct identifier ignored_any
+            }
+        }
+
+        let mut variants: &'static [&'static str] = &[];
+        let _ = T::deserialize(VariantNames(&mut variants));
+        variants
+    }
+
+    /// Variant name from `Debug`, e.g. `InsufficientFundsForRent` for
+    /// `InsufficientFundsForRent { account_index: 1 }`
+    fn get_variant_name(value: &impl std::fmt::Debug) -> String {
+        let mut name = format!("{value:?}");
+        if let Some(end) = name.find(|ch: char| !ch.is_ascii_alphanumeric()) {
+            name.truncate(end);
+        }
+        name
+    }
+}
+
+/// Bounded set of signatures already delivered on the stream,
+/// oldest signature is evicted once `window` or the caches memory budget is reached.
+#[derive(Debug, Default, Clone)]
+struct FilterTransactionsDedup {
+    window: usize,
+    signatures: HashSet<Signature>,
+    queue: VecDeque<Signature>,
+    memory: FilterCacheMemory,
+}
+
+impl FilterTransactionsDedup {
+    // signature in both the set and the queue
+    const ENTRY_SIZE: usize = 2 * size_of::<Signature>();
+
+    fn new(window: usize, limits: &FilterLimitsCaches) -> Self {
+        Self {
+            window,
+            signatures: HashSet::new(),
+            queue: VecDeque::new(),
+            memory: FilterCacheMemory::new(limits),
+        }
+    }
+
+    fn contains(&self, signature: &Signature) -> bool {
+        self.signatures.contains(signature)
+    }
+
+    fn insert(&mut self, signature: Signature) {
+        if self.window == 0 || !self.signatures.insert(signature) {
+            return;
+        }
+        if self.queue.len() >= self.window {
+            if let Some(signature) = self.queue.pop_front() {
+                self.signatures.remove(&signature);
+                self.memory.sub(Self::ENTRY_SIZE);
+            }
+        }
+        self.queue.push_back(signature);
+        self.memory.add(Self::ENTRY_SIZE);
+
+        // the inserted signature is the last in the queue and is never evicted
+        while self.queue.len() > 1 && self.memory.is_exceeded() {
+            let Some(signature) = self.queue.pop_front() else {
+                break;
+            };
+            self.signatures.remove(&signature);
+            self.memory.sub(Self::ENTRY_SIZE);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 struct FilterTransactions {
     filter_type: FilterTransactionsType,
     filters: HashMap<FilterName, FilterTransactionsInner>,
+    dedup: FilterTransactionsDedup,
+    // status stream where every filter is `failed: true` only, successful transactions are skipped
+    failed_only: bool,
 }
 
 impl FilterTransactions {
     fn new(
         configs: &HashMap<String, SubscribeRequestFilterTransactions>,
         limits: &FilterLimitsTransactions,
+        limits_caches: &FilterLimitsCaches,
         filter_type: FilterTransactionsType,
         names: &mut FilterNames,
     ) -> FilterResult<Self> {
@@ -694,116 +2931,51 @@ impl FilterTransactions {
 
         let mut filters = HashMap::new();
         for (name, filter) in configs {
-            FilterLimits::check_any(
-                filter.vote.is_none()
-                    && filter.failed.is_none()
-                    && filter.account_include.is_empty()
-                    && filter.account_exclude.is_empty()
-                    && filter.account_required.is_empty(),
-                limits.any,
-            )?;
-            FilterLimits::check_pubkey_max(
-                filter.account_include.len(),
-                limits.account_include_max,
-            )?;
-            FilterLimits::check_pubkey_max(
-                filter.account_exclude.len(),
-                limits.account_exclude_max,
-            )?;
-            FilterLimits::check_pubkey_max(
-                filter.account_required.len(),
-                limits.account_required_max,
-            )?;
-
             filters.insert(
                 names.get(name)?,
-                FilterTransactionsInner {
-                    vote: filter.vote,
-                    failed: filter.failed,
-                    signature: filter
-                        .signature
-                        .as_ref()
-                        .map(|signature_str| {
-                            signature_str.parse().map_err(FilterError::InvalidSignature)
-                        })
-                        .transpose()?,
-                    account_include: Filter::decode_pubkeys_into_set(
-                        &filter.account_include,
-                        &limits.account_include_reject,
-                    )?,
-                    account_exclude: Filter::decode_pubkeys_into_set(
-                        &filter.account_exclude,
-                        &HashSet::new(),
-                    )?,
-                    account_required: Filter::decode_pubkeys_into_set(
-                        &filter.account_required,
-                        &HashSet::new(),
-                    )?,
-                },
+                FilterTransactionsInner::new(filter, limits)?,
             );
         }
+        let dedup_window = if filters.values().any(|inner| inner.dedup_signatures) {
+            limits.dedup_signatures_window
+        } else {
+            0
+        };
+        let failed_only = filter_type == FilterTransactionsType::TransactionStatus
+            && !filters.is_empty()
+            && filters
+                .values()
+                .all(FilterTransactionsInner::is_failed_only);
         Ok(Self {
             filter_type,
             filters,
+            dedup: FilterTransactionsDedup::new(dedup_window, limits_caches),
+            failed_only,
         })
     }
 
-    pub fn get_updates(&self, message: &MessageTransaction) -> FilteredUpdates {
+    pub fn get_updates(&mut self, message: &MessageTransaction) -> FilteredUpdates {
+        if self.failed_only && message.transaction.meta.err.is_none() {
+            return FilteredUpdates::new();
+        }
+
+        let is_delivered = self.dedup.contains(&message.transaction.signature);
         let filters = self
             .filters
             .iter()
             .filter_map(|(name, inner)| {
-                if let Some(is_vote) = inner.vote {
-                    if is_vote != message.transaction.is_vote {
-                        return None;
-                    }
-                }
-
-                if let Some(is_failed) = inner.failed {
-                    if is_failed != message.transaction.meta.err.is_some() {
-                        return None;
-                    }
-                }
-
-                if let Some(signature) = &inner.signature {
-                    let tx_sig = message.transaction.transaction.signatures.first();
-                    if Some(signature.as_ref()) != tx_sig.map(|sig| sig.as_ref()) {
-                        return None;
-                    }
-                }
-
-                if !inner.account_include.is_empty()
-                    && inner
-                        .account_include
-                        .intersection(&message.transaction.account_keys)
-                        .next()
-                        .is_none()
-                {
-                    return None;
-                }
-
-                if !inner.account_exclude.is_empty()
-                    && inner
-                        .account_exclude
-                        .intersection(&message.transaction.account_keys)
-                        .next()
-                        .is_some()
-                {
-                    return None;
-                }
-
-                if !inner.account_required.is_empty()
-                    && !inner
-                        .account_required
-                        .is_subset(&message.transaction.account_keys)
-                {
+                if inner.dedup_signatures && is_delivered {
                     return None;
                 }
 
-                Some(name.clone())
+                inner.is_match(&message.transaction).then(|| name.clone())
             })
             .collect::<FilteredUpdateFilters>();
 
+        if !filters.is_empty() {
+            self.dedup.insert(message.transaction.signature);
+        }
+
         filtered_updates_once_owned!(
             filters,
             match self.filter_type {
@@ -815,11 +2987,36 @@ impl FilterTransactions {
             message.created_at
         )
     }
+
+    fn explain(&self, message: &MessageTransaction) -> Vec<FilterExplain> {
+        let kind = match self.filter_type {
+            FilterTransactionsType::Transaction => "transactions",
+            FilterTransactionsType::TransactionStatus => "transactions_status",
+        };
+        self.filters
+            .iter()
+            .map(|(name, inner)| FilterExplain {
+                kind,
+                name: name.clone(),
+                mismatch: inner.check(&message.transaction).err(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 struct FilterEntries {
     filters: Vec<FilterName>,
+    last_entry_only: HashSet<FilterName>,
+    last_entry_buffer: BTreeMap<Slot, FilterEntriesLast>,
+    // only filters with `slot_from` or `slot_to`
+    slot_ranges: HashMap<FilterName, RangeInclusive<Slot>>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct FilterEntriesLast {
+    entry: Option<Arc<MessageEntry>>,
+    last_index: Option<usize>,
 }
 
 impl FilterEntries {
@@ -830,30 +3027,162 @@ impl FilterEntries {
     ) -> FilterResult<Self> {
         FilterLimits::check_max(configs.len(), limits.max)?;
 
-        Ok(Self {
-            filters: configs
-                .iter()
-                .map(|(name, _filter)| names.get(name))
-                .collect::<Result<_, _>>()?,
-        })
+        let mut this = Self::default();
+        for (name, filter) in configs {
+            let name = names.get(name)?;
+            if filter.last_entry_only == Some(true) {
+                this.last_entry_only.insert(name.clone());
+            }
+            if filter.slot_from.is_some() || filter.slot_to.is_some() {
+                let range =
+                    filter.slot_from.unwrap_or(Slot::MIN)..=filter.slot_to.unwrap_or(Slot::MAX);
+                if range.is_empty() {
+                    return Err(FilterError::CreateEntriesSlotRange);
+                }
+                this.slot_ranges.insert(name.clone(), range);
+            }
+            this.filters.push(name);
+        }
+        Ok(this)
     }
 
-    fn get_updates(&self, message: &Arc<MessageEntry>) -> FilteredUpdates {
-        let filters = self.filters.as_slice();
-        filtered_updates_once_ref!(
+    fn is_slot_match(&self, name: &FilterName, slot: Slot) -> bool {
+        self.slot_ranges
+            .get(name)
+            .map_or(true, |range| range.contains(&slot))
+    }
+
+    fn is_last_entry_required(&self, slot: Slot) -> bool {
+        self.last_entry_only
+            .iter()
+            .any(|name| self.is_slot_match(name, slot))
+    }
+
+    fn get_updates(&mut self, message: &Arc<MessageEntry>) -> FilteredUpdates {
+        let filters = self
+            .filters
+            .iter()
+            .filter(|name| {
+                !self.last_entry_only.contains(*name) && self.is_slot_match(name, message.slot)
+            })
+            .cloned()
+            .collect::<FilteredUpdateFilters>();
+        let mut updates = filtered_updates_once_owned!(
             filters,
             FilteredUpdateOneof::entry(Arc::clone(message)),
             message.created_at
-        )
+        );
+
+        if self.is_last_entry_required(message.slot) {
+            let last = self.last_entry_buffer.entry(message.slot).or_default();
+            if last
+                .entry
+                .as_ref()
+                .map_or(true, |entry| entry.index < message.index)
+            {
+                last.entry = Some(Arc::clone(message));
+            }
+            if last.last_index == Some(message.index) {
+                updates.append(&mut self.get_last_entry_updates(message.slot));
+            }
+        }
+
+        updates
+    }
+
+    fn get_block_meta_updates(&mut self, message: &MessageBlockMeta) -> FilteredUpdates {
+        if !self.is_last_entry_required(message.slot) || message.entries_count == 0 {
+            return FilteredUpdates::new();
+        }
+
+        let last = self.last_entry_buffer.entry(message.slot).or_default();
+        let last_index = message.entries_count as usize - 1;
+        last.last_index = Some(last_index);
+        if last.entry.as_ref().map(|entry| entry.index) == Some(last_index) {
+            self.get_last_entry_updates(message.slot)
+        } else {
+            FilteredUpdates::new()
+        }
+    }
+
+    fn get_last_entry_updates(&mut self, slot: Slot) -> FilteredUpdates {
+        match self
+            .last_entry_buffer
+            .remove(&slot)
+            .and_then(|last| last.entry)
+        {
+            Some(message) => {
+                let filters = self
+                    .last_entry_only
+                    .iter()
+                    .filter(|name| self.is_slot_match(name, slot))
+                    .cloned()
+                    .collect::<FilteredUpdateFilters>();
+                filtered_updates_once_owned!(
+                    filters,
+                    FilteredUpdateOneof::entry(Arc::clone(&message)),
+                    message.created_at
+                )
+            }
+            None => FilteredUpdates::new(),
+        }
+    }
+
+    fn update_slot(&mut self, message: &MessageSlot) {
+        match message.status {
+            SlotStatus::Dead => {
+                self.last_entry_buffer.remove(&message.slot);
+            }
+            SlotStatus::Finalized => {
+                self.last_entry_buffer = self.last_entry_buffer.split_off(&(message.slot + 1));
+            }
+            _ => {}
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 struct FilterBlocksInner {
     account_include: HashSet<Pubkey>,
+    account_include_owner: bool,
+    account_owner_include: HashSet<Pubkey>,
     include_transactions: Option<bool>,
     include_accounts: Option<bool>,
     include_entries: Option<bool>,
+    require_matching_transaction: Option<FilterTransactionsInner>,
+    include_only_matching_transactions: bool,
+}
+
+impl FilterBlocksInner {
+    fn is_match_account(&self, pubkey: &Pubkey, owner: &Pubkey) -> bool {
+        (self.account_include.is_empty() && self.account_owner_include.is_empty())
+            || self.account_include.contains(pubkey)
+            || (self.account_include_owner && self.account_include.contains(owner))
+            || self.account_owner_include.contains(owner)
+    }
+
+    fn is_match_transaction(
+        &self,
+        account_keys: &HashSet<Pubkey>,
+        owners: &HashMap<Pubkey, Pubkey>,
+    ) -> bool {
+        if self.account_include.is_empty()
+            || self
+                .account_include
+                .intersection(account_keys)
+                .next()
+                .is_some()
+        {
+            return true;
+        }
+
+        self.account_include_owner
+            && account_keys.iter().any(|pubkey| {
+                owners
+                    .get(pubkey)
+                    .is_some_and(|owner| self.account_include.contains(owner))
+            })
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -865,6 +3194,7 @@ impl FilterBlocks {
     fn new(
         configs: &HashMap<String, SubscribeRequestFilterBlocks>,
         limits: &FilterLimitsBlocks,
+        limits_transactions: &FilterLimitsTransactions,
         names: &mut FilterNames,
     ) -> FilterResult<Self> {
         FilterLimits::check_max(configs.len(), limits.max)?;
@@ -879,6 +3209,10 @@ impl FilterBlocks {
                 filter.account_include.len(),
                 limits.account_include_max,
             )?;
+            FilterLimits::check_pubkey_max(
+                filter.account_owner_include.len(),
+                limits.account_owner_include_max,
+            )?;
             if !(filter.include_transactions == Some(false) || limits.include_transactions) {
                 return Err(FilterError::CreateBlocksNotAllowed("transactions"));
             }
@@ -888,6 +3222,26 @@ impl FilterBlocks {
             if !(matches!(filter.include_entries, None | Some(false)) || limits.include_accounts) {
                 return Err(FilterError::CreateBlocksNotAllowed("entries"));
             }
+            let require_matching_transaction = filter
+                .require_matching_transaction
+                .as_ref()
+                .map(|filter| {
+                    if filter.dedup_signatures.is_some() {
+                        return Err(FilterError::CreateBlocks(
+                            "`dedup_signatures` is not supported in `require_matching_transaction`",
+                        ));
+                    }
+                    FilterTransactionsInner::new(filter, limits_transactions)
+                })
+                .transpose()?;
+            let include_only_matching_transactions = filter
+                .include_only_matching_transactions
+                .unwrap_or_default();
+            if include_only_matching_transactions && require_matching_transaction.is_none() {
+                return Err(FilterError::CreateBlocks(
+                    "`include_only_matching_transactions` requires `require_matching_transaction`",
+                ));
+            }
 
             this.filters.insert(
                 names.get(name)?,
@@ -896,9 +3250,16 @@ impl FilterBlocks {
                         &filter.account_include,
                         &limits.account_include_reject,
                     )?,
+                    account_include_owner: filter.account_include_owner.unwrap_or_default(),
+                    account_owner_include: Filter::decode_pubkeys_into_set(
+                        &filter.account_owner_include,
+                        &HashSet::new(),
+                    )?,
                     include_transactions: filter.include_transactions,
                     include_accounts: filter.include_accounts,
                     include_entries: filter.include_entries,
+                    require_matching_transaction,
+                    include_only_matching_transactions,
                 },
             );
         }
@@ -909,46 +3270,77 @@ impl FilterBlocks {
         &self,
         message: &Arc<MessageBlock>,
         accounts_data_slice: &FilterAccountsDataSlice,
+        exclude_votes: bool,
     ) -> FilteredUpdates {
+        // accounts written by excluded votes are skipped too
+        let votes = if exclude_votes {
+            message
+                .transactions
+                .iter()
+                .filter(|tx| tx.is_vote)
+                .map(|tx| tx.signature)
+                .collect::<HashSet<_>>()
+        } else {
+            HashSet::new()
+        };
+        let is_included_tx = |tx: &MessageTransactionInfo| !(exclude_votes && tx.is_vote);
+
+        // owners are collected only once and only if any filter needs them
+        let owners = if self
+            .filters
+            .values()
+            .any(|inner| inner.account_include_owner)
+        {
+            message
+                .accounts
+                .iter()
+                .map(|account| (account.pubkey, account.owner))
+                .collect::<HashMap<_, _>>()
+        } else {
+            HashMap::new()
+        };
+
         let mut updates = FilteredUpdates::new();
         for (filter, inner) in self.filters.iter() {
-            #[allow(clippy::unnecessary_filter_map)]
+            let require_matching_transaction = inner.require_matching_transaction.as_ref();
+            if let Some(required) = require_matching_transaction {
+                if !message
+                    .transactions
+                    .iter()
+                    .any(|tx| is_included_tx(tx) && required.is_match(tx))
+                {
+                    continue;
+                }
+            }
+
             let transactions = if matches!(inner.include_transactions, None | Some(true)) {
                 message
                     .transactions
                     .iter()
-                    .filter_map(|tx| {
-                        if !inner.account_include.is_empty()
-                            && inner
-                                .account_include
-                                .intersection(&tx.account_keys)
-                                .next()
-                                .is_none()
-                        {
-                            None
-                        } else {
-                            Some(Arc::clone(tx))
-                        }
+                    .filter(|tx| is_included_tx(tx))
+                    .filter(|tx| inner.is_match_transaction(&tx.account_keys, &owners))
+                    .filter(|tx| {
+                        !inner.include_only_matching_transactions
+                            || require_matching_transaction
+                                .is_some_and(|required| required.is_match(tx))
                     })
+                    .map(Arc::clone)
                     .collect::<Vec<_>>()
             } else {
                 vec![]
             };
 
-            #[allow(clippy::unnecessary_filter_map)]
             let accounts = if inner.include_accounts == Some(true) {
                 message
                     .accounts
                     .iter()
-                    .filter_map(|account| {
-                        if !inner.account_include.is_empty()
-                            && !inner.account_include.contains(&account.pubkey)
-                        {
-                            None
-                        } else {
-                            Some(Arc::clone(account))
-                        }
+                    .filter(|account| inner.is_match_account(&account.pubkey, &account.owner))
+                    .filter(|account| {
+                        !account
+                            .txn_signature
+                            .is_some_and(|signature| votes.contains(&signature))
                     })
+                    .map(Arc::clone)
                     .collect::<Vec<_>>()
             } else {
                 vec![]
@@ -982,6 +3374,7 @@ impl FilterBlocks {
 #[derive(Debug, Default, Clone)]
 struct FilterBlocksMeta {
     filters: Vec<FilterName>,
+    rewards_only: HashSet<FilterName>,
 }
 
 impl FilterBlocksMeta {
@@ -992,31 +3385,161 @@ impl FilterBlocksMeta {
     ) -> FilterResult<Self> {
         FilterLimits::check_max(configs.len(), limits.max)?;
 
-        Ok(Self {
-            filters: configs
-                .iter()
-                .map(|(name, _filter)| names.get(name))
-                .collect::<Result<_, _>>()?,
-        })
+        let mut this = Self::default();
+        for (name, filter) in configs {
+            let name = names.get(name)?;
+            if filter.rewards_only == Some(true) {
+                this.rewards_only.insert(name.clone());
+            }
+            this.filters.push(name);
+        }
+        Ok(this)
     }
 
     fn get_updates(&self, message: &Arc<MessageBlockMeta>) -> FilteredUpdates {
-        let filters = self.filters.as_slice();
-        filtered_updates_once_ref!(
+        if self.rewards_only.is_empty() {
+            let filters = self.filters.as_slice();
+            return filtered_updates_once_ref!(
+                filters,
+                FilteredUpdateOneof::block_meta(Arc::clone(message)),
+                message.created_at
+            );
+        }
+
+        let (filters_rewards, filters): (Vec<_>, Vec<_>) = self
+            .filters
+            .iter()
+            .cloned()
+            .partition(|name| self.rewards_only.contains(name));
+        let filters = filters.as_slice();
+        let mut updates = filtered_updates_once_ref!(
             filters,
             FilteredUpdateOneof::block_meta(Arc::clone(message)),
             message.created_at
-        )
+        );
+        let filters = filters_rewards.as_slice();
+        updates.append(&mut filtered_updates_once_ref!(
+            filters,
+            FilteredUpdateOneof::block_meta(Arc::new(MessageBlockMeta {
+                block_meta: SubscribeUpdateBlockMeta {
+                    slot: message.slot,
+                    rewards: message.rewards.clone(),
+                    ..Default::default()
+                },
+                created_at: message.created_at,
+            })),
+            message.created_at
+        ));
+        updates
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct FilterProgramEvents {
+    filters: HashMap<Pubkey, Vec<FilterName>>,
+}
+
+impl FilterProgramEvents {
+    fn new(
+        configs: &HashMap<String, SubscribeRequestFilterProgramEvents>,
+        limits: &FilterLimitsProgramEvents,
+        names: &mut FilterNames,
+    ) -> FilterResult<Self> {
+        FilterLimits::check_max(configs.len(), limits.max)?;
+
+        let mut filters: HashMap<Pubkey, Vec<FilterName>> = HashMap::new();
+        for (name, filter) in configs {
+            let program = Pubkey::from_str(&filter.program)?;
+            filters.entry(program).or_default().push(names.get(name)?);
+        }
+        Ok(Self { filters })
+    }
+
+    fn filters_len(&self) -> usize {
+        self.filters.values().map(Vec::len).sum()
+    }
+
+    fn get_updates(&self, message: &MessageTransaction) -> FilteredUpdates {
+        let mut updates = FilteredUpdates::new();
+        if self.filters.is_empty() || message.transaction.meta.err.is_some() {
+            return updates;
+        }
+
+        for (log_index, program, data) in Self::parse_logs(&message.transaction.meta.log_messages) {
+            if let Some(filters) = self.filters.get(&program) {
+                let filters = filters.as_slice();
+                updates.append(&mut filtered_updates_once_ref!(
+                    filters,
+                    FilteredUpdateOneof::program_event(message, &program, log_index, data),
+                    message.created_at
+                ));
+            }
+        }
+        updates
+    }
+
+    /// Returns decoded payloads of `Program data:` and `Program log:` lines with the index of
+    /// the line and the program on top of the invocation stack, malformed lines are skipped
+    fn parse_logs(logs: &[String]) -> Vec<(usize, Pubkey, Vec<Vec<u8>>)> {
+        // Anchor event starts with 8 bytes of discriminator, shorter payloads of
+        // `Program log:` are most likely text messages
+        const LOG_EVENT_MIN_LEN: usize = 8;
+
+        let mut events = vec![];
+        let mut stack: Vec<Option<Pubkey>> = vec![];
+        for (index, line) in logs.iter().enumerate() {
+            if line == "Log truncated" {
+                break;
+            }
+            let Some(line) = line.strip_prefix("Program ") else {
+                continue;
+            };
+
+            if let Some(payload) = line.strip_prefix("data: ") {
+                let Some(Some(program)) = stack.last() else {
+                    continue;
+                };
+                let data = payload
+                    .split_whitespace()
+                    .map(|chunk| base64_engine.decode(chunk))
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()
+                    .filter(|data| !data.is_empty());
+                if let Some(data) = data {
+                    events.push((index, *program, data));
+                }
+            } else if let Some(payload) = line.strip_prefix("log: ") {
+                let Some(Some(program)) = stack.last() else {
+                    continue;
+                };
+                if let Ok(data) = base64_engine.decode(payload.trim()) {
+                    if data.len() >= LOG_EVENT_MIN_LEN {
+                        events.push((index, *program, vec![data]));
+                    }
+                }
+            } else if let Some((program, status)) = line.split_once(' ') {
+                if status.starts_with("invoke [") {
+                    stack.push(Pubkey::from_str(program).ok());
+                } else if status == "success" || status.starts_with("failed") {
+                    stack.pop();
+                }
+            }
+        }
+        events
     }
 }
 
 #[derive(Debug, Clone, Default, PartialEq)]
-pub struct FilterAccountsDataSlice(Arc<Vec<Range<usize>>>);
+pub struct FilterAccountsDataSlice {
+    slices: Arc<Vec<Range<usize>>>,
+    encoding: AccountsDataEncoding,
+    segments: bool,
+}
 
 impl AsRef<[Range<usize>]> for FilterAccountsDataSlice {
     #[inline]
     fn as_ref(&self) -> &[Range<usize>] {
-        &self.0
+        &self.slices
     }
 }
 
@@ -1052,15 +3575,60 @@ impl FilterAccountsDataSlice {
     }
 
     pub const fn new_unchecked(slices: Arc<Vec<Range<usize>>>) -> Self {
-        Self(slices)
+        Self {
+            slices,
+            encoding: AccountsDataEncoding::Raw,
+            segments: false,
+        }
+    }
+
+    pub const fn with_encoding(mut self, encoding: AccountsDataEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub const fn with_segments(mut self, segments: bool) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    pub const fn is_encoded(&self) -> bool {
+        !matches!(self.encoding, AccountsDataEncoding::Raw)
+    }
+
+    /// Slices are sent as separate segments, whole data is sent without slices
+    pub fn is_segmented(&self) -> bool {
+        self.segments && !self.slices.is_empty()
+    }
+
+    /// Slices within the data with their offsets
+    pub fn get_segments<'a>(
+        &'a self,
+        source: &'a [u8],
+    ) -> impl Iterator<Item = (usize, &'a [u8])> + 'a {
+        self.slices
+            .iter()
+            .filter(move |slice| source.len() >= slice.end)
+            .map(move |slice| (slice.start, &source[slice.start..slice.end]))
+    }
+
+    /// Sliced data as string if encoding is requested
+    pub fn get_encoded(&self, source: &[u8]) -> Option<String> {
+        match self.encoding {
+            AccountsDataEncoding::Raw => None,
+            AccountsDataEncoding::Base64 => Some(base64_engine.encode(self.get_slice(source))),
+            AccountsDataEncoding::Base58 => {
+                Some(bs58::encode(self.get_slice(source)).into_string())
+            }
+        }
     }
 
     pub fn get_slice(&self, source: &[u8]) -> Vec<u8> {
-        if self.0.is_empty() {
+        if self.slices.is_empty() {
             source.to_vec()
         } else {
-            let mut data = Vec::with_capacity(self.0.iter().map(|ds| ds.end - ds.start).sum());
-            for data_slice in self.0.iter() {
+            let mut data = Vec::with_capacity(self.slices.iter().map(|ds| ds.end - ds.start).sum());
+            for data_slice in self.slices.iter() {
                 if source.len() >= data_slice.end {
                     data.extend_from_slice(&source[data_slice.start..data_slice.end]);
                 }
@@ -1070,11 +3638,11 @@ impl FilterAccountsDataSlice {
     }
 
     pub fn get_slice_len(&self, source: &[u8]) -> usize {
-        if self.0.is_empty() {
+        if self.slices.is_empty() {
             source.len()
         } else {
             let mut len = 0;
-            for slice in self.0.iter() {
+            for slice in self.slices.iter() {
                 if source.len() >= slice.end {
                     len += source[slice.start..slice.end].len();
                 }
@@ -1083,16 +3651,32 @@ impl FilterAccountsDataSlice {
         }
     }
 
+    /// Size of the data in the update with slices, segments and encoding applied
+    pub fn get_sent_len(&self, source: &[u8]) -> usize {
+        let len = self.get_slice_len(source);
+        let encoded_len = match self.encoding {
+            AccountsDataEncoding::Raw => 0,
+            AccountsDataEncoding::Base64 => len.div_ceil(3) * 4,
+            // upper bound, exact size depends on the data
+            AccountsDataEncoding::Base58 => (len * 138).div_ceil(100),
+        };
+        if self.is_encoded() && !self.is_segmented() {
+            encoded_len
+        } else {
+            encoded_len + len
+        }
+    }
+
     pub fn slice_encode_raw(&self, tag: u32, source: &[u8], buf: &mut impl BufMut) {
         let len = self.get_slice_len(source) as u64;
         if len > 0 {
             encode_key(tag, WireType::LengthDelimited, buf);
             encode_varint(len, buf);
 
-            if self.0.is_empty() {
+            if self.slices.is_empty() {
                 buf.put_slice(source);
             } else {
-                for data_slice in self.0.iter() {
+                for data_slice in self.slices.iter() {
                     if source.len() >= data_slice.end {
                         buf.put_slice(&source[data_slice.start..data_slice.end]);
                     }
@@ -1100,38 +3684,106 @@ impl FilterAccountsDataSlice {
             }
         }
     }
+
+    fn segment_encoded_len(offset: usize, data: &[u8]) -> usize {
+        (if offset != 0 {
+            ::prost::encoding::uint64::encoded_len(1u32, &(offset as u64))
+        } else {
+            0
+        }) + if data.is_empty() {
+            0
+        } else {
+            prost_field_encoded_len(2u32, data.len())
+        }
+    }
+
+    pub fn segments_encoded_len(&self, tag: u32, source: &[u8]) -> usize {
+        self.get_segments(source)
+            .map(|(offset, data)| {
+                prost_field_encoded_len(tag, Self::segment_encoded_len(offset, data))
+            })
+            .sum()
+    }
+
+    pub fn segments_encode_raw(&self, tag: u32, source: &[u8], buf: &mut impl BufMut) {
+        for (offset, data) in self.get_segments(source) {
+            encode_key(tag, WireType::LengthDelimited, buf);
+            encode_varint(Self::segment_encoded_len(offset, data) as u64, buf);
+            if offset != 0 {
+                ::prost::encoding::uint64::encode(1u32, &(offset as u64), buf);
+            }
+            if !data.is_empty() {
+                encode_key(2u32, WireType::LengthDelimited, buf);
+                encode_varint(data.len() as u64, buf);
+                buf.put_slice(data);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use {
-        super::Filter,
+        super::{
+            Filter, FilterAccounts, FilterAccountsDataSlice, FilterAccountsSeen,
+            FilterAccountsState, FilterError, FilterMismatch, FilterTransactionsDedup,
+            TokenAccount,
+        },
         crate::{
             convert_to,
             geyser::{
-                SubscribeRequest, SubscribeRequestFilterAccounts,
-                SubscribeRequestFilterTransactions,
+                subscribe_request_filter_accounts_filter::Filter as AccountsFilterDataOneof,
+                subscribe_request_filter_accounts_filter_lamports::Cmp as AccountsFilterLamports,
+                subscribe_request_filter_accounts_filter_memcmp::Data as AccountsFilterMemcmpOneof,
+                subscribe_update::UpdateOneof, AccountsDataEncoding,
+                CommitmentLevel as CommitmentLevelProto, DecompressCodec,
+                SlotStatus as SlotStatusProto, SubscribeRequest, SubscribeRequestAccountsDataSlice,
+                SubscribeRequestFilterAccounts, SubscribeRequestFilterAccountsFilter,
+                SubscribeRequestFilterAccountsFilterChecksum,
+                SubscribeRequestFilterAccountsFilterLamports,
+                SubscribeRequestFilterAccountsFilterMemcmp, SubscribeRequestFilterBlocks,
+                SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterEntry,
+                SubscribeRequestFilterProgramEvents, SubscribeRequestFilterSlots,
+                SubscribeRequestFilterTransactions, SubscribeRequestFilterTransactionsError,
+                SubscribeRequestPing, SubscribeUpdate, SubscribeUpdateAccount,
+                SubscribeUpdateBlockMeta, SubscribeUpdateFilterStatsEntry, UpdatesEncoding,
             },
             plugin::{
                 filter::{
-                    limits::FilterLimits,
-                    message::{FilteredUpdateFilters, FilteredUpdateOneof},
-                    name::{FilterName, FilterNames},
+                    limits::{
+                        FilterLimits, FilterLimitsAccounts, FilterLimitsAccountsDataField,
+                        FilterLimitsCaches, FilterLimitsCheckError, FilterLimitsMinCommitment,
+                    },
+                    message::{FilteredUpdate, FilteredUpdateFilters, FilteredUpdateOneof},
+                    name::{FilterName, FilterNameError, FilterNames},
+                },
+                message::{
+                    CommitmentLevel, Message, MessageAccount, MessageAccountInfo, MessageBlock,
+                    MessageBlockMeta, MessageEntry, MessageSlot, MessageTransaction,
+                    MessageTransactionInfo, SlotStatus,
                 },
-                message::{Message, MessageTransaction, MessageTransactionInfo},
             },
+            slot_delta,
+            solana::storage::confirmed_block,
         },
+        base64::{engine::general_purpose::STANDARD as base64_engine, Engine},
+        prost::Message as _,
         prost_types::Timestamp,
+        serde_json::Value,
         solana_sdk::{
+            clock::Slot,
             hash::Hash,
+            instruction::InstructionError,
             message::{v0::LoadedAddresses, Message as SolMessage, MessageHeader},
-            pubkey::Pubkey,
+            pubkey::{ParsePubkeyError, Pubkey},
+            signature::{ParseSignatureError, Signature},
             signer::{keypair::Keypair, Signer},
-            transaction::{SanitizedTransaction, Transaction},
+            transaction::{SanitizedTransaction, Transaction, TransactionError},
         },
         solana_transaction_status::TransactionStatusMeta,
+        spl_token_2022::generic_token_account::GenericTokenAccount,
         std::{
-            collections::HashMap,
+            collections::{HashMap, HashSet},
             sync::Arc,
             time::{Duration, SystemTime},
         },
@@ -1207,6 +3859,17 @@ mod tests {
             accounts_data_slice: Vec::new(),
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         };
         let limit = FilterLimits::default();
         let filter = Filter::new(&config, &limit, &mut create_filter_names());
@@ -1224,6 +3887,23 @@ mod tests {
                 account: vec![],
                 owner: vec![],
                 filters: vec![],
+                dedup_by_slot: None,
+                deliver_at_commitment: None,
+                data_fields: vec![],
+                only_from_successful_tx: None,
+                new_accounts_only: None,
+                data_size_max: None,
+                decompress_data: None,
+                snapshot: None,
+                on_owner_change: None,
+                native_program_owned: None,
+                on_data_change_only: None,
+                on_data_change_include_lamports_owner: None,
+                diff: None,
+                rent_epoch_eq: None,
+                rent_epoch_gt: None,
+                rent_epoch_lt: None,
+                executable: None,
             },
         );
 
@@ -1239,6 +3919,17 @@ mod tests {
             accounts_data_slice: Vec::new(),
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         };
         let mut limit = FilterLimits::default();
         limit.accounts.any = false;
@@ -1247,6 +3938,202 @@ mod tests {
         assert!(filter.is_err());
     }
 
+    #[test]
+    fn test_accounts_checksum() {
+        let state = FilterAccountsState::new(
+            &[SubscribeRequestFilterAccountsFilter {
+                filter: Some(AccountsFilterDataOneof::Checksum(
+                    SubscribeRequestFilterAccountsFilterChecksum {
+                        crc_offset: 0,
+                        body_offset: 4,
+                        body_length: 8,
+                    },
+                )),
+            }],
+            &FilterLimitsAccounts::default(),
+        )
+        .unwrap();
+
+        let mut data = vec![0; 4];
+        data.extend_from_slice(b"yellowstone");
+        let crc = crc32fast::hash(&data[4..12]);
+        data[0..4].copy_from_slice(&crc.to_le_bytes());
+        assert_eq!(state.check(&data, 0, 0), Ok(()));
+
+        // corrupted body
+        let mut invalid = data.clone();
+        invalid[5] ^= 0xff;
+        assert_eq!(state.check(&invalid, 0, 0), Err(FilterMismatch::Checksum));
+
+        // body out of data
+        assert_eq!(
+            state.check(&data[0..8], 0, 0),
+            Err(FilterMismatch::Checksum)
+        );
+    }
+
+    #[test]
+    fn test_accounts_data_equals() {
+        let limits = FilterLimitsAccounts {
+            data_equals_max: 165,
+            ..Default::default()
+        };
+        let create_state = |data: Vec<u8>| {
+            FilterAccountsState::new(
+                &[SubscribeRequestFilterAccountsFilter {
+                    filter: Some(AccountsFilterDataOneof::DataEquals(data)),
+                }],
+                &limits,
+            )
+        };
+
+        let state = create_state(vec![0; 165]).unwrap();
+        assert!(!state.is_empty());
+        assert_eq!(state.check(&[0; 165], 0, 0), Ok(()));
+
+        // one byte different
+        let mut data = vec![0; 165];
+        data[100] = 1;
+        assert_eq!(state.check(&data, 0, 0), Err(FilterMismatch::DataEquals));
+
+        // different length with equal prefix
+        assert_eq!(
+            state.check(&[0; 164], 0, 0),
+            Err(FilterMismatch::DataEquals)
+        );
+        assert_eq!(
+            state.check(&[0; 166], 0, 0),
+            Err(FilterMismatch::DataEquals)
+        );
+
+        assert!(matches!(
+            create_state(vec![0; 166]),
+            Err(FilterError::CreateAccountStateDataEqualsMax { max: 165 })
+        ));
+    }
+
+    #[test]
+    fn test_accounts_rent_epoch() {
+        let mut accounts = HashMap::new();
+        for (name, rent_epoch_eq, rent_epoch_gt, rent_epoch_lt) in [
+            ("rent_exempt", Some(u64::MAX), None, None),
+            ("eq", Some(361), None, None),
+            ("gt", None, Some(361), None),
+            ("lt", None, None, Some(361)),
+        ] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    rent_epoch_eq,
+                    rent_epoch_gt,
+                    rent_epoch_lt,
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let mut filter = Filter::new(
+            &config,
+            &FilterLimits::default(),
+            &mut create_filter_names(),
+        )
+        .unwrap();
+
+        let mut get_filters = |rent_epoch| {
+            let Message::Account(mut message) = create_message_account(Pubkey::new_unique(), 10, 1)
+            else {
+                unreachable!();
+            };
+            Arc::make_mut(&mut message.account).rent_epoch = rent_epoch;
+            let updates =
+                filter.get_updates(&Message::Account(message), Some(CommitmentLevel::Processed));
+            let mut filters = updates
+                .iter()
+                .flat_map(|update| update.filters.iter())
+                .map(|name| name.as_ref().to_owned())
+                .collect::<Vec<_>>();
+            filters.sort();
+            filters
+        };
+        assert_eq!(get_filters(u64::MAX), vec!["gt", "rent_exempt"]);
+        assert_eq!(get_filters(361), vec!["eq"]);
+        assert_eq!(get_filters(362), vec!["gt"]);
+        assert_eq!(get_filters(0), vec!["lt"]);
+
+        // comparators are not combined in one filter
+        for (rent_epoch_eq, rent_epoch_gt, rent_epoch_lt) in [
+            (Some(361), Some(361), None),
+            (None, Some(100), Some(361)),
+            (Some(u64::MAX), Some(1), Some(2)),
+        ] {
+            let config = SubscribeRequest {
+                accounts: HashMap::from([(
+                    "rent_epoch".to_owned(),
+                    SubscribeRequestFilterAccounts {
+                        rent_epoch_eq,
+                        rent_epoch_gt,
+                        rent_epoch_lt,
+                        ..Default::default()
+                    },
+                )]),
+                ..Default::default()
+            };
+            assert!(matches!(
+                Filter::new(
+                    &config,
+                    &FilterLimits::default(),
+                    &mut create_filter_names()
+                ),
+                Err(FilterError::CreateAccountState(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_accounts_memcmp_datasize_bounds() {
+        let create_state = |offset: u64| {
+            FilterAccountsState::new(
+                &[
+                    SubscribeRequestFilterAccountsFilter {
+                        filter: Some(AccountsFilterDataOneof::Memcmp(
+                            SubscribeRequestFilterAccountsFilterMemcmp {
+                                offset,
+                                data: Some(AccountsFilterMemcmpOneof::Bytes(vec![1; 32])),
+                            },
+                        )),
+                    },
+                    SubscribeRequestFilterAccountsFilter {
+                        filter: Some(AccountsFilterDataOneof::Datasize(165)),
+                    },
+                ],
+                &FilterLimitsAccounts::default(),
+            )
+        };
+
+        // fits exactly
+        let state = create_state(133).unwrap();
+        let mut data = vec![0; 165];
+        data[133..].fill(1);
+        assert_eq!(state.check(&data, 0, 0), Ok(()));
+
+        // overruns datasize
+        assert!(matches!(
+            create_state(134),
+            Err(FilterError::CreateAccountStateMemcmpOutOfBounds {
+                offset: 134,
+                length: 32,
+                datasize: 165,
+            })
+        ));
+        assert!(matches!(
+            create_state(u64::MAX),
+            Err(FilterError::CreateAccountStateMemcmpOutOfBounds { .. })
+        ));
+    }
+
     #[test]
     fn test_filters_transaction_empty() {
         let mut transactions = HashMap::new();
@@ -1260,6 +4147,13 @@ mod tests {
                 account_include: vec![],
                 account_exclude: vec![],
                 account_required: vec![],
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
             },
         );
 
@@ -1275,6 +4169,17 @@ mod tests {
             accounts_data_slice: Vec::new(),
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         };
         let mut limit = FilterLimits::default();
         limit.transactions.any = false;
@@ -1295,6 +4200,13 @@ mod tests {
                 account_include: vec![],
                 account_exclude: vec![],
                 account_required: vec![],
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
             },
         );
 
@@ -1310,6 +4222,17 @@ mod tests {
             accounts_data_slice: Vec::new(),
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         };
         let mut limit = FilterLimits::default();
         limit.transactions.any = false;
@@ -1336,6 +4259,13 @@ mod tests {
                 account_include,
                 account_exclude: vec![],
                 account_required: vec![],
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
             },
         );
 
@@ -1351,9 +4281,20 @@ mod tests {
             accounts_data_slice: Vec::new(),
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         };
         let limit = FilterLimits::default();
-        let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
 
         let message_transaction =
             create_message_transaction(&keypair_b, vec![account_key_b, account_key_a]);
@@ -1370,7 +4311,7 @@ mod tests {
         ));
 
         config.transactions_status = transactions;
-        let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
         let updates = filter.get_updates(&message, None);
         assert_eq!(updates.len(), 2);
         assert_eq!(
@@ -1401,6 +4342,13 @@ mod tests {
                 account_include,
                 account_exclude: vec![],
                 account_required: vec![],
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
             },
         );
 
@@ -1416,9 +4364,20 @@ mod tests {
             accounts_data_slice: Vec::new(),
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         };
         let limit = FilterLimits::default();
-        let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
 
         let message_transaction =
             create_message_transaction(&keypair_b, vec![account_key_b, account_key_a]);
@@ -1435,7 +4394,7 @@ mod tests {
         ));
 
         config.transactions_status = transactions;
-        let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
         let updates = filter.get_updates(&message, None);
         assert_eq!(updates.len(), 2);
         assert_eq!(
@@ -1466,6 +4425,13 @@ mod tests {
                 account_include: vec![],
                 account_exclude,
                 account_required: vec![],
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
             },
         );
 
@@ -1481,9 +4447,20 @@ mod tests {
             accounts_data_slice: Vec::new(),
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         };
         let limit = FilterLimits::default();
-        let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
 
         let message_transaction =
             create_message_transaction(&keypair_b, vec![account_key_b, account_key_a]);
@@ -1517,6 +4494,13 @@ mod tests {
                 account_include,
                 account_exclude: vec![],
                 account_required,
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
             },
         );
 
@@ -1532,9 +4516,20 @@ mod tests {
             accounts_data_slice: Vec::new(),
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         };
         let limit = FilterLimits::default();
-        let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
 
         let message_transaction = create_message_transaction(
             &keypair_x,
@@ -1553,7 +4548,7 @@ mod tests {
         ));
 
         config.transactions_status = transactions;
-        let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
         let updates = filter.get_updates(&message, None);
         assert_eq!(updates.len(), 2);
         assert_eq!(
@@ -1590,6 +4585,13 @@ mod tests {
                 account_include,
                 account_exclude: vec![],
                 account_required,
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
             },
         );
 
@@ -1605,9 +4607,20 @@ mod tests {
             accounts_data_slice: Vec::new(),
             ping: None,
             from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
         };
         let limit = FilterLimits::default();
-        let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
 
         let message_transaction =
             create_message_transaction(&keypair_x, vec![account_key_x, account_key_z]);
@@ -1616,4 +4629,4511 @@ mod tests {
             assert!(message.filters.is_empty());
         }
     }
+
+    #[test]
+    fn test_transaction_dedup_signatures() {
+        let mut transactions = HashMap::new();
+
+        let keypair_a = Keypair::new();
+        let account_key_a = keypair_a.pubkey();
+        let keypair_b = Keypair::new();
+        let account_key_b = keypair_b.pubkey();
+        transactions.insert(
+            "serum".to_string(),
+            SubscribeRequestFilterTransactions {
+                vote: None,
+                failed: None,
+                signature: None,
+                account_include: vec![],
+                account_exclude: vec![],
+                account_required: vec![],
+                program_include: vec![],
+                dedup_signatures: Some(true),
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
+            },
+        );
+
+        let config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots: HashMap::new(),
+            transactions,
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let mut limit = FilterLimits::default();
+        limit.transactions.dedup_signatures_window = 1;
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let message_a =
+            Message::Transaction(create_message_transaction(&keypair_a, vec![account_key_a]));
+        let message_b =
+            Message::Transaction(create_message_transaction(&keypair_b, vec![account_key_b]));
+
+        // repeated signature within the window is delivered once
+        assert_eq!(filter.get_updates(&message_a, None).len(), 1);
+        assert_eq!(filter.get_updates(&message_a, None).len(), 0);
+
+        // signature evicted from the window is delivered again
+        assert_eq!(filter.get_updates(&message_b, None).len(), 1);
+        assert_eq!(filter.get_updates(&message_a, None).len(), 1);
+    }
+
+    #[test]
+    fn test_transaction_program_include() {
+        let mut transactions = HashMap::new();
+
+        let keypair_b = Keypair::new();
+        let account_key_b = keypair_b.pubkey();
+        let program_a = Pubkey::new_unique();
+        let program_c = Pubkey::new_unique();
+        let program_include = [program_a].iter().map(|k| k.to_string()).collect();
+        transactions.insert(
+            "serum".to_string(),
+            SubscribeRequestFilterTransactions {
+                vote: None,
+                failed: None,
+                signature: None,
+                account_include: vec![],
+                account_exclude: vec![],
+                account_required: vec![],
+                program_include,
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
+            },
+        );
+
+        let config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots: HashMap::new(),
+            transactions,
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        // account keys: 0 - signer, 1 - program_a, 2 - program_c
+        let create_message = |compiled: Option<u32>, inner: Option<u32>| {
+            let mut message_transaction =
+                create_message_transaction(&keypair_b, vec![account_key_b, program_a, program_c]);
+            let info = Arc::make_mut(&mut message_transaction.transaction);
+            if let Some(program_id_index) = compiled {
+                let message = info.transaction.message.as_mut().expect("defined message");
+                message
+                    .instructions
+                    .push(confirmed_block::CompiledInstruction {
+                        program_id_index,
+                        accounts: vec![1],
+                        data: vec![],
+                    });
+            }
+            if let Some(program_id_index) = inner {
+                info.meta
+                    .inner_instructions
+                    .push(confirmed_block::InnerInstructions {
+                        index: 0,
+                        instructions: vec![confirmed_block::InnerInstruction {
+                            program_id_index,
+                            accounts: vec![1],
+                            data: vec![],
+                            stack_height: Some(2),
+                        }],
+                    });
+            }
+            Message::Transaction(message_transaction)
+        };
+
+        // program_a is present as an account only
+        let message = create_message(Some(2), Some(2));
+        assert_eq!(filter.get_updates(&message, None).len(), 0);
+
+        // program_a invoked by compiled instruction
+        let message = create_message(Some(1), None);
+        let updates = filter.get_updates(&message, None);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].filters,
+            FilteredUpdateFilters::from_vec(vec![FilterName::new("serum")])
+        );
+
+        // program_a invoked by inner instruction
+        let message = create_message(Some(2), Some(1));
+        assert_eq!(filter.get_updates(&message, None).len(), 1);
+    }
+
+    #[test]
+    fn test_transaction_cpi_program_include() {
+        let keypair = Keypair::new();
+        let program_a = Pubkey::new_unique();
+        let program_b = Pubkey::new_unique();
+
+        let mut transactions = HashMap::new();
+        transactions.insert(
+            "cpi".to_owned(),
+            SubscribeRequestFilterTransactions {
+                cpi_program_include: vec![program_a.to_string()],
+                ..Default::default()
+            },
+        );
+        let config = SubscribeRequest {
+            transactions,
+            ..Default::default()
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        // account keys: 0 - signer, 1 - program_a, 2 - program_b
+        let create_message = |compiled: u32, inner: &[u32]| {
+            let mut message_transaction =
+                create_message_transaction(&keypair, vec![keypair.pubkey(), program_a, program_b]);
+            let info = Arc::make_mut(&mut message_transaction.transaction);
+            let message = info.transaction.message.as_mut().expect("defined message");
+            message
+                .instructions
+                .push(confirmed_block::CompiledInstruction {
+                    program_id_index: compiled,
+                    accounts: vec![],
+                    data: vec![],
+                });
+            info.meta
+                .inner_instructions
+                .push(confirmed_block::InnerInstructions {
+                    index: 0,
+                    instructions: inner
+                        .iter()
+                        .enumerate()
+                        .map(
+                            |(depth, program_id_index)| confirmed_block::InnerInstruction {
+                                program_id_index: *program_id_index,
+                                accounts: vec![],
+                                data: vec![],
+                                stack_height: Some(depth as u32 + 2),
+                            },
+                        )
+                        .collect(),
+                });
+            Message::Transaction(message_transaction)
+        };
+
+        // program_a called through nested CPI
+        let message = create_message(2, &[2, 1]);
+        assert_eq!(filter.get_updates(&message, None).len(), 1);
+
+        // program_a invoked only by top-level instruction
+        let message = create_message(1, &[2]);
+        assert_eq!(filter.get_updates(&message, None).len(), 0);
+
+        // no inner instructions
+        let message = create_message(1, &[]);
+        assert_eq!(filter.get_updates(&message, None).len(), 0);
+    }
+
+    #[test]
+    fn test_transaction_signer_include() {
+        let keypair = Keypair::new();
+        let signer = keypair.pubkey();
+        let writable = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+
+        let mut transactions = HashMap::new();
+        for (name, pubkey) in [
+            ("signer", signer),
+            ("writable", writable),
+            ("readonly", readonly),
+        ] {
+            transactions.insert(
+                name.to_owned(),
+                SubscribeRequestFilterTransactions {
+                    signer_include: vec![pubkey.to_string()],
+                    ..Default::default()
+                },
+            );
+        }
+        transactions.insert(
+            "account".to_owned(),
+            SubscribeRequestFilterTransactions {
+                account_include: vec![writable.to_string()],
+                ..Default::default()
+            },
+        );
+        let config = SubscribeRequest {
+            transactions,
+            ..Default::default()
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        // account keys: 0 - signer, 1 - writable non-signer, 2 - readonly non-signer
+        let mut message_transaction =
+            create_message_transaction(&keypair, vec![signer, writable, readonly]);
+        let info = Arc::make_mut(&mut message_transaction.transaction);
+        let message = info.transaction.message.as_mut().expect("defined message");
+        message
+            .header
+            .as_mut()
+            .expect("defined header")
+            .num_readonly_unsigned_accounts = 1;
+        let message = Message::Transaction(message_transaction);
+
+        let updates = filter.get_updates(&message, None);
+        assert_eq!(updates.len(), 1);
+        let mut filters = updates[0]
+            .filters
+            .iter()
+            .map(|name| name.as_ref())
+            .collect::<Vec<_>>();
+        filters.sort();
+        assert_eq!(filters, ["account", "signer"]);
+    }
+
+    #[test]
+    fn test_transaction_account_count() {
+        let keypair = Keypair::new();
+
+        let mut transactions = HashMap::new();
+        for (name, account_count_min, account_count_max) in [
+            ("min", Some(3), None),
+            ("max", None, Some(3)),
+            ("range", Some(2), Some(4)),
+        ] {
+            transactions.insert(
+                name.to_owned(),
+                SubscribeRequestFilterTransactions {
+                    account_count_min,
+                    account_count_max,
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            transactions,
+            ..Default::default()
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        for (count, expected) in [
+            (1, vec!["max"]),
+            (2, vec!["max", "range"]),
+            (3, vec!["max", "min", "range"]),
+            (4, vec!["min", "range"]),
+            (5, vec!["min"]),
+        ] {
+            let mut keys = vec![keypair.pubkey()];
+            keys.extend((1..count).map(|_| Pubkey::new_unique()));
+            let message = Message::Transaction(create_message_transaction(&keypair, keys));
+
+            let updates = filter.get_updates(&message, None);
+            assert_eq!(updates.len(), 1);
+            let mut filters = updates[0]
+                .filters
+                .iter()
+                .map(|name| name.as_ref())
+                .collect::<Vec<_>>();
+            filters.sort();
+            assert_eq!(filters, expected, "account keys: {count}");
+        }
+
+        // empty range
+        let mut transactions = HashMap::new();
+        transactions.insert(
+            "invalid".to_owned(),
+            SubscribeRequestFilterTransactions {
+                account_count_min: Some(4),
+                account_count_max: Some(3),
+                ..Default::default()
+            },
+        );
+        let config = SubscribeRequest {
+            transactions,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::CreateTransactionsAccountCountRange)
+        ));
+    }
+
+    #[test]
+    fn test_transaction_error_match() {
+        let keypair = Keypair::new();
+        let create_message = |error: Option<TransactionError>| {
+            let mut message = create_message_transaction(&keypair, vec![keypair.pubkey()]);
+            Arc::make_mut(&mut message.transaction).meta.err =
+                error.map(|error| confirmed_block::TransactionError {
+                    err: bincode::serialize(&error).unwrap(),
+                });
+            Message::Transaction(message)
+        };
+
+        let mut transactions = HashMap::new();
+        for (name, error, instruction_index, instruction_error) in [
+            ("fee", "InsufficientFundsForFee", None, None),
+            ("rent", "InsufficientFundsForRent", None, None),
+            ("instruction", "InstructionError", None, None),
+            ("instruction_1", "InstructionError", Some(1), None),
+            ("custom", "InstructionError", None, Some("Custom")),
+        ] {
+            transactions.insert(
+                name.to_owned(),
+                SubscribeRequestFilterTransactions {
+                    error_match: Some(SubscribeRequestFilterTransactionsError {
+                        error: error.to_owned(),
+                        instruction_index,
+                        instruction_error: instruction_error.map(ToOwned::to_owned),
+                    }),
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            transactions,
+            ..Default::default()
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        for (error, expected) in [
+            (None, vec![]),
+            (Some(TransactionError::InsufficientFundsForFee), vec!["fee"]),
+            (
+                Some(TransactionError::InsufficientFundsForRent { account_index: 1 }),
+                vec!["rent"],
+            ),
+            (Some(TransactionError::AccountInUse), vec![]),
+            (
+                Some(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(1),
+                )),
+                vec!["custom", "instruction"],
+            ),
+            (
+                Some(TransactionError::InstructionError(
+                    1,
+                    InstructionError::InvalidAccountData,
+                )),
+                vec!["instruction", "instruction_1"],
+            ),
+        ] {
+            let updates = filter.get_updates(&create_message(error.clone()), None);
+            let mut filters = updates
+                .iter()
+                .flat_map(|update| update.filters.iter())
+                .map(|name| name.as_ref())
+                .collect::<Vec<_>>();
+            filters.sort();
+            assert_eq!(filters, expected, "error: {error:?}");
+        }
+
+        // undecodable error never matches
+        let mut message = create_message_transaction(&keypair, vec![keypair.pubkey()]);
+        Arc::make_mut(&mut message.transaction).meta.err =
+            Some(confirmed_block::TransactionError { err: vec![255; 4] });
+        assert!(filter
+            .get_updates(&Message::Transaction(message), None)
+            .is_empty());
+
+        for (error, instruction_index, instruction_error) in [
+            ("", None, None),
+            ("InsufficientFundsForFee", Some(0), None),
+            ("AccountInUse", None, Some("Custom")),
+            ("InstructionError", Some(256), None),
+            ("InsufficientFundsForFe", None, None),
+            ("InstructionError", None, Some("Custom(1)")),
+        ] {
+            let mut transactions = HashMap::new();
+            transactions.insert(
+                "invalid".to_owned(),
+                SubscribeRequestFilterTransactions {
+                    error_match: Some(SubscribeRequestFilterTransactionsError {
+                        error: error.to_owned(),
+                        instruction_index,
+                        instruction_error: instruction_error.map(ToOwned::to_owned),
+                    }),
+                    ..Default::default()
+                },
+            );
+            let config = SubscribeRequest {
+                transactions,
+                ..Default::default()
+            };
+            assert!(matches!(
+                Filter::new(&config, &limit, &mut create_filter_names()),
+                Err(FilterError::CreateTransactionsErrorMatch(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_transaction_status_failed_only() {
+        let keypair = Keypair::new();
+        let create_message = |is_failed: bool| {
+            let mut message = create_message_transaction(&keypair, vec![keypair.pubkey()]);
+            if is_failed {
+                Arc::make_mut(&mut message.transaction).meta.err =
+                    Some(confirmed_block::TransactionError { err: vec![] });
+            }
+            Message::Transaction(message)
+        };
+
+        // `failed` only (fast path) and `failed` with another predicate
+        for vote in [None, Some(true)] {
+            let mut transactions_status = HashMap::new();
+            transactions_status.insert(
+                "failed".to_owned(),
+                SubscribeRequestFilterTransactions {
+                    vote,
+                    failed: Some(true),
+                    ..Default::default()
+                },
+            );
+            let config = SubscribeRequest {
+                transactions_status,
+                ..Default::default()
+            };
+            let mut filter = Filter::new(
+                &config,
+                &FilterLimits::default(),
+                &mut create_filter_names(),
+            )
+            .unwrap();
+
+            assert!(filter.get_updates(&create_message(false), None).is_empty());
+            let updates = filter.get_updates(&create_message(true), None);
+            assert_eq!(updates.len(), 1);
+            assert_eq!(
+                updates[0].filters,
+                FilteredUpdateFilters::from_vec(vec![FilterName::new("failed")])
+            );
+            assert!(matches!(
+                updates[0].message,
+                FilteredUpdateOneof::TransactionStatus(_)
+            ));
+        }
+
+        // another filter without `failed` receives successful transactions
+        let mut transactions_status = HashMap::new();
+        for (name, failed) in [("failed", Some(true)), ("all", None)] {
+            transactions_status.insert(
+                name.to_owned(),
+                SubscribeRequestFilterTransactions {
+                    failed,
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            transactions_status,
+            ..Default::default()
+        };
+        let mut filter = Filter::new(
+            &config,
+            &FilterLimits::default(),
+            &mut create_filter_names(),
+        )
+        .unwrap();
+        let updates = filter.get_updates(&create_message(false), None);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].filters,
+            FilteredUpdateFilters::from_vec(vec![FilterName::new("all")])
+        );
+    }
+
+    #[test]
+    fn test_blocks_meta_rewards_only() {
+        let mut blocks_meta = HashMap::new();
+        for (name, rewards_only) in [("full", None), ("rewards", Some(true))] {
+            blocks_meta.insert(
+                name.to_owned(),
+                SubscribeRequestFilterBlocksMeta { rewards_only },
+            );
+        }
+        let config = SubscribeRequest {
+            blocks_meta,
+            ..Default::default()
+        };
+        let limits = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limits, &mut create_filter_names()).unwrap();
+
+        let rewards = confirmed_block::Rewards {
+            rewards: vec![confirmed_block::Reward {
+                pubkey: Pubkey::new_unique().to_string(),
+                lamports: 42,
+                post_balance: 1_000,
+                reward_type: confirmed_block::RewardType::Voting as i32,
+                commission: "10".to_owned(),
+            }],
+            num_partitions: None,
+        };
+        let block_meta = SubscribeUpdateBlockMeta {
+            slot: 42,
+            blockhash: Hash::new_unique().to_string(),
+            rewards: Some(rewards.clone()),
+            block_time: Some(confirmed_block::UnixTimestamp {
+                timestamp: 1_700_000_000,
+            }),
+            block_height: Some(confirmed_block::BlockHeight { block_height: 40 }),
+            parent_slot: 41,
+            parent_blockhash: Hash::new_unique().to_string(),
+            executed_transaction_count: 1_000,
+            entries_count: 64,
+        };
+        let message = Message::BlockMeta(Arc::new(MessageBlockMeta {
+            block_meta: block_meta.clone(),
+            created_at: Timestamp::from(SystemTime::now()),
+        }));
+        let updates = filter.get_updates(&message, None);
+        assert_eq!(updates.len(), 2);
+        assert_eq!(
+            updates[0].filters,
+            FilteredUpdateFilters::from_vec(vec![FilterName::new("full")])
+        );
+        assert_eq!(
+            updates[1].filters,
+            FilteredUpdateFilters::from_vec(vec![FilterName::new("rewards")])
+        );
+
+        let FilteredUpdateOneof::BlockMeta(full) = &updates[0].message else {
+            panic!("expected block meta");
+        };
+        let FilteredUpdateOneof::BlockMeta(rewards_only) = &updates[1].message else {
+            panic!("expected block meta");
+        };
+        assert_eq!(full.block_meta, block_meta);
+        assert_eq!(
+            rewards_only.block_meta,
+            SubscribeUpdateBlockMeta {
+                slot: 42,
+                rewards: Some(rewards),
+                ..Default::default()
+            }
+        );
+
+        // payload is smaller by size of omitted fields
+        let size_full = updates[0].message.encoded_len();
+        let size_rewards_only = updates[1].message.encoded_len();
+        assert!(size_rewards_only < size_full);
+        assert!(size_full - size_rewards_only > 2 * 32);
+    }
+
+    #[test]
+    fn test_program_events() {
+        let program = Pubkey::new_unique();
+        let program_other = Pubkey::new_unique();
+        let mut program_events = HashMap::new();
+        program_events.insert(
+            "events".to_owned(),
+            SubscribeRequestFilterProgramEvents {
+                program: program.to_string(),
+            },
+        );
+        let config = SubscribeRequest {
+            program_events,
+            ..Default::default()
+        };
+        let mut filter = Filter::new(
+            &config,
+            &FilterLimits::default(),
+            &mut create_filter_names(),
+        )
+        .unwrap();
+
+        let event = (1..=16).collect::<Vec<u8>>();
+        let event_log = (17..=32).collect::<Vec<u8>>();
+        let logs = vec![
+            format!("Program {program} invoke [1]"),
+            "Program log: Instruction: Swap".to_owned(),
+            format!(
+                "Program data: {} {}",
+                base64_engine.encode(&event),
+                base64_engine.encode([9u8, 9])
+            ),
+            format!("Program {program_other} invoke [2]"),
+            // logs of other programs are skipped
+            format!("Program data: {}", base64_engine.encode([1u8; 16])),
+            format!("Program {program_other} success"),
+            // event of older Anchor versions
+            format!("Program log: {}", base64_engine.encode(&event_log)),
+            // malformed lines are skipped
+            "Program data: not-base64!".to_owned(),
+            format!("Program log: {}", base64_engine.encode([1u8, 2])),
+            format!("Program {program} consumed 1000 of 200000 compute units"),
+            format!("Program {program} success"),
+            // outside of the program invocation
+            format!("Program data: {}", base64_engine.encode(&event)),
+        ];
+        let create_message = |is_failed: bool| {
+            let mut message = create_message_transaction(&Keypair::new(), vec![program]);
+            let info = Arc::make_mut(&mut message.transaction);
+            info.meta.log_messages = logs.clone();
+            if is_failed {
+                info.meta.err = Some(confirmed_block::TransactionError { err: vec![] });
+            }
+            Message::Transaction(message)
+        };
+
+        let updates = filter.get_updates(&create_message(false), None);
+        let events = updates
+            .iter()
+            .map(|update| {
+                assert_eq!(
+                    update.filters,
+                    FilteredUpdateFilters::from_vec(vec![FilterName::new("events")])
+                );
+                let FilteredUpdateOneof::ProgramEvent(msg) = &update.message else {
+                    panic!("expected program event");
+                };
+                assert_eq!(msg.program, program.to_bytes().to_vec());
+                assert_eq!(msg.slot, 100);
+                (msg.log_index, msg.data.clone())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![(2, vec![event, vec![9, 9]]), (6, vec![event_log])]
+        );
+
+        // events of failed transactions are skipped
+        assert!(filter.get_updates(&create_message(true), None).is_empty());
+    }
+
+    #[test]
+    fn test_accounts_owner_index() {
+        let pubkey = Pubkey::new_unique();
+        let owner_x = Pubkey::new_unique();
+        let owner_y = Pubkey::new_unique();
+
+        let mut accounts = HashMap::new();
+        for (name, account, owner) in [
+            ("any", vec![], vec![]),
+            ("account", vec![pubkey], vec![]),
+            ("x", vec![], vec![owner_x]),
+            ("y", vec![], vec![owner_y]),
+            ("xy", vec![], vec![owner_x, owner_y]),
+            ("account_x", vec![pubkey], vec![owner_x]),
+        ] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    account: account.iter().map(|key| key.to_string()).collect(),
+                    owner: owner.iter().map(|key| key.to_string()).collect(),
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        for (owner, expected) in [
+            (owner_x, vec!["account", "account_x", "any", "x", "xy"]),
+            (owner_y, vec!["account", "any", "xy", "y"]),
+            (Pubkey::new_unique(), vec!["account", "any"]),
+        ] {
+            let mut message = create_message_account(pubkey, 100, 1);
+            let Message::Account(account) = &mut message else {
+                unreachable!();
+            };
+            Arc::make_mut(&mut account.account).owner = owner;
+
+            let updates = filter.get_updates(&message, Some(CommitmentLevel::Processed));
+            assert_eq!(updates.len(), 1);
+            let mut filters = updates[0]
+                .filters
+                .iter()
+                .map(|name| name.as_ref())
+                .collect::<Vec<_>>();
+            filters.sort();
+            assert_eq!(filters, expected);
+
+            // same filters as without the index
+            let explains = filter.explain(&message).unwrap();
+            let mut explained = explains
+                .iter()
+                .filter(|explain| explain.mismatch.is_none())
+                .map(|explain| explain.name.as_ref())
+                .collect::<Vec<_>>();
+            explained.sort();
+            assert_eq!(explained, expected);
+        }
+    }
+
+    #[test]
+    fn test_accounts_owner_allowlist() {
+        let pubkey = Pubkey::new_unique();
+        let allowed = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let mut accounts = HashMap::new();
+        for (name, owner) in [("all", vec![]), ("other", vec![other.to_string()])] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    owner,
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let limit = FilterLimits {
+            accounts: FilterLimitsAccounts {
+                owner_allowlist: HashSet::from([allowed]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let create_message = |owner| {
+            let mut message = create_message_account(pubkey, 10, 1);
+            let Message::Account(account) = &mut message else {
+                unreachable!();
+            };
+            Arc::make_mut(&mut account.account).owner = owner;
+            message
+        };
+
+        // broad filter receives only allowed owners
+        let updates =
+            filter.get_updates(&create_message(allowed), Some(CommitmentLevel::Processed));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].filters,
+            FilteredUpdateFilters::from_vec(vec![FilterName::new("all")])
+        );
+
+        // owner requested by the client but not allowed by the server
+        let message = create_message(other);
+        assert!(filter
+            .get_updates(&message, Some(CommitmentLevel::Processed))
+            .is_empty());
+        for explain in filter.explain(&message).unwrap() {
+            assert_eq!(explain.mismatch, Some(FilterMismatch::OwnerAllowlist));
+        }
+    }
+
+    #[test]
+    fn test_explain_accounts() {
+        let pubkey = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let other = Pubkey::new_unique().to_string();
+
+        let create_filters = |filter| {
+            vec![SubscribeRequestFilterAccountsFilter {
+                filter: Some(filter),
+            }]
+        };
+        let mut accounts = HashMap::new();
+        for (name, filter) in [
+            (
+                "match",
+                SubscribeRequestFilterAccounts {
+                    owner: vec![owner.to_string()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "nonempty_txn_signature",
+                SubscribeRequestFilterAccounts {
+                    nonempty_txn_signature: Some(true),
+                    ..Default::default()
+                },
+            ),
+            (
+                "account",
+                SubscribeRequestFilterAccounts {
+                    account: vec![other.clone()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "owner",
+                SubscribeRequestFilterAccounts {
+                    owner: vec![other.clone()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "data_size_max",
+                SubscribeRequestFilterAccounts {
+                    data_size_max: Some(10),
+                    ..Default::default()
+                },
+            ),
+            (
+                "native_program_owned",
+                SubscribeRequestFilterAccounts {
+                    native_program_owned: Some(true),
+                    ..Default::default()
+                },
+            ),
+            (
+                "datasize",
+                SubscribeRequestFilterAccounts {
+                    filters: create_filters(AccountsFilterDataOneof::Datasize(100)),
+                    ..Default::default()
+                },
+            ),
+            (
+                "lamports",
+                SubscribeRequestFilterAccounts {
+                    filters: create_filters(AccountsFilterDataOneof::Lamports(
+                        SubscribeRequestFilterAccountsFilterLamports {
+                            cmp: Some(AccountsFilterLamports::Eq(1)),
+                        },
+                    )),
+                    ..Default::default()
+                },
+            ),
+            (
+                "data_equals",
+                SubscribeRequestFilterAccounts {
+                    filters: create_filters(AccountsFilterDataOneof::DataEquals(vec![1; 165])),
+                    ..Default::default()
+                },
+            ),
+            (
+                "memcmp",
+                SubscribeRequestFilterAccounts {
+                    filters: create_filters(AccountsFilterDataOneof::Memcmp(
+                        SubscribeRequestFilterAccountsFilterMemcmp {
+                            offset: 8,
+                            data: Some(AccountsFilterMemcmpOneof::Bytes(vec![2])),
+                        },
+                    )),
+                    ..Default::default()
+                },
+            ),
+            (
+                "token_account_state",
+                SubscribeRequestFilterAccounts {
+                    filters: create_filters(AccountsFilterDataOneof::TokenAccountState(true)),
+                    ..Default::default()
+                },
+            ),
+            (
+                "checksum",
+                SubscribeRequestFilterAccounts {
+                    filters: create_filters(AccountsFilterDataOneof::Checksum(
+                        SubscribeRequestFilterAccountsFilterChecksum {
+                            crc_offset: 0,
+                            body_offset: 4,
+                            body_length: 8,
+                        },
+                    )),
+                    ..Default::default()
+                },
+            ),
+        ] {
+            accounts.insert(name.to_owned(), filter);
+        }
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let limit = FilterLimits::default();
+        let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let message = Message::Account(MessageAccount {
+            account: Arc::new(MessageAccountInfo {
+                pubkey,
+                lamports: 10,
+                owner,
+                executable: false,
+                rent_epoch: 0,
+                data: vec![0; 165],
+                write_version: 1,
+                txn_signature: None,
+                data_checksum: None,
+            }),
+            slot: 100,
+            is_startup: false,
+            created_at: Timestamp::from(SystemTime::now()),
+        });
+        let explains = filter
+            .explain(&message)
+            .expect("account update is supported")
+            .into_iter()
+            .map(|explain| {
+                assert_eq!(explain.kind, "accounts");
+                (explain.name.as_ref().to_owned(), explain.mismatch)
+            })
+            .collect::<HashMap<_, _>>();
+        assert_eq!(
+            explains,
+            HashMap::from([
+                ("match".to_owned(), None),
+                (
+                    "nonempty_txn_signature".to_owned(),
+                    Some(FilterMismatch::NonemptyTxnSignature)
+                ),
+                ("account".to_owned(), Some(FilterMismatch::Account)),
+                ("owner".to_owned(), Some(FilterMismatch::Owner)),
+                (
+                    "data_size_max".to_owned(),
+                    Some(FilterMismatch::DataSizeMax)
+                ),
+                (
+                    "native_program_owned".to_owned(),
+                    Some(FilterMismatch::NativeProgramOwned)
+                ),
+                ("datasize".to_owned(), Some(FilterMismatch::Datasize)),
+                ("lamports".to_owned(), Some(FilterMismatch::Lamports)),
+                ("data_equals".to_owned(), Some(FilterMismatch::DataEquals)),
+                (
+                    "memcmp".to_owned(),
+                    Some(FilterMismatch::Memcmp { offset: 8 })
+                ),
+                (
+                    "token_account_state".to_owned(),
+                    Some(FilterMismatch::TokenAccountState)
+                ),
+                ("checksum".to_owned(), Some(FilterMismatch::Checksum)),
+            ])
+        );
+        assert_eq!(
+            FilterMismatch::Memcmp { offset: 8 }.to_string(),
+            "memcmp mismatch at offset 8"
+        );
+
+        // explain doesn't produce updates, filter is not changed
+        assert_eq!(
+            filter.clone().get_updates(&message, None)[0].filters,
+            FilteredUpdateFilters::from_vec(vec![FilterName::new("match")])
+        );
+    }
+
+    #[test]
+    fn test_explain_transactions() {
+        let keypair = Keypair::new();
+        let account = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let other = Pubkey::new_unique().to_string();
+
+        let mut transactions = HashMap::new();
+        for (name, filter) in [
+            ("match", SubscribeRequestFilterTransactions::default()),
+            (
+                "vote",
+                SubscribeRequestFilterTransactions {
+                    vote: Some(true),
+                    ..Default::default()
+                },
+            ),
+            (
+                "failed",
+                SubscribeRequestFilterTransactions {
+                    failed: Some(true),
+                    ..Default::default()
+                },
+            ),
+            (
+                "signature",
+                SubscribeRequestFilterTransactions {
+                    signature: Some(Signature::new_unique().to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "account_include",
+                SubscribeRequestFilterTransactions {
+                    account_include: vec![other.clone()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "account_exclude",
+                SubscribeRequestFilterTransactions {
+                    account_exclude: vec![account.to_string()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "account_required",
+                SubscribeRequestFilterTransactions {
+                    account_required: vec![account.to_string(), other.clone()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "signer_include",
+                SubscribeRequestFilterTransactions {
+                    signer_include: vec![account.to_string()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "program_include",
+                SubscribeRequestFilterTransactions {
+                    program_include: vec![other.clone()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "cpi_program_include",
+                SubscribeRequestFilterTransactions {
+                    cpi_program_include: vec![program.to_string()],
+                    ..Default::default()
+                },
+            ),
+        ] {
+            transactions.insert(name.to_owned(), filter);
+        }
+        let mut transactions_status = HashMap::new();
+        transactions_status.insert("status".to_owned(), Default::default());
+
+        // account keys: 0 - signer, 1 - non-signer account, 2 - program invoked by top-level
+        // instruction
+        let mut message_transaction =
+            create_message_transaction(&keypair, vec![keypair.pubkey(), account, program]);
+        let info = Arc::make_mut(&mut message_transaction.transaction);
+        info.is_vote = false;
+        info.transaction
+            .message
+            .as_mut()
+            .expect("defined message")
+            .instructions
+            .push(confirmed_block::CompiledInstruction {
+                program_id_index: 2,
+                accounts: vec![1],
+                data: vec![],
+            });
+        let message = Message::Transaction(message_transaction);
+
+        for exclude_votes in [false, true] {
+            let config = SubscribeRequest {
+                transactions: transactions.clone(),
+                transactions_status: transactions_status.clone(),
+                exclude_votes: Some(exclude_votes),
+                ..Default::default()
+            };
+            let limit = FilterLimits::default();
+            let filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+            let explains = filter
+                .explain(&message)
+                .expect("transaction update is supported")
+                .into_iter()
+                .map(|explain| {
+                    (
+                        (explain.kind, explain.name.as_ref().to_owned()),
+                        explain.mismatch,
+                    )
+                })
+                .collect::<HashMap<_, _>>();
+            assert_eq!(
+                explains,
+                HashMap::from([
+                    (("transactions", "match".to_owned()), None),
+                    (
+                        ("transactions", "vote".to_owned()),
+                        Some(FilterMismatch::Vote)
+                    ),
+                    (
+                        ("transactions", "failed".to_owned()),
+                        Some(FilterMismatch::Failed)
+                    ),
+                    (
+                        ("transactions", "signature".to_owned()),
+                        Some(FilterMismatch::Signature)
+                    ),
+                    (
+                        ("transactions", "account_include".to_owned()),
+                        Some(FilterMismatch::AccountInclude)
+                    ),
+                    (
+                        ("transactions", "account_exclude".to_owned()),
+                        Some(FilterMismatch::AccountExclude)
+                    ),
+                    (
+                        ("transactions", "account_required".to_owned()),
+                        Some(FilterMismatch::AccountRequired)
+                    ),
+                    (
+                        ("transactions", "signer_include".to_owned()),
+                        Some(FilterMismatch::SignerInclude)
+                    ),
+                    (
+                        ("transactions", "program_include".to_owned()),
+                        Some(FilterMismatch::ProgramInclude)
+                    ),
+                    (
+                        ("transactions", "cpi_program_include".to_owned()),
+                        Some(FilterMismatch::CpiProgramInclude)
+                    ),
+                    (("transactions_status", "status".to_owned()), None),
+                ])
+            );
+
+            // votes are excluded from transactions filters only
+            let mut message = match &message {
+                Message::Transaction(message) => message.clone(),
+                _ => unreachable!(),
+            };
+            Arc::make_mut(&mut message.transaction).is_vote = true;
+            let explains = filter
+                .explain(&Message::Transaction(message))
+                .expect("transaction update is supported");
+            for explain in explains {
+                match (explain.kind, explain.name.as_ref()) {
+                    ("transactions_status", _) => assert_eq!(explain.mismatch, None),
+                    ("transactions", "match" | "vote") if exclude_votes => {
+                        assert_eq!(explain.mismatch, Some(FilterMismatch::ExcludeVotes))
+                    }
+                    ("transactions", "match" | "vote") => assert_eq!(explain.mismatch, None),
+                    _ => assert!(explain.mismatch.is_some()),
+                }
+            }
+        }
+
+        // only account and transaction updates are supported
+        let filter = Filter::new(
+            &SubscribeRequest::default(),
+            &FilterLimits::default(),
+            &mut create_filter_names(),
+        )
+        .unwrap();
+        assert!(filter
+            .explain(&create_message_account(Pubkey::new_unique(), 100, 1))
+            .is_some());
+        assert!(filter
+            .explain(&Message::BlockMeta(Arc::new(MessageBlockMeta {
+                block_meta: SubscribeUpdateBlockMeta::default(),
+                created_at: Timestamp::from(SystemTime::now()),
+            })))
+            .is_none());
+    }
+
+    #[test]
+    fn test_transaction_include_from_update_oneof() {
+        let mut transactions = HashMap::new();
+
+        let keypair_a = Keypair::new();
+        let account_key_a = keypair_a.pubkey();
+        let keypair_b = Keypair::new();
+        let account_key_b = keypair_b.pubkey();
+        let account_include = [account_key_a].iter().map(|k| k.to_string()).collect();
+        transactions.insert(
+            "serum".to_string(),
+            SubscribeRequestFilterTransactions {
+                vote: None,
+                failed: None,
+                signature: None,
+                account_include,
+                account_exclude: vec![],
+                account_required: vec![],
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
+            },
+        );
+
+        let config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots: HashMap::new(),
+            transactions,
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        // reconstruct transaction from the update, as replay tools do
+        let message_transaction =
+            create_message_transaction(&keypair_b, vec![account_key_b, account_key_a]);
+        let update =
+            FilteredUpdate::new_empty(FilteredUpdateOneof::transaction(&message_transaction))
+                .as_subscribe_update();
+        let message = Message::from_update_oneof(
+            update.update_oneof.expect("defined update"),
+            update.created_at.expect("defined created_at"),
+        )
+        .expect("valid update");
+
+        let updates = filter.get_updates(&message, None);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].filters,
+            FilteredUpdateFilters::from_vec(vec![FilterName::new("serum")])
+        );
+        assert!(matches!(
+            updates[0].message,
+            FilteredUpdateOneof::Transaction(_)
+        ));
+    }
+
+    fn create_message_account(pubkey: Pubkey, slot: Slot, write_version: u64) -> Message {
+        Message::Account(MessageAccount {
+            account: Arc::new(MessageAccountInfo {
+                pubkey,
+                lamports: write_version,
+                owner: Pubkey::default(),
+                executable: false,
+                rent_epoch: 0,
+                data: vec![],
+                write_version,
+                txn_signature: None,
+                data_checksum: None,
+            }),
+            slot,
+            is_startup: false,
+            created_at: Timestamp::from(SystemTime::now()),
+        })
+    }
+
+    fn get_account_write_version(update: &FilteredUpdate) -> u64 {
+        match &update.message {
+            FilteredUpdateOneof::Account(message) => message.account.write_version,
+            _ => panic!("expected account update"),
+        }
+    }
+
+    #[test]
+    fn test_accounts_dedup_by_slot() {
+        let pubkey = Pubkey::new_unique();
+        let mut accounts = HashMap::new();
+        for (name, dedup_by_slot) in [("dedup", Some(true)), ("all", None)] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    nonempty_txn_signature: None,
+                    account: vec![pubkey.to_string()],
+                    owner: vec![],
+                    filters: vec![],
+                    dedup_by_slot,
+                    deliver_at_commitment: None,
+                    data_fields: vec![],
+                    only_from_successful_tx: None,
+                    new_accounts_only: None,
+                    data_size_max: None,
+                    decompress_data: None,
+                    snapshot: None,
+                    on_owner_change: None,
+                    native_program_owned: None,
+                    on_data_change_only: None,
+                    on_data_change_include_lamports_owner: None,
+                    diff: None,
+                    rent_epoch_eq: None,
+                    rent_epoch_gt: None,
+                    rent_epoch_lt: None,
+                    executable: None,
+                },
+            );
+        }
+
+        let config = SubscribeRequest {
+            accounts,
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: Some(CommitmentLevelProto::Confirmed as i32),
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let commitment = Some(CommitmentLevel::Confirmed);
+
+        // every write is sent for filter without dedup
+        for (slot, write_version) in [(10, 1), (10, 3), (10, 2), (11, 4)] {
+            let message = create_message_account(pubkey, slot, write_version);
+            let updates = filter.get_updates(&message, commitment);
+            assert_eq!(updates.len(), 1);
+            assert_eq!(
+                updates[0].filters,
+                FilteredUpdateFilters::from_vec(vec![FilterName::new("all")])
+            );
+            assert_eq!(get_account_write_version(&updates[0]), write_version);
+        }
+
+        // buffered until slot reaches subscription commitment
+        let message = create_message_slot(10, Some(9), SlotStatus::Processed);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message_slot(10, Some(9), SlotStatus::Confirmed);
+        let updates = filter.get_updates(&message, commitment);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].filters,
+            FilteredUpdateFilters::from_vec(vec![FilterName::new("dedup")])
+        );
+        assert_eq!(get_account_write_version(&updates[0]), 3);
+
+        // flushed only once
+        let message = create_message_slot(10, Some(9), SlotStatus::Confirmed);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+
+        // dropped for dead slot
+        let message = create_message_slot(11, Some(10), SlotStatus::Dead);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message_slot(11, Some(10), SlotStatus::Confirmed);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+    }
+
+    #[test]
+    fn test_accounts_deliver_at_commitment() {
+        let pubkey = Pubkey::new_unique();
+        let mut accounts = HashMap::new();
+        for (name, deliver_at_commitment) in [
+            ("processed", Some(CommitmentLevelProto::Processed)),
+            ("confirmed", Some(CommitmentLevelProto::Confirmed)),
+            ("all", None),
+        ] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    nonempty_txn_signature: None,
+                    account: vec![pubkey.to_string()],
+                    owner: vec![],
+                    filters: vec![],
+                    dedup_by_slot: None,
+                    deliver_at_commitment: deliver_at_commitment.map(|value| value as i32),
+                    data_fields: vec![],
+                    only_from_successful_tx: None,
+                    new_accounts_only: None,
+                    data_size_max: None,
+                    decompress_data: None,
+                    snapshot: None,
+                    on_owner_change: None,
+                    native_program_owned: None,
+                    on_data_change_only: None,
+                    on_data_change_include_lamports_owner: None,
+                    diff: None,
+                    rent_epoch_eq: None,
+                    rent_epoch_gt: None,
+                    rent_epoch_lt: None,
+                    executable: None,
+                },
+            );
+        }
+
+        let config = SubscribeRequest {
+            accounts,
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let commitment = Some(CommitmentLevel::Processed);
+        let get_commitment = |update: &FilteredUpdate| match &update.message {
+            FilteredUpdateOneof::Account(message) => message.commitment,
+            _ => panic!("expected account update"),
+        };
+
+        // untagged and tagged with subscription commitment are sent immediately
+        for slot in [10, 11] {
+            let message = create_message_account(pubkey, slot, slot);
+            let updates = filter.get_updates(&message, commitment);
+            assert_eq!(updates.len(), 2);
+            assert_eq!(
+                updates[0].filters,
+                FilteredUpdateFilters::from_vec(vec![FilterName::new("processed")])
+            );
+            assert_eq!(
+                get_commitment(&updates[0]),
+                Some(CommitmentLevel::Processed)
+            );
+            assert_eq!(
+                updates[1].filters,
+                FilteredUpdateFilters::from_vec(vec![FilterName::new("all")])
+            );
+            assert_eq!(get_commitment(&updates[1]), None);
+        }
+
+        // held until the slot is confirmed
+        let message = create_message_slot(10, Some(9), SlotStatus::Processed);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message_slot(10, Some(9), SlotStatus::Confirmed);
+        let updates = filter.get_updates(&message, commitment);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].filters,
+            FilteredUpdateFilters::from_vec(vec![FilterName::new("confirmed")])
+        );
+        assert_eq!(
+            get_commitment(&updates[0]),
+            Some(CommitmentLevel::Confirmed)
+        );
+        assert_eq!(get_account_write_version(&updates[0]), 10);
+
+        // account in dead slot is not delivered at confirmed
+        let message = create_message_slot(11, Some(10), SlotStatus::Dead);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message_slot(11, Some(10), SlotStatus::Confirmed);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+    }
+
+    #[test]
+    fn test_accounts_deliver_at_commitment_with_dedup() {
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "solend".to_owned(),
+            SubscribeRequestFilterAccounts {
+                nonempty_txn_signature: None,
+                account: vec![],
+                owner: vec![],
+                filters: vec![],
+                dedup_by_slot: Some(true),
+                deliver_at_commitment: Some(CommitmentLevelProto::Confirmed as i32),
+                data_fields: vec![],
+                only_from_successful_tx: None,
+                new_accounts_only: None,
+                data_size_max: None,
+                decompress_data: None,
+                snapshot: None,
+                on_owner_change: None,
+                native_program_owned: None,
+                on_data_change_only: None,
+                on_data_change_include_lamports_owner: None,
+                diff: None,
+                rent_epoch_eq: None,
+                rent_epoch_gt: None,
+                rent_epoch_lt: None,
+                executable: None,
+            },
+        );
+
+        let config = SubscribeRequest {
+            accounts,
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        assert!(Filter::new(&config, &limit, &mut create_filter_names()).is_err());
+    }
+
+    #[test]
+    fn test_accounts_only_from_successful_tx() {
+        let pubkey = Pubkey::new_unique();
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "success".to_owned(),
+            SubscribeRequestFilterAccounts {
+                nonempty_txn_signature: None,
+                account: vec![pubkey.to_string()],
+                owner: vec![],
+                filters: vec![],
+                dedup_by_slot: None,
+                deliver_at_commitment: None,
+                data_fields: vec![],
+                only_from_successful_tx: Some(true),
+                new_accounts_only: None,
+                data_size_max: None,
+                decompress_data: None,
+                snapshot: None,
+                on_owner_change: None,
+                native_program_owned: None,
+                on_data_change_only: None,
+                on_data_change_include_lamports_owner: None,
+                diff: None,
+                rent_epoch_eq: None,
+                rent_epoch_gt: None,
+                rent_epoch_lt: None,
+                executable: None,
+            },
+        );
+
+        let config = SubscribeRequest {
+            accounts,
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let commitment = Some(CommitmentLevel::Processed);
+
+        let create_message = |write_version: u64, txn_signature: Option<Signature>| {
+            let mut message = create_message_account(pubkey, 100, write_version);
+            if let Message::Account(message) = &mut message {
+                Arc::make_mut(&mut message.account).txn_signature = txn_signature;
+            }
+            message
+        };
+
+        let tx_failed = {
+            let mut message = create_message_transaction(&Keypair::new(), vec![pubkey]);
+            Arc::make_mut(&mut message.transaction).meta.err =
+                Some(confirmed_block::TransactionError { err: vec![1] });
+            message
+        };
+        let tx_success = create_message_transaction(&Keypair::new(), vec![pubkey]);
+
+        // account without transaction is delivered as is
+        let updates = filter.get_updates(&create_message(1, None), commitment);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(get_account_write_version(&updates[0]), 1);
+
+        // accounts are held until transaction status is known
+        let message = create_message(2, Some(tx_failed.transaction.signature));
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message(3, Some(tx_success.transaction.signature));
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+
+        // account modified by failed transaction is suppressed
+        let message = Message::Transaction(tx_failed.clone());
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message(4, Some(tx_failed.transaction.signature));
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+
+        // account modified by successful transaction is delivered with the transaction
+        let message = Message::Transaction(tx_success.clone());
+        let updates = filter.get_updates(&message, commitment);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(get_account_write_version(&updates[0]), 3);
+
+        // once status is known accounts are delivered immediately
+        let message = create_message(5, Some(tx_success.transaction.signature));
+        let updates = filter.get_updates(&message, commitment);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(get_account_write_version(&updates[0]), 5);
+
+        // statuses are removed once the slot is finalized
+        let message = create_message_slot(100, Some(99), SlotStatus::Finalized);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message(6, Some(tx_success.transaction.signature));
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+    }
+
+    #[test]
+    fn test_accounts_new_accounts_only() {
+        let pubkeys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "new".to_owned(),
+            SubscribeRequestFilterAccounts {
+                nonempty_txn_signature: None,
+                account: pubkeys.iter().map(|pubkey| pubkey.to_string()).collect(),
+                owner: vec![],
+                filters: vec![],
+                dedup_by_slot: None,
+                deliver_at_commitment: None,
+                data_fields: vec![],
+                only_from_successful_tx: None,
+                new_accounts_only: Some(true),
+                data_size_max: None,
+                decompress_data: None,
+                snapshot: None,
+                on_owner_change: None,
+                native_program_owned: None,
+                on_data_change_only: None,
+                on_data_change_include_lamports_owner: None,
+                diff: None,
+                rent_epoch_eq: None,
+                rent_epoch_gt: None,
+                rent_epoch_lt: None,
+                executable: None,
+            },
+        );
+
+        let config = SubscribeRequest {
+            accounts,
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits {
+            accounts: FilterLimitsAccounts {
+                new_accounts_seen_max: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let commitment = Some(CommitmentLevel::Processed);
+
+        // account is sent once and suppressed on next writes
+        let message = create_message_account(pubkeys[0], 10, 1);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 1);
+        let message = create_message_account(pubkeys[0], 10, 2);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message_account(pubkeys[1], 11, 3);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 1);
+
+        // recently written account is kept, least recently written is evicted
+        let message = create_message_account(pubkeys[0], 12, 4);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message_account(pubkeys[2], 12, 5);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 1);
+        let message = create_message_account(pubkeys[0], 13, 6);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+
+        // evicted account is sent again
+        let message = create_message_account(pubkeys[1], 13, 7);
+        let updates = filter.get_updates(&message, commitment);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(get_account_write_version(&updates[0]), 7);
+    }
+
+    #[test]
+    fn test_accounts_on_owner_change() {
+        let pubkeys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "owner_change".to_owned(),
+            SubscribeRequestFilterAccounts {
+                account: pubkeys.iter().map(|pubkey| pubkey.to_string()).collect(),
+                on_owner_change: Some(true),
+                ..Default::default()
+            },
+        );
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let limit = FilterLimits {
+            accounts: FilterLimitsAccounts {
+                owner_change_seen_max: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let commitment = Some(CommitmentLevel::Processed);
+
+        let program = Pubkey::new_unique();
+        let create_message = |pubkey, owner, write_version| {
+            let Message::Account(mut message) = create_message_account(pubkey, 10, write_version)
+            else {
+                unreachable!();
+            };
+            Arc::make_mut(&mut message.account).owner = owner;
+            Message::Account(message)
+        };
+
+        // first write only records the owner
+        let message = create_message(pubkeys[0], Pubkey::default(), 1);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message(pubkeys[0], Pubkey::default(), 2);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+
+        // reassignment is sent once, next writes with the same owner are skipped
+        let message = create_message(pubkeys[0], program, 3);
+        let updates = filter.get_updates(&message, commitment);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(get_account_write_version(&updates[0]), 3);
+        let message = create_message(pubkeys[0], program, 4);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+
+        // owner changed back
+        let message = create_message(pubkeys[0], Pubkey::default(), 5);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 1);
+
+        // least recently written account is evicted and its owner is forgotten
+        let message = create_message(pubkeys[1], Pubkey::default(), 6);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message(pubkeys[2], Pubkey::default(), 7);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message(pubkeys[0], program, 8);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 0);
+        let message = create_message(pubkeys[2], program, 9);
+        assert_eq!(filter.get_updates(&message, commitment).len(), 1);
+    }
+
+    #[test]
+    fn test_accounts_on_data_change_only() {
+        let pubkeys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut accounts = HashMap::new();
+        for (name, on_data_change_include_lamports_owner) in [("data", false), ("data_meta", true)]
+        {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    account: pubkeys.iter().map(|pubkey| pubkey.to_string()).collect(),
+                    on_data_change_only: Some(true),
+                    on_data_change_include_lamports_owner: Some(
+                        on_data_change_include_lamports_owner,
+                    ),
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let limit = FilterLimits {
+            accounts: FilterLimitsAccounts {
+                data_change_seen_max: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let program = Pubkey::new_unique();
+        let mut write_version = 0;
+        let mut get_filters = |pubkey, data: &[u8], lamports, owner| {
+            write_version += 1;
+            let Message::Account(mut message) = create_message_account(pubkey, 10, write_version)
+            else {
+                unreachable!();
+            };
+            let account = Arc::make_mut(&mut message.account);
+            account.data = data.to_vec();
+            account.lamports = lamports;
+            account.owner = owner;
+            let updates =
+                filter.get_updates(&Message::Account(message), Some(CommitmentLevel::Processed));
+            let mut filters = updates
+                .iter()
+                .flat_map(|update| update.filters.iter())
+                .map(|name| name.as_ref().to_owned())
+                .collect::<Vec<_>>();
+            filters.sort();
+            filters
+        };
+        let owner = Pubkey::default();
+
+        // first write is sent, identical write is skipped
+        assert_eq!(
+            get_filters(pubkeys[0], &[1], 1, owner),
+            vec!["data", "data_meta"]
+        );
+        assert!(get_filters(pubkeys[0], &[1], 1, owner).is_empty());
+
+        // lamports or owner changes only with the companion flag
+        assert_eq!(get_filters(pubkeys[0], &[1], 2, owner), vec!["data_meta"]);
+        assert_eq!(get_filters(pubkeys[0], &[1], 2, program), vec!["data_meta"]);
+        assert!(get_filters(pubkeys[0], &[1], 2, program).is_empty());
+
+        // data changed
+        assert_eq!(
+            get_filters(pubkeys[0], &[2], 2, program),
+            vec!["data", "data_meta"]
+        );
+        assert_eq!(
+            get_filters(pubkeys[0], &[2, 0], 2, program),
+            vec!["data", "data_meta"]
+        );
+
+        // least recently written account is evicted and sent again
+        assert_eq!(get_filters(pubkeys[1], &[1], 1, owner).len(), 2);
+        assert_eq!(get_filters(pubkeys[2], &[1], 1, owner).len(), 2);
+        assert_eq!(get_filters(pubkeys[2], &[1], 1, owner).len(), 0);
+        assert_eq!(get_filters(pubkeys[0], &[2, 0], 2, program).len(), 2);
+
+        // companion flag requires `on_data_change_only`
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "invalid".to_owned(),
+            SubscribeRequestFilterAccounts {
+                on_data_change_include_lamports_owner: Some(true),
+                ..Default::default()
+            },
+        );
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::CreateAccountState(_))
+        ));
+    }
+
+    #[test]
+    fn test_accounts_diff() {
+        let pubkeys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut accounts = HashMap::new();
+        for (name, diff) in [("diff", true), ("full", false)] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    account: pubkeys.iter().map(|pubkey| pubkey.to_string()).collect(),
+                    diff: Some(diff),
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let limit = FilterLimits {
+            accounts: FilterLimitsAccounts {
+                diff_seen_max: 2,
+                diff_full_update_interval: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        // client side state, data and write version of the last update of the account
+        let mut state = HashMap::<Pubkey, (Vec<u8>, u64)>::new();
+        let mut write_version = 0;
+        let mut send = |pubkey: Pubkey, data: &[u8]| {
+            write_version += 1;
+            let Message::Account(mut message) = create_message_account(pubkey, 10, write_version)
+            else {
+                unreachable!();
+            };
+            Arc::make_mut(&mut message.account).data = data.to_vec();
+            let updates =
+                filter.get_updates(&Message::Account(message), Some(CommitmentLevel::Processed));
+            assert_eq!(updates.len(), 2);
+            let update = updates
+                .iter()
+                .find(|update| update.filters.iter().any(|name| name.as_ref() == "diff"))
+                .expect("diff filter update");
+            let update = SubscribeUpdate::decode(update.encode_to_vec().as_slice())
+                .expect("failed to decode");
+            let Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(account),
+                data_diff,
+                ..
+            })) = update.update_oneof
+            else {
+                panic!("expected account update");
+            };
+
+            let is_diff = match data_diff {
+                Some(data_diff) => {
+                    let (base, base_write_version) = state.get_mut(&pubkey).expect("known base");
+                    assert_eq!(data_diff.base_write_version, *base_write_version);
+                    assert!(account.data.is_empty());
+                    base.resize(data_diff.data_len as usize, 0);
+                    for segment in account.data_segments {
+                        let offset = segment.offset as usize;
+                        base[offset..offset + segment.data.len()].copy_from_slice(&segment.data);
+                    }
+                    *base_write_version = account.write_version;
+                    true
+                }
+                None => {
+                    state.insert(pubkey, (account.data, account.write_version));
+                    false
+                }
+            };
+            assert_eq!(state[&pubkey].0, data);
+            is_diff
+        };
+
+        // first update holds the whole data
+        let mut data = (0..200).collect::<Vec<u8>>();
+        assert!(!send(pubkeys[0], &data));
+
+        // changed bytes, grown, truncated and unchanged data are sent as diffs
+        data[10] = 0xff;
+        data[150..160].fill(0);
+        assert!(send(pubkeys[0], &data));
+        data[0] = 1;
+        data.extend_from_slice(&[1; 20]);
+        assert!(send(pubkeys[0], &data));
+        data.truncate(100);
+        assert!(send(pubkeys[0], &data));
+
+        // every 4th update holds the whole data
+        assert!(!send(pubkeys[0], &data));
+        assert!(send(pubkeys[0], &data));
+
+        // diff is not smaller than the data
+        let inverted = data.iter().map(|value| !value).collect::<Vec<_>>();
+        assert!(!send(pubkeys[0], &inverted));
+
+        // least recently written account is evicted and sent with the whole data
+        assert!(!send(pubkeys[1], &data));
+        assert!(!send(pubkeys[2], &data));
+        assert!(!send(pubkeys[0], &inverted));
+        data[50] = 0;
+        assert!(send(pubkeys[2], &data));
+
+        // `diff` requires the whole raw data
+        let create_config = |snapshot, accounts_data_slice| {
+            let mut accounts = HashMap::new();
+            accounts.insert(
+                "invalid".to_owned(),
+                SubscribeRequestFilterAccounts {
+                    diff: Some(true),
+                    snapshot: Some(snapshot),
+                    ..Default::default()
+                },
+            );
+            SubscribeRequest {
+                accounts,
+                accounts_data_slice,
+                ..Default::default()
+            }
+        };
+        assert!(matches!(
+            Filter::new(
+                &create_config(true, vec![]),
+                &limit,
+                &mut create_filter_names()
+            ),
+            Err(FilterError::CreateAccountState(_))
+        ));
+        let data_slice = vec![SubscribeRequestAccountsDataSlice {
+            offset: 0,
+            length: 1,
+        }];
+        assert!(matches!(
+            Filter::new(
+                &create_config(false, data_slice),
+                &limit,
+                &mut create_filter_names()
+            ),
+            Err(FilterError::CreateAccountsDiffWithDataOptions)
+        ));
+    }
+
+    #[test]
+    fn test_accounts_native_program_owned() {
+        let mut accounts = HashMap::new();
+        for (name, native_program_owned) in [("native", true), ("custom", false)] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    native_program_owned: Some(native_program_owned),
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let create_message = |owner| {
+            let Message::Account(mut message) = create_message_account(Pubkey::new_unique(), 10, 1)
+            else {
+                unreachable!();
+            };
+            Arc::make_mut(&mut message.account).owner = owner;
+            Message::Account(message)
+        };
+
+        for (owner, expected) in [
+            (solana_sdk::system_program::ID, "native"),
+            (solana_sdk::bpf_loader_upgradeable::ID, "native"),
+            (Pubkey::new_unique(), "custom"),
+        ] {
+            let updates = filter.get_updates(&create_message(owner), None);
+            assert_eq!(updates.len(), 1);
+            assert_eq!(
+                updates[0].filters,
+                FilteredUpdateFilters::from_vec(vec![FilterName::new(expected)])
+            );
+        }
+    }
+
+    #[test]
+    fn test_accounts_executable() {
+        let loader = solana_sdk::bpf_loader_upgradeable::ID;
+        let mut accounts = HashMap::new();
+        for (name, executable, owner) in [
+            ("program", Some(true), vec![]),
+            ("data", Some(false), vec![]),
+            ("any", None, vec![]),
+            ("loader_program", Some(true), vec![loader.to_string()]),
+        ] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    owner,
+                    executable,
+                    ..Default::default()
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let mut filter = Filter::new(
+            &config,
+            &FilterLimits::default(),
+            &mut create_filter_names(),
+        )
+        .unwrap();
+
+        let mut get_filters = |executable, owner| {
+            let Message::Account(mut message) = create_message_account(Pubkey::new_unique(), 10, 1)
+            else {
+                unreachable!();
+            };
+            let account = Arc::make_mut(&mut message.account);
+            account.executable = executable;
+            account.owner = owner;
+            let mut filters = filter
+                .get_updates(&Message::Account(message), None)
+                .iter()
+                .flat_map(|update| update.filters.iter())
+                .map(|name| name.as_ref().to_owned())
+                .collect::<Vec<_>>();
+            filters.sort();
+            filters
+        };
+        assert_eq!(
+            get_filters(true, loader),
+            vec!["any", "loader_program", "program"]
+        );
+        assert_eq!(
+            get_filters(true, Pubkey::new_unique()),
+            vec!["any", "program"]
+        );
+        assert_eq!(get_filters(false, loader), vec!["any", "data"]);
+        assert_eq!(
+            get_filters(false, Pubkey::new_unique()),
+            vec!["any", "data"]
+        );
+    }
+
+    #[test]
+    fn test_caches_memory_budget() {
+        let pubkeys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "new".to_owned(),
+            SubscribeRequestFilterAccounts {
+                account: pubkeys.iter().map(|pubkey| pubkey.to_string()).collect(),
+                new_accounts_only: Some(true),
+                ..Default::default()
+            },
+        );
+        let config = SubscribeRequest {
+            accounts,
+            ..Default::default()
+        };
+        let entry_size = FilterAccountsSeen::<()>::ENTRY_SIZE;
+        let limit = FilterLimits {
+            caches: FilterLimitsCaches {
+                memory_max: 2 * entry_size,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let memory = limit.caches.memory.clone();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let commitment = Some(CommitmentLevel::Processed);
+
+        let mut send = |pubkey, write_version| {
+            let message = create_message_account(pubkey, 10, write_version);
+            filter.get_updates(&message, commitment).len()
+        };
+        assert_eq!(send(pubkeys[0], 1), 1);
+        assert_eq!(send(pubkeys[1], 2), 1);
+        assert_eq!(send(pubkeys[0], 3), 0);
+        assert_eq!(memory.used(), 2 * entry_size);
+
+        // budget is exceeded, least recently written account is evicted and sent again
+        assert_eq!(send(pubkeys[2], 4), 1);
+        assert_eq!(memory.used(), 2 * entry_size);
+        assert_eq!(send(pubkeys[1], 5), 1);
+        assert_eq!(send(pubkeys[0], 6), 1);
+        assert!(memory.used() <= 2 * entry_size);
+
+        // cloned caches are accounted, memory is released on drop
+        let filter_clone = filter.clone();
+        assert_eq!(memory.used(), 4 * entry_size);
+        drop(filter_clone);
+        assert_eq!(memory.used(), 2 * entry_size);
+        drop(filter);
+        assert_eq!(memory.used(), 0);
+    }
+
+    #[test]
+    fn test_caches_memory_budget_shared() {
+        let entry_size = FilterAccountsSeen::<()>::ENTRY_SIZE;
+        let limits = FilterLimitsCaches {
+            memory_max: 4 * entry_size,
+            ..Default::default()
+        };
+        let mut big = FilterAccountsSeen::<()>::new(usize::MAX, &limits);
+        let mut small = FilterAccountsSeen::<()>::new(usize::MAX, &limits);
+
+        // the only cache can use the whole budget
+        for _ in 0..5 {
+            big.insert(Pubkey::new_unique(), ());
+        }
+        assert_eq!(big.accounts.len(), 4);
+
+        // over the budget only caches above the even share are evicted
+        let pubkey = Pubkey::new_unique();
+        assert!(small.insert(pubkey, ()).is_none());
+        assert!(small.get(&pubkey).is_some());
+        assert_eq!(limits.memory.used(), 5 * entry_size);
+        big.insert(Pubkey::new_unique(), ());
+        assert_eq!(big.accounts.len(), 3);
+        assert!(small.get(&pubkey).is_some());
+        assert_eq!(limits.memory.used(), 4 * entry_size);
+
+        // the inserted entry is kept even if it alone exceeds the budget
+        let limits = FilterLimitsCaches {
+            memory_max: 0,
+            ..Default::default()
+        };
+        let mut dedup = FilterTransactionsDedup::new(10, &limits);
+        let signatures = [Signature::new_unique(), Signature::new_unique()];
+        dedup.insert(signatures[0]);
+        assert!(dedup.contains(&signatures[0]));
+        dedup.insert(signatures[1]);
+        assert!(!dedup.contains(&signatures[0]));
+        assert!(dedup.contains(&signatures[1]));
+    }
+
+    #[test]
+    fn test_accounts_data_sent_len() {
+        let data = [42; 1024];
+        assert_eq!(FilterAccountsDataSlice::default().get_sent_len(&data), 1024);
+
+        let sliced = FilterAccountsDataSlice::new_unchecked(Arc::new(vec![0..8, 16..24]));
+        assert_eq!(sliced.get_sent_len(&data), 16);
+        assert_eq!(sliced.clone().with_segments(true).get_sent_len(&data), 16);
+
+        for encoding in [AccountsDataEncoding::Base64, AccountsDataEncoding::Base58] {
+            let encoded = sliced.clone().with_encoding(encoding);
+            let encoded_len = encoded.get_encoded(&data).expect("encoded").len();
+            let sent_len = encoded.get_sent_len(&data);
+            assert!(sent_len >= encoded_len, "{encoding:?}");
+            if encoding == AccountsDataEncoding::Base64 {
+                assert_eq!(sent_len, encoded_len);
+            }
+
+            // segments are sent together with encoded data
+            let segmented = encoded.with_segments(true);
+            assert_eq!(segmented.get_sent_len(&data), sent_len + 16);
+        }
+    }
+
+    #[test]
+    fn test_accounts_data_encoding() {
+        let pubkey = Pubkey::new_unique();
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "encoded".to_owned(),
+            SubscribeRequestFilterAccounts {
+                nonempty_txn_signature: None,
+                account: vec![pubkey.to_string()],
+                owner: vec![],
+                filters: vec![],
+                dedup_by_slot: None,
+                deliver_at_commitment: None,
+                data_fields: vec![],
+                only_from_successful_tx: None,
+                new_accounts_only: None,
+                data_size_max: None,
+                decompress_data: None,
+                snapshot: None,
+                on_owner_change: None,
+                native_program_owned: None,
+                on_data_change_only: None,
+                on_data_change_include_lamports_owner: None,
+                diff: None,
+                rent_epoch_eq: None,
+                rent_epoch_gt: None,
+                rent_epoch_lt: None,
+                executable: None,
+            },
+        );
+
+        let mut config = SubscribeRequest {
+            accounts,
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: Some(AccountsDataEncoding::Base64 as i32),
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let message = create_message_account(pubkey, 10, 1);
+        let updates = filter.get_updates(&message, Some(CommitmentLevel::Processed));
+        assert_eq!(updates.len(), 1);
+        assert!(matches!(
+            &updates[0].message,
+            FilteredUpdateOneof::Account(message) if message.data_slice.is_encoded()
+        ));
+
+        config.accounts_data_encoding = Some(100);
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::InvalidAccountsDataEncoding { encoding: 100 })
+        ));
+
+        config.accounts_data_encoding = Some(AccountsDataEncoding::Base64 as i32);
+        config.accounts_data_segments = Some(true);
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::CreateDataSegmentsWithEncoding)
+        ));
+
+        config.accounts_data_encoding = None;
+        config.accounts_data_slice = vec![SubscribeRequestAccountsDataSlice {
+            offset: 0,
+            length: 2,
+        }];
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let updates = filter.get_updates(&message, Some(CommitmentLevel::Processed));
+        assert_eq!(updates.len(), 1);
+        assert!(matches!(
+            &updates[0].message,
+            FilteredUpdateOneof::Account(message) if message.data_slice.is_segmented()
+        ));
+    }
+
+    #[test]
+    fn test_accounts_data_size_max() {
+        let pubkey = Pubkey::new_unique();
+        let mut accounts = HashMap::new();
+        for (name, data_size_max) in [("capped", Some(10)), ("all", None)] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    nonempty_txn_signature: None,
+                    account: vec![pubkey.to_string()],
+                    owner: vec![],
+                    filters: vec![],
+                    dedup_by_slot: None,
+                    deliver_at_commitment: None,
+                    data_fields: vec![],
+                    only_from_successful_tx: None,
+                    new_accounts_only: None,
+                    data_size_max,
+                    decompress_data: None,
+                    snapshot: None,
+                    on_owner_change: None,
+                    native_program_owned: None,
+                    on_data_change_only: None,
+                    on_data_change_include_lamports_owner: None,
+                    diff: None,
+                    rent_epoch_eq: None,
+                    rent_epoch_gt: None,
+                    rent_epoch_lt: None,
+                    executable: None,
+                },
+            );
+        }
+
+        let config = SubscribeRequest {
+            accounts,
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let mut get_filters = |data_len: usize| {
+            let mut message = create_message_account(pubkey, 10, 1);
+            if let Message::Account(message) = &mut message {
+                Arc::make_mut(&mut message.account).data = vec![0; data_len];
+            }
+            let updates = filter.get_updates(&message, Some(CommitmentLevel::Processed));
+            assert_eq!(updates.len(), 1);
+            let mut filters = updates[0]
+                .filters
+                .iter()
+                .map(|name| name.as_ref().to_owned())
+                .collect::<Vec<_>>();
+            filters.sort();
+            filters
+        };
+
+        // account just under or equal to the cap is sent to all filters
+        assert_eq!(get_filters(9), vec!["all", "capped"]);
+        assert_eq!(get_filters(10), vec!["all", "capped"]);
+        // account over the cap is dropped only for the capped filter
+        assert_eq!(get_filters(11), vec!["all"]);
+    }
+
+    #[test]
+    fn test_accounts_decompress_data() {
+        let pubkey = Pubkey::new_unique();
+        let mut accounts = HashMap::new();
+        for (name, decompress_data) in [
+            ("zstd", Some(DecompressCodec::Zstd as i32)),
+            ("gzip", Some(DecompressCodec::Gzip as i32)),
+            ("raw", None),
+        ] {
+            accounts.insert(
+                name.to_owned(),
+                SubscribeRequestFilterAccounts {
+                    nonempty_txn_signature: None,
+                    account: vec![pubkey.to_string()],
+                    owner: vec![],
+                    filters: vec![],
+                    dedup_by_slot: None,
+                    deliver_at_commitment: None,
+                    data_fields: vec![],
+                    only_from_successful_tx: None,
+                    new_accounts_only: None,
+                    data_size_max: None,
+                    decompress_data,
+                    snapshot: None,
+                    on_owner_change: None,
+                    native_program_owned: None,
+                    on_data_change_only: None,
+                    on_data_change_include_lamports_owner: None,
+                    diff: None,
+                    rent_epoch_eq: None,
+                    rent_epoch_gt: None,
+                    rent_epoch_lt: None,
+                    executable: None,
+                },
+            );
+        }
+
+        let mut config = SubscribeRequest {
+            accounts,
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: vec![SubscribeRequestAccountsDataSlice {
+                offset: 6,
+                length: 5,
+            }],
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let compressed = zstd::bulk::compress(b"hello world", 0).unwrap();
+        let mut message = create_message_account(pubkey, 10, 1);
+        let checksum = crc32fast::hash(&compressed);
+        if let Message::Account(message) = &mut message {
+            let account = Arc::make_mut(&mut message.account);
+            account.data.clone_from(&compressed);
+            account.data_checksum = Some(checksum);
+        }
+        let updates = filter.get_updates(&message, Some(CommitmentLevel::Processed));
+        assert_eq!(updates.len(), 3);
+        for update in updates.iter() {
+            let FilteredUpdateOneof::Account(account) = &update.message else {
+                panic!("expected account update");
+            };
+            // slices are applied to decompressed data
+            let (data, decompress_failed) = match update.filters[0].as_ref() {
+                "zstd" => (b"world".to_vec(), false),
+                "gzip" => (compressed[6..11].to_vec(), true),
+                "raw" => (compressed[6..11].to_vec(), false),
+                name => panic!("unexpected filter {name}"),
+            };
+            assert_eq!(account.data_slice.get_slice(&account.account.data), data);
+            assert_eq!(account.data_decompress_failed, decompress_failed);
+            // received data is kept for `GetAccountData` by its own length
+            let compressed_len = (update.filters[0].as_ref() == "zstd").then_some(compressed.len());
+            assert_eq!(account.data_compressed_len, compressed_len);
+            // checksum of the raw data
+            assert_eq!(account.account.data_checksum, Some(checksum));
+        }
+
+        if let Some(filter) = config.accounts.get_mut("zstd") {
+            filter.decompress_data = Some(100);
+        }
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::InvalidDecompressCodec { codec: 100 })
+        ));
+    }
+
+    #[test]
+    fn test_accounts_data_fields() {
+        let owner1 = Pubkey::new_unique();
+        let owner2 = Pubkey::new_unique();
+
+        let mut limits = FilterLimitsAccounts::default();
+        for (owner, fields) in [
+            (
+                owner1,
+                vec![("price", 8, 8), ("last_update", 0, 8), ("extra", 16, 4)],
+            ),
+            (
+                owner2,
+                vec![("price", 8, 8), ("last_update", 0, 8), ("extra", 20, 4)],
+            ),
+        ] {
+            limits.data_fields.insert(
+                owner,
+                fields
+                    .into_iter()
+                    .map(|(name, offset, length)| {
+                        (
+                            name.to_owned(),
+                            FilterLimitsAccountsDataField { offset, length },
+                        )
+                    })
+                    .collect(),
+            );
+        }
+
+        let fields = ["price".to_owned(), "last_update".to_owned()];
+        let owners = [owner1.to_string(), owner2.to_string()];
+        let data_slice = FilterAccounts::decode_data_fields(&fields, &owners, &limits).unwrap();
+        let expected = FilterAccountsDataSlice::new(
+            &[
+                SubscribeRequestAccountsDataSlice {
+                    offset: 0,
+                    length: 8,
+                },
+                SubscribeRequestAccountsDataSlice {
+                    offset: 8,
+                    length: 8,
+                },
+            ],
+            usize::MAX,
+        )
+        .unwrap();
+        assert_eq!(data_slice, expected);
+
+        assert!(matches!(
+            FilterAccounts::decode_data_fields(&fields, &[], &limits),
+            Err(FilterError::CreateDataFieldsWithoutOwner)
+        ));
+        assert!(matches!(
+            FilterAccounts::decode_data_fields(&["unknown".to_owned()], &owners[..1], &limits),
+            Err(FilterError::CreateDataFieldUnknown { owner, field }) if owner == owner1 && field == "unknown"
+        ));
+        assert!(matches!(
+            FilterAccounts::decode_data_fields(&["extra".to_owned()], &owners, &limits),
+            Err(FilterError::CreateDataFieldMismatch(field)) if field == "extra"
+        ));
+    }
+
+    fn create_message_slot(slot: Slot, parent: Option<Slot>, status: SlotStatus) -> Message {
+        Message::Slot(MessageSlot {
+            slot,
+            parent,
+            status,
+            dead_error: None,
+            raw_status: None,
+            created_at: Timestamp::from(SystemTime::now()),
+        })
+    }
+
+    #[test]
+    fn test_updates_encoding_json() {
+        let mut slots = HashMap::new();
+        slots.insert(
+            "client".to_owned(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: None,
+                interslot_updates: None,
+                slot_gaps: None,
+                tip_only: None,
+                statuses: vec![],
+                delta_batch_interval_ms: None,
+                replay_finalized: None,
+            },
+        );
+
+        let mut config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots,
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: Some(SubscribeRequestPing { id: 1 }),
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: Some(UpdatesEncoding::Json as i32),
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let message = create_message_slot(10, Some(9), SlotStatus::Processed);
+        let mut updates = filter.get_updates(&message, Some(CommitmentLevel::Processed));
+        assert_eq!(updates.len(), 1);
+        // updates are rendered only once wrapped
+        assert!(matches!(&updates[0].message, FilteredUpdateOneof::Slot(_)));
+        filter.wrap_update(&mut updates[0]);
+        let FilteredUpdateOneof::Json(msg) = &updates[0].message else {
+            panic!("expected json update");
+        };
+        assert_eq!(msg.kind, "slot");
+        let value = serde_json::from_str::<Value>(&msg.value).unwrap();
+        assert_eq!(value["slot"], 10);
+        assert_eq!(value["parent"], 9);
+        assert_eq!(value["status"], "SLOT_PROCESSED");
+
+        // service messages are rendered too, pongs are not
+        assert!(matches!(
+            filter.get_server_shutdown_msg().message,
+            FilteredUpdateOneof::Json(msg) if msg.kind == "serverShutdown"
+        ));
+        assert!(matches!(
+            filter.get_pong_msg().map(|update| update.message),
+            Some(FilteredUpdateOneof::Pong(_))
+        ));
+
+        config.encoding = Some(100);
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::InvalidUpdatesEncoding { encoding: 100 })
+        ));
+
+        config.encoding = Some(UpdatesEncoding::Json as i32);
+        config.envelope = Some(true);
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::CreateJsonWithEnvelope)
+        ));
+    }
+
+    #[test]
+    fn test_slots_gaps() {
+        let mut slots = HashMap::new();
+        slots.insert(
+            "gaps".to_owned(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: None,
+                interslot_updates: None,
+                slot_gaps: Some(true),
+                tip_only: None,
+                statuses: vec![],
+                delta_batch_interval_ms: None,
+                replay_finalized: None,
+            },
+        );
+        slots.insert(
+            "plain".to_owned(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: None,
+                interslot_updates: None,
+                slot_gaps: None,
+                tip_only: None,
+                statuses: vec![],
+                delta_batch_interval_ms: None,
+                replay_finalized: None,
+            },
+        );
+
+        let config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots,
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let mut get_gaps = |slot, parent, status| {
+            let message = create_message_slot(slot, parent, status);
+            let updates = filter.get_updates(&message, Some(CommitmentLevel::Processed));
+            let slot_updates = updates
+                .iter()
+                .filter(|update| matches!(update.message, FilteredUpdateOneof::Slot(_)))
+                .count();
+            assert_eq!(slot_updates, 1);
+            updates
+                .into_iter()
+                .filter_map(|update| match update.message {
+                    FilteredUpdateOneof::SlotGap(msg) => {
+                        assert_eq!(
+                            update.filters,
+                            FilteredUpdateFilters::from_vec(vec![FilterName::new("gaps")])
+                        );
+                        Some((msg.from_slot, msg.to_slot, msg.commitment))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let processed = CommitmentLevelProto::Processed as i32;
+        let confirmed = CommitmentLevelProto::Confirmed as i32;
+
+        // first slot and contiguous slots
+        assert_eq!(get_gaps(10, Some(9), SlotStatus::Processed), vec![]);
+        assert_eq!(get_gaps(11, Some(10), SlotStatus::Processed), vec![]);
+        // slots 12 and 13 are missed
+        assert_eq!(
+            get_gaps(14, Some(13), SlotStatus::Processed),
+            vec![(11, 14, processed)]
+        );
+        // out of order slot does not move last slot back
+        assert_eq!(get_gaps(13, Some(12), SlotStatus::Processed), vec![]);
+        assert_eq!(get_gaps(15, Some(14), SlotStatus::Processed), vec![]);
+        // slot 16 skipped by leader, parent is the last slot
+        assert_eq!(get_gaps(17, Some(15), SlotStatus::Processed), vec![]);
+        // commitments are tracked independently
+        assert_eq!(get_gaps(10, None, SlotStatus::Confirmed), vec![]);
+        assert_eq!(
+            get_gaps(12, None, SlotStatus::Confirmed),
+            vec![(10, 12, confirmed)]
+        );
+        assert_eq!(get_gaps(18, Some(17), SlotStatus::Processed), vec![]);
+    }
+
+    fn create_filter_slots_tip_only() -> Filter {
+        let mut slots = HashMap::new();
+        for (name, tip_only) in [("tip", Some(true)), ("all", None)] {
+            slots.insert(
+                name.to_owned(),
+                SubscribeRequestFilterSlots {
+                    filter_by_commitment: None,
+                    interslot_updates: None,
+                    slot_gaps: None,
+                    tip_only,
+                    statuses: vec![],
+                    delta_batch_interval_ms: None,
+                    replay_finalized: None,
+                },
+            );
+        }
+
+        let config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots,
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        Filter::new(
+            &config,
+            &FilterLimits::default(),
+            &mut create_filter_names(),
+        )
+        .unwrap()
+    }
+
+    fn get_slot_filters(
+        filter: &mut Filter,
+        slot: Slot,
+        status: SlotStatus,
+        created_at_ms: i64,
+    ) -> Vec<String> {
+        let message = Message::Slot(MessageSlot {
+            slot,
+            parent: None,
+            status,
+            dead_error: None,
+            raw_status: None,
+            created_at: Timestamp {
+                seconds: created_at_ms / 1_000,
+                nanos: (created_at_ms % 1_000) as i32 * 1_000_000,
+            },
+        });
+        let mut names = filter
+            .get_updates(&message, None)
+            .iter()
+            .flat_map(|update| update.filters.iter())
+            .map(|name| name.as_ref().to_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_slots_tip_only_catch_up() {
+        let mut filter = create_filter_slots_tip_only();
+
+        // tip is known from shreds while node replays old slots
+        assert!(get_slot_filters(&mut filter, 1_000, SlotStatus::FirstShredReceived, 0).is_empty());
+
+        // burst of processed slots, 100 slots per second
+        let mut created_at_ms = 0;
+        for slot in 100..=131 {
+            created_at_ms += 10;
+            let filters = get_slot_filters(&mut filter, slot, SlotStatus::Processed, created_at_ms);
+            if slot < 115 {
+                // not enough slots to detect catch-up
+                assert_eq!(filters, vec!["all", "tip"]);
+            } else {
+                assert_eq!(filters, vec!["all"]);
+            }
+        }
+
+        // slots near the tip are delivered during catch-up
+        created_at_ms += 10;
+        assert_eq!(
+            get_slot_filters(&mut filter, 990, SlotStatus::Processed, created_at_ms),
+            vec!["all", "tip"]
+        );
+        created_at_ms += 10;
+        assert_eq!(
+            get_slot_filters(&mut filter, 132, SlotStatus::Finalized, created_at_ms),
+            vec!["all"]
+        );
+    }
+
+    #[test]
+    fn test_slots_tip_only_real_time() {
+        let mut filter = create_filter_slots_tip_only();
+
+        assert!(get_slot_filters(&mut filter, 1_000, SlotStatus::FirstShredReceived, 0).is_empty());
+
+        // slots behind the tip are delivered at real time rate
+        let mut created_at_ms = 0;
+        for slot in 100..=131 {
+            created_at_ms += 400;
+            assert_eq!(
+                get_slot_filters(&mut filter, slot, SlotStatus::Processed, created_at_ms),
+                vec!["all", "tip"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_slots_delta_batch() {
+        let mut slots = HashMap::new();
+        for (name, statuses, delta_batch_interval_ms) in [
+            ("batch", vec![], Some(1_000)),
+            (
+                "batch_finalized",
+                vec![SlotStatusProto::SlotFinalized as i32],
+                Some(1_000),
+            ),
+            ("plain", vec![], None),
+        ] {
+            slots.insert(
+                name.to_owned(),
+                SubscribeRequestFilterSlots {
+                    filter_by_commitment: None,
+                    interslot_updates: None,
+                    slot_gaps: None,
+                    tip_only: None,
+                    statuses,
+                    delta_batch_interval_ms,
+                    replay_finalized: None,
+                },
+            );
+        }
+
+        let config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots,
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let mut get_updates = |slot, status, created_at_ms: i64| {
+            let message = Message::Slot(MessageSlot {
+                slot,
+                parent: None,
+                status,
+                dead_error: None,
+                raw_status: None,
+                created_at: Timestamp {
+                    seconds: created_at_ms / 1_000,
+                    nanos: (created_at_ms % 1_000) as i32 * 1_000_000,
+                },
+            });
+            filter
+                .get_updates(&message, None)
+                .into_iter()
+                .map(|update| {
+                    let filters = update
+                        .filters
+                        .iter()
+                        .map(|name| name.as_ref().to_owned())
+                        .collect::<Vec<_>>();
+                    let transitions = match update.message {
+                        FilteredUpdateOneof::Slot(_) => None,
+                        FilteredUpdateOneof::SlotDeltaBatch(batch) => {
+                            Some(slot_delta::decode(&batch).unwrap())
+                        }
+                        message => panic!("unexpected message: {message:?}"),
+                    };
+                    (filters, transitions)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // transitions are collected until the interval passed
+        assert_eq!(
+            get_updates(100, SlotStatus::Processed, 0),
+            vec![(vec!["plain".to_owned()], None)]
+        );
+        assert_eq!(
+            get_updates(68, SlotStatus::Finalized, 400),
+            vec![(vec!["plain".to_owned()], None)]
+        );
+        assert_eq!(
+            get_updates(101, SlotStatus::Processed, 800),
+            vec![(vec!["plain".to_owned()], None)]
+        );
+        assert_eq!(
+            get_updates(100, SlotStatus::Confirmed, 1_000),
+            vec![
+                (
+                    vec!["batch".to_owned()],
+                    Some(vec![
+                        (100, SlotStatusProto::SlotProcessed),
+                        (68, SlotStatusProto::SlotFinalized),
+                        (101, SlotStatusProto::SlotProcessed),
+                        (100, SlotStatusProto::SlotConfirmed),
+                    ])
+                ),
+                (vec!["plain".to_owned()], None)
+            ]
+        );
+
+        // not matched slot update flushes the batch started by finalized slot
+        assert_eq!(
+            get_updates(102, SlotStatus::Processed, 1_400),
+            vec![
+                (
+                    vec!["batch_finalized".to_owned()],
+                    Some(vec![(68, SlotStatusProto::SlotFinalized)])
+                ),
+                (vec!["plain".to_owned()], None)
+            ]
+        );
+        assert_eq!(
+            get_updates(103, SlotStatus::Processed, 1_800),
+            vec![(vec!["plain".to_owned()], None)]
+        );
+    }
+
+    #[test]
+    fn test_slots_statuses() {
+        let mut slots = HashMap::new();
+        for (name, filter_by_commitment, interslot_updates, statuses) in [
+            (
+                "shred_dead",
+                None,
+                None,
+                vec![
+                    SlotStatusProto::SlotFirstShredReceived,
+                    SlotStatusProto::SlotDead,
+                ],
+            ),
+            (
+                "confirmed",
+                Some(true),
+                None,
+                vec![SlotStatusProto::SlotConfirmed, SlotStatusProto::SlotDead],
+            ),
+            ("default", None, None, vec![]),
+            ("interslot", None, Some(true), vec![]),
+        ] {
+            slots.insert(
+                name.to_owned(),
+                SubscribeRequestFilterSlots {
+                    filter_by_commitment,
+                    interslot_updates,
+                    slot_gaps: None,
+                    tip_only: None,
+                    statuses: statuses.into_iter().map(|status| status as i32).collect(),
+                    delta_batch_interval_ms: None,
+                    replay_finalized: None,
+                },
+            );
+        }
+
+        let mut config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots,
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: Some(CommitmentLevelProto::Confirmed as i32),
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        for (status, expected) in [
+            (SlotStatus::Processed, vec!["default", "interslot"]),
+            (
+                SlotStatus::Confirmed,
+                vec!["confirmed", "default", "interslot"],
+            ),
+            (SlotStatus::Finalized, vec!["default", "interslot"]),
+            (
+                SlotStatus::FirstShredReceived,
+                vec!["interslot", "shred_dead"],
+            ),
+            (SlotStatus::Completed, vec!["interslot"]),
+            (SlotStatus::CreatedBank, vec!["interslot"]),
+            // `confirmed` has dead status but filtered by commitment
+            (SlotStatus::Dead, vec!["interslot", "shred_dead"]),
+        ] {
+            let message = create_message_slot(100, Some(99), status);
+            let mut names = filter
+                .get_updates(&message, Some(CommitmentLevel::Confirmed))
+                .iter()
+                .flat_map(|update| update.filters.iter())
+                .map(|name| name.as_ref().to_owned())
+                .collect::<Vec<_>>();
+            names.sort();
+            assert_eq!(names, expected, "status {status:?}");
+        }
+
+        config.slots.get_mut("default").unwrap().statuses.push(100);
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::InvalidSlotStatus { status: 100 })
+        ));
+    }
+
+    #[test]
+    fn test_filter_error_reason() {
+        let errors = [
+            FilterError::Name(FilterNameError::Oversized { limit: 1, size: 2 }),
+            FilterError::LimitsCheck(FilterLimitsCheckError::Max { max: 1 }),
+            FilterError::LimitsCheck(FilterLimitsCheckError::Any),
+            FilterError::LimitsCheck(FilterLimitsCheckError::MaxPubkey { max: 1 }),
+            FilterError::LimitsCheck(FilterLimitsCheckError::PubkeyReject {
+                pubkey: Pubkey::new_unique(),
+            }),
+            FilterError::InvalidCommitment { commitment: 100 },
+            FilterError::InvalidAccountsDataEncoding { encoding: 100 },
+            FilterError::InvalidUpdatesEncoding { encoding: 100 },
+            FilterError::InvalidDecompressCodec { codec: 100 },
+            FilterError::InvalidSlotStatus { status: 100 },
+            FilterError::InvalidPubkey(ParsePubkeyError::Invalid),
+            FilterError::InvalidSignature(ParseSignatureError::Invalid),
+            FilterError::CreateAccountStateMaxFilters { max: 1 },
+            FilterError::CreateAccountStateDataEqualsMax { max: 1 },
+            FilterError::CreateAccountStateMemcmpOutOfBounds {
+                offset: 1,
+                length: 1,
+                datasize: 1,
+            },
+            FilterError::CreateAccountState("error"),
+            FilterError::CreateBlocksNotAllowed("accounts"),
+            FilterError::CreateBlocks("error"),
+            FilterError::CreateDataSliceOutOfOrder,
+            FilterError::CreateDataSliceOverlap,
+            FilterError::CreateDataSegmentsWithEncoding,
+            FilterError::CreateAccountsDiffWithDataOptions,
+            FilterError::CreateJsonWithEnvelope,
+            FilterError::CreateDataFieldsWithoutOwner,
+            FilterError::CreateDataFieldUnknown {
+                owner: Pubkey::new_unique(),
+                field: "field".to_owned(),
+            },
+            FilterError::CreateDataFieldMismatch("field".to_owned()),
+            FilterError::CreateStatsInterval { min: 1 },
+            FilterError::CreateTransactionsAccountCountRange,
+            FilterError::CreateTransactionsErrorMatch("error"),
+            FilterError::CreateEntriesSlotRange,
+        ];
+        // every variant has own label
+        let reasons = errors
+            .iter()
+            .map(|error| error.reason())
+            .collect::<HashSet<_>>();
+        assert_eq!(reasons.len(), errors.len());
+    }
+
+    #[test]
+    fn test_slots_replay_finalized() {
+        let mut slots = HashMap::new();
+        for (name, filter_by_commitment, replay_finalized) in [
+            ("last2", None, Some(2)),
+            ("last5", None, Some(5)),
+            ("confirmed", Some(true), Some(5)),
+            ("live", None, None),
+        ] {
+            slots.insert(
+                name.to_owned(),
+                SubscribeRequestFilterSlots {
+                    filter_by_commitment,
+                    replay_finalized,
+                    ..Default::default()
+                },
+            );
+        }
+        let request = SubscribeRequest {
+            slots,
+            commitment: Some(CommitmentLevelProto::Confirmed as i32),
+            ..Default::default()
+        };
+        let filter = Filter::new(
+            &request,
+            &FilterLimits::default(),
+            &mut create_filter_names(),
+        )
+        .unwrap();
+        assert_eq!(filter.get_slots_replay_finalized(), 5);
+
+        let messages = (10..14)
+            .map(|slot| {
+                let Message::Slot(message) =
+                    create_message_slot(slot, Some(slot - 1), SlotStatus::Finalized)
+                else {
+                    unreachable!();
+                };
+                message
+            })
+            .collect::<Vec<_>>();
+        let updates = filter
+            .get_slots_replay_finalized_updates(&messages)
+            .into_iter()
+            .map(|update| {
+                let FilteredUpdateOneof::Slot(message) = update.message else {
+                    panic!("expected slot update");
+                };
+                let mut names = update
+                    .filters
+                    .iter()
+                    .map(|name| name.as_ref().to_owned())
+                    .collect::<Vec<_>>();
+                names.sort();
+                (message.slot, names)
+            })
+            .collect::<Vec<_>>();
+        // slots are sorted, finalized slots are filtered out by `confirmed` commitment
+        assert_eq!(
+            updates,
+            vec![
+                (10, vec!["last5".to_owned()]),
+                (11, vec!["last5".to_owned()]),
+                (12, vec!["last2".to_owned(), "last5".to_owned()]),
+                (13, vec!["last2".to_owned(), "last5".to_owned()]),
+            ]
+        );
+
+        let request = SubscribeRequest::default();
+        let filter = Filter::new(
+            &request,
+            &FilterLimits::default(),
+            &mut create_filter_names(),
+        )
+        .unwrap();
+        assert_eq!(filter.get_slots_replay_finalized(), 0);
+        assert!(filter
+            .get_slots_replay_finalized_updates(&messages)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_entries_last_entry_only() {
+        let mut entry = HashMap::new();
+        entry.insert(
+            "last".to_owned(),
+            SubscribeRequestFilterEntry {
+                last_entry_only: Some(true),
+                ..Default::default()
+            },
+        );
+        entry.insert(
+            "all".to_owned(),
+            SubscribeRequestFilterEntry {
+                last_entry_only: None,
+                ..Default::default()
+            },
+        );
+
+        let config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry,
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let create_message_entry = |slot, index| {
+            Message::Entry(Arc::new(MessageEntry {
+                slot,
+                index,
+                num_hashes: 0,
+                hash: Hash::default(),
+                executed_transaction_count: 0,
+                starting_transaction_index: 0,
+                created_at: Timestamp::from(SystemTime::now()),
+            }))
+        };
+        let create_message_block_meta = |slot, entries_count| {
+            Message::BlockMeta(Arc::new(MessageBlockMeta {
+                block_meta: SubscribeUpdateBlockMeta {
+                    slot,
+                    entries_count,
+                    ..Default::default()
+                },
+                created_at: Timestamp::from(SystemTime::now()),
+            }))
+        };
+        let mut get_entries = |message: &Message| {
+            filter
+                .get_updates(message, Some(CommitmentLevel::Processed))
+                .into_iter()
+                .filter_map(|update| match update.message {
+                    FilteredUpdateOneof::Entry(msg) => Some((
+                        update
+                            .filters
+                            .iter()
+                            .map(|name| name.as_ref().to_owned())
+                            .collect::<Vec<_>>(),
+                        msg.slot,
+                        msg.index,
+                    )),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let all = vec!["all".to_owned()];
+        let last = vec!["last".to_owned()];
+
+        // slot 10: entries before block meta, last entry is delivered on block meta
+        for index in 0..3 {
+            assert_eq!(
+                get_entries(&create_message_entry(10, index)),
+                vec![(all.clone(), 10, index)]
+            );
+        }
+        assert_eq!(
+            get_entries(&create_message_block_meta(10, 3)),
+            vec![(last.clone(), 10, 2)]
+        );
+
+        // slot 11: block meta before the last entry, last entry is delivered immediately
+        assert_eq!(
+            get_entries(&create_message_entry(11, 0)),
+            vec![(all.clone(), 11, 0)]
+        );
+        assert_eq!(get_entries(&create_message_block_meta(11, 2)), vec![]);
+        assert_eq!(
+            get_entries(&create_message_entry(11, 1)),
+            vec![(all.clone(), 11, 1), (last.clone(), 11, 1)]
+        );
+
+        // slot 12: dead slot, buffered entry is dropped
+        assert_eq!(
+            get_entries(&create_message_entry(12, 0)),
+            vec![(all.clone(), 12, 0)]
+        );
+        get_entries(&create_message_slot(12, Some(11), SlotStatus::Dead));
+        assert_eq!(get_entries(&create_message_block_meta(12, 1)), vec![]);
+    }
+
+    #[test]
+    fn test_entries_slot_range() {
+        let mut entry = HashMap::new();
+        for (name, last_entry_only, slot_from, slot_to) in [
+            ("all", None, None, None),
+            ("range", None, Some(10), Some(12)),
+            ("from", None, Some(11), None),
+            ("to", None, None, Some(10)),
+            ("last", Some(true), Some(12), Some(12)),
+        ] {
+            entry.insert(
+                name.to_owned(),
+                SubscribeRequestFilterEntry {
+                    last_entry_only,
+                    slot_from,
+                    slot_to,
+                },
+            );
+        }
+        let config = SubscribeRequest {
+            entry,
+            ..Default::default()
+        };
+        let limit = FilterLimits::default();
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+        let mut get_filters = |slot, index| {
+            let message = Message::Entry(Arc::new(MessageEntry {
+                slot,
+                index,
+                num_hashes: 0,
+                hash: Hash::default(),
+                executed_transaction_count: 0,
+                starting_transaction_index: 0,
+                created_at: Timestamp::from(SystemTime::now()),
+            }));
+            filter
+                .get_updates(&message, Some(CommitmentLevel::Processed))
+                .into_iter()
+                .map(|update| {
+                    let mut filters = update
+                        .filters
+                        .iter()
+                        .map(|name| name.as_ref().to_owned())
+                        .collect::<Vec<_>>();
+                    filters.sort();
+                    filters
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // below range
+        assert_eq!(get_filters(9, 0), vec![vec!["all", "to"]]);
+        // in range
+        assert_eq!(get_filters(10, 0), vec![vec!["all", "range", "to"]]);
+        assert_eq!(get_filters(11, 0), vec![vec!["all", "from", "range"]]);
+        // above range
+        assert_eq!(get_filters(13, 0), vec![vec!["all", "from"]]);
+
+        // last entry is buffered only for slots in range
+        get_filters(12, 0);
+        assert_eq!(
+            filter.entries.last_entry_buffer.keys().collect::<Vec<_>>(),
+            [&12]
+        );
+
+        // empty range
+        let mut entry = HashMap::new();
+        entry.insert(
+            "invalid".to_owned(),
+            SubscribeRequestFilterEntry {
+                slot_from: Some(11),
+                slot_to: Some(10),
+                ..Default::default()
+            },
+        );
+        let config = SubscribeRequest {
+            entry,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::CreateEntriesSlotRange)
+        ));
+    }
+
+    #[test]
+    fn test_blocks_account_include_owner() {
+        let program = Pubkey::new_unique();
+        let owned = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let create_account = |pubkey, owner| {
+            Arc::new(MessageAccountInfo {
+                pubkey,
+                lamports: 1,
+                owner,
+                executable: false,
+                rent_epoch: 0,
+                data: vec![],
+                write_version: 0,
+                txn_signature: None,
+                data_checksum: None,
+            })
+        };
+        let keypair_a = Keypair::new();
+        let keypair_b = Keypair::new();
+        let tx_owned = create_message_transaction(&keypair_a, vec![keypair_a.pubkey(), owned]);
+        let tx_other = create_message_transaction(&keypair_b, vec![keypair_b.pubkey(), other]);
+        let block = MessageBlock::new(
+            Arc::new(MessageBlockMeta {
+                block_meta: SubscribeUpdateBlockMeta {
+                    slot: 42,
+                    ..Default::default()
+                },
+                created_at: Timestamp::from(SystemTime::now()),
+            }),
+            vec![
+                Arc::clone(&tx_owned.transaction),
+                Arc::clone(&tx_other.transaction),
+            ],
+            vec![
+                create_account(owned, program),
+                create_account(other, Pubkey::new_unique()),
+                create_account(program, Pubkey::default()),
+            ],
+            vec![],
+        );
+        let message = Message::Block(Arc::new(block));
+
+        for (account_include_owner, accounts, transactions) in [
+            (None, vec![program], vec![]),
+            (
+                Some(true),
+                vec![owned, program],
+                vec![tx_owned.transaction.signature],
+            ),
+        ] {
+            let mut blocks = HashMap::new();
+            blocks.insert(
+                "program".to_owned(),
+                SubscribeRequestFilterBlocks {
+                    account_include: vec![program.to_string()],
+                    include_transactions: Some(true),
+                    include_accounts: Some(true),
+                    include_entries: None,
+                    account_include_owner,
+                    require_matching_transaction: None,
+                    include_only_matching_transactions: None,
+                    account_owner_include: vec![],
+                },
+            );
+            let config = SubscribeRequest {
+                accounts: HashMap::new(),
+                slots: HashMap::new(),
+                transactions: HashMap::new(),
+                transactions_status: HashMap::new(),
+                blocks,
+                blocks_meta: HashMap::new(),
+                entry: HashMap::new(),
+                commitment: None,
+                accounts_data_slice: Vec::new(),
+                ping: None,
+                from_slot: None,
+                ping_interval_ms: None,
+                envelope: None,
+                accounts_data_encoding: None,
+                stats_interval_ms: None,
+                max_updates_per_second: None,
+                program_events: HashMap::new(),
+                channel_capacity: None,
+                exclude_votes: None,
+                accounts_data_segments: None,
+                resume_token: None,
+                encoding: None,
+            };
+            let limit = FilterLimits::default();
+            let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+            let updates = filter.get_updates(&message, None);
+            assert_eq!(updates.len(), 1);
+            let FilteredUpdateOneof::Block(block) = &updates[0].message else {
+                panic!("expected block update");
+            };
+            assert_eq!(
+                block
+                    .accounts
+                    .iter()
+                    .map(|account| account.pubkey)
+                    .collect::<Vec<_>>(),
+                accounts
+            );
+            assert_eq!(
+                block
+                    .transactions
+                    .iter()
+                    .map(|tx| tx.signature)
+                    .collect::<Vec<_>>(),
+                transactions
+            );
+        }
+    }
+
+    #[test]
+    fn test_exclude_votes() {
+        let keypair_vote = Keypair::new();
+        let keypair_other = Keypair::new();
+        let tx_vote = create_message_transaction(&keypair_vote, vec![keypair_vote.pubkey()]);
+        let mut tx_other = create_message_transaction(&keypair_other, vec![keypair_other.pubkey()]);
+        Arc::make_mut(&mut tx_other.transaction).is_vote = false;
+
+        let create_account = |txn_signature| {
+            Arc::new(MessageAccountInfo {
+                pubkey: Pubkey::new_unique(),
+                lamports: 1,
+                owner: Pubkey::new_unique(),
+                executable: false,
+                rent_epoch: 0,
+                data: vec![],
+                write_version: 0,
+                txn_signature: Some(txn_signature),
+                data_checksum: None,
+            })
+        };
+        let account_vote = create_account(tx_vote.transaction.signature);
+        let account_other = create_account(tx_other.transaction.signature);
+        let block = MessageBlock::new(
+            Arc::new(MessageBlockMeta {
+                block_meta: SubscribeUpdateBlockMeta {
+                    slot: 100,
+                    ..Default::default()
+                },
+                created_at: Timestamp::from(SystemTime::now()),
+            }),
+            vec![
+                Arc::clone(&tx_vote.transaction),
+                Arc::clone(&tx_other.transaction),
+            ],
+            vec![Arc::clone(&account_vote), Arc::clone(&account_other)],
+            vec![],
+        );
+        let block = Message::Block(Arc::new(block));
+
+        for exclude_votes in [false, true] {
+            let mut transactions = HashMap::new();
+            transactions.insert("txs".to_owned(), Default::default());
+            let mut transactions_status = HashMap::new();
+            transactions_status.insert("statuses".to_owned(), Default::default());
+            let mut blocks = HashMap::new();
+            blocks.insert(
+                "blocks".to_owned(),
+                SubscribeRequestFilterBlocks {
+                    include_transactions: Some(true),
+                    include_accounts: Some(true),
+                    ..Default::default()
+                },
+            );
+            let config = SubscribeRequest {
+                transactions,
+                transactions_status,
+                blocks,
+                exclude_votes: Some(exclude_votes),
+                ..Default::default()
+            };
+            let limit = FilterLimits::default();
+            let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+            // vote transaction is still delivered to `transactions_status`
+            let mut kinds = |message: &MessageTransaction| {
+                let message = Message::Transaction(message.clone());
+                let mut kinds = filter
+                    .get_updates(&message, None)
+                    .iter()
+                    .map(|update| update.message.kind())
+                    .collect::<Vec<_>>();
+                kinds.sort();
+                kinds
+            };
+            let expected = if exclude_votes {
+                vec!["transaction_status"]
+            } else {
+                vec!["transaction", "transaction_status"]
+            };
+            assert_eq!(kinds(&tx_vote), expected);
+            assert_eq!(kinds(&tx_other), ["transaction", "transaction_status"]);
+
+            let updates = filter.get_updates(&block, None);
+            assert_eq!(updates.len(), 1);
+            let FilteredUpdateOneof::Block(block) = &updates[0].message else {
+                panic!("expected block update");
+            };
+            let (transactions, accounts) = if exclude_votes {
+                (
+                    vec![tx_other.transaction.signature],
+                    vec![account_other.pubkey],
+                )
+            } else {
+                (
+                    vec![
+                        tx_vote.transaction.signature,
+                        tx_other.transaction.signature,
+                    ],
+                    vec![account_vote.pubkey, account_other.pubkey],
+                )
+            };
+            assert_eq!(
+                block
+                    .transactions
+                    .iter()
+                    .map(|tx| tx.signature)
+                    .collect::<Vec<_>>(),
+                transactions
+            );
+            assert_eq!(
+                block
+                    .accounts
+                    .iter()
+                    .map(|account| account.pubkey)
+                    .collect::<Vec<_>>(),
+                accounts
+            );
+        }
+    }
+
+    #[test]
+    fn test_blocks_account_owner_include() {
+        let owner_a = Pubkey::new_unique();
+        let owner_b = Pubkey::new_unique();
+        let owner_c = Pubkey::new_unique();
+        let accounts = [
+            (Pubkey::new_unique(), owner_a),
+            (Pubkey::new_unique(), owner_b),
+            (Pubkey::new_unique(), owner_a),
+            (Pubkey::new_unique(), owner_c),
+        ];
+        let block = MessageBlock::new(
+            Arc::new(MessageBlockMeta {
+                block_meta: SubscribeUpdateBlockMeta {
+                    slot: 42,
+                    ..Default::default()
+                },
+                created_at: Timestamp::from(SystemTime::now()),
+            }),
+            vec![],
+            accounts
+                .iter()
+                .map(|(pubkey, owner)| {
+                    Arc::new(MessageAccountInfo {
+                        pubkey: *pubkey,
+                        lamports: 1,
+                        owner: *owner,
+                        executable: false,
+                        rent_epoch: 0,
+                        data: vec![],
+                        write_version: 0,
+                        txn_signature: None,
+                        data_checksum: None,
+                    })
+                })
+                .collect(),
+            vec![],
+        );
+        let message = Message::Block(Arc::new(block));
+
+        let create_config = |account_include: Vec<Pubkey>, account_owner_include: Vec<Pubkey>| {
+            let mut blocks = HashMap::new();
+            blocks.insert(
+                "blocks".to_owned(),
+                SubscribeRequestFilterBlocks {
+                    account_include: account_include.iter().map(|p| p.to_string()).collect(),
+                    include_accounts: Some(true),
+                    account_owner_include: account_owner_include
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect(),
+                    ..Default::default()
+                },
+            );
+            SubscribeRequest {
+                blocks,
+                ..Default::default()
+            }
+        };
+
+        for (account_include, account_owner_include, expected) in [
+            (vec![], vec![owner_a], vec![0, 2]),
+            (vec![], vec![owner_a, owner_b], vec![0, 1, 2]),
+            (vec![accounts[3].0], vec![owner_a], vec![0, 2, 3]),
+            (vec![], vec![], vec![0, 1, 2, 3]),
+        ] {
+            let config = create_config(account_include, account_owner_include);
+            let limits = FilterLimits::default();
+            let mut filter = Filter::new(&config, &limits, &mut create_filter_names()).unwrap();
+
+            let updates = filter.get_updates(&message, None);
+            assert_eq!(updates.len(), 1);
+            let FilteredUpdateOneof::Block(block) = &updates[0].message else {
+                panic!("expected block update");
+            };
+            assert_eq!(
+                block
+                    .accounts
+                    .iter()
+                    .map(|account| account.pubkey)
+                    .collect::<Vec<_>>(),
+                expected
+                    .into_iter()
+                    .map(|index| accounts[index].0)
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        // number of owners is limited
+        let config = create_config(vec![], vec![owner_a, owner_b]);
+        let mut limits = FilterLimits::default();
+        limits.blocks.account_owner_include_max = 1;
+        assert!(matches!(
+            Filter::new(&config, &limits, &mut create_filter_names()),
+            Err(FilterError::LimitsCheck(_))
+        ));
+    }
+
+    #[test]
+    fn test_blocks_require_matching_transaction() {
+        let program = Pubkey::new_unique();
+        let keypair_a = Keypair::new();
+        let keypair_b = Keypair::new();
+        let mut tx_invoke =
+            create_message_transaction(&keypair_a, vec![keypair_a.pubkey(), program]);
+        Arc::make_mut(&mut tx_invoke.transaction)
+            .transaction
+            .message
+            .as_mut()
+            .expect("defined message")
+            .instructions
+            .push(confirmed_block::CompiledInstruction {
+                program_id_index: 1,
+                accounts: vec![],
+                data: vec![],
+            });
+        let tx_other = create_message_transaction(&keypair_b, vec![keypair_b.pubkey(), program]);
+        let create_message = |slot, transactions: Vec<&MessageTransaction>| {
+            Message::Block(Arc::new(MessageBlock::new(
+                Arc::new(MessageBlockMeta {
+                    block_meta: SubscribeUpdateBlockMeta {
+                        slot,
+                        ..Default::default()
+                    },
+                    created_at: Timestamp::from(SystemTime::now()),
+                }),
+                transactions
+                    .into_iter()
+                    .map(|tx| Arc::clone(&tx.transaction))
+                    .collect(),
+                vec![],
+                vec![],
+            )))
+        };
+
+        for (include_only_matching_transactions, transactions) in [
+            (
+                None,
+                vec![
+                    tx_invoke.transaction.signature,
+                    tx_other.transaction.signature,
+                ],
+            ),
+            (Some(true), vec![tx_invoke.transaction.signature]),
+        ] {
+            let mut blocks = HashMap::new();
+            blocks.insert(
+                "program".to_owned(),
+                SubscribeRequestFilterBlocks {
+                    require_matching_transaction: Some(SubscribeRequestFilterTransactions {
+                        program_include: vec![program.to_string()],
+                        ..Default::default()
+                    }),
+                    include_only_matching_transactions,
+                    ..Default::default()
+                },
+            );
+            let config = SubscribeRequest {
+                blocks,
+                ..Default::default()
+            };
+            let limit = FilterLimits::default();
+            let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+
+            // program is referenced but not invoked, block is dropped
+            let message = create_message(41, vec![&tx_other]);
+            assert_eq!(filter.get_updates(&message, None).len(), 0);
+
+            let message = create_message(42, vec![&tx_invoke, &tx_other]);
+            let updates = filter.get_updates(&message, None);
+            assert_eq!(updates.len(), 1);
+            let FilteredUpdateOneof::Block(block) = &updates[0].message else {
+                panic!("expected block update");
+            };
+            assert_eq!(
+                block
+                    .transactions
+                    .iter()
+                    .map(|tx| tx.signature)
+                    .collect::<Vec<_>>(),
+                transactions
+            );
+        }
+
+        let mut blocks = HashMap::new();
+        blocks.insert(
+            "invalid".to_owned(),
+            SubscribeRequestFilterBlocks {
+                include_only_matching_transactions: Some(true),
+                ..Default::default()
+            },
+        );
+        let config = SubscribeRequest {
+            blocks,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Filter::new(
+                &config,
+                &FilterLimits::default(),
+                &mut create_filter_names()
+            ),
+            Err(FilterError::CreateBlocks(_))
+        ));
+    }
+
+    #[test]
+    fn test_min_commitment_per_type() {
+        let mut blocks = HashMap::new();
+        blocks.insert(
+            "blocks".to_owned(),
+            SubscribeRequestFilterBlocks {
+                account_include: vec![],
+                include_transactions: None,
+                include_accounts: None,
+                include_entries: None,
+                account_include_owner: None,
+                require_matching_transaction: None,
+                include_only_matching_transactions: None,
+                account_owner_include: vec![],
+            },
+        );
+        let mut slots = HashMap::new();
+        slots.insert(
+            "slots".to_owned(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: None,
+                interslot_updates: None,
+                slot_gaps: None,
+                tip_only: None,
+                statuses: vec![],
+                delta_batch_interval_ms: None,
+                replay_finalized: None,
+            },
+        );
+        let config = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots,
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks,
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: Some(CommitmentLevelProto::Processed as i32),
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits {
+            min_commitment_per_type: FilterLimitsMinCommitment {
+                block: Some(CommitmentLevel::Finalized),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        assert_eq!(filter.get_commitment_level(), CommitmentLevel::Processed);
+        assert!(filter.is_commitment_level_used(CommitmentLevel::Processed));
+        assert!(!filter.is_commitment_level_used(CommitmentLevel::Confirmed));
+        assert!(filter.is_commitment_level_used(CommitmentLevel::Finalized));
+
+        let block = Message::Block(Arc::new(MessageBlock::new(
+            Arc::new(MessageBlockMeta {
+                block_meta: SubscribeUpdateBlockMeta {
+                    slot: 100,
+                    ..Default::default()
+                },
+                created_at: Timestamp::from(SystemTime::now()),
+            }),
+            vec![],
+            vec![],
+            vec![],
+        )));
+        let slot = create_message_slot(100, Some(99), SlotStatus::Finalized);
+        for (commitment, blocks, slots) in [
+            (CommitmentLevel::Processed, 0, 1),
+            (CommitmentLevel::Confirmed, 0, 0),
+            (CommitmentLevel::Finalized, 1, 0),
+        ] {
+            let updates = filter.get_updates(&block, Some(commitment));
+            assert_eq!(updates.len(), blocks, "commitment {commitment:?}");
+            assert!(updates
+                .iter()
+                .all(|update| matches!(update.message, FilteredUpdateOneof::Block(_))));
+
+            let updates = filter.get_updates(&slot, Some(commitment));
+            assert_eq!(updates.len(), slots, "commitment {commitment:?}");
+        }
+    }
+
+    #[test]
+    fn test_filter_stats() {
+        let pubkeys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "accounts".to_owned(),
+            SubscribeRequestFilterAccounts {
+                nonempty_txn_signature: None,
+                account: pubkeys.iter().map(|pubkey| pubkey.to_string()).collect(),
+                owner: vec![],
+                filters: vec![],
+                dedup_by_slot: None,
+                deliver_at_commitment: None,
+                data_fields: vec![],
+                only_from_successful_tx: None,
+                new_accounts_only: None,
+                data_size_max: None,
+                decompress_data: None,
+                snapshot: None,
+                on_owner_change: None,
+                native_program_owned: None,
+                on_data_change_only: None,
+                on_data_change_include_lamports_owner: None,
+                diff: None,
+                rent_epoch_eq: None,
+                rent_epoch_gt: None,
+                rent_epoch_lt: None,
+                executable: None,
+            },
+        );
+        let mut transactions = HashMap::new();
+        transactions.insert(
+            "transactions".to_owned(),
+            SubscribeRequestFilterTransactions {
+                vote: None,
+                failed: None,
+                signature: None,
+                account_include: vec![],
+                account_exclude: vec![],
+                account_required: vec![],
+                program_include: vec![],
+                dedup_signatures: None,
+                signer_include: vec![],
+                cpi_program_include: vec![],
+                account_count_min: None,
+                account_count_max: None,
+                error_match: None,
+            },
+        );
+        let mut config = SubscribeRequest {
+            accounts,
+            slots: HashMap::new(),
+            transactions,
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: None,
+            accounts_data_slice: Vec::new(),
+            ping: None,
+            from_slot: None,
+            ping_interval_ms: None,
+            envelope: None,
+            accounts_data_encoding: None,
+            stats_interval_ms: None,
+            max_updates_per_second: None,
+            program_events: HashMap::new(),
+            channel_capacity: None,
+            exclude_votes: None,
+            accounts_data_segments: None,
+            resume_token: None,
+            encoding: None,
+        };
+        let limit = FilterLimits::default();
+
+        let keypair = Keypair::new();
+        let messages = [
+            create_message_account(pubkeys[0], 10, 1),
+            create_message_account(pubkeys[0], 10, 2),
+            create_message_account(pubkeys[1], 10, 3),
+            create_message_account(Pubkey::new_unique(), 10, 4),
+            Message::Transaction(create_message_transaction(
+                &keypair,
+                vec![keypair.pubkey(), Pubkey::new_unique()],
+            )),
+        ];
+
+        // aggregate raw updates
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        let mut expected: HashMap<String, (u64, u64, HashSet<Pubkey>)> = HashMap::new();
+        for message in messages.iter() {
+            for update in filter.get_updates(message, Some(CommitmentLevel::Processed)) {
+                let pubkeys = match &update.message {
+                    FilteredUpdateOneof::Account(msg) => HashSet::from([msg.account.pubkey]),
+                    FilteredUpdateOneof::Transaction(msg) => msg.transaction.account_keys.clone(),
+                    _ => panic!("unexpected update"),
+                };
+                for name in update.filters.iter() {
+                    let entry = expected.entry(name.as_ref().to_owned()).or_default();
+                    entry.0 += 1;
+                    entry.1 += update.message.encoded_len() as u64;
+                    entry.2.extend(&pubkeys);
+                }
+            }
+        }
+        assert_eq!(expected["accounts"].0, 3);
+        assert_eq!(expected["transactions"].0, 1);
+
+        // stats mode, updates are not sent
+        config.stats_interval_ms = Some(1_000);
+        let mut filter = Filter::new(&config, &limit, &mut create_filter_names()).unwrap();
+        assert_eq!(filter.get_stats_interval(), Some(Duration::from_secs(1)));
+        for message in messages.iter() {
+            assert!(filter
+                .get_updates(message, Some(CommitmentLevel::Processed))
+                .is_empty());
+        }
+        let Some(FilteredUpdate {
+            message: FilteredUpdateOneof::FilterStats(stats),
+            ..
+        }) = filter.get_stats_msg()
+        else {
+            panic!("expected filter stats");
+        };
+        assert_eq!(stats.interval_ms, 1_000);
+        let mut expected = expected
+            .into_iter()
+            .map(
+                |(filter, (count, bytes, pubkeys))| SubscribeUpdateFilterStatsEntry {
+                    filter,
+                    count,
+                    bytes,
+                    unique_pubkeys: pubkeys.len() as u64,
+                },
+            )
+            .collect::<Vec<_>>();
+        expected.sort_by(|a, b| a.filter.cmp(&b.filter));
+        assert_eq!(stats.filters, expected);
+
+        // accumulators are reset on flush
+        let Some(FilteredUpdate {
+            message: FilteredUpdateOneof::FilterStats(stats),
+            ..
+        }) = filter.get_stats_msg()
+        else {
+            panic!("expected filter stats");
+        };
+        assert!(stats.filters.is_empty());
+
+        config.stats_interval_ms = Some(10);
+        assert!(matches!(
+            Filter::new(&config, &limit, &mut create_filter_names()),
+            Err(FilterError::CreateStatsInterval { min: 1_000 })
+        ));
+    }
+
+    #[test]
+    fn test_accounts_filters_evaluation_order() {
+        // Simple LCG, keeps the test deterministic without extra dependencies
+        struct Rng(u64);
+        impl Rng {
+            fn below(&mut self, max: u64) -> u64 {
+                self.0 = self
+                    .0
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (self.0 >> 33) % max
+            }
+
+            fn chance(&mut self) -> bool {
+                self.below(2) == 0
+            }
+        }
+
+        struct Reference {
+            nonempty_txn_signature: Option<bool>,
+            account: Vec<Pubkey>,
+            owner: Vec<Pubkey>,
+            memcmp: Vec<(usize, Vec<u8>)>,
+            datasize: Option<usize>,
+            token_account_state: bool,
+            lamports_gt: Option<u64>,
+            checksum: bool,
+            data_size_max: Option<u64>,
+        }
+
+        impl Reference {
+            fn is_match(&self, account: &MessageAccountInfo) -> bool {
+                let data = &account.data;
+                self.nonempty_txn_signature
+                    .map_or(true, |value| value == account.txn_signature.is_some())
+                    && (self.account.is_empty() || self.account.contains(&account.pubkey))
+                    && (self.owner.is_empty() || self.owner.contains(&account.owner))
+                    && self
+                        .data_size_max
+                        .map_or(true, |max| data.len() as u64 <= max)
+                    && self.memcmp.iter().all(|(offset, bytes)| {
+                        data.get(*offset..*offset + bytes.len()) == Some(bytes.as_slice())
+                    })
+                    && self.datasize.map_or(true, |size| data.len() == size)
+                    && (!self.token_account_state || TokenAccount::valid_account_data(data))
+                    && self
+                        .lamports_gt
+                        .map_or(true, |value| account.lamports > value)
+                    && (!self.checksum
+                        || (data.len() >= 12
+                            && data[0..4] == crc32fast::hash(&data[4..12]).to_le_bytes()))
+            }
+        }
+
+        let accounts_pool = (0..3).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let owners_pool = (0..2).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let limits = FilterLimitsAccounts::default();
+        let mut rng = Rng(42);
+
+        for _ in 0..64 {
+            let mut configs = HashMap::new();
+            let mut references = HashMap::new();
+            for index in 0..rng.below(6) + 1 {
+                let reference = Reference {
+                    nonempty_txn_signature: rng.chance().then(|| rng.chance()),
+                    account: accounts_pool
+                        .iter()
+                        .filter(|_| rng.below(3) == 0)
+                        .copied()
+                        .collect(),
+                    owner: owners_pool
+                        .iter()
+                        .filter(|_| rng.below(3) == 0)
+                        .copied()
+                        .collect(),
+                    memcmp: (0..rng.below(3))
+                        .map(|_| {
+                            let offset = rng.below(4) as usize;
+                            let bytes = (0..rng.below(2) + 1).map(|_| rng.below(2) as u8).collect();
+                            (offset, bytes)
+                        })
+                        .collect(),
+                    datasize: rng.chance().then(|| [0, 12, 165][rng.below(3) as usize]),
+                    token_account_state: rng.below(4) == 0,
+                    lamports_gt: rng.chance().then(|| rng.below(10)),
+                    checksum: rng.below(4) == 0,
+                    data_size_max: (rng.below(4) == 0).then_some(12),
+                };
+
+                let mut filters = reference
+                    .memcmp
+                    .iter()
+                    .map(|(offset, bytes)| SubscribeRequestFilterAccountsFilter {
+                        filter: Some(AccountsFilterDataOneof::Memcmp(
+                            SubscribeRequestFilterAccountsFilterMemcmp {
+                                offset: *offset as u64,
+                                data: Some(AccountsFilterMemcmpOneof::Bytes(bytes.clone())),
+                            },
+                        )),
+                    })
+                    .collect::<Vec<_>>();
+                if let Some(datasize) = reference.datasize {
+                    filters.push(SubscribeRequestFilterAccountsFilter {
+                        filter: Some(AccountsFilterDataOneof::Datasize(datasize as u64)),
+                    });
+                }
+                if reference.token_account_state && filters.len() < 4 {
+                    filters.push(SubscribeRequestFilterAccountsFilter {
+                        filter: Some(AccountsFilterDataOneof::TokenAccountState(true)),
+                    });
+                }
+                if let Some(value) = reference.lamports_gt.filter(|_| filters.len() < 4) {
+                    filters.push(SubscribeRequestFilterAccountsFilter {
+                        filter: Some(AccountsFilterDataOneof::Lamports(
+                            SubscribeRequestFilterAccountsFilterLamports {
+                                cmp: Some(AccountsFilterLamports::Gt(value)),
+                            },
+                        )),
+                    });
+                }
+                if reference.checksum && filters.len() < 4 {
+                    filters.push(SubscribeRequestFilterAccountsFilter {
+                        filter: Some(AccountsFilterDataOneof::Checksum(
+                            SubscribeRequestFilterAccountsFilterChecksum {
+                                crc_offset: 0,
+                                body_offset: 4,
+                                body_length: 8,
+                            },
+                        )),
+                    });
+                }
+                // keep reference in sync with filters dropped by the limit above
+                let reference = Reference {
+                    token_account_state: filters.iter().any(|f| {
+                        matches!(
+                            f.filter,
+                            Some(AccountsFilterDataOneof::TokenAccountState(_))
+                        )
+                    }),
+                    lamports_gt: reference.lamports_gt.filter(|_| {
+                        filters
+                            .iter()
+                            .any(|f| matches!(f.filter, Some(AccountsFilterDataOneof::Lamports(_))))
+                    }),
+                    checksum: filters
+                        .iter()
+                        .any(|f| matches!(f.filter, Some(AccountsFilterDataOneof::Checksum(_)))),
+                    ..reference
+                };
+
+                let name = format!("filter-{index}");
+                configs.insert(
+                    name.clone(),
+                    SubscribeRequestFilterAccounts {
+                        nonempty_txn_signature: reference.nonempty_txn_signature,
+                        account: reference.account.iter().map(|p| p.to_string()).collect(),
+                        owner: reference.owner.iter().map(|p| p.to_string()).collect(),
+                        filters,
+                        dedup_by_slot: None,
+                        deliver_at_commitment: None,
+                        data_fields: vec![],
+                        only_from_successful_tx: None,
+                        new_accounts_only: None,
+                        data_size_max: reference.data_size_max,
+                        decompress_data: None,
+                        snapshot: None,
+                        on_owner_change: None,
+                        native_program_owned: None,
+                        on_data_change_only: None,
+                        on_data_change_include_lamports_owner: None,
+                        diff: None,
+                        rent_epoch_eq: None,
+                        rent_epoch_gt: None,
+                        rent_epoch_lt: None,
+                        executable: None,
+                    },
+                );
+                references.insert(name, reference);
+            }
+
+            let filter = FilterAccounts::new(
+                &configs,
+                &limits,
+                &FilterLimitsCaches::default(),
+                AccountsDataEncoding::Raw,
+                false,
+                &mut create_filter_names(),
+            )
+            .unwrap();
+
+            for write_version in 0..32 {
+                let mut data = (0..[0, 8, 12, 165][rng.below(4) as usize])
+                    .map(|_| rng.below(3) as u8)
+                    .collect::<Vec<u8>>();
+                if data.len() >= 12 && rng.chance() {
+                    let crc = crc32fast::hash(&data[4..12]);
+                    data[0..4].copy_from_slice(&crc.to_le_bytes());
+                }
+                let account = MessageAccountInfo {
+                    pubkey: accounts_pool[rng.below(3) as usize],
+                    lamports: rng.below(10),
+                    owner: owners_pool[rng.below(2) as usize],
+                    executable: false,
+                    rent_epoch: 0,
+                    data,
+                    write_version,
+                    txn_signature: rng.chance().then(Signature::new_unique),
+                    data_checksum: None,
+                };
+
+                let mut expected = references
+                    .iter()
+                    .filter(|(_, reference)| reference.is_match(&account))
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>();
+                expected.sort_unstable();
+
+                let message = MessageAccount {
+                    account: Arc::new(account),
+                    slot: 0,
+                    is_startup: false,
+                    created_at: Timestamp::from(SystemTime::now()),
+                };
+                let matched = filter.get_filters(&message);
+                let mut matched = matched.iter().map(|name| name.as_ref()).collect::<Vec<_>>();
+                matched.sort_unstable();
+
+                assert_eq!(matched, expected);
+            }
+        }
+    }
 }
@@ -1,7 +1,17 @@
 use {
+    crate::{
+        geyser::{get_filter_limits_response, GetFilterLimitsResponse},
+        plugin::message::CommitmentLevel,
+    },
     serde::{de, Deserialize, Deserializer},
     solana_sdk::pubkey::Pubkey,
-    std::collections::HashSet,
+    std::{
+        collections::{HashMap, HashSet},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    },
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -16,6 +26,18 @@ pub enum FilterLimitsCheckError {
     PubkeyReject { pubkey: Pubkey },
 }
 
+impl FilterLimitsCheckError {
+    /// Short name of the error for metrics labels
+    pub const fn reason(&self) -> &'static str {
+        match self {
+            Self::Max { .. } => "limits_max",
+            Self::Any => "limits_any",
+            Self::MaxPubkey { .. } => "limits_max_pubkey",
+            Self::PubkeyReject { .. } => "limits_pubkey_reject",
+        }
+    }
+}
+
 pub type FilterLimitsCheckResult = Result<(), FilterLimitsCheckError>;
 
 #[derive(Debug, Default, Clone, Deserialize)]
@@ -28,6 +50,9 @@ pub struct FilterLimits {
     pub blocks: FilterLimitsBlocks,
     pub blocks_meta: FilterLimitsBlocksMeta,
     pub entries: FilterLimitsEntries,
+    pub program_events: FilterLimitsProgramEvents,
+    pub min_commitment_per_type: FilterLimitsMinCommitment,
+    pub caches: FilterLimitsCaches,
 }
 
 impl FilterLimits {
@@ -64,6 +89,21 @@ impl FilterLimits {
     }
 }
 
+impl From<&FilterLimits> for GetFilterLimitsResponse {
+    fn from(limits: &FilterLimits) -> Self {
+        Self {
+            accounts: Some((&limits.accounts).into()),
+            slots: Some((&limits.slots).into()),
+            transactions: Some((&limits.transactions).into()),
+            transactions_status: Some((&limits.transactions_status).into()),
+            blocks: Some((&limits.blocks).into()),
+            blocks_meta: Some((&limits.blocks_meta).into()),
+            entries: Some((&limits.entries).into()),
+            program_events: Some((&limits.program_events).into()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct FilterLimitsAccounts {
@@ -75,7 +115,19 @@ pub struct FilterLimitsAccounts {
     pub owner_max: usize,
     #[serde(deserialize_with = "deserialize_pubkey_set")]
     pub owner_reject: HashSet<Pubkey>,
+    /// Only accounts owned by listed programs are sent, regardless of client filters
+    #[serde(deserialize_with = "deserialize_pubkey_set")]
+    pub owner_allowlist: HashSet<Pubkey>,
     pub data_slice_max: usize,
+    #[serde(deserialize_with = "deserialize_pubkey_map")]
+    pub data_fields: HashMap<Pubkey, HashMap<String, FilterLimitsAccountsDataField>>,
+    pub new_accounts_seen_max: usize,
+    pub owner_change_seen_max: usize,
+    pub data_equals_max: usize,
+    pub data_change_seen_max: usize,
+    pub diff_seen_max: usize,
+    /// Every n-th update of an account with `diff` is sent with the whole data
+    pub diff_full_update_interval: usize,
 }
 
 impl Default for FilterLimitsAccounts {
@@ -87,7 +139,43 @@ impl Default for FilterLimitsAccounts {
             account_reject: HashSet::new(),
             owner_max: usize::MAX,
             owner_reject: HashSet::new(),
+            owner_allowlist: HashSet::new(),
             data_slice_max: usize::MAX,
+            data_fields: HashMap::new(),
+            new_accounts_seen_max: 100_000,
+            owner_change_seen_max: 100_000,
+            data_equals_max: 10_240,
+            data_change_seen_max: 100_000,
+            diff_seen_max: 10_000,
+            diff_full_update_interval: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterLimitsAccountsDataField {
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl From<&FilterLimitsAccounts> for get_filter_limits_response::Accounts {
+    fn from(limits: &FilterLimitsAccounts) -> Self {
+        Self {
+            max: limits.max as u64,
+            any: limits.any,
+            account_max: limits.account_max as u64,
+            account_reject_size: limits.account_reject.len() as u64,
+            owner_max: limits.owner_max as u64,
+            owner_reject_size: limits.owner_reject.len() as u64,
+            data_slice_max: limits.data_slice_max as u64,
+            new_accounts_seen_max: limits.new_accounts_seen_max as u64,
+            owner_change_seen_max: limits.owner_change_seen_max as u64,
+            data_equals_max: limits.data_equals_max as u64,
+            data_change_seen_max: limits.data_change_seen_max as u64,
+            owner_allowlist_size: limits.owner_allowlist.len() as u64,
+            diff_seen_max: limits.diff_seen_max as u64,
+            diff_full_update_interval: limits.diff_full_update_interval as u64,
         }
     }
 }
@@ -105,6 +193,14 @@ impl Default for FilterLimitsSlots {
     }
 }
 
+impl From<&FilterLimitsSlots> for get_filter_limits_response::Slots {
+    fn from(limits: &FilterLimitsSlots) -> Self {
+        Self {
+            max: limits.max as u64,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct FilterLimitsTransactions {
@@ -119,6 +215,14 @@ pub struct FilterLimitsTransactions {
     pub account_exclude_max: usize,
     #[serde(deserialize_with = "deserialize_usize_str")]
     pub account_required_max: usize,
+    #[serde(deserialize_with = "deserialize_usize_str")]
+    pub program_include_max: usize,
+    #[serde(deserialize_with = "deserialize_usize_str")]
+    pub dedup_signatures_window: usize,
+    #[serde(deserialize_with = "deserialize_usize_str")]
+    pub signer_include_max: usize,
+    #[serde(deserialize_with = "deserialize_usize_str")]
+    pub cpi_program_include_max: usize,
 }
 
 impl Default for FilterLimitsTransactions {
@@ -130,6 +234,27 @@ impl Default for FilterLimitsTransactions {
             account_include_reject: HashSet::new(),
             account_exclude_max: usize::MAX,
             account_required_max: usize::MAX,
+            program_include_max: usize::MAX,
+            dedup_signatures_window: 10_000,
+            signer_include_max: usize::MAX,
+            cpi_program_include_max: usize::MAX,
+        }
+    }
+}
+
+impl From<&FilterLimitsTransactions> for get_filter_limits_response::Transactions {
+    fn from(limits: &FilterLimitsTransactions) -> Self {
+        Self {
+            max: limits.max as u64,
+            any: limits.any,
+            account_include_max: limits.account_include_max as u64,
+            account_include_reject_size: limits.account_include_reject.len() as u64,
+            account_exclude_max: limits.account_exclude_max as u64,
+            account_required_max: limits.account_required_max as u64,
+            program_include_max: limits.program_include_max as u64,
+            dedup_signatures_window: limits.dedup_signatures_window as u64,
+            signer_include_max: limits.signer_include_max as u64,
+            cpi_program_include_max: limits.cpi_program_include_max as u64,
         }
     }
 }
@@ -147,6 +272,8 @@ pub struct FilterLimitsBlocks {
     pub include_transactions: bool,
     pub include_accounts: bool,
     pub include_entries: bool,
+    #[serde(deserialize_with = "deserialize_usize_str")]
+    pub account_owner_include_max: usize,
 }
 
 impl Default for FilterLimitsBlocks {
@@ -159,6 +286,22 @@ impl Default for FilterLimitsBlocks {
             include_transactions: true,
             include_accounts: true,
             include_entries: true,
+            account_owner_include_max: usize::MAX,
+        }
+    }
+}
+
+impl From<&FilterLimitsBlocks> for get_filter_limits_response::Blocks {
+    fn from(limits: &FilterLimitsBlocks) -> Self {
+        Self {
+            max: limits.max as u64,
+            account_include_max: limits.account_include_max as u64,
+            account_include_any: limits.account_include_any,
+            account_include_reject_size: limits.account_include_reject.len() as u64,
+            include_transactions: limits.include_transactions,
+            include_accounts: limits.include_accounts,
+            include_entries: limits.include_entries,
+            account_owner_include_max: limits.account_owner_include_max as u64,
         }
     }
 }
@@ -176,6 +319,14 @@ impl Default for FilterLimitsBlocksMeta {
     }
 }
 
+impl From<&FilterLimitsBlocksMeta> for get_filter_limits_response::BlocksMeta {
+    fn from(limits: &FilterLimitsBlocksMeta) -> Self {
+        Self {
+            max: limits.max as u64,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct FilterLimitsEntries {
@@ -189,6 +340,120 @@ impl Default for FilterLimitsEntries {
     }
 }
 
+impl From<&FilterLimitsEntries> for get_filter_limits_response::Entries {
+    fn from(limits: &FilterLimitsEntries) -> Self {
+        Self {
+            max: limits.max as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterLimitsProgramEvents {
+    #[serde(deserialize_with = "deserialize_usize_str")]
+    pub max: usize,
+}
+
+impl Default for FilterLimitsProgramEvents {
+    fn default() -> Self {
+        Self { max: usize::MAX }
+    }
+}
+
+impl From<&FilterLimitsProgramEvents> for get_filter_limits_response::ProgramEvents {
+    fn from(limits: &FilterLimitsProgramEvents) -> Self {
+        Self {
+            max: limits.max as u64,
+        }
+    }
+}
+
+/// Memory budget shared by per-connection caches of all subscribers
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterLimitsCaches {
+    #[serde(deserialize_with = "deserialize_usize_str")]
+    pub memory_max: usize,
+    #[serde(skip)]
+    pub memory: FilterCachesMemory,
+}
+
+impl Default for FilterLimitsCaches {
+    fn default() -> Self {
+        Self {
+            memory_max: usize::MAX,
+            memory: FilterCachesMemory::default(),
+        }
+    }
+}
+
+/// Approximate amount of bytes used by caches and number of caches holding memory,
+/// shared between connections
+#[derive(Debug, Default, Clone)]
+pub struct FilterCachesMemory {
+    used: Arc<AtomicUsize>,
+    caches: Arc<AtomicUsize>,
+}
+
+impl FilterCachesMemory {
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn add(&self, bytes: usize) {
+        self.used.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn sub(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn cache_inc(&self) {
+        self.caches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn cache_dec(&self) {
+        self.caches.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Budget of one cache if `memory_max` is split evenly between caches holding memory
+    pub(crate) fn fair_share(&self, memory_max: usize) -> usize {
+        memory_max / self.caches.load(Ordering::Relaxed).max(1)
+    }
+}
+
+/// Commitment floor by message type, raises commitment of the request for these updates
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterLimitsMinCommitment {
+    /// Transactions and transactions statuses
+    #[serde(deserialize_with = "deserialize_commitment_maybe")]
+    pub transaction: Option<CommitmentLevel>,
+    #[serde(deserialize_with = "deserialize_commitment_maybe")]
+    pub block: Option<CommitmentLevel>,
+    #[serde(deserialize_with = "deserialize_commitment_maybe")]
+    pub block_meta: Option<CommitmentLevel>,
+}
+
+fn deserialize_commitment_maybe<'de, D>(
+    deserializer: D,
+) -> Result<Option<CommitmentLevel>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<&str>::deserialize(deserializer)?
+        .map(|value| match value {
+            "processed" => Ok(CommitmentLevel::Processed),
+            "confirmed" => Ok(CommitmentLevel::Confirmed),
+            "finalized" => Ok(CommitmentLevel::Finalized),
+            value => Err(de::Error::custom(format!(
+                "Unknown commitment level: {value}"
+            ))),
+        })
+        .transpose()
+}
+
 fn deserialize_usize_str<'de, D>(deserializer: D) -> Result<usize, D::Error>
 where
     D: Deserializer<'de>,
@@ -222,3 +487,183 @@ where
         })
         .collect::<Result<_, _>>()
 }
+
+fn deserialize_pubkey_map<'de, D, T>(deserializer: D) -> Result<HashMap<Pubkey, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    HashMap::<&str, T>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, value)| {
+            key.parse()
+                .map(|pubkey| (pubkey, value))
+                .map_err(|error| de::Error::custom(format!("Invalid pubkey: {key} ({error:?})")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::FilterLimits,
+        crate::{
+            geyser::{get_filter_limits_response, GetFilterLimitsResponse},
+            plugin::message::CommitmentLevel,
+        },
+    };
+
+    #[test]
+    fn test_filter_limits_response() {
+        let limits: FilterLimits = serde_json::from_str(
+            r#"{
+                "accounts": {
+                    "max": 1,
+                    "any": false,
+                    "account_max": 10,
+                    "account_reject": ["TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"],
+                    "owner_max": 10,
+                    "owner_reject": ["11111111111111111111111111111111"],
+                    "data_slice_max": 2,
+                    "new_accounts_seen_max": 1000,
+                    "owner_change_seen_max": 500,
+                    "data_equals_max": 165,
+                    "data_change_seen_max": 200,
+                    "diff_seen_max": 300,
+                    "diff_full_update_interval": 20,
+                    "owner_allowlist": ["TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"]
+                },
+                "slots": { "max": 1 },
+                "transactions": {
+                    "max": 1,
+                    "any": false,
+                    "account_include_max": 10,
+                    "account_include_reject": ["TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"],
+                    "account_exclude_max": 10,
+                    "account_required_max": 10,
+                    "program_include_max": 5,
+                    "dedup_signatures_window": "1_000",
+                    "signer_include_max": 3,
+                    "cpi_program_include_max": 4
+                },
+                "blocks": {
+                    "max": 1,
+                    "account_include_max": 10,
+                    "account_include_any": false,
+                    "include_transactions": true,
+                    "include_accounts": false,
+                    "include_entries": false
+                },
+                "blocks_meta": { "max": 1 },
+                "entries": { "max": 1 }
+            }"#,
+        )
+        .expect("valid limits");
+
+        let response = GetFilterLimitsResponse::from(&limits);
+        assert_eq!(
+            response.accounts,
+            Some(get_filter_limits_response::Accounts {
+                max: 1,
+                any: false,
+                account_max: 10,
+                account_reject_size: 1,
+                owner_max: 10,
+                owner_reject_size: 1,
+                data_slice_max: 2,
+                new_accounts_seen_max: 1000,
+                owner_change_seen_max: 500,
+                data_equals_max: 165,
+                data_change_seen_max: 200,
+                owner_allowlist_size: 1,
+                diff_seen_max: 300,
+                diff_full_update_interval: 20,
+            })
+        );
+        assert_eq!(
+            response.slots,
+            Some(get_filter_limits_response::Slots { max: 1 })
+        );
+        assert_eq!(
+            response.transactions,
+            Some(get_filter_limits_response::Transactions {
+                max: 1,
+                any: false,
+                account_include_max: 10,
+                account_include_reject_size: 1,
+                account_exclude_max: 10,
+                account_required_max: 10,
+                program_include_max: 5,
+                dedup_signatures_window: 1_000,
+                signer_include_max: 3,
+                cpi_program_include_max: 4,
+            })
+        );
+        // not configured, defaults are returned
+        assert_eq!(
+            response.transactions_status,
+            Some(get_filter_limits_response::Transactions {
+                max: u64::MAX,
+                any: true,
+                account_include_max: u64::MAX,
+                account_include_reject_size: 0,
+                account_exclude_max: u64::MAX,
+                account_required_max: u64::MAX,
+                program_include_max: u64::MAX,
+                dedup_signatures_window: 10_000,
+                signer_include_max: u64::MAX,
+                cpi_program_include_max: u64::MAX,
+            })
+        );
+        assert_eq!(
+            response.blocks,
+            Some(get_filter_limits_response::Blocks {
+                max: 1,
+                account_include_max: 10,
+                account_include_any: false,
+                account_include_reject_size: 0,
+                include_transactions: true,
+                include_accounts: false,
+                include_entries: false,
+                account_owner_include_max: u64::MAX,
+            })
+        );
+        assert_eq!(
+            response.blocks_meta,
+            Some(get_filter_limits_response::BlocksMeta { max: 1 })
+        );
+        assert_eq!(
+            response.entries,
+            Some(get_filter_limits_response::Entries { max: 1 })
+        );
+        assert_eq!(
+            response.program_events,
+            Some(get_filter_limits_response::ProgramEvents { max: u64::MAX })
+        );
+    }
+
+    #[test]
+    fn test_filter_limits_min_commitment() {
+        let limits: FilterLimits = serde_json::from_str(
+            r#"{ "min_commitment_per_type": { "block": "finalized", "transaction": "confirmed" } }"#,
+        )
+        .expect("valid limits");
+        assert_eq!(
+            limits.min_commitment_per_type.block,
+            Some(CommitmentLevel::Finalized)
+        );
+        assert_eq!(
+            limits.min_commitment_per_type.transaction,
+            Some(CommitmentLevel::Confirmed)
+        );
+        assert_eq!(limits.min_commitment_per_type.block_meta, None);
+
+        let error = serde_json::from_str::<FilterLimits>(
+            r#"{ "min_commitment_per_type": { "block": "rooted" } }"#,
+        )
+        .expect_err("invalid commitment");
+        assert!(error
+            .to_string()
+            .contains("Unknown commitment level: rooted"));
+    }
+}
@@ -1,19 +1,28 @@
 use {
     crate::{
         geyser::{
-            subscribe_update::UpdateOneof, SlotStatus as SlotStatusProto, SubscribeUpdate,
-            SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdateBlock,
-            SubscribeUpdateEntry, SubscribeUpdatePing, SubscribeUpdatePong, SubscribeUpdateSlot,
+            subscribe_update::UpdateOneof, CommitmentLevel as CommitmentLevelProto,
+            SlotStatus as SlotStatusProto, SubscribeUpdate, SubscribeUpdateAccount,
+            SubscribeUpdateAccountDataDiff, SubscribeUpdateAccountDataRef,
+            SubscribeUpdateAccountDataSegment, SubscribeUpdateAccountInfo, SubscribeUpdateBlock,
+            SubscribeUpdateEntry, SubscribeUpdateFilterStats, SubscribeUpdateJson,
+            SubscribeUpdatePing, SubscribeUpdatePong, SubscribeUpdateProgramEvent,
+            SubscribeUpdateServerShutdown, SubscribeUpdateSlot, SubscribeUpdateSlotDeltaBatch,
+            SubscribeUpdateSlotGap, SubscribeUpdateSnapshotComplete,
+            SubscribeUpdateSubscribeAccepted, SubscribeUpdateSubscribeAcceptedFilters,
             SubscribeUpdateTransaction, SubscribeUpdateTransactionInfo,
             SubscribeUpdateTransactionStatus,
         },
+        json,
         plugin::{
             filter::{name::FilterName, FilterAccountsDataSlice},
             message::{
-                MessageAccount, MessageAccountInfo, MessageBlock, MessageBlockMeta, MessageEntry,
-                MessageSlot, MessageTransaction, MessageTransactionInfo,
+                CommitmentLevel, MessageAccount, MessageAccountInfo, MessageBlock,
+                MessageBlockMeta, MessageEntry, MessageSlot, MessageTransaction,
+                MessageTransactionInfo, SlotStatus,
             },
         },
+        slot_delta,
         solana::storage::confirmed_block,
     },
     bytes::buf::{Buf, BufMut},
@@ -22,11 +31,12 @@ use {
             encode_key, encode_varint, encoded_len_varint, key_len, message, DecodeContext,
             WireType,
         },
-        DecodeError,
+        DecodeError, Message as _,
     },
-    prost_types::Timestamp,
+    prost_types::{Any, Timestamp},
+    serde_json::json,
     smallvec::SmallVec,
-    solana_sdk::signature::Signature,
+    solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature},
     std::{
         collections::HashSet,
         ops::{Deref, DerefMut},
@@ -70,6 +80,12 @@ pub struct FilteredUpdate {
     pub filters: FilteredUpdateFilters,
     pub message: FilteredUpdateOneof,
     pub created_at: Timestamp,
+    /// Sequence number of the update in the stream, assigned by the client loop
+    pub seq: Option<u64>,
+    /// Position of the stream after the update, assigned by the client loop
+    pub resume_token: Option<Vec<u8>>,
+    /// Sequence number of the update among updates of the slot, assigned by the client loop
+    pub slot_seq: Option<u64>,
 }
 
 impl prost::Message for FilteredUpdate {
@@ -81,12 +97,30 @@ impl prost::Message for FilteredUpdate {
         }
         self.message.encode_raw(buf);
         message::encode(11u32, &self.created_at, buf);
+        if let Some(seq) = self.seq {
+            ::prost::encoding::uint64::encode(19u32, &seq, buf);
+        }
+        if let Some(resume_token) = &self.resume_token {
+            ::prost::encoding::bytes::encode(21u32, resume_token, buf);
+        }
+        if let Some(slot_seq) = self.slot_seq {
+            ::prost::encoding::uint64::encode(22u32, &slot_seq, buf);
+        }
     }
 
     fn encoded_len(&self) -> usize {
         prost_repeated_encoded_len_map!(1u32, self.filters, |filter| filter.as_ref().len())
             + self.message.encoded_len()
             + message::encoded_len(11u32, &self.created_at)
+            + self
+                .seq
+                .map_or(0, |seq| ::prost::encoding::uint64::encoded_len(19u32, &seq))
+            + self.resume_token.as_ref().map_or(0, |resume_token| {
+                ::prost::encoding::bytes::encoded_len(21u32, resume_token)
+            })
+            + self.slot_seq.map_or(0, |slot_seq| {
+                ::prost::encoding::uint64::encoded_len(22u32, &slot_seq)
+            })
     }
 
     fn merge_field(
@@ -114,6 +148,9 @@ impl FilteredUpdate {
             filters,
             message,
             created_at,
+            seq: None,
+            resume_token: None,
+            slot_seq: None,
         }
     }
 
@@ -125,7 +162,7 @@ impl FilteredUpdate {
         )
     }
 
-    fn as_subscribe_update_account(
+    pub fn as_subscribe_update_account(
         message: &MessageAccountInfo,
         data_slice: &FilterAccountsDataSlice,
     ) -> SubscribeUpdateAccountInfo {
@@ -135,9 +172,26 @@ impl FilteredUpdate {
             owner: message.owner.as_ref().into(),
             executable: message.executable,
             rent_epoch: message.rent_epoch,
-            data: data_slice.get_slice(&message.data),
+            data: if data_slice.is_encoded() || data_slice.is_segmented() {
+                vec![]
+            } else {
+                data_slice.get_slice(&message.data)
+            },
             write_version: message.write_version,
             txn_signature: message.txn_signature.map(|s| s.as_ref().into()),
+            data_encoded: data_slice.get_encoded(&message.data),
+            data_checksum: message.data_checksum,
+            data_segments: if data_slice.is_segmented() {
+                data_slice
+                    .get_segments(&message.data)
+                    .map(|(offset, data)| SubscribeUpdateAccountDataSegment {
+                        offset: offset as u64,
+                        data: data.to_vec(),
+                    })
+                    .collect()
+            } else {
+                vec![]
+            },
         }
     }
 
@@ -164,8 +218,8 @@ impl FilteredUpdate {
         }
     }
 
-    pub fn as_subscribe_update(&self) -> SubscribeUpdate {
-        let message = match &self.message {
+    pub fn as_update_oneof(message: &FilteredUpdateOneof) -> UpdateOneof {
+        match message {
             FilteredUpdateOneof::Account(msg) => UpdateOneof::Account(SubscribeUpdateAccount {
                 account: Some(Self::as_subscribe_update_account(
                     msg.account.as_ref(),
@@ -173,12 +227,19 @@ impl FilteredUpdate {
                 )),
                 slot: msg.slot,
                 is_startup: msg.is_startup,
+                commitment: msg
+                    .commitment
+                    .map(|commitment| CommitmentLevelProto::from(commitment) as i32),
+                data_decompress_failed: msg.data_decompress_failed,
+                data_diff: msg.data_diff,
+                data_ref: msg.data_ref.clone(),
             }),
             FilteredUpdateOneof::Slot(msg) => UpdateOneof::Slot(SubscribeUpdateSlot {
                 slot: msg.slot,
                 parent: msg.parent,
                 status: msg.status as i32,
                 dead_error: msg.dead_error.clone(),
+                raw_status: msg.raw_status.clone(),
             }),
             FilteredUpdateOneof::Transaction(msg) => {
                 UpdateOneof::Transaction(SubscribeUpdateTransaction {
@@ -232,16 +293,34 @@ impl FilteredUpdate {
             FilteredUpdateOneof::Entry(msg) => {
                 UpdateOneof::Entry(Self::as_subscribe_update_entry(&msg.0))
             }
-        };
+            FilteredUpdateOneof::SlotGap(msg) => UpdateOneof::SlotGap(*msg),
+            FilteredUpdateOneof::FilterStats(msg) => UpdateOneof::FilterStats(msg.clone()),
+            FilteredUpdateOneof::SnapshotComplete(msg) => UpdateOneof::SnapshotComplete(*msg),
+            FilteredUpdateOneof::ServerShutdown => {
+                UpdateOneof::ServerShutdown(SubscribeUpdateServerShutdown {})
+            }
+            FilteredUpdateOneof::ProgramEvent(msg) => UpdateOneof::ProgramEvent(msg.clone()),
+            FilteredUpdateOneof::SlotDeltaBatch(msg) => UpdateOneof::SlotDeltaBatch(msg.clone()),
+            FilteredUpdateOneof::SubscribeAccepted(msg) => {
+                UpdateOneof::SubscribeAccepted(msg.clone())
+            }
+            FilteredUpdateOneof::Envelope(msg) => UpdateOneof::Envelope(msg.clone()),
+            FilteredUpdateOneof::Json(msg) => UpdateOneof::Json(msg.clone()),
+        }
+    }
 
+    pub fn as_subscribe_update(&self) -> SubscribeUpdate {
         SubscribeUpdate {
             filters: self
                 .filters
                 .iter()
                 .map(|name| name.as_ref().to_string())
                 .collect(),
-            update_oneof: Some(message),
+            update_oneof: Some(Self::as_update_oneof(&self.message)),
             created_at: Some(self.created_at),
+            seq: self.seq,
+            resume_token: self.resume_token.clone(),
+            slot_seq: self.slot_seq,
         }
     }
 
@@ -250,12 +329,28 @@ impl FilteredUpdate {
 
         let message = match update.update_oneof.ok_or("update should be defined")? {
             UpdateOneof::Account(msg) => {
+                let commitment = msg
+                    .commitment
+                    .map(|commitment| {
+                        CommitmentLevelProto::try_from(commitment)
+                            .map(Into::into)
+                            .map_err(|_| "failed to parse commitment level")
+                    })
+                    .transpose()?;
+                let data_decompress_failed = msg.data_decompress_failed;
+                let data_diff = msg.data_diff;
+                let data_ref = msg.data_ref.clone();
                 let account = MessageAccount::from_update_oneof(msg, created_at)?;
                 FilteredUpdateOneof::Account(FilteredUpdateAccount {
                     account: account.account,
                     slot: account.slot,
                     is_startup: account.is_startup,
                     data_slice: FilterAccountsDataSlice::default(),
+                    commitment,
+                    data_decompress_failed,
+                    data_compressed_len: None,
+                    data_diff,
+                    data_ref,
                 })
             }
             UpdateOneof::Slot(msg) => {
@@ -307,12 +402,24 @@ impl FilteredUpdate {
                 let entry = MessageEntry::from_update_oneof(&msg, created_at)?;
                 FilteredUpdateOneof::Entry(FilteredUpdateEntry(Arc::new(entry)))
             }
+            UpdateOneof::SlotGap(msg) => FilteredUpdateOneof::SlotGap(msg),
+            UpdateOneof::FilterStats(msg) => FilteredUpdateOneof::FilterStats(msg),
+            UpdateOneof::SnapshotComplete(msg) => FilteredUpdateOneof::SnapshotComplete(msg),
+            UpdateOneof::ServerShutdown(_) => FilteredUpdateOneof::ServerShutdown,
+            UpdateOneof::ProgramEvent(msg) => FilteredUpdateOneof::ProgramEvent(msg),
+            UpdateOneof::SlotDeltaBatch(msg) => FilteredUpdateOneof::SlotDeltaBatch(msg),
+            UpdateOneof::SubscribeAccepted(msg) => FilteredUpdateOneof::SubscribeAccepted(msg),
+            UpdateOneof::Envelope(msg) => FilteredUpdateOneof::Envelope(msg),
+            UpdateOneof::Json(msg) => FilteredUpdateOneof::Json(msg),
         };
 
         Ok(Self {
             filters: update.filters.into_iter().map(FilterName::new).collect(),
             message,
             created_at,
+            seq: update.seq,
+            resume_token: update.resume_token,
+            slot_seq: update.slot_seq,
         })
     }
 }
@@ -321,15 +428,24 @@ pub type FilteredUpdateFilters = SmallVec<[FilterName; 4]>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FilteredUpdateOneof {
-    Account(FilteredUpdateAccount),                     // 2
-    Slot(FilteredUpdateSlot),                           // 3
-    Transaction(FilteredUpdateTransaction),             // 4
-    TransactionStatus(FilteredUpdateTransactionStatus), // 10
-    Block(Box<FilteredUpdateBlock>),                    // 5
-    Ping,                                               // 6
-    Pong(SubscribeUpdatePong),                          // 9
-    BlockMeta(Arc<MessageBlockMeta>),                   // 7
-    Entry(FilteredUpdateEntry),                         // 8
+    Account(FilteredUpdateAccount),                      // 2
+    Slot(FilteredUpdateSlot),                            // 3
+    Transaction(FilteredUpdateTransaction),              // 4
+    TransactionStatus(FilteredUpdateTransactionStatus),  // 10
+    Block(Box<FilteredUpdateBlock>),                     // 5
+    Ping,                                                // 6
+    Pong(SubscribeUpdatePong),                           // 9
+    BlockMeta(Arc<MessageBlockMeta>),                    // 7
+    Entry(FilteredUpdateEntry),                          // 8
+    SlotGap(SubscribeUpdateSlotGap),                     // 12
+    Envelope(Any),                                       // 13
+    FilterStats(SubscribeUpdateFilterStats),             // 14
+    SnapshotComplete(SubscribeUpdateSnapshotComplete),   // 15
+    ServerShutdown,                                      // 16
+    ProgramEvent(SubscribeUpdateProgramEvent),           // 17
+    SlotDeltaBatch(SubscribeUpdateSlotDeltaBatch),       // 18
+    SubscribeAccepted(SubscribeUpdateSubscribeAccepted), // 20
+    Json(SubscribeUpdateJson),                           // 23
 }
 
 impl FilteredUpdateOneof {
@@ -339,6 +455,29 @@ impl FilteredUpdateOneof {
             account: Arc::clone(&message.account),
             is_startup: message.is_startup,
             data_slice,
+            commitment: None,
+            data_decompress_failed: false,
+            data_compressed_len: None,
+            data_diff: None,
+            data_ref: None,
+        })
+    }
+
+    pub fn account_at_commitment(
+        message: &MessageAccount,
+        data_slice: FilterAccountsDataSlice,
+        commitment: CommitmentLevel,
+    ) -> Self {
+        Self::Account(FilteredUpdateAccount {
+            slot: message.slot,
+            account: Arc::clone(&message.account),
+            is_startup: message.is_startup,
+            data_slice,
+            commitment: Some(commitment),
+            data_decompress_failed: false,
+            data_compressed_len: None,
+            data_diff: None,
+            data_ref: None,
         })
     }
 
@@ -379,6 +518,161 @@ impl FilteredUpdateOneof {
     pub const fn entry(message: Arc<MessageEntry>) -> Self {
         Self::Entry(FilteredUpdateEntry(message))
     }
+
+    pub fn slot_gap(from_slot: Slot, to_slot: Slot, commitment: CommitmentLevel) -> Self {
+        Self::SlotGap(SubscribeUpdateSlotGap {
+            from_slot,
+            to_slot,
+            commitment: CommitmentLevelProto::from(commitment) as i32,
+        })
+    }
+
+    pub const fn snapshot_complete(accounts: u64, slot: Slot) -> Self {
+        Self::SnapshotComplete(SubscribeUpdateSnapshotComplete { accounts, slot })
+    }
+
+    pub const fn server_shutdown() -> Self {
+        Self::ServerShutdown
+    }
+
+    pub fn program_event(
+        message: &MessageTransaction,
+        program: &Pubkey,
+        log_index: usize,
+        data: Vec<Vec<u8>>,
+    ) -> Self {
+        Self::ProgramEvent(SubscribeUpdateProgramEvent {
+            slot: message.slot,
+            signature: message.transaction.signature.as_ref().into(),
+            transaction_index: message.transaction.index as u64,
+            program: program.as_ref().into(),
+            log_index: log_index as u32,
+            data,
+        })
+    }
+
+    pub fn slot_delta_batch(transitions: &[(Slot, SlotStatus)]) -> Self {
+        let transitions = transitions
+            .iter()
+            .map(|(slot, status)| (*slot, SlotStatusProto::from(*status)))
+            .collect::<Vec<_>>();
+        Self::SlotDeltaBatch(slot_delta::encode(&transitions))
+    }
+
+    pub fn subscribe_accepted(filters: &[(&str, usize)], commitment: CommitmentLevel) -> Self {
+        Self::SubscribeAccepted(SubscribeUpdateSubscribeAccepted {
+            filters: filters
+                .iter()
+                .map(
+                    |(filter_type, count)| SubscribeUpdateSubscribeAcceptedFilters {
+                        filter_type: (*filter_type).to_owned(),
+                        count: *count as u64,
+                    },
+                )
+                .collect(),
+            commitment: CommitmentLevelProto::from(commitment) as i32,
+        })
+    }
+
+    /// Slot of the update, `None` for service updates, slot gaps, batches, envelopes and JSON
+    pub fn get_slot(&self) -> Option<Slot> {
+        match self {
+            Self::Account(msg) => Some(msg.slot),
+            Self::Slot(msg) => Some(msg.slot),
+            Self::Transaction(msg) => Some(msg.slot),
+            Self::TransactionStatus(msg) => Some(msg.slot),
+            Self::Block(msg) => Some(msg.meta.slot),
+            Self::BlockMeta(msg) => Some(msg.slot),
+            Self::Entry(msg) => Some(msg.0.slot),
+            Self::ProgramEvent(msg) => Some(msg.slot),
+            Self::Ping
+            | Self::Pong(_)
+            | Self::SlotGap(_)
+            | Self::Envelope(_)
+            | Self::FilterStats(_)
+            | Self::SnapshotComplete(_)
+            | Self::ServerShutdown
+            | Self::SlotDeltaBatch(_)
+            | Self::SubscribeAccepted(_)
+            | Self::Json(_) => None,
+        }
+    }
+
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Account(_) => "account",
+            Self::Slot(_) => "slot",
+            Self::Transaction(_) => "transaction",
+            Self::TransactionStatus(_) => "transaction_status",
+            Self::Block(_) => "block",
+            Self::Ping => "ping",
+            Self::Pong(_) => "pong",
+            Self::BlockMeta(_) => "block_meta",
+            Self::Entry(_) => "entry",
+            Self::SlotGap(_) => "slot_gap",
+            Self::Envelope(_) => "envelope",
+            Self::FilterStats(_) => "filter_stats",
+            Self::SnapshotComplete(_) => "snapshot_complete",
+            Self::ServerShutdown => "server_shutdown",
+            Self::ProgramEvent(_) => "program_event",
+            Self::SlotDeltaBatch(_) => "slot_delta_batch",
+            Self::SubscribeAccepted(_) => "subscribe_accepted",
+            Self::Json(_) => "json",
+        }
+    }
+
+    pub fn as_envelope(&self) -> Self {
+        let (name, value) = match self {
+            Self::Account(msg) => ("SubscribeUpdateAccount", msg.encode_to_vec()),
+            Self::Slot(msg) => ("SubscribeUpdateSlot", msg.encode_to_vec()),
+            Self::Transaction(msg) => ("SubscribeUpdateTransaction", msg.encode_to_vec()),
+            Self::TransactionStatus(msg) => {
+                ("SubscribeUpdateTransactionStatus", msg.encode_to_vec())
+            }
+            Self::Block(msg) => ("SubscribeUpdateBlock", msg.encode_to_vec()),
+            Self::Ping => (
+                "SubscribeUpdatePing",
+                SubscribeUpdatePing {}.encode_to_vec(),
+            ),
+            Self::Pong(msg) => ("SubscribeUpdatePong", msg.encode_to_vec()),
+            Self::BlockMeta(msg) => ("SubscribeUpdateBlockMeta", msg.block_meta.encode_to_vec()),
+            Self::Entry(msg) => ("SubscribeUpdateEntry", msg.encode_to_vec()),
+            Self::SlotGap(msg) => ("SubscribeUpdateSlotGap", msg.encode_to_vec()),
+            Self::FilterStats(msg) => ("SubscribeUpdateFilterStats", msg.encode_to_vec()),
+            Self::SnapshotComplete(msg) => ("SubscribeUpdateSnapshotComplete", msg.encode_to_vec()),
+            Self::ServerShutdown => (
+                "SubscribeUpdateServerShutdown",
+                SubscribeUpdateServerShutdown {}.encode_to_vec(),
+            ),
+            Self::ProgramEvent(msg) => ("SubscribeUpdateProgramEvent", msg.encode_to_vec()),
+            Self::SlotDeltaBatch(msg) => ("SubscribeUpdateSlotDeltaBatch", msg.encode_to_vec()),
+            Self::SubscribeAccepted(msg) => {
+                ("SubscribeUpdateSubscribeAccepted", msg.encode_to_vec())
+            }
+            Self::Json(msg) => ("SubscribeUpdateJson", msg.encode_to_vec()),
+            Self::Envelope(_) => return self.clone(),
+        };
+        Self::Envelope(Any {
+            type_url: format!("type.googleapis.com/geyser.{name}"),
+            value,
+        })
+    }
+
+    /// Update rendered as JSON, pings and pongs are not changed. Updates which can't be rendered
+    /// are replaced with `error` kind so the client doesn't receive protobuf it didn't ask for
+    pub fn as_json(&self) -> Self {
+        match json::create_pretty_update(FilteredUpdate::as_update_oneof(self)) {
+            Ok(Some((kind, value))) => Self::Json(SubscribeUpdateJson {
+                kind: kind.to_owned(),
+                value: value.to_string(),
+            }),
+            Ok(None) => self.clone(),
+            Err(error) => Self::Json(SubscribeUpdateJson {
+                kind: "error".to_owned(),
+                value: json!({ "error": error }).to_string(),
+            }),
+        }
+    }
 }
 
 impl prost::Message for FilteredUpdateOneof {
@@ -396,6 +690,18 @@ impl prost::Message for FilteredUpdateOneof {
             Self::Pong(msg) => message::encode(9u32, msg, buf),
             Self::BlockMeta(msg) => message::encode(7u32, &msg.block_meta, buf),
             Self::Entry(msg) => message::encode(8u32, msg, buf),
+            Self::SlotGap(msg) => message::encode(12u32, msg, buf),
+            Self::Envelope(msg) => message::encode(13u32, msg, buf),
+            Self::FilterStats(msg) => message::encode(14u32, msg, buf),
+            Self::SnapshotComplete(msg) => message::encode(15u32, msg, buf),
+            Self::ServerShutdown => {
+                encode_key(16u32, WireType::LengthDelimited, buf);
+                encode_varint(0, buf);
+            }
+            Self::ProgramEvent(msg) => message::encode(17u32, msg, buf),
+            Self::SlotDeltaBatch(msg) => message::encode(18u32, msg, buf),
+            Self::SubscribeAccepted(msg) => message::encode(20u32, msg, buf),
+            Self::Json(msg) => message::encode(23u32, msg, buf),
         }
     }
 
@@ -410,6 +716,15 @@ impl prost::Message for FilteredUpdateOneof {
             Self::Pong(msg) => message::encoded_len(9u32, msg),
             Self::BlockMeta(msg) => message::encoded_len(7u32, &msg.block_meta),
             Self::Entry(msg) => message::encoded_len(8u32, msg),
+            Self::SlotGap(msg) => message::encoded_len(12u32, msg),
+            Self::Envelope(msg) => message::encoded_len(13u32, msg),
+            Self::FilterStats(msg) => message::encoded_len(14u32, msg),
+            Self::SnapshotComplete(msg) => message::encoded_len(15u32, msg),
+            Self::ServerShutdown => key_len(16u32) + encoded_len_varint(0),
+            Self::ProgramEvent(msg) => message::encoded_len(17u32, msg),
+            Self::SlotDeltaBatch(msg) => message::encoded_len(18u32, msg),
+            Self::SubscribeAccepted(msg) => message::encoded_len(20u32, msg),
+            Self::Json(msg) => message::encoded_len(23u32, msg),
         }
     }
 
@@ -434,6 +749,15 @@ pub struct FilteredUpdateAccount {
     pub slot: u64,
     pub is_startup: bool,
     pub data_slice: FilterAccountsDataSlice,
+    pub commitment: Option<CommitmentLevel>,
+    pub data_decompress_failed: bool,
+    /// Length of the data received from geyser if `account` holds data decompressed with
+    /// `decompress_data`, the server keeps only received data for `GetAccountData`
+    pub data_compressed_len: Option<usize>,
+    /// Set if `data_slice` holds changed ranges against the previous update of the account
+    pub data_diff: Option<SubscribeUpdateAccountDataDiff>,
+    /// Set if data is omitted from the update, see `set_data_out_of_band`
+    pub data_ref: Option<SubscribeUpdateAccountDataRef>,
 }
 
 impl prost::Message for FilteredUpdateAccount {
@@ -445,6 +769,19 @@ impl prost::Message for FilteredUpdateAccount {
         if self.is_startup {
             ::prost::encoding::bool::encode(3u32, &self.is_startup, buf);
         }
+        if let Some(commitment) = self.commitment {
+            let value = CommitmentLevelProto::from(commitment) as i32;
+            ::prost::encoding::int32::encode(4u32, &value, buf);
+        }
+        if self.data_decompress_failed {
+            ::prost::encoding::bool::encode(5u32, &self.data_decompress_failed, buf);
+        }
+        if let Some(data_diff) = &self.data_diff {
+            message::encode(6u32, data_diff, buf);
+        }
+        if let Some(data_ref) = &self.data_ref {
+            message::encode(7u32, data_ref, buf);
+        }
     }
 
     fn encoded_len(&self) -> usize {
@@ -459,7 +796,23 @@ impl prost::Message for FilteredUpdateAccount {
             ::prost::encoding::bool::encoded_len(3u32, &self.is_startup)
         } else {
             0
-        }
+        } + if let Some(commitment) = self.commitment {
+            let value = CommitmentLevelProto::from(commitment) as i32;
+            ::prost::encoding::int32::encoded_len(4u32, &value)
+        } else {
+            0
+        } + if self.data_decompress_failed {
+            ::prost::encoding::bool::encoded_len(5u32, &self.data_decompress_failed)
+        } else {
+            0
+        } + self
+            .data_diff
+            .as_ref()
+            .map_or(0, |data_diff| message::encoded_len(6u32, data_diff))
+            + self
+                .data_ref
+                .as_ref()
+                .map_or(0, |data_ref| message::encoded_len(7u32, data_ref))
     }
 
     fn merge_field(
@@ -478,6 +831,35 @@ impl prost::Message for FilteredUpdateAccount {
 }
 
 impl FilteredUpdateAccount {
+    /// Data of the length received from geyser is kept by the server for `GetAccountData`, the
+    /// same check is used for the kept data and for updates with `data_ref`
+    pub const fn is_data_out_of_band(data_len: usize, threshold: usize) -> bool {
+        data_len > threshold
+    }
+
+    /// Omits data if the update would carry more than `threshold` bytes of it (after slices and
+    /// encoding), the update holds a reference to fetch the full data with `GetAccountData`
+    /// instead. Diff of the data is replaced by the reference to full data. Data which is not
+    /// kept by the server (see `is_data_out_of_band`) is always sent inline, `data_len` of the
+    /// reference is the length of the kept data (compressed if `decompress_data` is used).
+    pub fn set_data_out_of_band(&mut self, threshold: usize) -> bool {
+        let data_len = self.data_compressed_len.unwrap_or(self.account.data.len());
+        if !Self::is_data_out_of_band(data_len, threshold)
+            || self.data_slice.get_sent_len(&self.account.data) <= threshold
+        {
+            return false;
+        }
+        self.data_slice = FilterAccountsDataSlice::new_unchecked(Arc::new(vec![0..0]));
+        self.data_diff = None;
+        self.data_ref = Some(SubscribeUpdateAccountDataRef {
+            pubkey: self.account.pubkey.as_ref().into(),
+            slot: self.slot,
+            write_version: self.account.write_version,
+            data_len: data_len as u64,
+        });
+        true
+    }
+
     fn account_encode_raw(
         tag: u32,
         account: &MessageAccountInfo,
@@ -498,20 +880,37 @@ impl FilteredUpdateAccount {
         if account.rent_epoch != 0u64 {
             ::prost::encoding::uint64::encode(5u32, &account.rent_epoch, buf);
         }
-        data_slice.slice_encode_raw(6u32, &account.data, buf);
+        let data_encoded = data_slice.get_encoded(&account.data);
+        if data_encoded.is_none() && !data_slice.is_segmented() {
+            data_slice.slice_encode_raw(6u32, &account.data, buf);
+        }
         if account.write_version != 0u64 {
             ::prost::encoding::uint64::encode(7u32, &account.write_version, buf);
         }
         if let Some(value) = &account.txn_signature {
             prost_bytes_encode_raw(8u32, value.as_ref(), buf);
         }
+        if let Some(value) = &data_encoded {
+            ::prost::encoding::string::encode(9u32, value, buf);
+        }
+        if let Some(value) = &account.data_checksum {
+            ::prost::encoding::uint32::encode(10u32, value, buf);
+        }
+        if data_slice.is_segmented() {
+            data_slice.segments_encode_raw(11u32, &account.data, buf);
+        }
     }
 
     fn account_encoded_len(
         account: &MessageAccountInfo,
         data_slice: &FilterAccountsDataSlice,
     ) -> usize {
-        let data_len = data_slice.get_slice_len(&account.data);
+        let data_encoded = data_slice.get_encoded(&account.data);
+        let data_len = if data_encoded.is_none() && !data_slice.is_segmented() {
+            data_slice.get_slice_len(&account.data)
+        } else {
+            0
+        };
 
         prost_bytes_encoded_len(1u32, account.pubkey.as_ref())
             + if account.lamports != 0u64 {
@@ -543,6 +942,17 @@ impl FilteredUpdateAccount {
             + account
                 .txn_signature
                 .map_or(0, |sig| prost_bytes_encoded_len(8u32, sig.as_ref()))
+            + data_encoded.map_or(0, |value| {
+                ::prost::encoding::string::encoded_len(9u32, &value)
+            })
+            + account.data_checksum.map_or(0, |value| {
+                ::prost::encoding::uint32::encoded_len(10u32, &value)
+            })
+            + if data_slice.is_segmented() {
+                data_slice.segments_encoded_len(11u32, &account.data)
+            } else {
+                0
+            }
     }
 }
 
@@ -578,6 +988,9 @@ impl prost::Message for FilteredUpdateSlot {
         if let Some(error) = &self.dead_error {
             ::prost::encoding::string::encode(4u32, error, buf);
         }
+        if let Some(raw_status) = &self.raw_status {
+            ::prost::encoding::string::encode(5u32, raw_status, buf);
+        }
     }
 
     fn encoded_len(&self) -> usize {
@@ -597,7 +1010,9 @@ impl prost::Message for FilteredUpdateSlot {
             ::prost::encoding::string::encoded_len(4u32, error)
         } else {
             0
-        }
+        } + self.raw_status.as_ref().map_or(0, |value| {
+            ::prost::encoding::string::encoded_len(5u32, value)
+        })
     }
 
     fn merge_field(
@@ -982,35 +1397,35 @@ pub mod tests {
         super::{FilteredUpdate, FilteredUpdateBlock, FilteredUpdateFilters, FilteredUpdateOneof},
         crate::{
             convert_to,
-            geyser::{SubscribeUpdate, SubscribeUpdateBlockMeta},
+            geyser::{
+                subscribe_update::UpdateOneof, AccountsDataEncoding, SlotStatus as SlotStatusProto,
+                SubscribeUpdate, SubscribeUpdateAccount, SubscribeUpdateAccountDataDiff,
+                SubscribeUpdateAccountDataRef, SubscribeUpdateBlock, SubscribeUpdateBlockMeta,
+                SubscribeUpdateEntry, SubscribeUpdateFilterStats, SubscribeUpdateFilterStatsEntry,
+                SubscribeUpdatePing, SubscribeUpdatePong, SubscribeUpdateProgramEvent,
+                SubscribeUpdateServerShutdown, SubscribeUpdateSlot, SubscribeUpdateSlotDeltaBatch,
+                SubscribeUpdateSlotGap, SubscribeUpdateSnapshotComplete,
+                SubscribeUpdateSubscribeAccepted, SubscribeUpdateTransaction,
+                SubscribeUpdateTransactionStatus,
+            },
+            json::create_pretty_update,
             plugin::{
                 filter::{name::FilterName, FilterAccountsDataSlice},
+                fixtures,
                 message::{
-                    MessageAccount, MessageAccountInfo, MessageBlockMeta, MessageEntry,
-                    MessageSlot, MessageTransaction, MessageTransactionInfo, SlotStatus,
+                    CommitmentLevel, MessageAccount, MessageAccountInfo, MessageBlockMeta,
+                    MessageEntry, MessageSlot, MessageTransaction, MessageTransactionInfo,
+                    SlotStatus,
                 },
             },
         },
+        agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus as GeyserSlotStatus,
+        base64::{engine::general_purpose::STANDARD as base64_engine, Engine},
         prost::Message as _,
-        prost_011::Message as _,
         prost_types::Timestamp,
-        solana_sdk::{
-            hash::Hash,
-            message::SimpleAddressLoader,
-            pubkey::Pubkey,
-            signature::Signature,
-            transaction::{MessageHash, SanitizedTransaction},
-        },
-        solana_storage_proto::convert::generated,
-        solana_transaction_status::{ConfirmedBlock, TransactionWithStatusMeta},
-        std::{
-            collections::{HashMap, HashSet},
-            fs,
-            ops::Range,
-            str::FromStr,
-            sync::Arc,
-            time::SystemTime,
-        },
+        serde_json::{json, Value},
+        solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature},
+        std::{collections::HashMap, ops::Range, str::FromStr, sync::Arc, time::SystemTime},
     };
 
     pub fn create_message_filters(names: &[&str]) -> FilteredUpdateFilters {
@@ -1051,16 +1466,19 @@ pub mod tests {
                     ] {
                         for write_version in [0, 1] {
                             for txn_signature in [None, Some(txn_signature)] {
-                                accounts.push(Arc::new(MessageAccountInfo {
-                                    pubkey,
-                                    lamports,
-                                    owner,
-                                    executable,
-                                    rent_epoch,
-                                    data: data.clone(),
-                                    write_version,
-                                    txn_signature,
-                                }));
+                                for data_checksum in [None, Some(crc32fast::hash(&data))] {
+                                    accounts.push(Arc::new(MessageAccountInfo {
+                                        pubkey,
+                                        lamports,
+                                        owner,
+                                        executable,
+                                        rent_epoch,
+                                        data: data.clone(),
+                                        write_version,
+                                        txn_signature,
+                                        data_checksum,
+                                    }));
+                                }
                             }
                         }
                     }
@@ -1116,20 +1534,6 @@ pub mod tests {
         .collect()
     }
 
-    pub fn load_predefined() -> Vec<ConfirmedBlock> {
-        fs::read_dir("./fixtures/blocks")
-            .expect("failed to read `blocks` dir")
-            .map(|entry| {
-                let path = entry.expect("failed to read `blocks` dir entry").path();
-                let data = fs::read(path).expect("failed to read block");
-                generated::ConfirmedBlock::decode(data.as_slice())
-                    .expect("failed to decode block")
-                    .try_into()
-                    .expect("failed to convert decoded block")
-            })
-            .collect()
-    }
-
     pub fn load_predefined_blockmeta() -> Vec<Arc<MessageBlockMeta>> {
         load_predefined_blocks()
             .into_iter()
@@ -1149,36 +1553,10 @@ pub mod tests {
     }
 
     pub fn load_predefined_blocks() -> Vec<FilteredUpdateBlock> {
-        load_predefined()
+        fixtures::load_predefined()
             .into_iter()
             .flat_map(|block| {
-                let transactions = block
-                    .transactions
-                    .into_iter()
-                    .enumerate()
-                    .map(|(index, tx)| {
-                        let TransactionWithStatusMeta::Complete(tx) = tx else {
-                            panic!("tx with missed meta");
-                        };
-                        let transaction = SanitizedTransaction::try_create(
-                            tx.transaction.clone(),
-                            MessageHash::Compute,
-                            None,
-                            SimpleAddressLoader::Disabled,
-                            &HashSet::new(),
-                        )
-                        .expect("failed to create tx");
-                        MessageTransactionInfo {
-                            signature: tx.transaction.signatures[0],
-                            is_vote: true,
-                            transaction: convert_to::create_transaction(&transaction),
-                            meta: convert_to::create_transaction_meta(&tx.meta),
-                            index,
-                            account_keys: HashSet::new(),
-                        }
-                    })
-                    .map(Arc::new)
-                    .collect::<Vec<_>>();
+                let transactions = fixtures::create_transactions(&block);
 
                 let entries = create_entries();
 
@@ -1239,6 +1617,9 @@ pub mod tests {
             filters: create_message_filters(filters),
             message,
             created_at: Timestamp::from(SystemTime::now()),
+            seq: None,
+            resume_token: None,
+            slot_seq: None,
         };
         let update = msg.as_subscribe_update();
         assert_eq!(msg.encoded_len(), update.encoded_len());
@@ -1256,7 +1637,190 @@ pub mod tests {
     #[test]
     fn test_message_account() {
         for (msg, data_slice) in create_accounts() {
-            encode_decode_cmp(&["123"], FilteredUpdateOneof::account(&msg, data_slice));
+            encode_decode_cmp(
+                &["123"],
+                FilteredUpdateOneof::account(&msg, data_slice.clone()),
+            );
+            for commitment in [
+                CommitmentLevel::Processed,
+                CommitmentLevel::Confirmed,
+                CommitmentLevel::Finalized,
+            ] {
+                encode_decode_cmp(
+                    &["123"],
+                    FilteredUpdateOneof::account_at_commitment(
+                        &msg,
+                        data_slice.clone(),
+                        commitment,
+                    ),
+                );
+            }
+            let mut update = FilteredUpdateOneof::account(&msg, data_slice.clone());
+            if let FilteredUpdateOneof::Account(account) = &mut update {
+                account.data_decompress_failed = true;
+            }
+            encode_decode_cmp(&["123"], update);
+            let mut update = FilteredUpdateOneof::account(&msg, data_slice);
+            if let FilteredUpdateOneof::Account(account) = &mut update {
+                account.data_diff = Some(SubscribeUpdateAccountDataDiff {
+                    base_write_version: 42,
+                    data_len: account.account.data.len() as u64,
+                });
+            }
+            encode_decode_cmp(&["123"], update);
+        }
+    }
+
+    #[test]
+    fn test_message_account_data_out_of_band() {
+        for (msg, data_slice) in create_accounts() {
+            let FilteredUpdateOneof::Account(mut account) =
+                FilteredUpdateOneof::account(&msg, data_slice)
+            else {
+                unreachable!();
+            };
+            let data_len = msg.account.data.len();
+            let sent_len = account.data_slice.get_sent_len(&msg.account.data);
+
+            // below and at the threshold data is sent inline, sliced data is compared by
+            // the size of slices
+            let mut inline = account.clone();
+            assert!(!inline.set_data_out_of_band(sent_len));
+            assert_eq!(inline, account);
+            encode_decode_cmp(&["123"], FilteredUpdateOneof::Account(inline));
+
+            if sent_len == 0 {
+                continue;
+            }
+            account.data_diff = Some(SubscribeUpdateAccountDataDiff {
+                base_write_version: 42,
+                data_len: data_len as u64,
+            });
+            assert!(account.set_data_out_of_band(sent_len - 1));
+            assert_eq!(account.data_diff, None);
+            assert_eq!(
+                account.data_ref,
+                Some(SubscribeUpdateAccountDataRef {
+                    pubkey: msg.account.pubkey.to_bytes().to_vec(),
+                    slot: msg.slot,
+                    write_version: msg.account.write_version,
+                    data_len: data_len as u64,
+                })
+            );
+            let update = FilteredUpdate::new_empty(FilteredUpdateOneof::Account(account.clone()))
+                .as_subscribe_update();
+            let Some(UpdateOneof::Account(update)) = update.update_oneof else {
+                unreachable!();
+            };
+            let info = update.account.expect("account");
+            assert!(info.data.is_empty() && info.data_encoded.is_none());
+            assert!(info.data_segments.is_empty());
+            encode_decode_cmp(&["123"], FilteredUpdateOneof::Account(account));
+        }
+    }
+
+    #[test]
+    fn test_message_account_data_encoding() {
+        for (msg, data_slice) in create_accounts() {
+            for encoding in [AccountsDataEncoding::Base64, AccountsDataEncoding::Base58] {
+                // base58 encoding is quadratic, skip big accounts
+                if encoding == AccountsDataEncoding::Base58 && msg.account.data.len() > 1024 {
+                    continue;
+                }
+
+                let data = data_slice.get_slice(&msg.account.data);
+                let msg = FilteredUpdate {
+                    filters: create_message_filters(&["123"]),
+                    message: FilteredUpdateOneof::account(
+                        &msg,
+                        data_slice.clone().with_encoding(encoding),
+                    ),
+                    created_at: Timestamp::from(SystemTime::now()),
+                    seq: None,
+                    resume_token: None,
+                    slot_seq: None,
+                };
+                let update = msg.as_subscribe_update();
+                assert_eq!(msg.encoded_len(), update.encoded_len());
+                assert_eq!(
+                    SubscribeUpdate::decode(msg.encode_to_vec().as_slice())
+                        .expect("failed to decode"),
+                    update
+                );
+
+                let Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                    account: Some(account),
+                    ..
+                })) = update.update_oneof
+                else {
+                    panic!("expected account update");
+                };
+                assert!(account.data.is_empty());
+                let data_encoded = account.data_encoded.expect("encoded data");
+                let decoded = match encoding {
+                    AccountsDataEncoding::Base64 => base64_engine.decode(data_encoded).unwrap(),
+                    AccountsDataEncoding::Base58 => bs58::decode(data_encoded).into_vec().unwrap(),
+                    AccountsDataEncoding::Raw => unreachable!(),
+                };
+                assert_eq!(decoded, data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_message_account_data_segments() {
+        for (msg, data_slice) in create_accounts() {
+            let data_slice = data_slice.with_segments(true);
+            let msg = FilteredUpdate {
+                filters: create_message_filters(&["123"]),
+                message: FilteredUpdateOneof::account(&msg, data_slice.clone()),
+                created_at: Timestamp::from(SystemTime::now()),
+                seq: None,
+                resume_token: None,
+                slot_seq: None,
+            };
+            let update = msg.as_subscribe_update();
+            assert_eq!(msg.encoded_len(), update.encoded_len());
+            assert_eq!(
+                SubscribeUpdate::decode(msg.encode_to_vec().as_slice()).expect("failed to decode"),
+                update
+            );
+
+            let FilteredUpdateOneof::Account(message) = &msg.message else {
+                panic!("expected account update");
+            };
+            let source = &message.account.data;
+            let Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(account),
+                ..
+            })) = update.update_oneof
+            else {
+                panic!("expected account update");
+            };
+            if data_slice.is_segmented() {
+                // segments joined together are equal to the concatenated output
+                assert!(account.data.is_empty());
+                let joined = account
+                    .data_segments
+                    .iter()
+                    .flat_map(|segment| segment.data.iter().copied())
+                    .collect::<Vec<_>>();
+                assert_eq!(joined, data_slice.get_slice(source));
+                assert_eq!(
+                    account
+                        .data_segments
+                        .iter()
+                        .map(|segment| segment.offset)
+                        .collect::<Vec<_>>(),
+                    data_slice
+                        .get_segments(source)
+                        .map(|(offset, _data)| offset as u64)
+                        .collect::<Vec<_>>()
+                );
+            } else {
+                assert!(account.data_segments.is_empty());
+                assert_eq!(account.data, *source);
+            }
         }
     }
 
@@ -1280,6 +1844,7 @@ pub mod tests {
                             parent,
                             status,
                             dead_error: None,
+                            raw_status: None,
                             created_at: Timestamp::from(SystemTime::now()),
                         }),
                     )
@@ -1291,6 +1856,7 @@ pub mod tests {
                         parent,
                         status: SlotStatus::Dead,
                         dead_error: Some("123".to_owned()),
+                        raw_status: None,
                         created_at: Timestamp::from(SystemTime::now()),
                     }),
                 )
@@ -1298,6 +1864,71 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_message_slot_raw_status() {
+        for (status, commitment, raw_status) in [
+            (
+                GeyserSlotStatus::Processed,
+                Some(CommitmentLevel::Processed),
+                "processed",
+            ),
+            (
+                GeyserSlotStatus::Confirmed,
+                Some(CommitmentLevel::Confirmed),
+                "confirmed",
+            ),
+            (
+                GeyserSlotStatus::Rooted,
+                Some(CommitmentLevel::Finalized),
+                "rooted",
+            ),
+            (GeyserSlotStatus::CreatedBank, None, "created_bank"),
+            (GeyserSlotStatus::Dead("123".to_owned()), None, "dead"),
+        ] {
+            let msg = MessageSlot::from_geyser(42, Some(41), &status);
+            assert_eq!(msg.raw_status.as_deref(), Some(raw_status));
+            // mapped commitment is kept alongside the raw status
+            for level in [
+                CommitmentLevel::Processed,
+                CommitmentLevel::Confirmed,
+                CommitmentLevel::Finalized,
+            ] {
+                assert_eq!(level == msg.status, commitment == Some(level));
+            }
+
+            let update = FilteredUpdate::new_empty(FilteredUpdateOneof::slot(msg.clone()));
+            match update.as_subscribe_update().update_oneof {
+                Some(UpdateOneof::Slot(slot)) => {
+                    assert_eq!(slot.raw_status.as_deref(), Some(raw_status));
+                    let decoded =
+                        MessageSlot::from_update_oneof(&slot, msg.created_at).expect("valid slot");
+                    assert_eq!(decoded, msg);
+                }
+                _ => panic!("expected slot update"),
+            }
+            encode_decode_cmp(&["123"], FilteredUpdateOneof::slot(msg));
+        }
+    }
+
+    #[test]
+    fn test_message_slot_dead_error() {
+        let error = "bank hash mismatch".to_owned();
+        let msg = MessageSlot::from_geyser(42, Some(41), &GeyserSlotStatus::Dead(error.clone()));
+        assert_eq!(msg.status, SlotStatus::Dead);
+        assert_eq!(msg.dead_error.as_ref(), Some(&error));
+
+        let update = FilteredUpdate::new_empty(FilteredUpdateOneof::slot(msg));
+        let decoded =
+            SubscribeUpdate::decode(update.encode_to_vec().as_slice()).expect("failed to decode");
+        for update in [decoded, update.as_subscribe_update()] {
+            let Some(UpdateOneof::Slot(slot)) = update.update_oneof else {
+                panic!("expected slot update");
+            };
+            assert_eq!(slot.status, SlotStatusProto::SlotDead as i32);
+            assert_eq!(slot.dead_error.as_ref(), Some(&error));
+        }
+    }
+
     #[test]
     fn test_message_transaction() {
         for transaction in load_predefined_transactions() {
@@ -1342,4 +1973,405 @@ pub mod tests {
             encode_decode_cmp(&["123"], FilteredUpdateOneof::entry(entry));
         }
     }
+
+    #[test]
+    fn test_message_slot_gap() {
+        for commitment in [
+            CommitmentLevel::Processed,
+            CommitmentLevel::Confirmed,
+            CommitmentLevel::Finalized,
+        ] {
+            encode_decode_cmp(&["123"], FilteredUpdateOneof::slot_gap(0, 2, commitment));
+            encode_decode_cmp(&["123"], FilteredUpdateOneof::slot_gap(42, 100, commitment));
+        }
+    }
+
+    fn envelope_cmp(message: FilteredUpdateOneof) {
+        let expected = FilteredUpdate::new_empty(message.clone())
+            .as_subscribe_update()
+            .update_oneof
+            .expect("defined update");
+        let envelope = message.as_envelope();
+        encode_decode_cmp(&["123"], envelope.clone());
+
+        let FilteredUpdateOneof::Envelope(any) = envelope else {
+            panic!("expected envelope");
+        };
+        let value = any.value.as_slice();
+        let decoded = match any.type_url.strip_prefix("type.googleapis.com/geyser.") {
+            Some("SubscribeUpdateAccount") => {
+                UpdateOneof::Account(SubscribeUpdateAccount::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateSlot") => {
+                UpdateOneof::Slot(SubscribeUpdateSlot::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateTransaction") => {
+                UpdateOneof::Transaction(SubscribeUpdateTransaction::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateTransactionStatus") => UpdateOneof::TransactionStatus(
+                SubscribeUpdateTransactionStatus::decode(value).unwrap(),
+            ),
+            Some("SubscribeUpdateBlock") => {
+                UpdateOneof::Block(SubscribeUpdateBlock::decode(value).unwrap())
+            }
+            Some("SubscribeUpdatePing") => {
+                UpdateOneof::Ping(SubscribeUpdatePing::decode(value).unwrap())
+            }
+            Some("SubscribeUpdatePong") => {
+                UpdateOneof::Pong(SubscribeUpdatePong::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateBlockMeta") => {
+                UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateEntry") => {
+                UpdateOneof::Entry(SubscribeUpdateEntry::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateSlotGap") => {
+                UpdateOneof::SlotGap(SubscribeUpdateSlotGap::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateFilterStats") => {
+                UpdateOneof::FilterStats(SubscribeUpdateFilterStats::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateSnapshotComplete") => UpdateOneof::SnapshotComplete(
+                SubscribeUpdateSnapshotComplete::decode(value).unwrap(),
+            ),
+            Some("SubscribeUpdateServerShutdown") => {
+                UpdateOneof::ServerShutdown(SubscribeUpdateServerShutdown::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateProgramEvent") => {
+                UpdateOneof::ProgramEvent(SubscribeUpdateProgramEvent::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateSlotDeltaBatch") => {
+                UpdateOneof::SlotDeltaBatch(SubscribeUpdateSlotDeltaBatch::decode(value).unwrap())
+            }
+            Some("SubscribeUpdateSubscribeAccepted") => UpdateOneof::SubscribeAccepted(
+                SubscribeUpdateSubscribeAccepted::decode(value).unwrap(),
+            ),
+            _ => panic!("unexpected type url: {}", any.type_url),
+        };
+        assert_eq!(decoded, expected);
+    }
+
+    fn json_cmp(message: FilteredUpdateOneof) {
+        // rendering of the client from the received protobuf update
+        let encoded = FilteredUpdate::new_empty(message.clone()).encode_to_vec();
+        let update = SubscribeUpdate::decode(encoded.as_slice())
+            .expect("valid update")
+            .update_oneof
+            .expect("defined update");
+        let expected = create_pretty_update(update).expect("valid update");
+
+        let json = message.as_json();
+        encode_decode_cmp(&["123"], json.clone());
+        match expected {
+            Some((kind, value)) => {
+                let FilteredUpdateOneof::Json(msg) = json else {
+                    panic!("expected json");
+                };
+                assert_eq!(msg.kind, kind);
+                let decoded = serde_json::from_str::<Value>(&msg.value).expect("valid json");
+                assert_eq!(decoded, value);
+            }
+            None => assert_eq!(json, message),
+        }
+    }
+
+    fn create_filter_stats() -> SubscribeUpdateFilterStats {
+        SubscribeUpdateFilterStats {
+            interval_ms: 1_000,
+            filters: vec![
+                SubscribeUpdateFilterStatsEntry {
+                    filter: "accounts".to_owned(),
+                    count: 10,
+                    bytes: 1_024,
+                    unique_pubkeys: 3,
+                },
+                SubscribeUpdateFilterStatsEntry {
+                    filter: "transactions".to_owned(),
+                    count: 0,
+                    bytes: 0,
+                    unique_pubkeys: 0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_message_filter_stats() {
+        encode_decode_cmp(&[], FilteredUpdateOneof::FilterStats(create_filter_stats()));
+        encode_decode_cmp(
+            &[],
+            FilteredUpdateOneof::FilterStats(SubscribeUpdateFilterStats::default()),
+        );
+    }
+
+    #[test]
+    fn test_message_snapshot_complete() {
+        encode_decode_cmp(&[], FilteredUpdateOneof::snapshot_complete(0, 0));
+        encode_decode_cmp(
+            &["snapshot"],
+            FilteredUpdateOneof::snapshot_complete(1_000, 299_888_121),
+        );
+    }
+
+    #[test]
+    fn test_message_server_shutdown() {
+        encode_decode_cmp(&[], FilteredUpdateOneof::server_shutdown());
+    }
+
+    #[test]
+    fn test_message_subscribe_accepted() {
+        encode_decode_cmp(
+            &[],
+            FilteredUpdateOneof::subscribe_accepted(&[], CommitmentLevel::Processed),
+        );
+        encode_decode_cmp(
+            &[],
+            FilteredUpdateOneof::subscribe_accepted(
+                &[("accounts", 2), ("slots", 1), ("all", 3)],
+                CommitmentLevel::Confirmed,
+            ),
+        );
+    }
+
+    fn create_program_event() -> SubscribeUpdateProgramEvent {
+        SubscribeUpdateProgramEvent {
+            slot: 42,
+            signature: vec![1; 64],
+            transaction_index: 7,
+            program: vec![2; 32],
+            log_index: 3,
+            data: vec![vec![1, 2, 3, 4, 5, 6, 7, 8], vec![9]],
+        }
+    }
+
+    #[test]
+    fn test_message_program_event() {
+        encode_decode_cmp(
+            &["events"],
+            FilteredUpdateOneof::ProgramEvent(create_program_event()),
+        );
+        encode_decode_cmp(
+            &[],
+            FilteredUpdateOneof::ProgramEvent(SubscribeUpdateProgramEvent::default()),
+        );
+    }
+
+    fn create_slot_delta_batch() -> FilteredUpdateOneof {
+        FilteredUpdateOneof::slot_delta_batch(&[
+            (42, SlotStatus::Processed),
+            (42, SlotStatus::Completed),
+            (41, SlotStatus::Confirmed),
+            (10, SlotStatus::Finalized),
+            (43, SlotStatus::Dead),
+        ])
+    }
+
+    #[test]
+    fn test_message_slot_delta_batch() {
+        encode_decode_cmp(&["slots"], create_slot_delta_batch());
+        encode_decode_cmp(&[], FilteredUpdateOneof::slot_delta_batch(&[]));
+    }
+
+    #[test]
+    fn test_message_created_at() {
+        for created_at in [
+            Timestamp::default(),
+            Timestamp {
+                seconds: 1_700_000_000,
+                nanos: 123_456_789,
+            },
+        ] {
+            let msg = FilteredUpdate::new(
+                create_message_filters(&["123"]),
+                FilteredUpdateOneof::pong(42),
+                created_at,
+            );
+            let update =
+                SubscribeUpdate::decode(msg.encode_to_vec().as_slice()).expect("failed to decode");
+            assert_eq!(update.created_at, Some(created_at));
+            assert_eq!(
+                FilteredUpdate::from_subscribe_update(update).map(|msg| msg.created_at),
+                Ok(created_at)
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_seq() {
+        for seq in [None, Some(0), Some(1), Some(u64::MAX)] {
+            let mut msg = FilteredUpdate::new_empty(FilteredUpdateOneof::pong(42));
+            msg.seq = seq;
+            let update = msg.as_subscribe_update();
+            assert_eq!(msg.encoded_len(), update.encoded_len());
+            assert_eq!(msg.encode_to_vec(), update.encode_to_vec());
+            let update =
+                SubscribeUpdate::decode(msg.encode_to_vec().as_slice()).expect("failed to decode");
+            assert_eq!(update.seq, seq);
+            assert_eq!(
+                FilteredUpdate::from_subscribe_update(update).map(|msg| msg.seq),
+                Ok(seq)
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_slot_seq() {
+        for slot_seq in [None, Some(0), Some(1), Some(u64::MAX)] {
+            let mut msg = FilteredUpdate::new_empty(FilteredUpdateOneof::pong(42));
+            msg.seq = Some(7);
+            msg.slot_seq = slot_seq;
+            let update = msg.as_subscribe_update();
+            assert_eq!(msg.encoded_len(), update.encoded_len());
+            assert_eq!(msg.encode_to_vec(), update.encode_to_vec());
+            let update =
+                SubscribeUpdate::decode(msg.encode_to_vec().as_slice()).expect("failed to decode");
+            assert_eq!(update.slot_seq, slot_seq);
+            assert_eq!(
+                FilteredUpdate::from_subscribe_update(update).map(|msg| msg.slot_seq),
+                Ok(slot_seq)
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_resume_token() {
+        for resume_token in [None, Some(vec![]), Some(vec![1; 26])] {
+            let mut msg = FilteredUpdate::new_empty(FilteredUpdateOneof::pong(42));
+            msg.seq = Some(7);
+            msg.resume_token.clone_from(&resume_token);
+            let update = msg.as_subscribe_update();
+            assert_eq!(msg.encoded_len(), update.encoded_len());
+            assert_eq!(msg.encode_to_vec(), update.encode_to_vec());
+            let update =
+                SubscribeUpdate::decode(msg.encode_to_vec().as_slice()).expect("failed to decode");
+            assert_eq!(update.resume_token, resume_token);
+            assert_eq!(
+                FilteredUpdate::from_subscribe_update(update).map(|msg| msg.resume_token),
+                Ok(resume_token)
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_envelope() {
+        for (msg, data_slice) in create_accounts() {
+            envelope_cmp(FilteredUpdateOneof::account(&msg, data_slice));
+        }
+        envelope_cmp(FilteredUpdateOneof::slot(MessageSlot {
+            slot: 42,
+            parent: Some(41),
+            status: SlotStatus::Confirmed,
+            dead_error: None,
+            raw_status: None,
+            created_at: Timestamp::from(SystemTime::now()),
+        }));
+        for transaction in load_predefined_transactions() {
+            let msg = MessageTransaction {
+                transaction,
+                slot: 42,
+                created_at: Timestamp::from(SystemTime::now()),
+            };
+            envelope_cmp(FilteredUpdateOneof::transaction(&msg));
+            envelope_cmp(FilteredUpdateOneof::transaction_status(&msg));
+        }
+        for block in load_predefined_blocks() {
+            envelope_cmp(FilteredUpdateOneof::block(Box::new(block)));
+        }
+        envelope_cmp(FilteredUpdateOneof::Ping);
+        envelope_cmp(FilteredUpdateOneof::pong(42));
+        for block_meta in load_predefined_blockmeta() {
+            envelope_cmp(FilteredUpdateOneof::block_meta(block_meta));
+        }
+        for entry in create_entries() {
+            envelope_cmp(FilteredUpdateOneof::entry(entry));
+        }
+        envelope_cmp(FilteredUpdateOneof::slot_gap(
+            42,
+            100,
+            CommitmentLevel::Finalized,
+        ));
+        envelope_cmp(FilteredUpdateOneof::FilterStats(create_filter_stats()));
+        envelope_cmp(FilteredUpdateOneof::snapshot_complete(42, 100));
+        envelope_cmp(FilteredUpdateOneof::server_shutdown());
+        envelope_cmp(FilteredUpdateOneof::ProgramEvent(create_program_event()));
+        envelope_cmp(create_slot_delta_batch());
+        envelope_cmp(FilteredUpdateOneof::subscribe_accepted(
+            &[("slots", 1), ("all", 1)],
+            CommitmentLevel::Finalized,
+        ));
+    }
+    #[test]
+    fn test_message_json() {
+        for (msg, data_slice) in create_accounts() {
+            json_cmp(FilteredUpdateOneof::account(&msg, data_slice));
+        }
+        for (status, dead_error) in [
+            (SlotStatus::Confirmed, None),
+            (SlotStatus::FirstShredReceived, None),
+            (SlotStatus::Completed, None),
+            (SlotStatus::CreatedBank, None),
+            (SlotStatus::Dead, Some("dead".to_owned())),
+        ] {
+            json_cmp(FilteredUpdateOneof::slot(MessageSlot {
+                slot: 42,
+                parent: Some(41),
+                status,
+                dead_error,
+                raw_status: None,
+                created_at: Timestamp::from(SystemTime::now()),
+            }));
+        }
+        for transaction in load_predefined_transactions() {
+            let msg = MessageTransaction {
+                transaction,
+                slot: 42,
+                created_at: Timestamp::from(SystemTime::now()),
+            };
+            json_cmp(FilteredUpdateOneof::transaction(&msg));
+            json_cmp(FilteredUpdateOneof::transaction_status(&msg));
+        }
+        for block in load_predefined_blocks() {
+            json_cmp(FilteredUpdateOneof::block(Box::new(block)));
+        }
+        json_cmp(FilteredUpdateOneof::Ping);
+        json_cmp(FilteredUpdateOneof::pong(42));
+        for block_meta in load_predefined_blockmeta() {
+            json_cmp(FilteredUpdateOneof::block_meta(block_meta));
+        }
+        for entry in create_entries() {
+            json_cmp(FilteredUpdateOneof::entry(entry));
+        }
+        json_cmp(FilteredUpdateOneof::slot_gap(
+            42,
+            100,
+            CommitmentLevel::Finalized,
+        ));
+        json_cmp(FilteredUpdateOneof::FilterStats(create_filter_stats()));
+        json_cmp(FilteredUpdateOneof::snapshot_complete(42, 100));
+        json_cmp(FilteredUpdateOneof::server_shutdown());
+        json_cmp(FilteredUpdateOneof::ProgramEvent(create_program_event()));
+        json_cmp(create_slot_delta_batch());
+        json_cmp(FilteredUpdateOneof::subscribe_accepted(
+            &[("slots", 1), ("all", 1)],
+            CommitmentLevel::Finalized,
+        ));
+        json_cmp(FilteredUpdateOneof::server_shutdown().as_envelope());
+    }
+
+    #[test]
+    fn test_message_json_error() {
+        let message = FilteredUpdateOneof::ProgramEvent(SubscribeUpdateProgramEvent {
+            signature: vec![1, 2, 3],
+            ..create_program_event()
+        });
+        let FilteredUpdateOneof::Json(msg) = message.as_json() else {
+            panic!("expected json");
+        };
+        assert_eq!(msg.kind, "error");
+        assert_eq!(
+            serde_json::from_str::<Value>(&msg.value).expect("valid json"),
+            json!({ "error": "invalid signature" })
+        );
+    }
 }
@@ -1,7 +1,10 @@
+pub mod cache;
 #[allow(clippy::module_inception)]
 mod filter;
 pub mod limits;
 pub mod message;
 pub mod name;
 
-pub use filter::{Filter, FilterAccountsDataSlice, FilterError, FilterResult};
+pub use filter::{
+    Filter, FilterAccountsDataSlice, FilterError, FilterExplain, FilterMismatch, FilterResult,
+};
@@ -49,6 +49,13 @@ impl FilterName {
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FilterNamesStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
 #[derive(Debug)]
 pub struct FilterNames {
     name_size_limit: usize,
@@ -56,6 +63,7 @@ pub struct FilterNames {
     names_size_limit: usize,
     cleanup_ts: Instant,
     cleanup_interval: Duration,
+    stats: FilterNamesStats,
 }
 
 impl FilterNames {
@@ -70,21 +78,44 @@ impl FilterNames {
             names_size_limit,
             cleanup_ts: Instant::now(),
             cleanup_interval,
+            stats: FilterNamesStats::default(),
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns stats collected since the previous call
+    pub fn take_stats(&mut self) -> FilterNamesStats {
+        std::mem::take(&mut self.stats)
+    }
+
     pub fn try_clean(&mut self) {
+        self.try_clean_at(Instant::now())
+    }
+
+    pub fn try_clean_at(&mut self, now: Instant) {
         if self.names.len() > self.names_size_limit
-            && self.cleanup_ts.elapsed() > self.cleanup_interval
+            && now.saturating_duration_since(self.cleanup_ts) > self.cleanup_interval
         {
+            let size = self.names.len();
             self.names.retain(|name| !name.is_uniq());
-            self.cleanup_ts = Instant::now();
+            self.stats.evictions += (size - self.names.len()) as u64;
+            self.cleanup_ts = now;
         }
     }
 
     pub fn get(&mut self, name: &str) -> FilterNameResult<FilterName> {
         match self.names.get(name) {
-            Some(name) => Ok(name.clone()),
+            Some(name) => {
+                self.stats.hits += 1;
+                Ok(name.clone())
+            }
             None => {
                 if name.len() > self.name_size_limit {
                     Err(FilterNameError::Oversized {
@@ -92,6 +123,7 @@ impl FilterNames {
                         size: name.len(),
                     })
                 } else {
+                    self.stats.misses += 1;
                     let name = FilterName::new(name);
                     self.names.insert(name.clone());
                     Ok(name)
@@ -100,3 +132,44 @@ impl FilterNames {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{FilterNames, FilterNamesStats},
+        std::time::{Duration, Instant},
+    };
+
+    #[test]
+    fn test_filter_names_stats() {
+        let interval = Duration::from_secs(1);
+        let mut names = FilterNames::new(8, 2, interval);
+        let start = Instant::now();
+
+        let kept = names.get("kept").unwrap();
+        names.get("kept").unwrap();
+        for name in ["a", "b", "c"] {
+            names.get(name).unwrap();
+        }
+        assert_eq!(names.len(), 4);
+        assert_eq!(
+            names.take_stats(),
+            FilterNamesStats {
+                hits: 1,
+                misses: 4,
+                evictions: 0,
+            }
+        );
+
+        // cleanup interval is not reached
+        names.try_clean_at(start);
+        assert_eq!(names.len(), 4);
+        assert_eq!(names.take_stats(), FilterNamesStats::default());
+
+        // unused names are evicted once interval passed
+        names.try_clean_at(start + interval * 2);
+        assert_eq!(names.len(), 1);
+        assert_eq!(names.take_stats().evictions, 3);
+        assert!(!kept.is_uniq());
+    }
+}
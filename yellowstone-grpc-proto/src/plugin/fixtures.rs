@@ -0,0 +1,139 @@
+//! Mainnet blocks from `fixtures/blocks` for tests and benches of crates using the plugin
+//! messages and filters
+
+use {
+    crate::{
+        convert_to,
+        geyser::SubscribeUpdateBlockMeta,
+        plugin::message::{
+            Message, MessageBlock, MessageBlockMeta, MessageSlot, MessageTransaction,
+            MessageTransactionInfo, SlotStatus,
+        },
+    },
+    prost_011::Message as _,
+    prost_types::Timestamp,
+    solana_sdk::{
+        message::SimpleAddressLoader,
+        transaction::{MessageHash, SanitizedTransaction},
+    },
+    solana_storage_proto::convert::generated,
+    solana_transaction_status::{ConfirmedBlock, TransactionWithStatusMeta},
+    std::{collections::HashSet, fs, sync::Arc, time::SystemTime},
+};
+
+/// Blocks sorted by the file name
+pub fn load_predefined() -> Vec<ConfirmedBlock> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/blocks");
+    let mut paths = fs::read_dir(dir)
+        .expect("failed to read `blocks` dir")
+        .map(|entry| entry.expect("failed to read `blocks` dir entry").path())
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let data = fs::read(path).expect("failed to read block");
+            generated::ConfirmedBlock::decode(data.as_slice())
+                .expect("failed to decode block")
+                .try_into()
+                .expect("failed to convert decoded block")
+        })
+        .collect()
+}
+
+/// Transactions of the block in the order of execution
+pub fn create_transactions(block: &ConfirmedBlock) -> Vec<Arc<MessageTransactionInfo>> {
+    block
+        .transactions
+        .iter()
+        .enumerate()
+        .map(|(index, tx)| {
+            let TransactionWithStatusMeta::Complete(tx) = tx else {
+                panic!("tx with missed meta");
+            };
+            let transaction = SanitizedTransaction::try_create(
+                tx.transaction.clone(),
+                MessageHash::Compute,
+                None,
+                SimpleAddressLoader::Disabled,
+                &HashSet::new(),
+            )
+            .expect("failed to create tx");
+            Arc::new(MessageTransactionInfo {
+                signature: tx.transaction.signatures[0],
+                is_vote: transaction.is_simple_vote_transaction(),
+                transaction: convert_to::create_transaction(&transaction),
+                meta: convert_to::create_transaction_meta(&tx.meta),
+                index,
+                account_keys: transaction
+                    .message()
+                    .account_keys()
+                    .iter()
+                    .copied()
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Block meta without entries
+pub fn create_block_meta(block: &ConfirmedBlock) -> MessageBlockMeta {
+    MessageBlockMeta {
+        block_meta: SubscribeUpdateBlockMeta {
+            parent_slot: block.parent_slot,
+            slot: block.parent_slot + 1,
+            parent_blockhash: block.previous_blockhash.clone(),
+            blockhash: block.blockhash.clone(),
+            rewards: Some(convert_to::create_rewards_obj(
+                &block.rewards,
+                block.num_partitions,
+            )),
+            block_time: block.block_time.map(convert_to::create_timestamp),
+            block_height: block.block_height.map(convert_to::create_block_height),
+            executed_transaction_count: block.transactions.len() as u64,
+            entries_count: 0,
+        },
+        created_at: Timestamp::from(SystemTime::now()),
+    }
+}
+
+/// Messages of every block in the order of the plugin: transactions, block meta, block and
+/// slot statuses up to finalized. Blocks have no accounts and entries.
+pub fn load_predefined_messages() -> Vec<Message> {
+    let mut messages = vec![];
+    for block in load_predefined() {
+        let transactions = create_transactions(&block);
+        let block_meta = Arc::new(create_block_meta(&block));
+        let slot = block_meta.slot;
+
+        messages.extend(transactions.iter().map(|transaction| {
+            Message::Transaction(MessageTransaction {
+                transaction: Arc::clone(transaction),
+                slot,
+                created_at: Timestamp::from(SystemTime::now()),
+            })
+        }));
+        messages.push(Message::BlockMeta(Arc::clone(&block_meta)));
+        messages.push(Message::Block(Arc::new(MessageBlock::new(
+            block_meta,
+            transactions,
+            vec![],
+            vec![],
+        ))));
+        for status in [
+            SlotStatus::Processed,
+            SlotStatus::Confirmed,
+            SlotStatus::Finalized,
+        ] {
+            messages.push(Message::Slot(MessageSlot {
+                slot,
+                parent: Some(block.parent_slot),
+                status,
+                dead_error: None,
+                raw_status: None,
+                created_at: Timestamp::from(SystemTime::now()),
+            }));
+        }
+    }
+    messages
+}
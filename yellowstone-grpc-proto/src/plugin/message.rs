@@ -67,7 +67,7 @@ impl CommitmentLevel {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum SlotStatus {
     Processed,
     Confirmed,
@@ -151,6 +151,7 @@ pub struct MessageSlot {
     pub parent: Option<Slot>,
     pub status: SlotStatus,
     pub dead_error: Option<String>,
+    pub raw_status: Option<String>,
     pub created_at: Timestamp,
 }
 
@@ -165,6 +166,7 @@ impl MessageSlot {
             } else {
                 None
             },
+            raw_status: Some(status.as_str().to_owned()),
             created_at: Timestamp::from(SystemTime::now()),
         }
     }
@@ -180,6 +182,7 @@ impl MessageSlot {
                 .map_err(|_| "failed to parse slot status")?
                 .into(),
             dead_error: msg.dead_error.clone(),
+            raw_status: msg.raw_status.clone(),
             created_at,
         })
     }
@@ -195,10 +198,12 @@ pub struct MessageAccountInfo {
     pub data: Vec<u8>,
     pub write_version: u64,
     pub txn_signature: Option<Signature>,
+    /// CRC32 of `data`
+    pub data_checksum: Option<u32>,
 }
 
 impl MessageAccountInfo {
-    pub fn from_geyser(info: &ReplicaAccountInfoV3<'_>) -> Self {
+    pub fn from_geyser(info: &ReplicaAccountInfoV3<'_>, data_checksum: bool) -> Self {
         Self {
             pubkey: Pubkey::try_from(info.pubkey).expect("valid Pubkey"),
             lamports: info.lamports,
@@ -208,10 +213,18 @@ impl MessageAccountInfo {
             data: info.data.into(),
             write_version: info.write_version,
             txn_signature: info.txn.map(|txn| *txn.signature()),
+            data_checksum: data_checksum.then(|| crc32fast::hash(info.data)),
         }
     }
 
     pub fn from_update_oneof(msg: SubscribeUpdateAccountInfo) -> FromUpdateOneofResult<Self> {
+        if msg.data_encoded.is_some() {
+            return Err("encoded account data is not supported");
+        }
+        if !msg.data_segments.is_empty() {
+            return Err("segmented account data is not supported");
+        }
+
         Ok(Self {
             pubkey: Pubkey::try_from(msg.pubkey.as_slice()).map_err(|_| "invalid pubkey length")?,
             lamports: msg.lamports,
@@ -226,6 +239,7 @@ impl MessageAccountInfo {
                     Signature::try_from(sig.as_slice()).map_err(|_| "invalid signature length")
                 })
                 .transpose()?,
+            data_checksum: msg.data_checksum,
         })
     }
 }
@@ -239,9 +253,14 @@ pub struct MessageAccount {
 }
 
 impl MessageAccount {
-    pub fn from_geyser(info: &ReplicaAccountInfoV3<'_>, slot: Slot, is_startup: bool) -> Self {
+    pub fn from_geyser(
+        info: &ReplicaAccountInfoV3<'_>,
+        slot: Slot,
+        is_startup: bool,
+        data_checksum: bool,
+    ) -> Self {
         Self {
-            account: Arc::new(MessageAccountInfo::from_geyser(info)),
+            account: Arc::new(MessageAccountInfo::from_geyser(info, data_checksum)),
             slot,
             is_startup,
             created_at: Timestamp::from(SystemTime::now()),
@@ -307,6 +326,15 @@ impl MessageTransactionInfo {
         })
     }
 
+    /// Same as `from_update_oneof` but with `account_keys` filled, required by transaction filters
+    pub fn from_update_oneof_filled(
+        msg: SubscribeUpdateTransactionInfo,
+    ) -> FromUpdateOneofResult<Self> {
+        let mut tx = Self::from_update_oneof(msg)?;
+        tx.fill_account_keys()?;
+        Ok(tx)
+    }
+
     pub fn fill_account_keys(&mut self) -> FromUpdateOneofResult<()> {
         let mut account_keys = HashSet::new();
 
@@ -337,6 +365,77 @@ impl MessageTransactionInfo {
         self.account_keys = account_keys;
         Ok(())
     }
+
+    /// Resolve account index from instructions, loaded addresses follow static keys
+    pub fn get_account_key(&self, index: u32) -> Option<Pubkey> {
+        let mut index = index as usize;
+        let static_keys = self
+            .transaction
+            .message
+            .as_ref()
+            .map(|msg| msg.account_keys.as_slice())
+            .unwrap_or_default();
+        let pubkey = [
+            static_keys,
+            self.meta.loaded_writable_addresses.as_slice(),
+            self.meta.loaded_readonly_addresses.as_slice(),
+        ]
+        .into_iter()
+        .find_map(|keys| match keys.get(index) {
+            Some(pubkey) => Some(pubkey),
+            None => {
+                index -= keys.len();
+                None
+            }
+        })?;
+        Pubkey::try_from(pubkey.as_slice()).ok()
+    }
+
+    /// Signers of the transaction, first `num_required_signatures` static account keys
+    pub fn signers(&self) -> impl Iterator<Item = Pubkey> + '_ {
+        let (num_required_signatures, account_keys) = self
+            .transaction
+            .message
+            .as_ref()
+            .map(|msg| {
+                (
+                    msg.header
+                        .as_ref()
+                        .map(|header| header.num_required_signatures as usize)
+                        .unwrap_or_default(),
+                    msg.account_keys.as_slice(),
+                )
+            })
+            .unwrap_or_default();
+        account_keys
+            .iter()
+            .take(num_required_signatures)
+            .filter_map(|pubkey| Pubkey::try_from(pubkey.as_slice()).ok())
+    }
+
+    /// Programs invoked by compiled and inner instructions
+    pub fn program_ids(&self) -> impl Iterator<Item = Pubkey> + '_ {
+        let compiled = self
+            .transaction
+            .message
+            .as_ref()
+            .map(|msg| msg.instructions.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|ix| ix.program_id_index);
+        compiled
+            .filter_map(|index| self.get_account_key(index))
+            .chain(self.inner_program_ids())
+    }
+
+    /// Programs invoked by inner instructions (CPI)
+    pub fn inner_program_ids(&self) -> impl Iterator<Item = Pubkey> + '_ {
+        self.meta
+            .inner_instructions
+            .iter()
+            .flat_map(|ixs| ixs.instructions.iter())
+            .filter_map(|ix| self.get_account_key(ix.program_id_index))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -360,7 +459,7 @@ impl MessageTransaction {
         created_at: Timestamp,
     ) -> FromUpdateOneofResult<Self> {
         Ok(Self {
-            transaction: Arc::new(MessageTransactionInfo::from_update_oneof(
+            transaction: Arc::new(MessageTransactionInfo::from_update_oneof_filled(
                 msg.transaction
                     .ok_or("transaction message should be defined")?,
             )?),
@@ -517,7 +616,7 @@ impl MessageBlock {
             transactions: msg
                 .transactions
                 .into_iter()
-                .map(|tx| MessageTransactionInfo::from_update_oneof(tx).map(Arc::new))
+                .map(|tx| MessageTransactionInfo::from_update_oneof_filled(tx).map(Arc::new))
                 .collect::<Result<Vec<_>, _>>()?,
             updated_account_count: msg.updated_account_count,
             accounts: msg
@@ -557,6 +656,17 @@ impl Message {
         }
     }
 
+    pub fn get_created_at(&self) -> Timestamp {
+        match self {
+            Self::Slot(msg) => msg.created_at,
+            Self::Account(msg) => msg.created_at,
+            Self::Transaction(msg) => msg.created_at,
+            Self::Entry(msg) => msg.created_at,
+            Self::BlockMeta(msg) => msg.created_at,
+            Self::Block(msg) => msg.created_at,
+        }
+    }
+
     pub fn from_update_oneof(
         oneof: UpdateOneof,
         created_at: Timestamp,
@@ -583,6 +693,23 @@ impl Message {
             UpdateOneof::Entry(msg) => {
                 Self::Entry(Arc::new(MessageEntry::from_update_oneof(&msg, created_at)?))
             }
+            UpdateOneof::SlotGap(_) => return Err("SlotGap message is not supported"),
+            UpdateOneof::Envelope(_) => return Err("Envelope message is not supported"),
+            UpdateOneof::FilterStats(_) => return Err("FilterStats message is not supported"),
+            UpdateOneof::SnapshotComplete(_) => {
+                return Err("SnapshotComplete message is not supported")
+            }
+            UpdateOneof::ServerShutdown(_) => {
+                return Err("ServerShutdown message is not supported")
+            }
+            UpdateOneof::ProgramEvent(_) => return Err("ProgramEvent message is not supported"),
+            UpdateOneof::SlotDeltaBatch(_) => {
+                return Err("SlotDeltaBatch message is not supported")
+            }
+            UpdateOneof::SubscribeAccepted(_) => {
+                return Err("SubscribeAccepted message is not supported")
+            }
+            UpdateOneof::Json(_) => return Err("Json message is not supported"),
         })
     }
 }
@@ -1,4 +1,6 @@
 pub mod filter;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixtures;
 pub mod message;
 
 pub mod proto {